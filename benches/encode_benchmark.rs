@@ -6,7 +6,7 @@ const TARGET_TOTAL_BYTES: usize = 128 * 1024 * 1024;
 const SYMBOL_COUNTS: [usize; 10] = [10, 100, 250, 500, 1000, 2000, 5000, 10000, 20000, 50000];
 
 fn black_box(value: u64) {
-    if value == rand::thread_rng().gen() {
+    if value == rand::thread_rng().gen::<u64>() {
         println!("{}", value);
     }
 }
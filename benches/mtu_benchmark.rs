@@ -0,0 +1,66 @@
+use rand::Rng;
+use raptorq::{Config, Encoder};
+use std::time::Instant;
+
+const TARGET_TOTAL_BYTES: usize = 1024 * 1024 * 1024;
+// Packet sizes: the QUIC safe minimum, Ethernet less the IPv6 and UDP headers, and jumbo frames
+const MTUS: [u16; 3] = [1200, 1452, 8960];
+const OBJECT_SIZES: [usize; 4] = [
+    1024 * 1024,
+    16 * 1024 * 1024,
+    128 * 1024 * 1024,
+    1024 * 1024 * 1024,
+];
+// Repair symbols per block, as a percentage of the source symbols, plus one
+const OVERHEAD_PERCENT: u32 = 5;
+
+fn black_box(value: u64) {
+    if value == rand::thread_rng().gen::<u64>() {
+        println!("{}", value);
+    }
+}
+
+fn main() {
+    let mut black_box_value = 0;
+    let mut data: Vec<u8> = vec![0; OBJECT_SIZES[OBJECT_SIZES.len() - 1]];
+    rand::thread_rng().fill(&mut data[..]);
+
+    for &mtu in MTUS.iter() {
+        let config = Config::new(mtu, 8192);
+        println!(
+            "MTU: {} bytes, symbol size: {} bytes",
+            mtu,
+            config.symbol_size()
+        );
+        for &object_size in OBJECT_SIZES.iter() {
+            let object = &data[..object_size];
+            let iterations = (TARGET_TOTAL_BYTES / object_size).max(1);
+            let mut packets = 0;
+            let now = Instant::now();
+            for _ in 0..iterations {
+                let encoder = Encoder::with_config(object, &config);
+                // Generate the packets of one block at a time, so that the largest objects fit in
+                // memory
+                for block in encoder.get_block_encoders() {
+                    let source_packets = block.source_packets();
+                    let source_symbols = source_packets.len() as u32;
+                    let repair_packets =
+                        block.repair_packets(0, source_symbols * OVERHEAD_PERCENT / 100 + 1);
+                    packets += source_packets.len() + repair_packets.len();
+                    black_box_value += u64::from(repair_packets[0].data()[0]);
+                }
+            }
+            let elapsed = now.elapsed();
+            let elapsed = elapsed.as_secs() as f64 + elapsed.subsec_millis() as f64 * 0.001;
+            println!(
+                "object size = {} MB, encoded {} MB in {:.3}secs, throughput: {:.1}MB/s, {:.0} packets/s",
+                object_size / 1024 / 1024,
+                object_size * iterations / 1024 / 1024,
+                elapsed,
+                (object_size * iterations) as f64 / 1024.0 / 1024.0 / elapsed,
+                packets as f64 / elapsed
+            );
+        }
+    }
+    black_box(black_box_value);
+}
@@ -0,0 +1,55 @@
+use criterion::criterion_group;
+use criterion::criterion_main;
+use criterion::BenchmarkId;
+use criterion::Criterion;
+use criterion::Throughput;
+
+use rand::Rng;
+use raptorq::Octet;
+use raptorq::{add_assign, fused_addassign_mul_scalar};
+
+const SIZES: [usize; 14] = [
+    8, 16, 32, 64, 128, 256, 512, 1024, 1280, 2048, 4096, 8192, 16384, 65536,
+];
+
+fn random_octets(size: usize) -> Vec<u8> {
+    let mut data: Vec<u8> = vec![0; size];
+    for byte in data.iter_mut() {
+        *byte = rand::thread_rng().gen();
+    }
+    data
+}
+
+fn criterion_benchmark(c: &mut Criterion) {
+    let mut group = c.benchmark_group("add_assign()");
+    for &size in SIZES.iter() {
+        let mut data1 = random_octets(size);
+        let data2 = random_octets(size);
+        group.throughput(Throughput::Bytes(size as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(size), &size, |b, _| {
+            b.iter(|| {
+                add_assign(&mut data1, &data2);
+                data1[0]
+            })
+        });
+    }
+    group.finish();
+
+    let mut group = c.benchmark_group("fused_addassign_mul_scalar()");
+    let scalar = Octet::new(rand::thread_rng().gen_range(2, 255));
+    for &size in SIZES.iter() {
+        let mut data1 = random_octets(size);
+        let data2 = random_octets(size);
+        group.throughput(Throughput::Bytes(size as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(size), &size, |b, _| {
+            b.iter(|| {
+                fused_addassign_mul_scalar(&mut data1, &data2, &scalar);
+                data1[0]
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, criterion_benchmark);
+criterion_main!(benches);
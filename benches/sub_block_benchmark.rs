@@ -0,0 +1,69 @@
+use rand::Rng;
+use raptorq::{SourceBlockDecoder, SourceBlockEncoder};
+use std::time::Instant;
+
+const TARGET_TOTAL_BYTES: usize = 64 * 1024 * 1024;
+const SYMBOL_SIZE: usize = 1280;
+const SOURCE_SYMBOLS: usize = 1000;
+const SUB_BLOCKS: [usize; 4] = [1, 2, 4, 8];
+
+fn black_box(value: u64) {
+    if value == rand::thread_rng().gen::<u64>() {
+        println!("{}", value);
+    }
+}
+
+// Decodes each sub-block of a source block from repair symbols, and returns the elapsed seconds.
+// With separate sub-blocks, each is decoded with its own decoder and sub-symbol size. With
+// interleaved sub-blocks, as the encoder and decoder store them, a single decoder operates on
+// whole symbols, each made of one sub-symbol from every sub-block
+fn decode(sub_blocks: usize, interleaved: bool, iterations: usize) -> f64 {
+    let (decoders, symbol_size) = if interleaved {
+        (1, SYMBOL_SIZE)
+    } else {
+        (sub_blocks, SYMBOL_SIZE / sub_blocks)
+    };
+    let elements = SOURCE_SYMBOLS * symbol_size;
+    let mut data: Vec<u8> = vec![0; elements];
+    rand::thread_rng().fill(&mut data[..]);
+    let encoder = SourceBlockEncoder::new(1, symbol_size as u16, &data);
+    let packets = encoder.repair_packets(0, SOURCE_SYMBOLS as u32 + 2);
+
+    let mut black_box_value = 0;
+    let now = Instant::now();
+    for _ in 0..(iterations * decoders) {
+        let mut decoder = SourceBlockDecoder::new(1, symbol_size as u16, elements as u64);
+        let result = decoder.decode(packets.clone()).unwrap();
+        black_box_value += result[0] as u64;
+    }
+    let elapsed = now.elapsed();
+    black_box(black_box_value);
+    elapsed.as_secs() as f64 + elapsed.subsec_millis() as f64 * 0.001
+}
+
+fn main() {
+    let block_size = SOURCE_SYMBOLS * SYMBOL_SIZE;
+    let iterations = TARGET_TOTAL_BYTES / block_size;
+    println!(
+        "Symbol size: {} bytes, source symbols: {}",
+        SYMBOL_SIZE, SOURCE_SYMBOLS
+    );
+    for &sub_blocks in SUB_BLOCKS.iter() {
+        for &interleaved in [false, true].iter() {
+            let elapsed = decode(sub_blocks, interleaved, iterations);
+            let throughput = (block_size * iterations * 8) as f64 / 1024.0 / 1024.0 / elapsed;
+            println!(
+                "sub-blocks = {}, layout = {}, decoded {} MB in {:.3}secs, throughput: {:.1}Mbit/s",
+                sub_blocks,
+                if interleaved {
+                    "interleaved"
+                } else {
+                    "separate"
+                },
+                block_size * iterations / 1024 / 1024,
+                elapsed,
+                throughput
+            );
+        }
+    }
+}
@@ -0,0 +1,54 @@
+use rand::Rng;
+use raptorq::{
+    extended_source_block_symbols, generate_constraint_matrix, DenseOctetMatrix,
+    IntermediateSymbolDecoder, Octet, OctetMatrix, SparseOctetMatrix, Symbol,
+};
+use std::time::Instant;
+
+fn seconds(now: Instant) -> f64 {
+    let elapsed = now.elapsed();
+    elapsed.as_secs() as f64 + elapsed.subsec_millis() as f64 * 0.001
+}
+
+fn random_dense(height: usize, width: usize) -> DenseOctetMatrix {
+    let mut matrix = DenseOctetMatrix::new(height, width, 0, 0, 0);
+    for i in 0..height {
+        for j in 0..width {
+            matrix.set(i, j, Octet::new(rand::thread_rng().gen()));
+        }
+    }
+    matrix
+}
+
+fn main() {
+    // The product of the X and A matrices at the end of the third phase, as a dense matrix whose
+    // width is that of the constraint matrix at K' >= 10000
+    for &(rows, width) in [(512, 10_000), (512, 40_000), (1024, 56_403)].iter() {
+        let other = random_dense(rows, rows);
+        let mut matrix = random_dense(rows, width);
+        let now = Instant::now();
+        matrix.mul_assign_submatrix(&other, rows);
+        let elapsed = seconds(now);
+        println!(
+            "mul_assign_submatrix {}x{} by {}x{}: {:.3}secs, {:.1}MB/s",
+            rows,
+            rows,
+            rows,
+            width,
+            elapsed,
+            (rows * rows * width) as f64 / 1024.0 / 1024.0 / elapsed
+        );
+    }
+
+    // The whole solve, in which the third phase uses mul_assign_submatrix()
+    for &source_symbols in [10_000, 20_000, 40_000].iter() {
+        let num_symbols = extended_source_block_symbols(source_symbols);
+        let indices: Vec<u32> = (0..num_symbols).collect();
+        let a = generate_constraint_matrix::<SparseOctetMatrix>(num_symbols, &indices);
+        let symbols = vec![Symbol::zero(1usize); a.width()];
+        let mut decoder = IntermediateSymbolDecoder::new(a, symbols, num_symbols);
+        let now = Instant::now();
+        decoder.execute().unwrap();
+        println!("K' = {}: solved in {:.3}secs", num_symbols, seconds(now));
+    }
+}
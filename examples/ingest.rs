@@ -0,0 +1,69 @@
+use rand::Rng;
+use raptorq::{Decoder, Encoder, EncodingPacket, IngestError, PacketIngest};
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+fn main() {
+    // Generate some random data to send
+    let mut data: Vec<u8> = vec![0; 1_000_000];
+    for byte in data.iter_mut() {
+        *byte = rand::thread_rng().gen();
+    }
+    let encoder = Encoder::with_defaults(&data, 1400);
+
+    // Stand-in for a socket, which delivers packets in bursts
+    let (socket_sender, socket) = mpsc::channel();
+    let packets: Vec<Vec<u8>> = encoder
+        .get_encoded_packets(200)
+        .iter()
+        .map(|packet| packet.serialize())
+        .collect();
+    thread::spawn(move || {
+        for burst in packets.chunks(100) {
+            for packet in burst {
+                if socket_sender.send(packet.clone()).is_err() {
+                    return;
+                }
+            }
+            thread::sleep(Duration::from_millis(5));
+        }
+    });
+
+    // Holds at most 256 packets, between the reader thread and the decoding thread
+    let (ingest, queue) = PacketIngest::new(256);
+
+    let reader = thread::spawn(move || {
+        let mut dropped = 0;
+        for datagram in socket.iter() {
+            match ingest.try_add(EncodingPacket::deserialize(&datagram)) {
+                Ok(()) => {}
+                // The decoder is not keeping up. Dropping the packet is fine, because any
+                // sufficiently large set of packets can be decoded
+                Err(IngestError::Full(_)) => dropped += 1,
+                // The object has been decoded
+                Err(IngestError::Closed(_)) => break,
+            }
+        }
+        dropped
+    });
+
+    let mut decoder = Decoder::new(encoder.get_config());
+    let result = queue.decode(&mut decoder);
+    drop(queue);
+    let dropped = reader.join().unwrap();
+
+    match result {
+        Some(result) => {
+            assert_eq!(result, data);
+            println!(
+                "Decoded, with {} packets dropped due to backpressure",
+                dropped
+            );
+        }
+        None => println!(
+            "Too many packets ({}) dropped due to backpressure to decode",
+            dropped
+        ),
+    }
+}
@@ -0,0 +1,67 @@
+use raptorq::{
+    BernoulliChannel, Channel, Decoder, DecoderReceiver, Encoder, EncoderSender,
+    GilbertElliottChannel, Simulation,
+};
+
+const RUNS: u64 = 20;
+const MAX_TICKS: u64 = 100_000;
+
+fn channel(bursty: bool) -> Box<dyn Channel> {
+    if bursty {
+        // Bursts average 5 packets, during which 80% of packets are lost. About 7% overall
+        Box::new(GilbertElliottChannel::new(0.02, 0.2, 0.0, 0.8, 10))
+    } else {
+        Box::new(BernoulliChannel::new(0.07, 10))
+    }
+}
+
+fn main() {
+    let data: Vec<u8> = (0..1_000_000).map(|i| (i % 251) as u8).collect();
+    let encoder = Encoder::with_defaults(&data, 1400);
+    let source_packets = encoder.get_encoded_packets(0).len() as u64;
+
+    println!(
+        "{} source packets, 10 packets per tick, 10 tick one-way delay",
+        source_packets
+    );
+    for &bursty in [false, true].iter() {
+        // Open loop with 10% repair packets, and closed loop with no initial repair packets, which
+        // acknowledges every 50 packets
+        for &(repair_percent, ack_interval) in [(10, 0), (0, 50)].iter() {
+            let mut completed = 0;
+            let mut ticks = 0;
+            let mut packets = 0;
+            for seed in 0..RUNS {
+                let blocks = encoder.get_block_encoders().len() as u32;
+                let repair_per_block = source_packets as u32 * repair_percent / 100 / blocks;
+                let mut simulation = Simulation::new(
+                    EncoderSender::new(encoder.clone(), 10, repair_per_block, 2),
+                    DecoderReceiver::new(Decoder::new(encoder.get_config()), ack_interval),
+                    channel(bursty),
+                    channel(bursty),
+                    seed,
+                );
+                let report = simulation.run(MAX_TICKS);
+                if let Some(tick) = report.completed_at() {
+                    assert_eq!(simulation.receiver().result().unwrap(), &data[..]);
+                    completed += 1;
+                    ticks += tick;
+                }
+                packets += report.packets_sent();
+            }
+            println!(
+                "{} loss, {}: completed {}/{} runs, mean completion {:.0} ticks, {:.1}% overhead",
+                if bursty { "bursty" } else { "uniform" },
+                if ack_interval == 0 {
+                    "open loop"
+                } else {
+                    "acknowledged"
+                },
+                completed,
+                RUNS,
+                ticks as f64 / completed.max(1) as f64,
+                (packets as f64 / RUNS as f64 / source_packets as f64 - 1.0) * 100.0
+            );
+        }
+    }
+}
@@ -0,0 +1,67 @@
+use io_uring::IoUring;
+use rand::Rng;
+use raptorq::{Decoder, Encoder, EncodingPacket, RegisteredPacketBuffers};
+use std::net::UdpSocket;
+use std::os::unix::io::AsRawFd;
+
+// Number of packets in flight at once, one per registered buffer
+const BATCH: u16 = 16;
+
+fn main() {
+    // Generate some random data to send
+    let mut data: Vec<u8> = vec![0; 100_000];
+    for byte in data.iter_mut() {
+        *byte = rand::thread_rng().gen();
+    }
+    let encoder = Encoder::with_defaults(&data, 1400);
+    let symbol_size = encoder.get_config().symbol_size() as usize;
+
+    let receiver = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let sender = UdpSocket::bind("127.0.0.1:0").unwrap();
+    sender.connect(receiver.local_addr().unwrap()).unwrap();
+    receiver.set_nonblocking(true).unwrap();
+
+    let mut ring = IoUring::new(u32::from(BATCH)).expect("io_uring is unavailable");
+    let mut buffers = RegisteredPacketBuffers::register(&ring, BATCH, symbol_size + 4).unwrap();
+
+    // Send only repair packets, as if every source packet had been lost
+    let mut decoder = Decoder::new(encoder.get_config());
+    let mut datagram = vec![0; symbol_size + 4];
+    let mut sent = 0;
+    let mut result = None;
+    for block in encoder.get_block_encoders() {
+        let source_symbols = block.source_packets().len() as u32;
+        // Repair symbols follow the padding symbols
+        let first_repair = block.repair_packets(0, 1)[0]
+            .payload_id()
+            .encoding_symbol_id();
+        // A few more repair packets than source symbols, so that the block decodes with high
+        // probability
+        let end = first_repair + source_symbols + 4;
+        for batch_start in (first_repair..end).step_by(BATCH as usize) {
+            for index in 0..BATCH {
+                buffers
+                    .write_repair_packet(index, block, batch_start + u32::from(index))
+                    .unwrap();
+                unsafe {
+                    ring.submission()
+                        .push(&buffers.write_entry(index, sender.as_raw_fd()))
+                        .unwrap();
+                }
+            }
+            // The buffers are rewritten in the next batch, so wait until they have been sent
+            ring.submit_and_wait(BATCH as usize).unwrap();
+            for completion in ring.completion() {
+                assert!(completion.result() >= 0);
+                sent += 1;
+            }
+            while let Ok(length) = receiver.recv(&mut datagram) {
+                result = decoder.decode(EncodingPacket::deserialize(&datagram[..length]));
+            }
+        }
+    }
+    buffers.unregister().unwrap();
+
+    println!("Sent {} repair packets", sent);
+    assert_eq!(result.unwrap(), data);
+}
@@ -1,3 +1,5 @@
+use alloc::vec;
+use alloc::vec::Vec;
 use serde::{Deserialize, Serialize};
 
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, PartialOrd, Eq, Ord, Hash)]
@@ -6,7 +8,7 @@ pub struct ArrayMap<T> {
     elements: Vec<Option<T>>,
 }
 
-impl<T: std::clone::Clone> ArrayMap<T> {
+impl<T: Clone> ArrayMap<T> {
     pub fn new(start_key: usize, end_key: usize) -> ArrayMap<T> {
         ArrayMap {
             offset: start_key,
@@ -81,10 +83,6 @@ impl UsizeArrayMap {
     pub fn decrement(&mut self, key: usize) {
         self.elements[key - self.offset] -= 1;
     }
-
-    pub fn increment(&mut self, key: usize) {
-        self.elements[key - self.offset] += 1;
-    }
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, PartialOrd, Eq, Ord, Hash)]
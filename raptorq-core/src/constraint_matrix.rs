@@ -0,0 +1,321 @@
+use crate::gf256::gf256_matmul;
+use crate::matrix::OctetMatrix;
+use crate::octet::Octet;
+use crate::rng::rand;
+use crate::systematic_constants::extended_source_block_symbols;
+use crate::systematic_constants::num_hdpc_symbols;
+use crate::systematic_constants::num_intermediate_symbols;
+use crate::systematic_constants::num_ldpc_symbols;
+use crate::systematic_constants::num_lt_symbols;
+use crate::systematic_constants::num_pi_symbols;
+use crate::systematic_constants::{calculate_p1, systematic_index};
+use crate::tuple::intermediate_tuple;
+use alloc::vec;
+use alloc::vec::Vec;
+
+// Simulates Enc[] function to get indices of accessed intermediate symbols, as defined in section 5.3.5.3
+pub fn enc_indices(
+    source_tuple: (u32, u32, u32, u32, u32, u32),
+    lt_symbols: u32,
+    pi_symbols: u32,
+    p1: u32,
+) -> Vec<usize> {
+    let (d, _, _, d1, _, _) = source_tuple;
+    let mut indices = Vec::with_capacity((d + d1) as usize);
+    for_each_enc_index(source_tuple, lt_symbols, pi_symbols, p1, |i| {
+        indices.push(i)
+    });
+    indices
+}
+
+// Same as enc_indices(), but passes each index to `f` rather than allocating a Vec of them
+#[allow(clippy::many_single_char_names)]
+pub fn for_each_enc_index<F: FnMut(usize)>(
+    source_tuple: (u32, u32, u32, u32, u32, u32),
+    lt_symbols: u32,
+    pi_symbols: u32,
+    p1: u32,
+    mut f: F,
+) {
+    let w = lt_symbols;
+    let p = pi_symbols;
+    let (d, a, mut b, d1, a1, mut b1) = source_tuple;
+
+    assert!(d > 0);
+    assert!(1 <= a && a < w);
+    assert!(b < w);
+    assert!(d1 == 2 || d1 == 3);
+    assert!(1 <= a1 && a1 < p1);
+    assert!(b1 < p1);
+
+    f(b as usize);
+
+    for _ in 1..d {
+        b = (b + a) % w;
+        f(b as usize);
+    }
+
+    while b1 >= p {
+        b1 = (b1 + a1) % p1;
+    }
+
+    f((w + b1) as usize);
+
+    for _ in 1..d1 {
+        b1 = (b1 + a1) % p1;
+        while b1 >= p {
+            b1 = (b1 + a1) % p1;
+        }
+        f((w + b1) as usize);
+    }
+}
+
+// Sets the G_ENC rows, starting at first_row, for the given encoded symbol indices
+fn set_encoding_rows<T: OctetMatrix>(
+    matrix: &mut T,
+    first_row: usize,
+    source_block_symbols: u32,
+    encoded_symbol_indices: &[u32],
+) {
+    let kprime = extended_source_block_symbols(source_block_symbols);
+    let lt_symbols = num_lt_symbols(kprime);
+    let pi_symbols = num_pi_symbols(kprime);
+    let sys_index = systematic_index(kprime);
+    let p1 = calculate_p1(kprime);
+    for (row, &i) in encoded_symbol_indices.iter().enumerate() {
+        // row != i, because i is the ESI
+        let tuple = intermediate_tuple(i, lt_symbols, sys_index, p1);
+
+        for j in enc_indices(tuple, lt_symbols, pi_symbols, p1) {
+            matrix.set(row + first_row, j, Octet::one());
+        }
+    }
+}
+
+// Generates only the G_ENC rows for the given encoded symbol indices, for example to add symbols to
+// a decoding attempt which failed
+pub fn generate_encoding_rows<T: OctetMatrix>(
+    source_block_symbols: u32,
+    encoded_symbol_indices: &[u32],
+) -> T {
+    let intermediate_symbols = num_intermediate_symbols(source_block_symbols) as usize;
+    let mut matrix = T::new(encoded_symbol_indices.len(), intermediate_symbols, 0, 0, 0);
+    set_encoding_rows(&mut matrix, 0, source_block_symbols, encoded_symbol_indices);
+    matrix
+}
+
+// Returns, for each of the S LDPC rows of the constraint matrix, the columns which are one. All
+// other entries are zero. See section 5.3.3.3
+#[allow(non_snake_case)]
+pub fn ldpc_rows(source_block_symbols: u32) -> Vec<Vec<usize>> {
+    let S = num_ldpc_symbols(source_block_symbols) as usize;
+    let W = num_lt_symbols(source_block_symbols) as usize;
+    let B = W - S;
+    let P = num_pi_symbols(source_block_symbols) as usize;
+
+    let mut rows = vec![vec![]; S];
+    // G_LDPC,1
+    for i in 0..B {
+        let a = 1 + i / S;
+
+        let b = i % S;
+        rows[b].push(i);
+
+        let b = (b + a) % S;
+        rows[b].push(i);
+
+        let b = (b + a) % S;
+        rows[b].push(i);
+    }
+
+    for (i, row) in rows.iter_mut().enumerate() {
+        // I_S
+        row.push(i + B);
+        // G_LDPC,2
+        row.push((i % P) + W);
+        row.push(((i + 1) % P) + W);
+    }
+    rows
+}
+
+// Columns of the GAMMA matrix which are multiplied at a time by hdpc_rows()
+const HDPC_BLOCK_COLUMNS: usize = 256;
+
+// Returns rows of the lower triangular block of the GAMMA matrix which is `width` columns wide,
+// followed by a row which carries the contribution of the columns to its right into it
+fn gamma_block(width: usize) -> Vec<Vec<u8>> {
+    let mut rows = vec![vec![0; width]; width + 1];
+    for (j, row) in rows.iter_mut().enumerate() {
+        for (k, value) in row.iter_mut().enumerate().take(j + 1) {
+            // The spec says "alpha ^^ (i-j)". However, this clearly can overflow since alpha() is
+            // only defined up to input < 256. Since alpha() only has 255 unique values, we must
+            // take the input mod 255. Without this the constraint matrix ends up being singular
+            // for 1698 and 8837 source symbols.
+            *value = Octet::alpha((j - k) % 255).byte();
+        }
+    }
+    rows
+}
+
+// Returns, for each of the H HDPC rows of the constraint matrix, the entries of its first K' + S
+// columns, which are MT * GAMMA. The remaining columns are I_H. See section 5.3.3.3
+//
+// GAMMA is too large to materialize, so the product is computed in blocks of columns from right
+// to left, each of which is a dense multiply by a block of GAMMA. Every entry of a row of the
+// product is alpha times the entry to its right, plus the entry of MT, so the contribution of all
+// the columns to the right of a block only depends on the first column of the previous block.
+#[allow(non_snake_case)]
+pub fn hdpc_rows(source_block_symbols: u32) -> Vec<Vec<u8>> {
+    let Kprime = extended_source_block_symbols(source_block_symbols) as usize;
+    let S = num_ldpc_symbols(source_block_symbols) as usize;
+    let H = num_hdpc_symbols(source_block_symbols) as usize;
+    let width = Kprime + S;
+
+    // Generates the MT matrix
+    let mut mt: Vec<Vec<u8>> = vec![vec![0; width]; H];
+    #[allow(clippy::needless_range_loop)]
+    for j in 0..(width - 1) {
+        let rand6 = rand((j + 1) as u32, 6u32, H as u32) as usize;
+        let rand7 = rand((j + 1) as u32, 7u32, (H - 1) as u32) as usize;
+        mt[rand6][j] = 1;
+        mt[(rand6 + rand7 + 1) % H][j] = 1;
+    }
+    for (i, row) in mt.iter_mut().enumerate() {
+        row[width - 1] = Octet::alpha(i).byte();
+    }
+
+    // Multiply by the GAMMA matrix
+    let mut result = vec![vec![0; width]; H];
+    let full_block = gamma_block(HDPC_BLOCK_COLUMNS);
+    let mut end = width;
+    while end > 0 {
+        let start = end.saturating_sub(HDPC_BLOCK_COLUMNS);
+        let block = end - start;
+        let partial_block;
+        let gamma = if block == HDPC_BLOCK_COLUMNS {
+            &full_block
+        } else {
+            partial_block = gamma_block(block);
+            &partial_block
+        };
+        let operand: Vec<Vec<u8>> = (0..H)
+            .map(|i| {
+                let mut row = mt[i][start..end].to_vec();
+                row.push(if end < width { result[i][end] } else { 0 });
+                row
+            })
+            .collect();
+        for (i, row) in gf256_matmul(&operand, gamma).into_iter().enumerate() {
+            result[i][start..end].copy_from_slice(&row);
+        }
+        end = start;
+    }
+    result
+}
+
+// Returns the columns of the G_ENC row of the given ISI, which are one
+pub fn encoding_row(source_block_symbols: u32, isi: u32) -> Vec<usize> {
+    let kprime = extended_source_block_symbols(source_block_symbols);
+    let lt_symbols = num_lt_symbols(kprime);
+    let pi_symbols = num_pi_symbols(kprime);
+    let sys_index = systematic_index(kprime);
+    let p1 = calculate_p1(kprime);
+    let tuple = intermediate_tuple(isi, lt_symbols, sys_index, p1);
+    enc_indices(tuple, lt_symbols, pi_symbols, p1)
+}
+
+// See section 5.3.3.4.2
+#[allow(non_snake_case)]
+pub fn generate_constraint_matrix<T: OctetMatrix>(
+    source_block_symbols: u32,
+    encoded_symbol_indices: &[u32],
+) -> T {
+    let Kprime = extended_source_block_symbols(source_block_symbols) as usize;
+    let S = num_ldpc_symbols(source_block_symbols) as usize;
+    let H = num_hdpc_symbols(source_block_symbols) as usize;
+    let P = num_pi_symbols(source_block_symbols) as usize;
+    let L = num_intermediate_symbols(source_block_symbols) as usize;
+
+    assert!(S + H + encoded_symbol_indices.len() >= L);
+    let mut matrix = T::new(S + H + encoded_symbol_indices.len(), L, P, S, H);
+
+    // G_LDPC,1, I_S and G_LDPC,2
+    for (row, columns) in ldpc_rows(source_block_symbols).iter().enumerate() {
+        for &col in columns.iter() {
+            matrix.set(row, col, Octet::one());
+        }
+    }
+
+    // G_ENC
+    set_encoding_rows(
+        &mut matrix,
+        S + H,
+        source_block_symbols,
+        encoded_symbol_indices,
+    );
+
+    // G_HDPC
+    for (i, row) in hdpc_rows(source_block_symbols).iter().enumerate() {
+        for (j, &value) in row.iter().enumerate() {
+            if value != 0 {
+                matrix.set(i + S, j, Octet::new(value));
+            }
+        }
+    }
+
+    // I_H
+    for i in 0..H {
+        matrix.set(i + S as usize, i + (Kprime + S) as usize, Octet::one());
+    }
+
+    matrix
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::constraint_matrix::hdpc_rows;
+    use crate::octet::Octet;
+    use crate::octets::{add_assign, fused_addassign_mul_scalar};
+    use crate::rng::rand;
+    use crate::systematic_constants::{
+        extended_source_block_symbols, num_hdpc_symbols, num_ldpc_symbols,
+    };
+
+    #[test]
+    #[allow(non_snake_case)]
+    fn hdpc_rows_match_definition() {
+        for &symbols in [10, 257, 1698, 8837].iter() {
+            let Kprime = extended_source_block_symbols(symbols) as usize;
+            let S = num_ldpc_symbols(symbols) as usize;
+            let H = num_hdpc_symbols(symbols) as usize;
+            let rows = hdpc_rows(symbols);
+            assert_eq!(rows.len(), H);
+            // Multiply MT by GAMMA directly, one row of GAMMA at a time
+            for (i, row) in rows.iter().enumerate() {
+                let mut expected = vec![0; Kprime + S];
+                for j in 0..(Kprime + S) {
+                    let scalar = if j == Kprime + S - 1 {
+                        Octet::alpha(i)
+                    } else {
+                        let rand6 = rand((j + 1) as u32, 6u32, H as u32) as usize;
+                        let rand7 = rand((j + 1) as u32, 7u32, (H - 1) as u32) as usize;
+                        if i == rand6 || i == (rand6 + rand7 + 1) % H {
+                            Octet::one()
+                        } else {
+                            continue;
+                        }
+                    };
+                    let gamma: Vec<u8> = (0..=j)
+                        .map(|k| Octet::alpha((j - k) % 255).byte())
+                        .collect();
+                    if scalar == Octet::one() {
+                        add_assign(&mut expected[..=j], &gamma);
+                    } else {
+                        fused_addassign_mul_scalar(&mut expected[..=j], &gamma, &scalar);
+                    }
+                }
+                assert_eq!(row, &expected);
+            }
+        }
+    }
+}
@@ -0,0 +1,18 @@
+use serde::{Deserialize, Serialize};
+
+/// Failures which can be forced in the solver, so that applications can test how they handle a
+/// block which fails to decode, e.g. by requesting more symbols or the whole object again. While
+/// a failure is injected, every decoding attempt which needs the solver fails in that way. Blocks
+/// whose source symbols are all received are decoded without the solver, and are unaffected.
+///
+/// Only available with the `failure_injection` feature, which is intended for tests, and must not
+/// be enabled in production builds.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, PartialOrd, Eq, Ord, Hash)]
+pub enum InjectedFailure {
+    /// No pivot row is found in the first phase, as if too few independent symbols were received
+    PivotSelection,
+    /// U_lower is singular in the second phase
+    SingularMatrix,
+    /// Allocating the solver's working state fails, so no attempt is made
+    Allocation,
+}
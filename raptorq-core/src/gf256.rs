@@ -0,0 +1,57 @@
+use crate::matrix::mul_add_tiled;
+use crate::octet::Octet;
+use alloc::vec;
+use alloc::vec::Vec;
+
+// Returns the product of the dense matrices `a` and `b` over GF(256), each given as a list of rows.
+// Each nonzero entry of `a` adds a multiple of a row of `b` to a row of the result, with the SIMD
+// row kernels of the octets module, and the rows of `b` are processed in tiles which stay in cache.
+// Zero entries of `a` cost nothing, so this is also efficient when `a` is sparse.
+pub fn gf256_matmul(a: &[Vec<u8>], b: &[Vec<u8>]) -> Vec<Vec<u8>> {
+    let width = b.first().map_or(0, |row| row.len());
+    assert!(b.iter().all(|row| row.len() == width));
+    let coefficients: Vec<Vec<(usize, Octet)>> = a
+        .iter()
+        .map(|row| {
+            assert_eq!(row.len(), b.len());
+            row.iter()
+                .enumerate()
+                .filter(|(_, value)| **value != 0)
+                .map(|(i, value)| (i, Octet::new(*value)))
+                .collect()
+        })
+        .collect();
+    let mut result = vec![vec![0; width]; a.len()];
+    mul_add_tiled(&mut result, |i| &b[i], b.len(), &coefficients);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::gf256::gf256_matmul;
+    use crate::octet::Octet;
+    use rand::Rng;
+
+    #[test]
+    fn matmul() {
+        let (m, n, p) = (17, 300, 9000);
+        let random = |height: usize, width: usize| -> Vec<Vec<u8>> {
+            (0..height)
+                .map(|_| (0..width).map(|_| rand::thread_rng().gen()).collect())
+                .collect()
+        };
+        let a = random(m, n);
+        let b = random(n, p);
+        let result = gf256_matmul(&a, &b);
+        assert_eq!(result.len(), m);
+        for i in 0..m {
+            for j in (0..p).step_by(97) {
+                let mut expected = Octet::zero();
+                for k in 0..n {
+                    expected += &(Octet::new(a[i][k]) * Octet::new(b[k][j]));
+                }
+                assert_eq!(result[i][j], expected.byte());
+            }
+        }
+    }
+}
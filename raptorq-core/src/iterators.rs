@@ -1,5 +1,6 @@
 use crate::octet::Octet;
 use crate::sparse_vec::{SparseOctetVec, SparseValuelessVec};
+use alloc::vec::Vec;
 use serde::{Deserialize, Serialize};
 
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, PartialOrd, Eq, Ord, Hash)]
@@ -31,6 +32,8 @@ pub struct BorrowedKeyIter<'a> {
     sparse: bool,
     dense_index: usize,
     dense_end: usize,
+    // Rows from this physical index on are dense, and are not read from the index
+    physical_dense_start: usize,
     sparse_rows: Option<&'a SparseValuelessVec>,
     sparse_start_row: usize,
     sparse_end_row: usize,
@@ -51,6 +54,7 @@ impl<'a> BorrowedKeyIter<'a> {
             sparse: true,
             dense_index: physical_dense_start,
             dense_end: physical_dense_end,
+            physical_dense_start,
             sparse_rows: Some(sparse_rows),
             sparse_start_row,
             sparse_end_row,
@@ -64,6 +68,7 @@ impl<'a> BorrowedKeyIter<'a> {
             sparse: false,
             dense_index,
             dense_end,
+            physical_dense_start: dense_index,
             sparse_rows: None,
             sparse_start_row: 0,
             sparse_end_row: 0,
@@ -72,11 +77,13 @@ impl<'a> BorrowedKeyIter<'a> {
         }
     }
 
+    #[allow(clippy::should_implement_trait)]
     pub fn clone(&self) -> KeyIter {
         // Convert to logical indices, since ClonedOctetIter doesn't handle physical
         let sparse_rows = self.sparse_rows.map(|x| {
             let mut rows: Vec<usize> = x
                 .keys()
+                .filter(|physical_row| **physical_row < self.physical_dense_start)
                 .map(|physical_row| self.physical_row_to_logical.unwrap()[*physical_row])
                 .filter(|logical_row| {
                     *logical_row >= self.sparse_start_row && *logical_row < self.sparse_end_row
@@ -110,6 +117,11 @@ impl<'a> Iterator for BorrowedKeyIter<'a> {
             while self.sparse_index < elements.len() {
                 let physical_row = elements.get_by_raw_index(self.sparse_index);
                 self.sparse_index += 1;
+                // The index may still contain rows which have been converted to dense storage,
+                // which are returned below
+                if *physical_row >= self.physical_dense_start {
+                    continue;
+                }
                 let logical_row = self.physical_row_to_logical.unwrap()[*physical_row];
                 if logical_row >= self.sparse_start_row && logical_row < self.sparse_end_row {
                     return Some(logical_row);
@@ -180,6 +192,11 @@ pub struct OctetIter<'a> {
     sparse_elements: Option<&'a SparseOctetVec>,
     sparse_index: usize,
     sparse_physical_col_to_logical: Option<&'a [usize]>,
+    // Trailing dense columns, which are stored right-most first. See SparseOctetMatrix
+    dense_columns: Option<&'a [u8]>,
+    dense_columns_index: usize,
+    dense_columns_end: usize,
+    width: usize,
 }
 
 impl<'a> OctetIter<'a> {
@@ -198,6 +215,10 @@ impl<'a> OctetIter<'a> {
             sparse_elements: Some(sparse_elements),
             sparse_index: 0,
             sparse_physical_col_to_logical: Some(sparse_physical_col_to_logical),
+            dense_columns: None,
+            dense_columns_index: 0,
+            dense_columns_end: 0,
+            width: 0,
         }
     }
 
@@ -217,10 +238,41 @@ impl<'a> OctetIter<'a> {
             sparse_elements: None,
             sparse_index: 0,
             sparse_physical_col_to_logical: None,
+            dense_columns: None,
+            dense_columns_index: 0,
+            dense_columns_end: 0,
+            width: 0,
         }
     }
 
+    // Continues the iteration over the logical columns [start_col, end_col), which are stored
+    // right-most first in dense_columns
+    pub fn with_dense_columns(
+        mut self,
+        start_col: usize,
+        end_col: usize,
+        dense_columns: &'a [u8],
+        width: usize,
+    ) -> OctetIter<'a> {
+        self.dense_columns = Some(dense_columns);
+        self.dense_columns_index = start_col;
+        self.dense_columns_end = end_col;
+        self.width = width;
+        self
+    }
+
+    #[allow(clippy::should_implement_trait)]
     pub fn clone(&self) -> ClonedOctetIter {
+        if self.dense_columns.is_some() {
+            return ClonedOctetIter {
+                sparse: true,
+                end_col: self.end_col,
+                dense_elements: None,
+                dense_index: 0,
+                sparse_elements: Some(Clone::clone(self).collect()),
+                sparse_index: 0,
+            };
+        }
         // Convert to logical indices, since ClonedOctetIter doesn't handle physical
         let sparse_elements = self.sparse_elements.map(|x| {
             x.keys_values()
@@ -246,10 +298,18 @@ impl<'a> OctetIter<'a> {
     }
 }
 
-impl<'a> Iterator for OctetIter<'a> {
-    type Item = (usize, Octet);
+impl<'a> OctetIter<'a> {
+    fn next_dense_column(&mut self) -> Option<(usize, Octet)> {
+        let dense_columns = self.dense_columns?;
+        if self.dense_columns_index >= self.dense_columns_end {
+            return None;
+        }
+        let col = self.dense_columns_index;
+        self.dense_columns_index += 1;
+        return Some((col, Octet::new(dense_columns[self.width - col - 1])));
+    }
 
-    fn next(&mut self) -> Option<Self::Item> {
+    fn next_row_storage(&mut self) -> Option<(usize, Octet)> {
         if self.sparse {
             let elements = self.sparse_elements.unwrap();
             // Need to iterate over the whole array, since they're not sorted by logical col
@@ -278,3 +338,11 @@ impl<'a> Iterator for OctetIter<'a> {
         }
     }
 }
+
+impl<'a> Iterator for OctetIter<'a> {
+    type Item = (usize, Octet);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_row_storage().or_else(|| self.next_dense_column())
+    }
+}
@@ -0,0 +1,49 @@
+//! The arithmetic at the core of RaptorQ (RFC6330): symbols over GF(256), the systematic
+//! constants and tuple generator, the constraint matrix, and the solver for the intermediate
+//! symbols. This crate is `no_std` (it requires `alloc`) when its default features are disabled.
+//!
+//! Most users want the `raptorq` crate, which builds the encoder and decoder on top of this, and
+//! re-exports what is needed from it.
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+#![allow(clippy::needless_return, clippy::unreadable_literal)]
+
+extern crate alloc;
+
+// The modules used by raptorq are public, so that it can build on them, but are not part of the
+// API of this crate, and may change in any release. The types which raptorq exposes in its API are
+// re-exported below
+mod arraymap;
+#[doc(hidden)]
+pub mod constraint_matrix;
+#[cfg(feature = "failure_injection")]
+#[doc(hidden)]
+pub mod failure_injection;
+mod gf256;
+mod iterators;
+#[doc(hidden)]
+pub mod matrix;
+#[doc(hidden)]
+pub mod octet;
+#[doc(hidden)]
+pub mod octets;
+#[doc(hidden)]
+pub mod pi_solver;
+#[doc(hidden)]
+pub mod rank_tracker;
+#[doc(hidden)]
+pub mod rng;
+#[doc(hidden)]
+pub mod sparse_matrix;
+mod sparse_vec;
+#[doc(hidden)]
+pub mod symbol;
+#[doc(hidden)]
+pub mod systematic_constants;
+#[doc(hidden)]
+pub mod tuple;
+#[doc(hidden)]
+pub mod util;
+
+#[cfg(feature = "failure_injection")]
+pub use crate::failure_injection::InjectedFailure;
+pub use crate::pi_solver::{IntegrityCheck, SolverLimit, SolverPermutations};
@@ -0,0 +1,676 @@
+use crate::iterators::{BorrowedKeyIter, OctetIter};
+use crate::octet::Octet;
+use crate::octets::fused_addassign_mul_scalar;
+use crate::octets::{add_assign, count_ones_and_nonzeros, mulassign_scalar};
+use crate::util::get_both_indices;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::cmp::{max, min};
+use serde::{Deserialize, Serialize};
+
+// Bytes of each row, and of all the source rows together, in a tile of mul_add_tiled(). A tile of
+// the sources fits in the L2 cache of most processors
+const TILE_COLUMNS: usize = 4096;
+const TILE_BYTES: usize = 128 * 1024;
+
+// Adds `scalar * source(i)` to each row of `dest`, for each `(i, scalar)` in the coefficients of
+// that row, which must be in increasing order of `i`, with `i < sources`. Rather than streaming
+// every source row from memory for each destination row, this works on tiles of the columns and
+// of the source rows, each of which stays in cache while it is added into every destination row.
+pub(crate) fn mul_add_tiled<'a, F: Fn(usize) -> &'a [u8]>(
+    dest: &mut [Vec<u8>],
+    source: F,
+    sources: usize,
+    coefficients: &[Vec<(usize, Octet)>],
+) {
+    assert_eq!(dest.len(), coefficients.len());
+    let width = dest.first().map_or(0, |row| row.len());
+    // Position in the coefficients of each row, of the first one in the current tile
+    let mut cursors = vec![0; dest.len()];
+    for start_col in (0..width).step_by(TILE_COLUMNS) {
+        let end_col = min(start_col + TILE_COLUMNS, width);
+        let tile_rows = max(1, TILE_BYTES / (end_col - start_col));
+        for cursor in cursors.iter_mut() {
+            *cursor = 0;
+        }
+        for start_row in (0..sources).step_by(tile_rows) {
+            let end_row = start_row + tile_rows;
+            for (row, dest_row) in dest.iter_mut().enumerate() {
+                let row_coefficients = &coefficients[row];
+                while let Some((i, scalar)) = row_coefficients.get(cursors[row]) {
+                    if *i >= end_row {
+                        break;
+                    }
+                    let dest_tile = &mut dest_row[start_col..end_col];
+                    let source_tile = &source(*i)[start_col..end_col];
+                    if *scalar == Octet::one() {
+                        add_assign(dest_tile, source_tile);
+                    } else {
+                        fused_addassign_mul_scalar(dest_tile, source_tile, scalar);
+                    }
+                    cursors[row] += 1;
+                }
+            }
+        }
+    }
+}
+
+pub trait OctetMatrix: Clone {
+    fn new(
+        height: usize,
+        width: usize,
+        trailing_dense_column_hint: usize,
+        start_dense_row_hint: usize,
+        num_dense_rows_hint: usize,
+    ) -> Self;
+
+    fn set(&mut self, i: usize, j: usize, value: Octet);
+
+    fn height(&self) -> usize;
+
+    fn width(&self) -> usize;
+
+    fn count_ones_and_nonzeros(
+        &self,
+        row: usize,
+        start_col: usize,
+        end_col: usize,
+    ) -> (usize, usize);
+
+    fn mul_assign_row(&mut self, row: usize, value: &Octet);
+
+    // Once "impl Trait" is supported in traits, it would be better to return "impl Iterator<...>"
+    fn get_row_iter(&self, row: usize, start_col: usize, end_col: usize) -> OctetIter;
+
+    // An iterator over rows for the given col, that may have non-zero values
+    fn get_col_index_iter(&self, col: usize, start_row: usize, end_row: usize) -> BorrowedKeyIter;
+
+    fn get(&self, i: usize, j: usize) -> Octet;
+
+    fn swap_rows(&mut self, i: usize, j: usize);
+
+    // start_row_hint indicates that all preceding rows don't need to be swapped, because they have
+    // identical values
+    fn swap_columns(&mut self, i: usize, j: usize, start_row_hint: usize);
+
+    // Reorders the rows so that row i is the previous row permutation[i], in one pass rather than
+    // a swap at a time
+    fn apply_row_permutation(&mut self, permutation: &[usize]);
+
+    // Reorders the columns so that column j is the previous column permutation[j], in one pass
+    // rather than a swap at a time
+    fn apply_column_permutation(&mut self, permutation: &[usize]);
+
+    fn enable_column_acccess_acceleration(&mut self);
+
+    // Same as enable_column_acccess_acceleration(), but only the columns in [start_col, end_col)
+    // are accelerated, which is cheaper to set up. get_col_index_iter() and
+    // hint_column_dense_and_frozen() may only be called on the columns which were in that range,
+    // wherever they have since been swapped to
+    fn enable_partial_column_acccess_acceleration(&mut self, start_col: usize, end_col: usize) {
+        let _ = (start_col, end_col);
+        self.enable_column_acccess_acceleration();
+    }
+
+    // After calling this method swap_columns() and other column oriented methods, may be much slower
+    fn disable_column_acccess_acceleration(&mut self);
+
+    // Hints that the dense rows should be compacted because they likely have a large fraction zeros
+    fn hint_compact_dense_rows(&mut self);
+
+    // Hints that column i will not be swapped again, and is likely to become dense'ish
+    fn hint_column_dense_and_frozen(&mut self, i: usize);
+
+    // other must be a rows x rows matrix
+    // sets self[0..rows][..] = X * self[0..rows][..]
+    fn mul_assign_submatrix(&mut self, other: &Self, rows: usize);
+
+    fn fma_rows(&mut self, dest: usize, multiplicand: usize, scalar: &Octet);
+
+    fn resize(&mut self, new_height: usize, new_width: usize);
+
+    // Releases memory which was reserved for columns becoming dense, once no more are expected
+    // to. This is also done by resize()
+    fn shrink_dense_storage(&mut self);
+
+    // Appends the given number of zero rows to the bottom of the matrix
+    fn add_rows(&mut self, rows: usize);
+
+    // Returns, for each logical row and each logical column, the index at which it is stored.
+    // Intended for diagnostics, rather than the decoding path
+    fn storage_permutations(&self) -> (Vec<usize>, Vec<usize>) {
+        ((0..self.height()).collect(), (0..self.width()).collect())
+    }
+
+    // Checks that the internal storage is consistent, such as indexes with the values they index,
+    // and panics if it is not. Called by the solver at IntegrityCheck::Full
+    fn verify(&self) {}
+
+    // Returns the transpose, without any of the storage hints of this matrix. Intended for
+    // diagnostics, rather than the decoding path
+    #[cfg(any(test, feature = "benchmarking"))]
+    fn transpose(&self) -> Self {
+        let mut result = Self::new(self.width(), self.height(), 0, 0, 0);
+        for i in 0..self.height() {
+            for (j, value) in self.get_row_iter(i, 0, self.width()) {
+                if value != Octet::zero() {
+                    result.set(j, i, value);
+                }
+            }
+        }
+        result
+    }
+
+    // Returns the rank, computed by Gaussian elimination of a dense copy. Intended for
+    // diagnostics, rather than the decoding path
+    fn rank(&self) -> usize {
+        let width = self.width();
+        let mut rows: Vec<Vec<u8>> = (0..self.height())
+            .map(|i| {
+                let mut row = vec![0; width];
+                for (j, value) in self.get_row_iter(i, 0, width) {
+                    row[j] = value.byte();
+                }
+                row
+            })
+            .collect();
+        let mut rank = 0;
+        for col in 0..width {
+            let pivot = match (rank..rows.len()).find(|&row| rows[row][col] != 0) {
+                Some(pivot) => pivot,
+                None => continue,
+            };
+            rows.swap(rank, pivot);
+            let inverse = &Octet::one() / &Octet::new(rows[rank][col]);
+            mulassign_scalar(&mut rows[rank], &inverse);
+            let (pivot_rows, other_rows) = rows.split_at_mut(rank + 1);
+            let pivot_row = &pivot_rows[rank];
+            for row in other_rows.iter_mut() {
+                let scalar = Octet::new(row[col]);
+                if scalar == Octet::one() {
+                    add_assign(row, pivot_row);
+                } else if scalar != Octet::zero() {
+                    fused_addassign_mul_scalar(row, pivot_row, &scalar);
+                }
+            }
+            rank += 1;
+        }
+        rank
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, PartialOrd, Eq, Ord, Serialize, Deserialize, Hash)]
+pub struct DenseOctetMatrix {
+    height: usize,
+    width: usize,
+    elements: Vec<Vec<u8>>,
+}
+
+impl OctetMatrix for DenseOctetMatrix {
+    fn new(height: usize, width: usize, _: usize, _: usize, _: usize) -> DenseOctetMatrix {
+        let mut elements: Vec<Vec<u8>> = Vec::with_capacity(height);
+        for _ in 0..height {
+            elements.push(vec![0; width]);
+        }
+        DenseOctetMatrix {
+            height,
+            width,
+            elements,
+        }
+    }
+
+    fn set(&mut self, i: usize, j: usize, value: Octet) {
+        self.elements[i][j] = value.byte();
+    }
+
+    fn height(&self) -> usize {
+        self.height
+    }
+
+    fn width(&self) -> usize {
+        self.width
+    }
+
+    fn count_ones_and_nonzeros(
+        &self,
+        row: usize,
+        start_col: usize,
+        end_col: usize,
+    ) -> (usize, usize) {
+        count_ones_and_nonzeros(&self.elements[row][start_col..end_col])
+    }
+
+    fn mul_assign_row(&mut self, row: usize, value: &Octet) {
+        mulassign_scalar(&mut self.elements[row], value);
+    }
+
+    fn get_row_iter(&self, row: usize, start_col: usize, end_col: usize) -> OctetIter {
+        OctetIter::new_dense(start_col, end_col, &self.elements[row], start_col)
+    }
+
+    fn get_col_index_iter(&self, _: usize, start_row: usize, end_row: usize) -> BorrowedKeyIter {
+        BorrowedKeyIter::new_dense(start_row, end_row)
+    }
+
+    fn get(&self, i: usize, j: usize) -> Octet {
+        Octet::new(self.elements[i][j])
+    }
+
+    fn swap_rows(&mut self, i: usize, j: usize) {
+        self.elements.swap(i, j);
+    }
+
+    fn swap_columns(&mut self, i: usize, j: usize, start_row_hint: usize) {
+        for row in start_row_hint..self.elements.len() {
+            self.elements[row].swap(i, j);
+        }
+    }
+
+    fn apply_row_permutation(&mut self, permutation: &[usize]) {
+        assert_eq!(permutation.len(), self.height);
+        let mut rows: Vec<Option<Vec<u8>>> = self.elements.drain(..).map(Some).collect();
+        self.elements = permutation
+            .iter()
+            .map(|&i| rows[i].take().expect("Not a permutation"))
+            .collect();
+    }
+
+    fn apply_column_permutation(&mut self, permutation: &[usize]) {
+        assert_eq!(permutation.len(), self.width);
+        let mut temp = vec![0; self.width];
+        for row in self.elements.iter_mut() {
+            for (value, &j) in temp.iter_mut().zip(permutation.iter()) {
+                *value = row[j];
+            }
+            row.copy_from_slice(&temp);
+        }
+    }
+
+    fn enable_column_acccess_acceleration(&mut self) {
+        // No-op
+    }
+
+    fn disable_column_acccess_acceleration(&mut self) {
+        // No-op
+    }
+
+    fn hint_compact_dense_rows(&mut self) {
+        // No-op
+    }
+
+    fn hint_column_dense_and_frozen(&mut self, _: usize) {
+        // No-op
+    }
+
+    // other must be a rows x rows matrix
+    // sets self[0..rows][..] = X * self[0..rows][..]
+    fn mul_assign_submatrix(&mut self, other: &DenseOctetMatrix, rows: usize) {
+        assert_eq!(rows, other.height());
+        assert_eq!(rows, other.width());
+        assert!(rows <= self.height());
+        let coefficients: Vec<Vec<(usize, Octet)>> = (0..rows)
+            .map(|row| {
+                (0..rows)
+                    .map(|i| (i, other.get(row, i)))
+                    .filter(|(_, scalar)| *scalar != Octet::zero())
+                    .collect()
+            })
+            .collect();
+        let mut temp = vec![vec![0; self.width]; rows];
+        let elements = &self.elements;
+        mul_add_tiled(&mut temp, |i| &elements[i], rows, &coefficients);
+        for row in (0..rows).rev() {
+            self.elements[row] = temp.pop().unwrap();
+        }
+    }
+
+    fn fma_rows(&mut self, dest: usize, multiplicand: usize, scalar: &Octet) {
+        assert_ne!(dest, multiplicand);
+        let (dest_row, temp_row) = get_both_indices(&mut self.elements, dest, multiplicand);
+
+        if *scalar == Octet::one() {
+            add_assign(dest_row, temp_row);
+        } else {
+            fused_addassign_mul_scalar(dest_row, temp_row, scalar);
+        }
+    }
+
+    fn resize(&mut self, new_height: usize, new_width: usize) {
+        assert!(new_height <= self.height);
+        assert!(new_width <= self.width);
+        self.elements.truncate(new_height);
+        for row in 0..self.elements.len() {
+            self.elements[row].truncate(new_width);
+        }
+        self.height = new_height;
+        self.width = new_width;
+    }
+
+    fn shrink_dense_storage(&mut self) {
+        // Every column is stored densely, so nothing is reserved
+    }
+
+    fn add_rows(&mut self, rows: usize) {
+        for _ in 0..rows {
+            self.elements.push(vec![0; self.width]);
+        }
+        self.height += rows;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::Rng;
+
+    use crate::matrix::{DenseOctetMatrix, OctetMatrix, TILE_COLUMNS};
+    use crate::octet::Octet;
+    use crate::octets::{add_assign, fused_addassign_mul_scalar};
+    use crate::sparse_matrix::SparseOctetMatrix;
+
+    fn dense_identity(size: usize) -> DenseOctetMatrix {
+        let mut result = DenseOctetMatrix::new(size, size, 0, 0, 0);
+        for i in 0..size {
+            result.set(i, i, Octet::one());
+        }
+        result
+    }
+
+    fn sparse_identity(size: usize, dense_rows: usize) -> SparseOctetMatrix {
+        let mut result = SparseOctetMatrix::new(size, size, 0, 0, dense_rows);
+        for i in 0..size {
+            result.set(i, i, Octet::one());
+        }
+        result
+    }
+
+    fn rand_dense_and_sparse(
+        size: usize,
+        dense_rows: usize,
+    ) -> (DenseOctetMatrix, SparseOctetMatrix) {
+        let mut dense = DenseOctetMatrix::new(size, size, 0, 0, 0);
+        let mut sparse = SparseOctetMatrix::new(size, size, 1, 0, dense_rows);
+        // Generate 50% filled random matrices
+        for _ in 0..(size * size / 2) {
+            let i = rand::thread_rng().gen_range(0, size);
+            let j = rand::thread_rng().gen_range(0, size);
+            let value = rand::thread_rng().gen();
+            dense.set(i, j, Octet::new(value));
+            sparse.set(i, j, Octet::new(value));
+        }
+
+        return (dense, sparse);
+    }
+
+    fn assert_matrices_eq<T: OctetMatrix, U: OctetMatrix>(matrix1: &T, matrix2: &U) {
+        assert_eq!(matrix1.height(), matrix2.height());
+        assert_eq!(matrix1.width(), matrix2.width());
+        for i in 0..matrix1.height() {
+            for j in 0..matrix1.width() {
+                assert_eq!(
+                    matrix1.get(i, j),
+                    matrix2.get(i, j),
+                    "Matrices are not equal at row={} col={}",
+                    i,
+                    j
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn transpose() {
+        let (dense, sparse) = rand_dense_and_sparse(8, 2);
+        let dense_transpose = dense.transpose();
+        let sparse_transpose = sparse.transpose();
+        assert_matrices_eq(&dense_transpose, &sparse_transpose);
+        for i in 0..8 {
+            for j in 0..8 {
+                assert_eq!(dense.get(i, j), dense_transpose.get(j, i));
+            }
+        }
+        assert_matrices_eq(&dense, &dense_transpose.transpose());
+    }
+
+    #[test]
+    fn rank() {
+        assert_eq!(8, dense_identity(8).rank());
+        assert_eq!(8, sparse_identity(8, 3).rank());
+
+        let (mut dense, mut sparse) = rand_dense_and_sparse(8, 2);
+        assert_eq!(dense.rank(), sparse.rank());
+        assert_eq!(dense.rank(), dense.transpose().rank());
+
+        // Make row 7 a linear combination of rows 0 and 1
+        for j in 0..8 {
+            let value = &(&dense.get(0, j) * &Octet::new(3)) + &dense.get(1, j);
+            dense.set(7, j, value.clone());
+            sparse.set(7, j, value);
+        }
+        assert!(dense.rank() < 8);
+        assert_eq!(dense.rank(), sparse.rank());
+
+        let zero = DenseOctetMatrix::new(4, 6, 0, 0, 0);
+        assert_eq!(0, zero.rank());
+    }
+
+    #[test]
+    fn swap_rows() {
+        // rand_dense_and_sparse uses set(), so just check that it works
+        let (mut dense, mut sparse) = rand_dense_and_sparse(8, 3);
+        dense.swap_rows(0, 4);
+        dense.swap_rows(1, 6);
+        dense.swap_rows(1, 7);
+        sparse.swap_rows(0, 4);
+        sparse.swap_rows(1, 6);
+        sparse.swap_rows(1, 7);
+        assert_matrices_eq(&dense, &sparse);
+    }
+
+    #[test]
+    fn apply_permutations() {
+        let (mut dense, mut sparse) = rand_dense_and_sparse(8, 3);
+        let original = dense.clone();
+        // The last column of the sparse matrix is dense, and stays in place
+        let rows = [3, 0, 7, 1, 6, 2, 4, 5];
+        let columns = [6, 2, 0, 5, 1, 3, 4, 7];
+        dense.apply_row_permutation(&rows);
+        dense.apply_column_permutation(&columns);
+        sparse.apply_row_permutation(&rows);
+        sparse.apply_column_permutation(&columns);
+        assert_matrices_eq(&dense, &sparse);
+        for (i, &row) in rows.iter().enumerate() {
+            for (j, &col) in columns.iter().enumerate() {
+                assert_eq!(dense.get(i, j), original.get(row, col));
+            }
+        }
+    }
+
+    #[test]
+    fn swap_columns() {
+        // rand_dense_and_sparse uses set(), so just check that it works
+        let (mut dense, mut sparse) = rand_dense_and_sparse(8, 3);
+        dense.swap_columns(0, 4, 0);
+        dense.swap_columns(1, 6, 0);
+        dense.swap_columns(1, 1, 0);
+        sparse.swap_columns(0, 4, 0);
+        sparse.swap_columns(1, 6, 0);
+        sparse.swap_columns(1, 1, 0);
+        assert_matrices_eq(&dense, &sparse);
+    }
+
+    #[test]
+    fn count_ones_and_nonzeros() {
+        // rand_dense_and_sparse uses set(), so just check that it works
+        let (dense, sparse) = rand_dense_and_sparse(8, 3);
+        assert_eq!(
+            dense.count_ones_and_nonzeros(0, 0, 5),
+            sparse.count_ones_and_nonzeros(0, 0, 5)
+        );
+        assert_eq!(
+            dense.count_ones_and_nonzeros(2, 2, 6),
+            sparse.count_ones_and_nonzeros(2, 2, 6)
+        );
+        assert_eq!(
+            dense.count_ones_and_nonzeros(3, 1, 2),
+            sparse.count_ones_and_nonzeros(3, 1, 2)
+        );
+    }
+
+    #[test]
+    fn count_ones_and_nonzeros_dense_columns() {
+        // The last column is stored as a trailing dense column in the sparse matrix
+        let (dense, sparse) = rand_dense_and_sparse(8, 3);
+        for row in 0..8 {
+            assert_eq!(
+                dense.count_ones_and_nonzeros(row, 0, 8),
+                sparse.count_ones_and_nonzeros(row, 0, 8)
+            );
+            assert_eq!(
+                dense.count_ones_and_nonzeros(row, 7, 8),
+                sparse.count_ones_and_nonzeros(row, 7, 8)
+            );
+        }
+    }
+
+    #[test]
+    fn row_iter() {
+        fn nonzeros<T: Iterator<Item = (usize, Octet)>>(iter: T) -> Vec<(usize, Octet)> {
+            let mut elements: Vec<(usize, Octet)> =
+                iter.filter(|(_, value)| *value != Octet::zero()).collect();
+            elements.sort();
+            elements
+        }
+
+        // The last column is stored as a trailing dense column in the sparse matrix
+        let (dense, sparse) = rand_dense_and_sparse(8, 3);
+        for row in 0..8 {
+            for &(start_col, end_col) in [(0, 8), (2, 6), (5, 8), (7, 8)].iter() {
+                let expected = nonzeros(dense.get_row_iter(row, start_col, end_col));
+                assert_eq!(
+                    nonzeros(sparse.get_row_iter(row, start_col, end_col)),
+                    expected
+                );
+                assert_eq!(
+                    nonzeros(sparse.get_row_iter(row, start_col, end_col).clone()),
+                    expected
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn mul_assign_row() {
+        // rand_dense_and_sparse uses set(), so just check that it works
+        let (mut dense, mut sparse) = rand_dense_and_sparse(8, 3);
+        dense.mul_assign_row(0, &Octet::new(5));
+        dense.mul_assign_row(2, &Octet::one());
+        dense.mul_assign_row(7, &Octet::new(66));
+        sparse.mul_assign_row(0, &Octet::new(5));
+        sparse.mul_assign_row(2, &Octet::one());
+        sparse.mul_assign_row(7, &Octet::new(66));
+        assert_matrices_eq(&dense, &sparse);
+    }
+
+    #[test]
+    fn mul_assign_submatrix() {
+        // rand_dense_and_sparse uses set(), so just check that it works
+        let (mut dense, mut sparse) = rand_dense_and_sparse(8, 0);
+        let original = dense.clone();
+
+        let identity = dense_identity(5);
+        dense.mul_assign_submatrix(&identity, 5);
+        assert_matrices_eq(&dense, &original);
+
+        let identity = dense_identity(8);
+        dense.mul_assign_submatrix(&identity, 8);
+        assert_matrices_eq(&dense, &original);
+
+        let identity = sparse_identity(5, 0);
+        sparse.mul_assign_submatrix(&identity, 5);
+        assert_matrices_eq(&sparse, &original);
+
+        let identity = sparse_identity(8, 0);
+        sparse.mul_assign_submatrix(&identity, 8);
+        assert_matrices_eq(&sparse, &original);
+    }
+
+    #[test]
+    fn mul_add_tiled() {
+        // Several tiles in each dimension
+        let rows = 40;
+        let width = TILE_COLUMNS * 2 + 100;
+        let sources: Vec<Vec<u8>> = (0..rows)
+            .map(|_| (0..width).map(|_| rand::thread_rng().gen()).collect())
+            .collect();
+        let coefficients: Vec<Vec<(usize, Octet)>> = (0..rows)
+            .map(|_| {
+                (0..rows)
+                    .map(|i| (i, Octet::new(rand::thread_rng().gen_range(0, 4))))
+                    .filter(|(_, scalar)| *scalar != Octet::zero())
+                    .collect()
+            })
+            .collect();
+        let mut tiled = vec![vec![0; width]; rows];
+        super::mul_add_tiled(&mut tiled, |i| &sources[i], rows, &coefficients);
+        for (row, row_coefficients) in coefficients.iter().enumerate() {
+            let mut expected = vec![0; width];
+            for (i, scalar) in row_coefficients.iter() {
+                if *scalar == Octet::one() {
+                    add_assign(&mut expected, &sources[*i]);
+                } else {
+                    fused_addassign_mul_scalar(&mut expected, &sources[*i], scalar);
+                }
+            }
+            assert_eq!(tiled[row], expected);
+        }
+    }
+
+    #[test]
+    fn fma_rows() {
+        // rand_dense_and_sparse uses set(), so just check that it works
+        let (mut dense, mut sparse) = rand_dense_and_sparse(8, 1);
+        dense.fma_rows(0, 1, &Octet::new(5));
+        dense.fma_rows(0, 2, &Octet::new(55));
+        dense.fma_rows(2, 1, &Octet::one());
+        sparse.fma_rows(0, 1, &Octet::new(5));
+        sparse.fma_rows(0, 2, &Octet::new(55));
+        sparse.fma_rows(2, 1, &Octet::one());
+        assert_matrices_eq(&dense, &sparse);
+    }
+
+    #[test]
+    fn resize() {
+        // rand_dense_and_sparse uses set(), so just check that it works
+        let (mut dense, mut sparse) = rand_dense_and_sparse(8, 0);
+        dense.disable_column_acccess_acceleration();
+        sparse.disable_column_acccess_acceleration();
+        dense.resize(5, 5);
+        sparse.resize(5, 5);
+        assert_matrices_eq(&dense, &sparse);
+    }
+
+    #[test]
+    fn add_rows() {
+        let (mut dense, mut sparse) = rand_dense_and_sparse(8, 3);
+        dense.add_rows(2);
+        sparse.add_rows(2);
+        assert_eq!(10, dense.height());
+        assert_eq!(10, sparse.height());
+        for i in 0..8 {
+            let value = Octet::new(rand::thread_rng().gen());
+            dense.set(8, i, value.clone());
+            sparse.set(8, i, value);
+        }
+        assert_matrices_eq(&dense, &sparse);
+    }
+
+    #[test]
+    fn hint_column_dense_and_frozen() {
+        // rand_dense_and_sparse uses set(), so just check that it works
+        let (dense, mut sparse) = rand_dense_and_sparse(8, 3);
+        sparse.enable_column_acccess_acceleration();
+        sparse.hint_column_dense_and_frozen(6);
+        sparse.hint_column_dense_and_frozen(5);
+        assert_matrices_eq(&dense, &sparse);
+    }
+}
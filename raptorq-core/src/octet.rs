@@ -1,9 +1,9 @@
+use core::ops::Add;
+use core::ops::AddAssign;
+use core::ops::Div;
+use core::ops::Mul;
+use core::ops::Sub;
 use serde::{Deserialize, Serialize};
-use std::ops::Add;
-use std::ops::AddAssign;
-use std::ops::Div;
-use std::ops::Mul;
-use std::ops::Sub;
 
 // As defined in section 5.7.3
 #[rustfmt::skip]
@@ -70,13 +70,16 @@ pub const OCTET_MUL: [[u8; 256]; 256] = calculate_octet_mul_table();
 
 // See "Screaming Fast Galois Field Arithmetic Using Intel SIMD Instructions" by Plank et al.
 // Further adapted to AVX2
+#[cfg(all(feature = "simd", any(target_arch = "x86", target_arch = "x86_64")))]
 pub const OCTET_MUL_HI_BITS: [[u8; 32]; 256] = calculate_octet_mul_hi_table();
+#[cfg(all(feature = "simd", any(target_arch = "x86", target_arch = "x86_64")))]
 pub const OCTET_MUL_LOW_BITS: [[u8; 32]; 256] = calculate_octet_mul_low_table();
 
 const fn const_mul(x: usize, y: usize) -> u8 {
     return OCT_EXP[OCT_LOG[x] as usize + OCT_LOG[y] as usize];
 }
 
+#[cfg(all(feature = "simd", any(target_arch = "x86", target_arch = "x86_64")))]
 const fn calculate_octet_mul_hi_table() -> [[u8; 32]; 256] {
     return [
         [0; 32],
@@ -338,6 +341,7 @@ const fn calculate_octet_mul_hi_table() -> [[u8; 32]; 256] {
     ];
 }
 
+#[cfg(all(feature = "simd", any(target_arch = "x86", target_arch = "x86_64")))]
 const fn calculate_octet_mul_hi_table_inner(x: usize) -> [u8; 32] {
     return [
         0,
@@ -375,6 +379,7 @@ const fn calculate_octet_mul_hi_table_inner(x: usize) -> [u8; 32] {
     ];
 }
 
+#[cfg(all(feature = "simd", any(target_arch = "x86", target_arch = "x86_64")))]
 const fn calculate_octet_mul_low_table() -> [[u8; 32]; 256] {
     return [
         [0; 32],
@@ -636,6 +641,7 @@ const fn calculate_octet_mul_low_table() -> [[u8; 32]; 256] {
     ];
 }
 
+#[cfg(all(feature = "simd", any(target_arch = "x86", target_arch = "x86_64")))]
 const fn calculate_octet_mul_low_table_inner(x: usize) -> [u8; 32] {
     return [
         0,
@@ -1347,12 +1353,15 @@ mod tests {
     use rand::Rng;
 
     use crate::octet::Octet;
+    #[cfg(all(feature = "simd", any(target_arch = "x86", target_arch = "x86_64")))]
     use crate::octet::OCTET_MUL_HI_BITS;
+    #[cfg(all(feature = "simd", any(target_arch = "x86", target_arch = "x86_64")))]
     use crate::octet::OCTET_MUL_LOW_BITS;
     use crate::octet::OCT_EXP;
     use crate::octet::OCT_LOG;
 
     #[test]
+    #[cfg(all(feature = "simd", any(target_arch = "x86", target_arch = "x86_64")))]
     fn multiplication_tables() {
         for i in 0..=255 {
             for j in 0..=255 {
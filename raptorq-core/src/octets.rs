@@ -0,0 +1,664 @@
+use crate::octet::Octet;
+use crate::octet::OCTET_MUL;
+#[cfg(all(feature = "simd", any(target_arch = "x86", target_arch = "x86_64")))]
+use crate::octet::OCTET_MUL_HI_BITS;
+#[cfg(all(feature = "simd", any(target_arch = "x86", target_arch = "x86_64")))]
+use crate::octet::OCTET_MUL_LOW_BITS;
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+// Symbols at least this large are split into chunks which are processed on multiple threads, in
+// addition to using SIMD within each chunk. Below this size the threading overhead dominates.
+#[cfg(feature = "parallel")]
+const PARALLEL_THRESHOLD: usize = 256 * 1024;
+// Must be smaller than PARALLEL_THRESHOLD, so that each chunk is processed single threaded
+#[cfg(feature = "parallel")]
+const PARALLEL_CHUNK_SIZE: usize = 64 * 1024;
+
+// Symbols at least this large are processed two vectors at a time, with independent dependency
+// chains, so that the loads of one can overlap the arithmetic of the other. Below this size the
+// extra remainder handling costs more than it saves.
+const UNROLL_THRESHOLD: usize = 128;
+
+fn mulassign_scalar_fallback(octets: &mut [u8], scalar: &Octet) {
+    let scalar_index = usize::from(scalar.byte());
+    for item in octets {
+        let octet_index = usize::from(*item);
+        // SAFETY: `OCTET_MUL` is a 256x256 matrix, both indexes are `u8` inputs.
+        *item = unsafe {
+            *OCTET_MUL
+                .get_unchecked(scalar_index)
+                .get_unchecked(octet_index)
+        };
+    }
+}
+
+#[cfg(all(feature = "simd", any(target_arch = "x86", target_arch = "x86_64")))]
+#[target_feature(enable = "avx2")]
+unsafe fn mulassign_scalar_avx2(octets: &mut [u8], scalar: &Octet) {
+    #[cfg(target_arch = "x86")]
+    use core::arch::x86::*;
+    #[cfg(target_arch = "x86_64")]
+    use core::arch::x86_64::*;
+
+    let low_mask = _mm256_set1_epi8(0x0F);
+    let hi_mask = _mm256_set1_epi8(0xF0 as u8 as i8);
+    let self_avx_ptr = octets.as_mut_ptr();
+    // Safe because _mm256_loadu_si256 loads from unaligned memory
+    #[allow(clippy::cast_ptr_alignment)]
+    let low_table =
+        _mm256_loadu_si256(OCTET_MUL_LOW_BITS[scalar.byte() as usize].as_ptr() as *const __m256i);
+    // Safe because _mm256_loadu_si256 loads from unaligned memory
+    #[allow(clippy::cast_ptr_alignment)]
+    let hi_table =
+        _mm256_loadu_si256(OCTET_MUL_HI_BITS[scalar.byte() as usize].as_ptr() as *const __m256i);
+
+    for i in 0..(octets.len() / 32) {
+        #[allow(clippy::cast_ptr_alignment)]
+        let self_vec = _mm256_loadu_si256((self_avx_ptr as *const __m256i).add(i));
+        let low = _mm256_and_si256(self_vec, low_mask);
+        let low_result = _mm256_shuffle_epi8(low_table, low);
+        let hi = _mm256_and_si256(self_vec, hi_mask);
+        let hi = _mm256_srli_epi64(hi, 4);
+        let hi_result = _mm256_shuffle_epi8(hi_table, hi);
+        let result = _mm256_xor_si256(hi_result, low_result);
+        #[allow(clippy::cast_ptr_alignment)]
+        _mm256_storeu_si256((self_avx_ptr as *mut __m256i).add(i), result);
+    }
+
+    let remainder = octets.len() % 32;
+    let scalar_index = scalar.byte() as usize;
+    for i in (octets.len() - remainder)..octets.len() {
+        *octets.get_unchecked_mut(i) = *OCTET_MUL
+            .get_unchecked(scalar_index)
+            .get_unchecked(*octets.get_unchecked(i) as usize);
+    }
+}
+
+pub fn mulassign_scalar(octets: &mut [u8], scalar: &Octet) {
+    #[cfg(feature = "parallel")]
+    {
+        if octets.len() >= PARALLEL_THRESHOLD {
+            return octets
+                .par_chunks_mut(PARALLEL_CHUNK_SIZE)
+                .for_each(|chunk| mulassign_scalar(chunk, scalar));
+        }
+    }
+
+    #[cfg(all(feature = "simd", any(target_arch = "x86", target_arch = "x86_64")))]
+    {
+        if is_x86_feature_detected!("avx2") {
+            unsafe {
+                return mulassign_scalar_avx2(octets, scalar);
+            }
+        }
+    }
+
+    return mulassign_scalar_fallback(octets, scalar);
+}
+
+fn fused_addassign_mul_scalar_fallback(octets: &mut [u8], other: &[u8], scalar: &Octet) {
+    let scalar_index = scalar.byte() as usize;
+    for i in 0..octets.len() {
+        unsafe {
+            *octets.get_unchecked_mut(i) ^= *OCTET_MUL
+                .get_unchecked(scalar_index)
+                .get_unchecked(*other.get_unchecked(i) as usize);
+        }
+    }
+}
+
+#[cfg(all(feature = "simd", any(target_arch = "x86", target_arch = "x86_64")))]
+#[target_feature(enable = "avx2")]
+unsafe fn fused_addassign_mul_scalar_avx2(octets: &mut [u8], other: &[u8], scalar: &Octet) {
+    #[cfg(target_arch = "x86")]
+    use core::arch::x86::*;
+    #[cfg(target_arch = "x86_64")]
+    use core::arch::x86_64::*;
+
+    let low_mask = _mm256_set1_epi8(0x0F);
+    let hi_mask = _mm256_set1_epi8(0xF0 as u8 as i8);
+    let self_avx_ptr = octets.as_mut_ptr();
+    let other_avx_ptr = other.as_ptr();
+    // Safe because _mm256_loadu_si256 loads from unaligned memory
+    #[allow(clippy::cast_ptr_alignment)]
+    let low_table =
+        _mm256_loadu_si256(OCTET_MUL_LOW_BITS[scalar.byte() as usize].as_ptr() as *const __m256i);
+    // Safe because _mm256_loadu_si256 loads from unaligned memory
+    #[allow(clippy::cast_ptr_alignment)]
+    let hi_table =
+        _mm256_loadu_si256(OCTET_MUL_HI_BITS[scalar.byte() as usize].as_ptr() as *const __m256i);
+
+    for i in 0..(octets.len() / 32) {
+        // Multiply by scalar
+        #[allow(clippy::cast_ptr_alignment)]
+        let other_vec = _mm256_loadu_si256((other_avx_ptr as *const __m256i).add(i));
+        let low = _mm256_and_si256(other_vec, low_mask);
+        let low_result = _mm256_shuffle_epi8(low_table, low);
+        let hi = _mm256_and_si256(other_vec, hi_mask);
+        let hi = _mm256_srli_epi64(hi, 4);
+        let hi_result = _mm256_shuffle_epi8(hi_table, hi);
+        let other_vec = _mm256_xor_si256(hi_result, low_result);
+
+        // Add to self
+        #[allow(clippy::cast_ptr_alignment)]
+        let self_vec = _mm256_loadu_si256((self_avx_ptr as *const __m256i).add(i));
+        let result = _mm256_xor_si256(self_vec, other_vec);
+        #[allow(clippy::cast_ptr_alignment)]
+        _mm256_storeu_si256((self_avx_ptr as *mut __m256i).add(i), result);
+    }
+
+    let remainder = octets.len() % 32;
+    let scalar_index = scalar.byte() as usize;
+    for i in (octets.len() - remainder)..octets.len() {
+        *octets.get_unchecked_mut(i) ^= *OCTET_MUL
+            .get_unchecked(scalar_index)
+            .get_unchecked(*other.get_unchecked(i) as usize);
+    }
+}
+
+#[cfg(all(feature = "simd", any(target_arch = "x86", target_arch = "x86_64")))]
+#[target_feature(enable = "avx2")]
+unsafe fn fused_addassign_mul_scalar_avx2_unrolled(
+    octets: &mut [u8],
+    other: &[u8],
+    scalar: &Octet,
+) {
+    #[cfg(target_arch = "x86")]
+    use core::arch::x86::*;
+    #[cfg(target_arch = "x86_64")]
+    use core::arch::x86_64::*;
+
+    let low_mask = _mm256_set1_epi8(0x0F);
+    let hi_mask = _mm256_set1_epi8(0xF0 as u8 as i8);
+    let self_avx_ptr = octets.as_mut_ptr();
+    let other_avx_ptr = other.as_ptr();
+    // Safe because _mm256_loadu_si256 loads from unaligned memory
+    #[allow(clippy::cast_ptr_alignment)]
+    let low_table =
+        _mm256_loadu_si256(OCTET_MUL_LOW_BITS[scalar.byte() as usize].as_ptr() as *const __m256i);
+    // Safe because _mm256_loadu_si256 loads from unaligned memory
+    #[allow(clippy::cast_ptr_alignment)]
+    let hi_table =
+        _mm256_loadu_si256(OCTET_MUL_HI_BITS[scalar.byte() as usize].as_ptr() as *const __m256i);
+
+    for i in 0..(octets.len() / 64) {
+        #[allow(clippy::cast_ptr_alignment)]
+        let other_vec0 = _mm256_loadu_si256((other_avx_ptr as *const __m256i).add(2 * i));
+        #[allow(clippy::cast_ptr_alignment)]
+        let other_vec1 = _mm256_loadu_si256((other_avx_ptr as *const __m256i).add(2 * i + 1));
+        #[allow(clippy::cast_ptr_alignment)]
+        let self_vec0 = _mm256_loadu_si256((self_avx_ptr as *const __m256i).add(2 * i));
+        #[allow(clippy::cast_ptr_alignment)]
+        let self_vec1 = _mm256_loadu_si256((self_avx_ptr as *const __m256i).add(2 * i + 1));
+
+        // Multiply by scalar
+        let low0 = _mm256_shuffle_epi8(low_table, _mm256_and_si256(other_vec0, low_mask));
+        let low1 = _mm256_shuffle_epi8(low_table, _mm256_and_si256(other_vec1, low_mask));
+        let hi0 = _mm256_srli_epi64(_mm256_and_si256(other_vec0, hi_mask), 4);
+        let hi1 = _mm256_srli_epi64(_mm256_and_si256(other_vec1, hi_mask), 4);
+        let hi0 = _mm256_shuffle_epi8(hi_table, hi0);
+        let hi1 = _mm256_shuffle_epi8(hi_table, hi1);
+
+        // Add to self
+        let result0 = _mm256_xor_si256(self_vec0, _mm256_xor_si256(hi0, low0));
+        let result1 = _mm256_xor_si256(self_vec1, _mm256_xor_si256(hi1, low1));
+        #[allow(clippy::cast_ptr_alignment)]
+        _mm256_storeu_si256((self_avx_ptr as *mut __m256i).add(2 * i), result0);
+        #[allow(clippy::cast_ptr_alignment)]
+        _mm256_storeu_si256((self_avx_ptr as *mut __m256i).add(2 * i + 1), result1);
+    }
+
+    let processed = octets.len() - octets.len() % 64;
+    fused_addassign_mul_scalar_avx2(&mut octets[processed..], &other[processed..], scalar);
+}
+
+pub fn fused_addassign_mul_scalar(octets: &mut [u8], other: &[u8], scalar: &Octet) {
+    debug_assert_ne!(
+        *scalar,
+        Octet::one(),
+        "Don't call this with one. Use += instead"
+    );
+    debug_assert_ne!(
+        *scalar,
+        Octet::zero(),
+        "Don't call with zero. It's very inefficient"
+    );
+
+    assert_eq!(octets.len(), other.len());
+    #[cfg(feature = "parallel")]
+    {
+        if octets.len() >= PARALLEL_THRESHOLD {
+            return octets
+                .par_chunks_mut(PARALLEL_CHUNK_SIZE)
+                .zip(other.par_chunks(PARALLEL_CHUNK_SIZE))
+                .for_each(|(chunk, other_chunk)| {
+                    fused_addassign_mul_scalar(chunk, other_chunk, scalar)
+                });
+        }
+    }
+
+    #[cfg(all(feature = "simd", any(target_arch = "x86", target_arch = "x86_64")))]
+    {
+        if is_x86_feature_detected!("avx2") {
+            unsafe {
+                if octets.len() >= UNROLL_THRESHOLD {
+                    return fused_addassign_mul_scalar_avx2_unrolled(octets, other, scalar);
+                }
+                return fused_addassign_mul_scalar_avx2(octets, other, scalar);
+            }
+        }
+    }
+
+    return fused_addassign_mul_scalar_fallback(octets, other, scalar);
+}
+
+fn add_assign_fallback(octets: &mut [u8], other: &[u8]) {
+    assert_eq!(octets.len(), other.len());
+    let self_ptr = octets.as_mut_ptr();
+    let other_ptr = other.as_ptr();
+    for i in 0..(octets.len() / 8) {
+        unsafe {
+            #[allow(clippy::cast_ptr_alignment)]
+            let self_value = (self_ptr as *const u64).add(i).read_unaligned();
+            #[allow(clippy::cast_ptr_alignment)]
+            let other_value = (other_ptr as *const u64).add(i).read_unaligned();
+            let result = self_value ^ other_value;
+            #[allow(clippy::cast_ptr_alignment)]
+            (self_ptr as *mut u64).add(i).write_unaligned(result);
+        }
+    }
+    let remainder = octets.len() % 8;
+    for i in (octets.len() - remainder)..octets.len() {
+        unsafe {
+            *octets.get_unchecked_mut(i) ^= other.get_unchecked(i);
+        }
+    }
+}
+
+// XORs 128 bits at a time, two words per iteration. On most targets u128 is lowered to a pair of
+// registers, or a vector register, so this is a portable wide path
+fn add_assign_fallback_unrolled(octets: &mut [u8], other: &[u8]) {
+    assert_eq!(octets.len(), other.len());
+    let self_ptr = octets.as_mut_ptr();
+    let other_ptr = other.as_ptr();
+    for i in 0..(octets.len() / 32) {
+        unsafe {
+            #[allow(clippy::cast_ptr_alignment)]
+            let self_value0 = (self_ptr as *const u128).add(2 * i).read_unaligned();
+            #[allow(clippy::cast_ptr_alignment)]
+            let self_value1 = (self_ptr as *const u128).add(2 * i + 1).read_unaligned();
+            #[allow(clippy::cast_ptr_alignment)]
+            let other_value0 = (other_ptr as *const u128).add(2 * i).read_unaligned();
+            #[allow(clippy::cast_ptr_alignment)]
+            let other_value1 = (other_ptr as *const u128).add(2 * i + 1).read_unaligned();
+            #[allow(clippy::cast_ptr_alignment)]
+            (self_ptr as *mut u128)
+                .add(2 * i)
+                .write_unaligned(self_value0 ^ other_value0);
+            #[allow(clippy::cast_ptr_alignment)]
+            (self_ptr as *mut u128)
+                .add(2 * i + 1)
+                .write_unaligned(self_value1 ^ other_value1);
+        }
+    }
+    let processed = octets.len() - octets.len() % 32;
+    add_assign_fallback(&mut octets[processed..], &other[processed..]);
+}
+
+#[cfg(all(feature = "simd", any(target_arch = "x86", target_arch = "x86_64")))]
+#[target_feature(enable = "avx2")]
+unsafe fn add_assign_avx2(octets: &mut [u8], other: &[u8]) {
+    #[cfg(target_arch = "x86")]
+    use core::arch::x86::*;
+    #[cfg(target_arch = "x86_64")]
+    use core::arch::x86_64::*;
+
+    assert_eq!(octets.len(), other.len());
+    let self_avx_ptr = octets.as_mut_ptr();
+    let other_avx_ptr = other.as_ptr();
+    for i in 0..(octets.len() / 32) {
+        #[allow(clippy::cast_ptr_alignment)]
+        let self_vec = _mm256_loadu_si256((self_avx_ptr as *const __m256i).add(i));
+        #[allow(clippy::cast_ptr_alignment)]
+        let other_vec = _mm256_loadu_si256((other_avx_ptr as *const __m256i).add(i));
+        let result = _mm256_xor_si256(self_vec, other_vec);
+        #[allow(clippy::cast_ptr_alignment)]
+        _mm256_storeu_si256((self_avx_ptr as *mut __m256i).add(i), result);
+    }
+
+    let remainder = octets.len() % 32;
+    let self_ptr = octets.as_mut_ptr();
+    let other_ptr = other.as_ptr();
+    for i in ((octets.len() - remainder) / 8)..(octets.len() / 8) {
+        #[allow(clippy::cast_ptr_alignment)]
+        let self_value = (self_ptr as *mut u64).add(i).read_unaligned();
+        #[allow(clippy::cast_ptr_alignment)]
+        let other_value = (other_ptr as *mut u64).add(i).read_unaligned();
+        let result = self_value ^ other_value;
+        #[allow(clippy::cast_ptr_alignment)]
+        (self_ptr as *mut u64).add(i).write_unaligned(result);
+    }
+
+    let remainder = octets.len() % 8;
+    for i in (octets.len() - remainder)..octets.len() {
+        *octets.get_unchecked_mut(i) ^= other.get_unchecked(i);
+    }
+}
+
+#[cfg(all(feature = "simd", any(target_arch = "x86", target_arch = "x86_64")))]
+#[target_feature(enable = "avx2")]
+unsafe fn add_assign_avx2_unrolled(octets: &mut [u8], other: &[u8]) {
+    #[cfg(target_arch = "x86")]
+    use core::arch::x86::*;
+    #[cfg(target_arch = "x86_64")]
+    use core::arch::x86_64::*;
+
+    assert_eq!(octets.len(), other.len());
+    let self_avx_ptr = octets.as_mut_ptr();
+    let other_avx_ptr = other.as_ptr();
+    for i in 0..(octets.len() / 64) {
+        #[allow(clippy::cast_ptr_alignment)]
+        let self_vec0 = _mm256_loadu_si256((self_avx_ptr as *const __m256i).add(2 * i));
+        #[allow(clippy::cast_ptr_alignment)]
+        let self_vec1 = _mm256_loadu_si256((self_avx_ptr as *const __m256i).add(2 * i + 1));
+        #[allow(clippy::cast_ptr_alignment)]
+        let other_vec0 = _mm256_loadu_si256((other_avx_ptr as *const __m256i).add(2 * i));
+        #[allow(clippy::cast_ptr_alignment)]
+        let other_vec1 = _mm256_loadu_si256((other_avx_ptr as *const __m256i).add(2 * i + 1));
+        let result0 = _mm256_xor_si256(self_vec0, other_vec0);
+        let result1 = _mm256_xor_si256(self_vec1, other_vec1);
+        #[allow(clippy::cast_ptr_alignment)]
+        _mm256_storeu_si256((self_avx_ptr as *mut __m256i).add(2 * i), result0);
+        #[allow(clippy::cast_ptr_alignment)]
+        _mm256_storeu_si256((self_avx_ptr as *mut __m256i).add(2 * i + 1), result1);
+    }
+
+    let processed = octets.len() - octets.len() % 64;
+    add_assign_avx2(&mut octets[processed..], &other[processed..]);
+}
+
+#[cfg(all(feature = "avx512", any(target_arch = "x86", target_arch = "x86_64")))]
+#[target_feature(enable = "avx512f")]
+unsafe fn add_assign_avx512(octets: &mut [u8], other: &[u8]) {
+    #[cfg(target_arch = "x86")]
+    use core::arch::x86::*;
+    #[cfg(target_arch = "x86_64")]
+    use core::arch::x86_64::*;
+
+    assert_eq!(octets.len(), other.len());
+    let self_avx_ptr = octets.as_mut_ptr();
+    let other_avx_ptr = other.as_ptr();
+    for i in 0..(octets.len() / 128) {
+        #[allow(clippy::cast_ptr_alignment)]
+        let self_vec0 = _mm512_loadu_si512((self_avx_ptr as *const __m512i).add(2 * i));
+        #[allow(clippy::cast_ptr_alignment)]
+        let self_vec1 = _mm512_loadu_si512((self_avx_ptr as *const __m512i).add(2 * i + 1));
+        #[allow(clippy::cast_ptr_alignment)]
+        let other_vec0 = _mm512_loadu_si512((other_avx_ptr as *const __m512i).add(2 * i));
+        #[allow(clippy::cast_ptr_alignment)]
+        let other_vec1 = _mm512_loadu_si512((other_avx_ptr as *const __m512i).add(2 * i + 1));
+        let result0 = _mm512_xor_si512(self_vec0, other_vec0);
+        let result1 = _mm512_xor_si512(self_vec1, other_vec1);
+        #[allow(clippy::cast_ptr_alignment)]
+        _mm512_storeu_si512((self_avx_ptr as *mut __m512i).add(2 * i), result0);
+        #[allow(clippy::cast_ptr_alignment)]
+        _mm512_storeu_si512((self_avx_ptr as *mut __m512i).add(2 * i + 1), result1);
+    }
+
+    // The remainder is less than two vectors
+    let processed = octets.len() - octets.len() % 128;
+    add_assign_fallback_unrolled(&mut octets[processed..], &other[processed..]);
+}
+
+pub fn add_assign(octets: &mut [u8], other: &[u8]) {
+    #[cfg(feature = "parallel")]
+    {
+        if octets.len() >= PARALLEL_THRESHOLD {
+            assert_eq!(octets.len(), other.len());
+            return octets
+                .par_chunks_mut(PARALLEL_CHUNK_SIZE)
+                .zip(other.par_chunks(PARALLEL_CHUNK_SIZE))
+                .for_each(|(chunk, other_chunk)| add_assign(chunk, other_chunk));
+        }
+    }
+
+    #[cfg(all(feature = "avx512", any(target_arch = "x86", target_arch = "x86_64")))]
+    {
+        if octets.len() >= UNROLL_THRESHOLD && is_x86_feature_detected!("avx512f") {
+            unsafe {
+                return add_assign_avx512(octets, other);
+            }
+        }
+    }
+
+    #[cfg(all(feature = "simd", any(target_arch = "x86", target_arch = "x86_64")))]
+    {
+        if is_x86_feature_detected!("avx2") {
+            unsafe {
+                if octets.len() >= UNROLL_THRESHOLD {
+                    return add_assign_avx2_unrolled(octets, other);
+                }
+                return add_assign_avx2(octets, other);
+            }
+        }
+    }
+
+    if octets.len() >= UNROLL_THRESHOLD {
+        return add_assign_fallback_unrolled(octets, other);
+    }
+    return add_assign_fallback(octets, other);
+}
+
+#[cfg(all(feature = "simd", any(target_arch = "x86", target_arch = "x86_64")))]
+#[target_feature(enable = "avx2")]
+unsafe fn count_ones_and_nonzeros_avx2(octets: &[u8]) -> (usize, usize) {
+    #[cfg(target_arch = "x86")]
+    use core::arch::x86::*;
+    #[cfg(target_arch = "x86_64")]
+    use core::arch::x86_64::*;
+
+    let avx_ones = _mm256_set1_epi8(1);
+    let avx_zeros = _mm256_set1_epi8(0);
+    let avx_ptr = octets.as_ptr();
+
+    let mut ones = 0;
+    let mut non_zeros = 0;
+    for i in 0..(octets.len() / 32) {
+        #[allow(clippy::cast_ptr_alignment)]
+        let vec = _mm256_loadu_si256((avx_ptr as *const __m256i).add(i));
+        let compared_ones = _mm256_cmpeq_epi8(vec, avx_ones);
+        ones += _mm256_extract_epi64(compared_ones, 0).count_ones() / 8;
+        ones += _mm256_extract_epi64(compared_ones, 1).count_ones() / 8;
+        ones += _mm256_extract_epi64(compared_ones, 2).count_ones() / 8;
+        ones += _mm256_extract_epi64(compared_ones, 3).count_ones() / 8;
+
+        let compared_zeros = _mm256_cmpeq_epi8(vec, avx_zeros);
+        non_zeros += 32;
+        non_zeros -= _mm256_extract_epi64(compared_zeros, 0).count_ones() / 8;
+        non_zeros -= _mm256_extract_epi64(compared_zeros, 1).count_ones() / 8;
+        non_zeros -= _mm256_extract_epi64(compared_zeros, 2).count_ones() / 8;
+        non_zeros -= _mm256_extract_epi64(compared_zeros, 3).count_ones() / 8;
+    }
+
+    let mut remainder = octets.len() % 32;
+    if remainder >= 16 {
+        remainder -= 16;
+        let avx_ones = _mm_set1_epi8(1);
+        let avx_zeros = _mm_set1_epi8(0);
+        let avx_ptr = octets.as_ptr().add((octets.len() / 32) * 32);
+
+        // Safe because _mm_lddqu_si128 loads from unaligned memory
+        #[allow(clippy::cast_ptr_alignment)]
+        let vec = _mm_lddqu_si128(avx_ptr as *const __m128i);
+        let compared_ones = _mm_cmpeq_epi8(vec, avx_ones);
+        ones += _mm_extract_epi64(compared_ones, 0).count_ones() / 8;
+        ones += _mm_extract_epi64(compared_ones, 1).count_ones() / 8;
+
+        let compared_zeros = _mm_cmpeq_epi8(vec, avx_zeros);
+        non_zeros += 16;
+        non_zeros -= _mm_extract_epi64(compared_zeros, 0).count_ones() / 8;
+        non_zeros -= _mm_extract_epi64(compared_zeros, 1).count_ones() / 8;
+    }
+
+    for i in (octets.len() - remainder)..octets.len() {
+        let value = octets.get_unchecked(i);
+        if *value == 1 {
+            ones += 1;
+        }
+        if *value != 0 {
+            non_zeros += 1;
+        }
+    }
+    (ones as usize, non_zeros as usize)
+}
+
+fn count_ones_and_nonzeros_fallback(octets: &[u8]) -> (usize, usize) {
+    let mut ones = 0;
+    let mut non_zeros = 0;
+    for value in octets.iter() {
+        if *value == 1 {
+            ones += 1;
+        }
+        if *value != 0 {
+            non_zeros += 1;
+        }
+    }
+    (ones, non_zeros)
+}
+
+pub fn count_ones_and_nonzeros(octets: &[u8]) -> (usize, usize) {
+    #[cfg(all(feature = "simd", any(target_arch = "x86", target_arch = "x86_64")))]
+    {
+        if is_x86_feature_detected!("avx2") {
+            unsafe {
+                return count_ones_and_nonzeros_avx2(octets);
+            }
+        }
+    }
+
+    return count_ones_and_nonzeros_fallback(octets);
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::Rng;
+
+    use crate::octet::Octet;
+    use crate::octets::add_assign;
+    use crate::octets::fused_addassign_mul_scalar;
+    use crate::octets::mulassign_scalar;
+
+    #[test]
+    fn mul_assign() {
+        let size = 41;
+        let scalar = Octet::new(rand::thread_rng().gen_range(1, 255));
+        let mut data1: Vec<u8> = vec![0; size];
+        let mut expected: Vec<u8> = vec![0; size];
+        for i in 0..size {
+            data1[i] = rand::thread_rng().gen();
+            expected[i] = (&Octet::new(data1[i]) * &scalar).byte();
+        }
+
+        mulassign_scalar(&mut data1, &scalar);
+
+        assert_eq!(expected, data1);
+    }
+
+    #[test]
+    fn fma() {
+        let size = 41;
+        let scalar = Octet::new(rand::thread_rng().gen_range(1, 255));
+        let mut data1: Vec<u8> = vec![0; size];
+        let mut data2: Vec<u8> = vec![0; size];
+        let mut expected: Vec<u8> = vec![0; size];
+        for i in 0..size {
+            data1[i] = rand::thread_rng().gen();
+            data2[i] = rand::thread_rng().gen();
+            expected[i] = (Octet::new(data1[i]) + &Octet::new(data2[i]) * &scalar).byte();
+        }
+
+        fused_addassign_mul_scalar(&mut data1, &data2, &scalar);
+
+        assert_eq!(expected, data1);
+    }
+
+    #[test]
+    fn unrolled_sizes() {
+        // Cover sizes on both sides of the unroll threshold, with every possible remainder
+        for size in 0..(2 * super::UNROLL_THRESHOLD + 64) {
+            let scalar = Octet::new(rand::thread_rng().gen_range(2, 255));
+            let mut data1: Vec<u8> = vec![0; size];
+            let mut data2: Vec<u8> = vec![0; size];
+            for i in 0..size {
+                data1[i] = rand::thread_rng().gen();
+                data2[i] = rand::thread_rng().gen();
+            }
+
+            let mut expected_add = data1.clone();
+            let mut expected_fma = data1.clone();
+            for i in 0..size {
+                expected_add[i] ^= data2[i];
+                expected_fma[i] = (Octet::new(data1[i]) + &Octet::new(data2[i]) * &scalar).byte();
+            }
+
+            let mut actual = data1.clone();
+            add_assign(&mut actual, &data2);
+            assert_eq!(expected_add, actual, "size = {}", size);
+
+            let mut actual = data1.clone();
+            super::add_assign_fallback_unrolled(&mut actual, &data2);
+            assert_eq!(expected_add, actual, "size = {}", size);
+
+            #[cfg(all(feature = "avx512", any(target_arch = "x86", target_arch = "x86_64")))]
+            {
+                if is_x86_feature_detected!("avx512f") {
+                    let mut actual = data1.clone();
+                    unsafe {
+                        super::add_assign_avx512(&mut actual, &data2);
+                    }
+                    assert_eq!(expected_add, actual, "size = {}", size);
+                }
+            }
+
+            let mut actual = data1;
+            fused_addassign_mul_scalar(&mut actual, &data2, &scalar);
+            assert_eq!(expected_fma, actual, "size = {}", size);
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "parallel")]
+    fn parallel_large_symbols() {
+        // Not a multiple of the chunk size, so that the last chunk is partial
+        let size = super::PARALLEL_THRESHOLD * 2 + 41;
+        let scalar = Octet::new(rand::thread_rng().gen_range(2, 255));
+        let mut data1: Vec<u8> = vec![0; size];
+        let mut data2: Vec<u8> = vec![0; size];
+        for i in 0..size {
+            data1[i] = rand::thread_rng().gen();
+            data2[i] = rand::thread_rng().gen();
+        }
+
+        let mut expected_add = data1.clone();
+        let mut expected_fma = data1.clone();
+        let mut expected_mul = data1.clone();
+        for i in 0..size {
+            expected_add[i] ^= data2[i];
+            expected_fma[i] = (Octet::new(data1[i]) + &Octet::new(data2[i]) * &scalar).byte();
+            expected_mul[i] = (&Octet::new(data1[i]) * &scalar).byte();
+        }
+
+        let mut actual = data1.clone();
+        add_assign(&mut actual, &data2);
+        assert_eq!(expected_add, actual);
+
+        let mut actual = data1.clone();
+        fused_addassign_mul_scalar(&mut actual, &data2, &scalar);
+        assert_eq!(expected_fma, actual);
+
+        let mut actual = data1;
+        mulassign_scalar(&mut actual, &scalar);
+        assert_eq!(expected_mul, actual);
+    }
+}
@@ -0,0 +1,1750 @@
+use crate::arraymap::UsizeArrayMap;
+use crate::arraymap::{ArrayMap, BoolArrayMap};
+#[cfg(feature = "failure_injection")]
+use crate::failure_injection::InjectedFailure;
+use crate::matrix::OctetMatrix;
+use crate::octet::Octet;
+use crate::symbol::{Symbol, SymbolOp};
+use crate::systematic_constants::num_hdpc_symbols;
+use crate::systematic_constants::num_intermediate_symbols;
+use crate::systematic_constants::num_ldpc_symbols;
+use crate::systematic_constants::num_pi_symbols;
+use alloc::vec;
+use alloc::vec::Vec;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, PartialOrd, Eq, Ord, Hash)]
+struct FirstPhaseRowSelectionStats {
+    original_degree: UsizeArrayMap,
+    non_zeros_per_row: UsizeArrayMap,
+    ones_per_row: UsizeArrayMap,
+    // Index of the rows of V by their number of non-zeros, so that the rows with exactly r
+    // non-zeros can be found without scanning every row. Each row's position within its list is
+    // stored in row_index_position, so that it can be moved in constant time
+    rows_by_non_zeros: Vec<Vec<usize>>,
+    row_index_position: UsizeArrayMap,
+    // Columns of the non-zeros of rows with one or two non-zeros, which are needed when such a
+    // row is chosen, or forms an edge of the r = 2 graph. Entries may be stale, see
+    // non_zero_columns()
+    non_zero_columns_cache: Vec<Option<[usize; 2]>>,
+    hdpc_rows: Vec<bool>,
+    start_col: usize,
+    end_col: usize,
+    start_row: usize,
+    // Scratch data struct that is reused across calls because it's expensive to construct
+    scratch_adjacent_nodes: ArrayMap<Vec<(usize, usize)>>,
+    // Whether to check the index of rows with two non-zeros before using it. Set by the solver
+    // at IntegrityCheck::Full
+    verify: bool,
+}
+
+impl FirstPhaseRowSelectionStats {
+    #[inline(never)]
+    #[allow(non_snake_case)]
+    // start is the first row and column of V, which is non-zero when resuming the first phase
+    pub fn new<T: OctetMatrix>(
+        matrix: &T,
+        start: usize,
+        end_col: usize,
+        hdpc_rows: Vec<bool>,
+    ) -> FirstPhaseRowSelectionStats {
+        let mut result = FirstPhaseRowSelectionStats {
+            original_degree: UsizeArrayMap::new(0, 0),
+            non_zeros_per_row: UsizeArrayMap::new(0, matrix.height()),
+            ones_per_row: UsizeArrayMap::new(0, matrix.height()),
+            rows_by_non_zeros: vec![vec![]; end_col - start + 1],
+            row_index_position: UsizeArrayMap::new(0, matrix.height()),
+            non_zero_columns_cache: vec![None; matrix.height()],
+            hdpc_rows,
+            start_col: start,
+            end_col,
+            start_row: start,
+            scratch_adjacent_nodes: ArrayMap::new(0, end_col),
+            verify: cfg!(debug_assertions),
+        };
+
+        for row in start..matrix.height() {
+            let (ones, non_zero) = matrix.count_ones_and_nonzeros(row, start, end_col);
+            result.non_zeros_per_row.insert(row, non_zero);
+            result.ones_per_row.insert(row, ones);
+            result.index_insert(row, non_zero);
+        }
+        // Original degree is the degree of each row before processing begins
+        result.original_degree = result.non_zeros_per_row.clone();
+
+        result
+    }
+
+    fn index_insert(&mut self, row: usize, non_zeros: usize) {
+        self.row_index_position
+            .insert(row, self.rows_by_non_zeros[non_zeros].len());
+        self.rows_by_non_zeros[non_zeros].push(row);
+    }
+
+    fn index_remove(&mut self, row: usize, non_zeros: usize) {
+        let position = self.row_index_position.get(row);
+        let rows = &mut self.rows_by_non_zeros[non_zeros];
+        debug_assert_eq!(rows[position], row);
+        rows.swap_remove(position);
+        if position < rows.len() {
+            let moved = rows[position];
+            self.row_index_position.insert(moved, position);
+        }
+    }
+
+    // Updates the number of non-zeros of the row, and its position in the index
+    fn set_non_zeros(&mut self, row: usize, non_zeros: usize) {
+        self.index_remove(row, self.non_zeros_per_row.get(row));
+        self.index_insert(row, non_zeros);
+        self.non_zeros_per_row.insert(row, non_zeros);
+    }
+
+    // Returns the rows of V, in no particular order, which have exactly r non-zeros
+    pub fn rows_with_non_zeros(&self, r: usize) -> &[usize] {
+        &self.rows_by_non_zeros[r]
+    }
+
+    // Returns the columns, in ascending order, of the non-zeros in V of a row which has one or
+    // two of them. For a single non-zero both entries are its column.
+    pub fn non_zero_columns<T: OctetMatrix>(&mut self, row: usize, matrix: &T) -> [usize; 2] {
+        let non_zeros = self.non_zeros_per_row.get(row);
+        debug_assert!(non_zeros == 1 || non_zeros == 2);
+        // The cache isn't updated when columns are swapped, or rows are modified. However, the
+        // number of non-zeros is always accurate, so if the cached columns are still non-zero
+        // they are the only ones
+        if let Some(columns) = self.non_zero_columns_cache[row] {
+            let valid = columns.iter().all(|&col| {
+                col >= self.start_col && col < self.end_col && matrix.get(row, col) != Octet::zero()
+            }) && (non_zeros == 1) == (columns[0] == columns[1]);
+            if valid {
+                return columns;
+            }
+        }
+
+        let mut columns = [0; 2];
+        let mut found = 0;
+        for (col, value) in matrix.get_row_iter(row, self.start_col, self.end_col) {
+            if value != Octet::zero() {
+                columns[found] = col;
+                found += 1;
+                if found == non_zeros {
+                    break;
+                }
+            }
+        }
+        assert_eq!(found, non_zeros);
+        if non_zeros == 1 {
+            columns[1] = columns[0];
+        } else if columns[1] < columns[0] {
+            // Sparse rows are iterated in physical column order
+            columns.swap(0, 1);
+        }
+        self.non_zero_columns_cache[row] = Some(columns);
+        columns
+    }
+
+    pub fn swap_rows(&mut self, i: usize, j: usize) {
+        let position_i = self.row_index_position.get(i);
+        let position_j = self.row_index_position.get(j);
+        self.rows_by_non_zeros[self.non_zeros_per_row.get(i)][position_i] = j;
+        self.rows_by_non_zeros[self.non_zeros_per_row.get(j)][position_j] = i;
+        self.row_index_position.swap(i, j);
+        self.non_zero_columns_cache.swap(i, j);
+        self.non_zeros_per_row.swap(i, j);
+        self.ones_per_row.swap(i, j);
+        self.original_degree.swap(i, j);
+        self.hdpc_rows.swap(i, j);
+    }
+
+    // Recompute all stored statistics for the given row
+    pub fn recompute_row<T: OctetMatrix>(&mut self, row: usize, matrix: &T) {
+        let (ones, non_zero) = matrix.count_ones_and_nonzeros(row, self.start_col, self.end_col);
+        self.set_non_zeros(row, non_zero);
+        self.ones_per_row.insert(row, ones);
+    }
+
+    pub fn eliminate_leading_value(&mut self, row: usize, value: &Octet) {
+        debug_assert_ne!(*value, Octet::zero());
+        if *value == Octet::one() {
+            self.ones_per_row.decrement(row);
+        }
+        let non_zeros = self.non_zeros_per_row.get(row);
+        self.set_non_zeros(row, non_zeros - 1);
+    }
+
+    // Set the valid columns, and recalculate statistics
+    // All values in column "start_col - 1" in rows start_row..end_row must be zero
+    #[inline(never)]
+    pub fn resize<T: OctetMatrix>(
+        &mut self,
+        start_row: usize,
+        end_row: usize,
+        start_col: usize,
+        end_col: usize,
+        matrix: &T,
+    ) {
+        // Only shrinking is supported
+        assert!(end_col <= self.end_col);
+        assert_eq!(self.start_row, start_row - 1);
+        assert_eq!(self.start_col, start_col - 1);
+
+        self.index_remove(self.start_row, self.non_zeros_per_row.get(self.start_row));
+
+        for col in end_col..self.end_col {
+            for row in matrix.get_col_index_iter(col, start_row, end_row) {
+                if matrix.get(row, col) == Octet::one() {
+                    self.ones_per_row.decrement(row);
+                }
+                if matrix.get(row, col) != Octet::zero() {
+                    let non_zeros = self.non_zeros_per_row.get(row);
+                    self.set_non_zeros(row, non_zeros - 1);
+                }
+            }
+        }
+
+        self.start_col = start_col;
+        self.end_col = end_col;
+        self.start_row = start_row;
+    }
+
+    #[inline(never)]
+    fn first_phase_graph_substep_build_adjacency<T: OctetMatrix>(
+        &mut self,
+        rows_with_two_ones: &[usize],
+        matrix: &T,
+    ) {
+        self.scratch_adjacent_nodes
+            .reset(self.start_col, self.end_col, |nodes| nodes.clear());
+
+        for row in rows_with_two_ones.iter() {
+            if self.hdpc_rows[*row] {
+                continue;
+            }
+            // "The following graph defined by the structure of V is used in determining which
+            // row of A is chosen. The columns that intersect V are the nodes in the graph,
+            // and the rows that have exactly 2 nonzero entries in V and are not HDPC rows
+            // are the edges of the graph that connect the two columns (nodes) in the positions
+            // of the two ones."
+            // This part of the matrix is over GF(2), so "nonzero entries" is equivalent to "ones"
+            let ones = self.non_zero_columns(*row, matrix);
+            let first = self.scratch_adjacent_nodes.get_mut(ones[0]);
+            if first == None {
+                let mut new_nodes = Vec::with_capacity(10);
+                new_nodes.push((ones[1], *row));
+                self.scratch_adjacent_nodes.insert(ones[0], new_nodes);
+            } else {
+                first.unwrap().push((ones[1], *row));
+            }
+            let second = self.scratch_adjacent_nodes.get_mut(ones[1]);
+            if second == None {
+                let mut new_nodes = Vec::with_capacity(10);
+                new_nodes.push((ones[0], *row));
+                self.scratch_adjacent_nodes.insert(ones[1], new_nodes);
+            } else {
+                second.unwrap().push((ones[0], *row));
+            }
+        }
+    }
+
+    #[inline(never)]
+    fn first_phase_graph_substep<T: OctetMatrix>(
+        &mut self,
+        start_row: usize,
+        end_row: usize,
+        rows_with_two_ones: &[usize],
+        matrix: &T,
+    ) -> usize {
+        self.first_phase_graph_substep_build_adjacency(rows_with_two_ones, matrix);
+        let mut visited = BoolArrayMap::new(start_row, end_row);
+
+        let mut examplar_largest_component_row = None;
+        let mut largest_component_size = 0;
+
+        let mut node_queue = Vec::with_capacity(10);
+        for key in self.scratch_adjacent_nodes.keys() {
+            let mut component_size = 0;
+            let mut examplar_row = None;
+            // Pick arbitrary node (column) to start
+            node_queue.clear();
+            node_queue.push(key);
+            while !node_queue.is_empty() {
+                let node = node_queue.pop().unwrap();
+                if visited.get(node) {
+                    continue;
+                }
+                visited.insert(node, true);
+                let next_nodes = self.scratch_adjacent_nodes.get(node).unwrap();
+                component_size += 1;
+                for &(next_node, row) in next_nodes.iter() {
+                    node_queue.push(next_node);
+                    examplar_row = Some(row);
+                }
+            }
+
+            if component_size > largest_component_size {
+                examplar_largest_component_row = examplar_row;
+                largest_component_size = component_size;
+            }
+        }
+
+        return examplar_largest_component_row.unwrap();
+    }
+
+    #[inline(never)]
+    fn first_phase_original_degree_substep(&self, r: usize) -> usize {
+        let mut chosen_hdpc = None;
+        let mut chosen_non_hdpc = None;
+        // Ties are broken by choosing the first row
+        for &row in self.rows_with_non_zeros(r).iter() {
+            let candidate = Some((self.original_degree.get(row), row));
+            let chosen = if self.hdpc_rows[row] {
+                &mut chosen_hdpc
+            } else {
+                &mut chosen_non_hdpc
+            };
+            if chosen.is_none() || candidate < *chosen {
+                *chosen = candidate;
+            }
+        }
+        return chosen_non_hdpc.or(chosen_hdpc).unwrap().1;
+    }
+
+    // Verify there there are no non-HPDC rows with exactly two non-zero entries, greater than one
+    #[inline(never)]
+    fn first_phase_graph_substep_verify(
+        &self,
+        start_row: usize,
+        end_row: usize,
+        rows_with_two_ones: &[usize],
+    ) {
+        for row in start_row..end_row {
+            if self.non_zeros_per_row.get(row) == 2 {
+                assert!(rows_with_two_ones.contains(&row) || self.hdpc_rows[row]);
+            }
+        }
+    }
+
+    // Helper method for decoder phase 1
+    // selects from [start_row, end_row) reading [start_col, end_col)
+    // Returns (the chosen row, and "r" number of non-zero values the row has, is_HDPC)
+    pub fn first_phase_selection<T: OctetMatrix>(
+        &mut self,
+        start_row: usize,
+        end_row: usize,
+        matrix: &T,
+    ) -> (Option<usize>, Option<usize>, Option<bool>) {
+        let r = (1..=(self.end_col - self.start_col))
+            .find(|&i| !self.rows_with_non_zeros(i).is_empty());
+
+        if r == None {
+            return (None, None, None);
+        }
+
+        if r.unwrap() == 2 {
+            let mut rows_with_two_ones = vec![];
+            let mut row_with_two_greater_than_one = None;
+            for &row in self.rows_with_non_zeros(2).iter() {
+                if self.ones_per_row.get(row) == 2 {
+                    rows_with_two_ones.push(row);
+                } else if row_with_two_greater_than_one.is_none()
+                    || Some(row) > row_with_two_greater_than_one
+                {
+                    row_with_two_greater_than_one = Some(row);
+                }
+            }
+            // The index is unordered, so sort to keep the choice of row deterministic
+            rows_with_two_ones.sort_unstable();
+
+            // See paragraph starting "If r = 2 and there is a row with exactly 2 ones in V..."
+            if !rows_with_two_ones.is_empty() {
+                if self.verify {
+                    self.first_phase_graph_substep_verify(start_row, end_row, &rows_with_two_ones);
+                }
+                let row =
+                    self.first_phase_graph_substep(start_row, end_row, &rows_with_two_ones, matrix);
+                return (Some(row), r, Some(self.hdpc_rows[row]));
+            } else {
+                // See paragraph starting "If r = 2 and there is no row with exactly 2 ones in V"
+                return (
+                    row_with_two_greater_than_one,
+                    r,
+                    Some(self.hdpc_rows[row_with_two_greater_than_one.unwrap()]),
+                );
+            }
+        } else {
+            let row = self.first_phase_original_degree_substep(r.unwrap());
+            return (Some(row), r, Some(self.hdpc_rows[row]));
+        }
+    }
+}
+
+/// A bound on the work of solving for the intermediate symbols, which caused a solver to abort
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, PartialOrd, Eq, Ord, Hash)]
+pub enum SolverLimit {
+    /// More symbol additions than allowed by `IntermediateSymbolDecoder::set_max_symbol_add_ops()`
+    SymbolAddOps,
+    /// More columns were inactivated than allowed by
+    /// `IntermediateSymbolDecoder::set_max_inactivated_columns()`
+    Density,
+}
+
+/// How thoroughly the solver checks its invariants, which detects bugs and memory corruption
+/// rather than producing wrong symbols. See `IntermediateSymbolDecoder::set_integrity_check()`.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, PartialOrd, Eq, Ord, Hash)]
+pub enum IntegrityCheck {
+    /// No checks
+    Off,
+    /// Checks which cost O(L) per phase, such as that the row and column permutations are valid
+    /// and that the pivots are one. These are cheap enough for production use.
+    Cheap,
+    /// Additionally checks the structure of the whole matrix after each phase, as described in
+    /// section 5.4.2, which costs O(L^2) per phase. This is the default in debug builds.
+    Full,
+}
+
+impl Default for IntegrityCheck {
+    fn default() -> IntegrityCheck {
+        if cfg!(debug_assertions) {
+            IntegrityCheck::Full
+        } else {
+            IntegrityCheck::Off
+        }
+    }
+}
+
+/// The row and column permutations chosen by a solver, for analysis of its pivoting and
+/// inactivation patterns. See `IntermediateSymbolDecoder::permutations()`.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub struct SolverPermutations {
+    row_order: Vec<usize>,
+    column_order: Vec<usize>,
+    first_phase_pivots: Vec<(usize, usize)>,
+    inactivated_columns: usize,
+    physical_rows: Vec<usize>,
+    physical_columns: Vec<usize>,
+}
+
+impl SolverPermutations {
+    /// The original index of the row at each position of the reduced matrix, i.e. the vector d
+    /// of section 5.4.2.1
+    pub fn row_order(&self) -> &[usize] {
+        &self.row_order
+    }
+
+    /// The original index of the column at each position of the reduced matrix, i.e. the vector
+    /// c of section 5.4.2.1
+    pub fn column_order(&self) -> &[usize] {
+        &self.column_order
+    }
+
+    /// The original index of each row chosen in the first phase, in the order they were chosen,
+    /// with its number of non-zeros in V (r in section 5.4.2.2) when it was chosen. Every row with
+    /// r > 1 inactivated r - 1 columns. Empty unless the solver recorded them, see
+    /// `IntermediateSymbolDecoder::record_permutations()`.
+    pub fn first_phase_pivots(&self) -> &[(usize, usize)] {
+        &self.first_phase_pivots
+    }
+
+    /// The number of columns which were inactivated in the first phase (u in section 5.4.2.2),
+    /// including the PI symbols
+    pub fn inactivated_columns(&self) -> usize {
+        self.inactivated_columns
+    }
+
+    /// For each logical row of the matrix (after the row permutation), the index at which it is
+    /// stored. This is the identity for a dense matrix.
+    pub fn physical_rows(&self) -> &[usize] {
+        &self.physical_rows
+    }
+
+    /// For each logical column of the matrix, the index at which it is stored. See
+    /// `physical_rows()`.
+    pub fn physical_columns(&self) -> &[usize] {
+        &self.physical_columns
+    }
+}
+
+// See section 5.4.2.1
+#[allow(non_snake_case)]
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, PartialOrd, Eq, Ord, Hash)]
+pub struct IntermediateSymbolDecoder<T: OctetMatrix> {
+    A: T,
+    X: T,
+    D: Vec<Symbol>,
+    c: Vec<usize>,
+    d: Vec<usize>,
+    i: usize,
+    u: usize,
+    L: usize,
+    num_source_symbols: u32,
+    first_phase_complete: bool,
+    max_symbol_add_ops: Option<u32>,
+    max_inactivated_columns: Option<usize>,
+    exceeded_limit: Option<SolverLimit>,
+    integrity_check: IntegrityCheck,
+    // Original row index and r of each row chosen in the first phase, if they are being recorded
+    first_phase_pivots: Option<Vec<(usize, usize)>>,
+    // Every operation performed on D, if they are being recorded
+    recorded_operations: Option<Vec<SymbolOp>>,
+    #[cfg(feature = "failure_injection")]
+    injected_failure: Option<InjectedFailure>,
+    debug_symbol_mul_ops: u32,
+    debug_symbol_add_ops: u32,
+    debug_symbol_mul_ops_by_phase: Vec<u32>,
+    debug_symbol_add_ops_by_phase: Vec<u32>,
+}
+
+impl<T: OctetMatrix> IntermediateSymbolDecoder<T> {
+    pub fn new(
+        matrix: T,
+        symbols: Vec<Symbol>,
+        num_source_symbols: u32,
+    ) -> IntermediateSymbolDecoder<T> {
+        assert!(matrix.width() <= symbols.len());
+        assert_eq!(matrix.height(), symbols.len());
+        let mut c = Vec::with_capacity(matrix.width());
+        let mut d = Vec::with_capacity(symbols.len());
+        for i in 0..matrix.width() {
+            c.push(i);
+        }
+        for i in 0..symbols.len() {
+            d.push(i);
+        }
+
+        IntermediateSymbolDecoder {
+            A: matrix.clone(),
+            X: matrix,
+            D: symbols,
+            c,
+            d,
+            i: 0,
+            u: num_pi_symbols(num_source_symbols) as usize,
+            L: num_intermediate_symbols(num_source_symbols) as usize,
+            num_source_symbols,
+            first_phase_complete: false,
+            max_symbol_add_ops: None,
+            max_inactivated_columns: None,
+            exceeded_limit: None,
+            integrity_check: IntegrityCheck::default(),
+            first_phase_pivots: None,
+            recorded_operations: None,
+            #[cfg(feature = "failure_injection")]
+            injected_failure: None,
+            debug_symbol_mul_ops: 0,
+            debug_symbol_add_ops: 0,
+            debug_symbol_mul_ops_by_phase: vec![0; 5],
+            debug_symbol_add_ops_by_phase: vec![0; 5],
+        }
+    }
+
+    /// Bounds the number of symbol additions the solver may perform. If the limit is exceeded,
+    /// solving is aborted, as if it had failed, and the partially reduced state can not be
+    /// resumed. See `aborted()`.
+    pub fn set_max_symbol_add_ops(&mut self, limit: u32) {
+        self.max_symbol_add_ops = Some(limit);
+    }
+
+    /// Bounds the number of columns of the constraint matrix which may be inactivated, including
+    /// the PI symbols, which always are. Inactivated columns form the dense part of the matrix, so
+    /// this bounds its density. Solving a matrix with u inactivated columns costs O(u^2) symbol
+    /// operations, so if the limit is exceeded solving is aborted, in the same way as for
+    /// `set_max_symbol_add_ops()`. Well formed input only inactivates a few columns beyond the PI
+    /// symbols, while crafted or degenerate sets of symbols can inactivate most of them.
+    pub fn set_max_inactivated_columns(&mut self, limit: usize) {
+        self.max_inactivated_columns = Some(limit);
+    }
+
+    /// Sets how thoroughly the solver checks its invariants after each phase. Failed checks panic.
+    /// Defaults to `IntegrityCheck::Full` in debug builds, and `IntegrityCheck::Off` otherwise.
+    pub fn set_integrity_check(&mut self, level: IntegrityCheck) {
+        self.integrity_check = level;
+    }
+
+    /// Returns true if solving was aborted because it exceeded one of its limits
+    pub fn aborted(&self) -> bool {
+        self.exceeded_limit.is_some()
+    }
+
+    /// Returns the limit which caused solving to be aborted, if any
+    pub fn exceeded_limit(&self) -> Option<SolverLimit> {
+        self.exceeded_limit
+    }
+
+    /// Returns the permutations chosen so far. Intended for analysis, rather than the decoding
+    /// path, since it copies them. The first phase pivots are only included if
+    /// `record_permutations()` was called before solving.
+    pub fn permutations(&self) -> SolverPermutations {
+        let (physical_rows, physical_columns) = self.A.storage_permutations();
+        SolverPermutations {
+            row_order: self.d.clone(),
+            column_order: self.c.clone(),
+            first_phase_pivots: self.first_phase_pivots.clone().unwrap_or_default(),
+            inactivated_columns: self.u,
+            physical_rows,
+            physical_columns,
+        }
+    }
+
+    /// Records the rows chosen in the first phase from now on, for `permutations()`. The row and
+    /// column orders are always available, since solving needs them.
+    pub fn record_permutations(&mut self) {
+        if self.first_phase_pivots.is_none() {
+            self.first_phase_pivots = Some(vec![]);
+        }
+    }
+
+    /// Records every operation performed on the symbols from now on. The operations depend only
+    /// on the matrix, so applying them to other symbols, followed by the reordering given by
+    /// `permutations()`, solves for those symbols with the same matrix. See
+    /// `take_recorded_operations()`.
+    pub fn record_operations(&mut self) {
+        self.recorded_operations = Some(vec![]);
+    }
+
+    /// Returns the operations recorded since `record_operations()`, and stops recording
+    pub fn take_recorded_operations(&mut self) -> Option<Vec<SymbolOp>> {
+        self.recorded_operations.take()
+    }
+
+    /// Forces solving to fail, see `InjectedFailure`
+    #[cfg(feature = "failure_injection")]
+    pub fn set_injected_failure(&mut self, failure: Option<InjectedFailure>) {
+        self.injected_failure = failure;
+    }
+
+    #[cfg(feature = "failure_injection")]
+    fn injected(&self, failure: InjectedFailure) -> bool {
+        self.injected_failure == Some(failure)
+    }
+
+    fn over_budget(&mut self) -> bool {
+        if let Some(limit) = self.max_symbol_add_ops {
+            if self.debug_symbol_add_ops > limit {
+                self.exceeded_limit = Some(SolverLimit::SymbolAddOps);
+            }
+        }
+        if let Some(limit) = self.max_inactivated_columns {
+            if self.u > limit {
+                self.exceeded_limit = Some(SolverLimit::Density);
+            }
+        }
+        self.aborted()
+    }
+
+    // Returns true iff all elements in A between [start_row, end_row)
+    // and [start_column, end_column) are zero
+    fn all_zeroes(
+        &self,
+        start_row: usize,
+        end_row: usize,
+        start_column: usize,
+        end_column: usize,
+    ) -> bool {
+        for row in start_row..end_row {
+            for column in start_column..end_column {
+                if self.A.get(row, column) != Octet::zero() {
+                    return false;
+                }
+            }
+        }
+        return true;
+    }
+
+    // Performs the column swapping substep of first phase, after the row has been chosen
+    #[inline(never)]
+    // non_zero_columns must be provided when r is one or two. See
+    // FirstPhaseRowSelectionStats::non_zero_columns()
+    fn first_phase_swap_columns_substep(&mut self, r: usize, non_zero_columns: Option<[usize; 2]>) {
+        let mut swapped_columns = 0;
+        // Fast path when r <= 2, since this is very common
+        if let Some([first, second]) = non_zero_columns {
+            // No need to swap the first i rows, as they are all zero (see submatrix above V)
+            self.swap_columns(self.i, first, self.i);
+            swapped_columns += 1;
+            if r == 2 {
+                let dest = self.A.width() - self.u - 1;
+                if dest != second {
+                    self.swap_columns(dest, second, self.i);
+                }
+                swapped_columns += 1;
+            }
+        } else {
+            for col in self.i..(self.A.width() - self.u) {
+                if self.A.get(self.i, col) != Octet::zero() {
+                    let mut dest;
+                    if swapped_columns == 0 {
+                        dest = self.i;
+                    } else {
+                        dest = self.A.width() - self.u - swapped_columns;
+                        // Some of the right most columns may already contain non-zeros
+                        while self.A.get(self.i, dest) != Octet::zero() {
+                            dest -= 1;
+                            swapped_columns += 1;
+                        }
+                    }
+                    if swapped_columns == r {
+                        break;
+                    }
+                    // No need to swap the first i rows, as they are all zero (see submatrix above V)
+                    self.swap_columns(dest, col, self.i);
+                    swapped_columns += 1;
+                    if swapped_columns == r {
+                        break;
+                    }
+                }
+            }
+        }
+        assert_eq!(r, swapped_columns);
+    }
+
+    // First phase (section 5.4.2.2)
+    #[allow(non_snake_case)]
+    #[inline(never)]
+    fn first_phase(&mut self) -> bool {
+        // First phase (section 5.4.2.2)
+
+        //    ----------> i                 u <--------
+        //  | +-----------+-----------------+---------+
+        //  | |           |                 |         |
+        //  | |     I     |    All Zeros    |         |
+        //  v |           |                 |         |
+        //  i +-----------+-----------------+    U    |
+        //    |           |                 |         |
+        //    |           |                 |         |
+        //    | All Zeros |       V         |         |
+        //    |           |                 |         |
+        //    |           |                 |         |
+        //    +-----------+-----------------+---------+
+        // Figure 6: Submatrices of A in the First Phase
+
+        // See section 5.3.3.4.2, Figure 5. Rows may have been reordered by a previous attempt, so
+        // HDPC rows are identified by their original index, which is tracked by d
+        let S = num_ldpc_symbols(self.num_source_symbols) as usize;
+        let H = num_hdpc_symbols(self.num_source_symbols) as usize;
+        let hdpc_rows = (0..self.A.height())
+            .map(|row| self.d[row] >= S && self.d[row] < S + H)
+            .collect();
+
+        let mut selection_helper =
+            FirstPhaseRowSelectionStats::new(&self.A, self.i, self.A.width() - self.u, hdpc_rows);
+        selection_helper.verify = self.integrity_check == IntegrityCheck::Full;
+
+        #[cfg(feature = "failure_injection")]
+        {
+            if self.injected(InjectedFailure::PivotSelection) {
+                return false;
+            }
+        }
+
+        while self.i + self.u < self.L {
+            // Calculate r
+            // "Let r be the minimum integer such that at least one row of A has
+            // exactly r nonzeros in V."
+            let (chosen_row, r, is_hdpc) =
+                selection_helper.first_phase_selection(self.i, self.A.height(), &self.A);
+
+            if r == None {
+                return false;
+            }
+            let r = r.unwrap();
+            let chosen_row = chosen_row.unwrap();
+            let is_hdpc = is_hdpc.unwrap();
+            assert!(chosen_row >= self.i);
+
+            // HDPC rows are chosen last, so they are likely mostly sparse by now
+            if is_hdpc {
+                self.A.hint_compact_dense_rows();
+            }
+
+            // See paragraph beginning: "After the row is chosen in this step..."
+            // Reorder rows
+            let temp = self.i;
+            self.swap_rows(temp, chosen_row);
+            selection_helper.swap_rows(temp, chosen_row);
+            if let Some(pivots) = self.first_phase_pivots.as_mut() {
+                pivots.push((self.d[temp], r));
+            }
+            // Reorder columns
+            let non_zero_columns = if r <= 2 {
+                Some(selection_helper.non_zero_columns(temp, &self.A))
+            } else {
+                None
+            };
+            self.first_phase_swap_columns_substep(r, non_zero_columns);
+            // Zero out leading value in following rows
+            let temp = self.i;
+            let temp_value = self.A.get(temp, temp);
+            // Cloning the iterator is safe here, because we don't re-read any of the rows that
+            // we add to
+            for row in self
+                .A
+                .get_col_index_iter(temp, self.i + 1, self.A.height())
+                .clone()
+            {
+                let leading_value = self.A.get(row, temp);
+                if leading_value != Octet::zero() {
+                    // Addition is equivalent to subtraction
+                    let beta = &leading_value / &temp_value;
+                    self.fma_rows(temp, row, beta);
+                    if r == 1 {
+                        // Hot path for r == 1, since it's very common due to maximum connected
+                        // component selection, and recompute_row() is expensive
+                        selection_helper.eliminate_leading_value(row, &leading_value);
+                    } else {
+                        selection_helper.recompute_row(row, &self.A);
+                    }
+                }
+            }
+
+            for i in 0..(r - 1) {
+                self.A
+                    .hint_column_dense_and_frozen(self.A.width() - self.u - 1 - i);
+            }
+
+            self.i += 1;
+            self.u += r - 1;
+            if self.over_budget() {
+                return false;
+            }
+            selection_helper.resize(
+                self.i,
+                self.A.height(),
+                self.i,
+                self.A.width() - self.u,
+                &self.A,
+            );
+            if self.integrity_check == IntegrityCheck::Full {
+                self.first_phase_verify();
+            }
+        }
+
+        if self.integrity_check != IntegrityCheck::Off {
+            self.cheap_verify(self.i);
+        }
+        self.record_symbol_ops(0);
+        return true;
+    }
+
+    // Verifies that c and d are permutations, that the symbols have the same length, and that the
+    // first `pivots` elements of the diagonal of A are one, in O(L) time
+    #[inline(never)]
+    fn cheap_verify(&self, pivots: usize) {
+        let mut seen = vec![false; self.c.len()];
+        for &col in self.c.iter() {
+            assert!(
+                !seen[col],
+                "Column {} appears twice in the permutation",
+                col
+            );
+            seen[col] = true;
+        }
+        let mut seen = vec![false; self.D.len()];
+        for &row in self.d.iter() {
+            assert!(
+                !seen[row],
+                "Symbol {} appears twice in the permutation",
+                row
+            );
+            seen[row] = true;
+        }
+        let symbol_len = self.D.first().map_or(0, |symbol| symbol.as_bytes().len());
+        assert!(self
+            .D
+            .iter()
+            .all(|symbol| symbol.as_bytes().len() == symbol_len));
+        for row in 0..pivots {
+            assert_eq!(
+                Octet::one(),
+                self.A.get(row, row),
+                "Pivot {} is not one",
+                row
+            );
+        }
+    }
+
+    // See section 5.4.2.2. Verifies the two all-zeros submatrices and the identity submatrix
+    #[inline(never)]
+    fn first_phase_verify(&self) {
+        self.A.verify();
+        for row in 0..self.i {
+            for col in 0..self.i {
+                if row == col {
+                    assert_eq!(Octet::one(), self.A.get(row, col));
+                } else {
+                    assert_eq!(Octet::zero(), self.A.get(row, col));
+                }
+            }
+        }
+        assert!(self.all_zeroes(0, self.i, self.i, self.A.width() - self.u));
+        assert!(self.all_zeroes(self.i, self.A.height(), 0, self.i));
+    }
+
+    // Second phase (section 5.4.2.3)
+    #[allow(non_snake_case)]
+    #[inline(never)]
+    fn second_phase(&mut self) -> bool {
+        if self.integrity_check == IntegrityCheck::Full {
+            self.second_phase_verify();
+        }
+
+        self.X.resize(self.i, self.i);
+
+        // Convert U_lower to row echelon form
+        let temp = self.i;
+        let size = self.u;
+        #[cfg(feature = "failure_injection")]
+        {
+            if self.injected(InjectedFailure::SingularMatrix) {
+                return false;
+            }
+        }
+        if !self.reduce_to_row_echelon(temp, temp, size) {
+            return false;
+        }
+
+        // Perform backwards elimination
+        if !self.backwards_elimination(temp, temp, size) {
+            return false;
+        }
+
+        // This also releases the memory reserved for columns becoming dense, which is significant
+        // when many columns were inactivated
+        self.A.resize(self.L, self.L);
+        if self.integrity_check != IntegrityCheck::Off {
+            self.cheap_verify(self.L);
+        }
+
+        self.record_symbol_ops(1);
+        return true;
+    }
+
+    // Verifies that X is lower triangular. See section 5.4.2.3
+    #[inline(never)]
+    fn second_phase_verify(&self) {
+        self.A.verify();
+        for row in 0..self.i {
+            for col in (row + 1)..self.i {
+                assert_eq!(Octet::zero(), self.X.get(row, col));
+            }
+        }
+    }
+
+    // Third phase (section 5.4.2.4). Returns false if it was aborted for exceeding the budget
+    #[allow(non_snake_case)]
+    #[inline(never)]
+    fn third_phase(&mut self) -> bool {
+        if self.integrity_check == IntegrityCheck::Full {
+            self.third_phase_verify();
+        }
+
+        // A[0..i][..] = X * A[0..i][..]
+        self.A.mul_assign_submatrix(&self.X, self.i);
+
+        // Now apply the same operations to D.
+        // Note that X is lower triangular, so the row must be processed last to first
+        for row in (0..self.i).rev() {
+            if self.over_budget() {
+                return false;
+            }
+            if self.X.get(row, row) != Octet::one() {
+                self.debug_symbol_mul_ops += 1;
+                apply_symbol_op(
+                    &mut self.D,
+                    &mut self.recorded_operations,
+                    SymbolOp::MulAssign {
+                        dest: self.d[row],
+                        scalar: self.X.get(row, row),
+                    },
+                );
+            }
+
+            for (col, value) in self.X.get_row_iter(row, 0, row) {
+                if value == Octet::zero() {
+                    continue;
+                }
+                if value == Octet::one() {
+                    self.debug_symbol_add_ops += 1;
+                    apply_symbol_op(
+                        &mut self.D,
+                        &mut self.recorded_operations,
+                        SymbolOp::AddAssign {
+                            dest: self.d[row],
+                            src: self.d[col],
+                        },
+                    );
+                } else {
+                    self.debug_symbol_mul_ops += 1;
+                    self.debug_symbol_add_ops += 1;
+                    apply_symbol_op(
+                        &mut self.D,
+                        &mut self.recorded_operations,
+                        SymbolOp::FusedAddAssignMul {
+                            dest: self.d[row],
+                            src: self.d[col],
+                            scalar: value,
+                        },
+                    );
+                }
+            }
+        }
+
+        self.record_symbol_ops(2);
+
+        if self.integrity_check == IntegrityCheck::Full {
+            self.third_phase_verify_end();
+        }
+        true
+    }
+
+    #[inline(never)]
+    fn third_phase_verify(&self) {
+        self.A.verify();
+        for row in 0..self.A.height() {
+            for col in 0..self.A.width() {
+                if row < self.i && col >= self.A.width() - self.u {
+                    // element is in U_upper, which can have arbitrary values at this point
+                    continue;
+                }
+                // The rest of A should be identity matrix
+                if row == col {
+                    assert_eq!(Octet::one(), self.A.get(row, col));
+                } else {
+                    assert_eq!(Octet::zero(), self.A.get(row, col));
+                }
+            }
+        }
+    }
+
+    #[inline(never)]
+    fn third_phase_verify_end(&self) {
+        self.A.verify();
+        for row in 0..self.i {
+            for col in 0..self.i {
+                assert_eq!(self.X.get(row, col), self.A.get(row, col));
+            }
+        }
+    }
+
+    // Fourth phase (section 5.4.2.5). Returns false if it was aborted for exceeding the budget
+    #[allow(non_snake_case)]
+    #[inline(never)]
+    fn fourth_phase(&mut self) -> bool {
+        for i in 0..self.i {
+            if self.over_budget() {
+                return false;
+            }
+            // TODO: optimize for sparse
+            for j in 0..self.u {
+                let b = self.A.get(i, j + self.i);
+                if b != Octet::zero() {
+                    let temp = self.i;
+                    self.fma_rows(temp + j, i, b);
+                }
+            }
+        }
+
+        self.record_symbol_ops(3);
+
+        if self.integrity_check == IntegrityCheck::Full {
+            self.fourth_phase_verify();
+        }
+        true
+    }
+
+    #[inline(never)]
+    fn fourth_phase_verify(&self) {
+        self.A.verify();
+        //    ---------> i u <------
+        //  | +-----------+--------+
+        //  | |\          |        |
+        //  | |  \ Zeros  | Zeros  |
+        //  v |     \     |        |
+        //  i |  X     \  |        |
+        //  u +---------- +--------+
+        //  ^ |           |        |
+        //  | | All Zeros |   I    |
+        //  | |           |        |
+        //    +-----------+--------+
+        // Same assertion about X being equal to the upper left of A
+        self.third_phase_verify_end();
+        assert!(self.all_zeroes(0, self.i, self.A.width() - self.u, self.A.width()));
+        assert!(self.all_zeroes(self.A.height() - self.u, self.A.height(), 0, self.i));
+        for row in (self.A.height() - self.u)..self.A.height() {
+            for col in (self.A.width() - self.u)..self.A.width() {
+                if row == col {
+                    assert_eq!(Octet::one(), self.A.get(row, col));
+                } else {
+                    assert_eq!(Octet::zero(), self.A.get(row, col));
+                }
+            }
+        }
+    }
+
+    // Fifth phase (section 5.4.2.6). Returns false if it was aborted for exceeding the budget
+    #[allow(non_snake_case)]
+    #[inline(never)]
+    fn fifth_phase(&mut self) -> bool {
+        // "For j from 1 to i". Note that A is 1-indexed in the spec, and ranges are inclusive,
+        // this is means [1, i], which is equal to [0, i)
+        for j in 0..self.i as usize {
+            if self.over_budget() {
+                return false;
+            }
+            if self.A.get(j, j) != Octet::one() {
+                let temp = self.A.get(j, j);
+                self.mul_row(j, Octet::one() / temp)
+            }
+            // "For l from 1 to j-1". This means the lower triangular columns, not including the
+            // diagonal, which is [0, j)
+            for (l, _) in self.A.get_row_iter(j, 0, j).clone() {
+                let temp = self.A.get(j, l);
+                if temp != Octet::zero() {
+                    self.fma_rows(l, j, temp);
+                }
+            }
+        }
+
+        self.record_symbol_ops(4);
+
+        if self.integrity_check != IntegrityCheck::Off {
+            self.cheap_verify(self.L);
+        }
+        if self.integrity_check == IntegrityCheck::Full {
+            self.fifth_phase_verify();
+        }
+        true
+    }
+
+    #[inline(never)]
+    fn fifth_phase_verify(&self) {
+        self.A.verify();
+        assert_eq!(self.L, self.A.height());
+        for row in 0..self.A.height() {
+            assert_eq!(self.L, self.A.width());
+            for col in 0..self.A.width() {
+                if row == col {
+                    assert_eq!(Octet::one(), self.A.get(row, col));
+                } else {
+                    assert_eq!(Octet::zero(), self.A.get(row, col));
+                }
+            }
+        }
+    }
+
+    fn record_symbol_ops(&mut self, phase: usize) {
+        self.debug_symbol_add_ops_by_phase[phase] = self.debug_symbol_add_ops;
+        self.debug_symbol_mul_ops_by_phase[phase] = self.debug_symbol_mul_ops;
+        for i in 0..phase {
+            self.debug_symbol_add_ops_by_phase[phase] -= self.debug_symbol_add_ops_by_phase[i];
+            self.debug_symbol_mul_ops_by_phase[phase] -= self.debug_symbol_mul_ops_by_phase[i];
+        }
+    }
+
+    // Reduces the size x size submatrix, starting at row_offset and col_offset as the upper left
+    // corner, to row echelon form. Returns false if the submatrix is singular, or if the budget
+    // was exceeded
+    #[inline(never)]
+    // TODO: optimize for sparse
+    fn reduce_to_row_echelon(&mut self, row_offset: usize, col_offset: usize, size: usize) -> bool {
+        for i in 0..size {
+            if self.over_budget() {
+                return false;
+            }
+            // Swap a row with leading coefficient i into place
+            for j in (row_offset + i)..self.A.height() {
+                if self.A.get(j, col_offset + i) != Octet::zero() {
+                    self.swap_rows(row_offset + i, j);
+                    break;
+                }
+            }
+
+            if self.A.get(row_offset + i, col_offset + i) == Octet::zero() {
+                // If all following rows are zero in this column, then matrix is singular
+                return false;
+            }
+
+            // Scale leading coefficient to 1
+            if self.A.get(row_offset + i, col_offset + i) != Octet::one() {
+                let element_inverse = Octet::one() / self.A.get(row_offset + i, col_offset + i);
+                self.mul_row(row_offset + i, element_inverse);
+            }
+
+            // Zero out all following elements in i'th column
+            for j in (row_offset + i + 1)..self.A.height() {
+                if self.A.get(j, col_offset + i) != Octet::zero() {
+                    let scalar = self.A.get(j, col_offset + i);
+                    self.fma_rows(row_offset + i, j, scalar);
+                }
+            }
+        }
+
+        return true;
+    }
+
+    // Performs backwards elimination in a size x size submatrix, starting at
+    // row_offset and col_offset as the upper left corner of the submatrix. Returns false if it
+    // was aborted for exceeding the budget
+    #[inline(never)]
+    // TODO: optimize for sparse
+    fn backwards_elimination(&mut self, row_offset: usize, col_offset: usize, size: usize) -> bool {
+        // Perform backwards elimination
+        for i in (0..size).rev() {
+            if self.over_budget() {
+                return false;
+            }
+            // Zero out all preceding elements in i'th column
+            for j in 0..i {
+                if self.A.get(row_offset + j, col_offset + i) != Octet::zero() {
+                    let scalar = self.A.get(row_offset + j, col_offset + i);
+                    self.fma_rows(row_offset + i, row_offset + j, scalar);
+                }
+            }
+        }
+        true
+    }
+
+    #[allow(dead_code)]
+    pub fn get_symbol_mul_ops(&self) -> u32 {
+        self.debug_symbol_mul_ops
+    }
+
+    #[allow(dead_code)]
+    pub fn get_symbol_add_ops(&self) -> u32 {
+        self.debug_symbol_add_ops
+    }
+
+    #[allow(dead_code)]
+    pub fn get_symbol_mul_ops_by_phase(&self) -> Vec<u32> {
+        self.debug_symbol_mul_ops_by_phase.clone()
+    }
+
+    #[allow(dead_code)]
+    pub fn get_symbol_add_ops_by_phase(&self) -> Vec<u32> {
+        self.debug_symbol_add_ops_by_phase.clone()
+    }
+
+    // Helper operations to apply operations to A, also to D
+    fn mul_row(&mut self, i: usize, beta: Octet) {
+        self.debug_symbol_mul_ops += 1;
+        apply_symbol_op(
+            &mut self.D,
+            &mut self.recorded_operations,
+            SymbolOp::MulAssign {
+                dest: self.d[i],
+                scalar: beta.clone(),
+            },
+        );
+        self.A.mul_assign_row(i, &beta);
+    }
+
+    fn fma_rows(&mut self, i: usize, iprime: usize, beta: Octet) {
+        if beta == Octet::one() {
+            self.debug_symbol_add_ops += 1;
+            apply_symbol_op(
+                &mut self.D,
+                &mut self.recorded_operations,
+                SymbolOp::AddAssign {
+                    dest: self.d[iprime],
+                    src: self.d[i],
+                },
+            );
+        } else {
+            self.debug_symbol_add_ops += 1;
+            self.debug_symbol_mul_ops += 1;
+            apply_symbol_op(
+                &mut self.D,
+                &mut self.recorded_operations,
+                SymbolOp::FusedAddAssignMul {
+                    dest: self.d[iprime],
+                    src: self.d[i],
+                    scalar: beta.clone(),
+                },
+            );
+        }
+        self.A.fma_rows(iprime, i, &beta);
+    }
+
+    fn swap_rows(&mut self, i: usize, iprime: usize) {
+        self.A.swap_rows(i, iprime);
+        self.d.swap(i, iprime);
+    }
+
+    fn swap_columns(&mut self, j: usize, jprime: usize, start_row: usize) {
+        self.A.swap_columns(j, jprime, start_row);
+        self.c.swap(j, jprime);
+    }
+
+    // Appends rows (with columns in their original order) and their corresponding symbols, after
+    // execute() has failed. They are eliminated in the columns already chosen in the first phase,
+    // as if they had been present from the start, so that execute() can be resumed rather than
+    // starting over.
+    pub fn add_rows(&mut self, rows: &T, symbols: Vec<Symbol>) {
+        assert_eq!(rows.height(), symbols.len());
+        assert_eq!(rows.width(), self.A.width());
+        let first_row = self.A.height();
+        let mut col_position = vec![0; self.c.len()];
+        for (position, original) in self.c.iter().enumerate() {
+            col_position[*original] = position;
+        }
+
+        self.A.add_rows(rows.height());
+        // X is only needed for the first i rows, once the first phase is complete. Until then, its
+        // rows and columns are in their original order
+        let extend_x = !self.first_phase_complete;
+        if extend_x {
+            self.X.add_rows(rows.height());
+        }
+        for (row, symbol) in symbols.into_iter().enumerate() {
+            for (col, value) in rows.get_row_iter(row, 0, rows.width()) {
+                if value != Octet::zero() {
+                    self.A
+                        .set(first_row + row, col_position[col], value.clone());
+                    if extend_x {
+                        self.X.set(first_row + row, col, value);
+                    }
+                }
+            }
+            self.d.push(self.D.len());
+            self.D.push(symbol);
+        }
+
+        for row in first_row..self.A.height() {
+            for col in 0..self.i {
+                let value = self.A.get(row, col);
+                if value != Octet::zero() {
+                    let beta = &value / &self.A.get(col, col);
+                    self.fma_rows(col, row, beta);
+                }
+            }
+        }
+    }
+
+    #[inline(never)]
+    pub fn execute(&mut self) -> Option<Vec<Symbol>> {
+        if self.aborted() {
+            return None;
+        }
+        #[cfg(feature = "failure_injection")]
+        {
+            if self.injected(InjectedFailure::Allocation) {
+                return None;
+            }
+        }
+        if !self.first_phase_complete {
+            self.X.disable_column_acccess_acceleration();
+            // Only the columns of V are ever eliminated or frozen in the first phase
+            let v_end = self.A.width() - self.u;
+            self.A
+                .enable_partial_column_acccess_acceleration(self.i, v_end);
+
+            if !self.first_phase() {
+                return None;
+            }
+
+            self.A.hint_compact_dense_rows();
+            self.A.disable_column_acccess_acceleration();
+            // X is the original matrix with the rows and columns of A reordered in the same way. X
+            // is left as it is during the first phase, and reordered once here, which is cheaper
+            // than repeating every swap on it
+            self.X.apply_row_permutation(&self.d);
+            self.X.apply_column_permutation(&self.c);
+            self.first_phase_complete = true;
+        }
+
+        // If this is a retry after the second phase failed, then the rows which were already
+        // reduced are unaffected, since they have a leading one in place
+
+        if !self.second_phase() || self.over_budget() {
+            return None;
+        }
+
+        // Only compact after second phase because dense rows aren't eliminated from X during first
+        // phase
+        self.X.hint_compact_dense_rows();
+
+        if !self.third_phase() || !self.fourth_phase() || !self.fifth_phase() {
+            return None;
+        }
+
+        // See end of section 5.4.2.1
+        let mut index_mapping = UsizeArrayMap::new(0, self.L);
+        for i in 0..self.L {
+            index_mapping.insert(self.c[i], self.d[i]);
+        }
+
+        #[allow(non_snake_case)]
+        let mut removable_D: Vec<Option<Symbol>> = self.D.drain(..).map(Some).collect();
+
+        let mut result = Vec::with_capacity(self.L);
+        for i in 0..self.L {
+            // push a None so it can be swapped in
+            removable_D.push(None);
+            result.push(removable_D.swap_remove(index_mapping.get(i)).unwrap());
+        }
+        Some(result)
+    }
+}
+
+// Applies the operation to the symbols, and records it if operations are being recorded
+#[allow(non_snake_case)]
+fn apply_symbol_op(D: &mut Vec<Symbol>, recorded: &mut Option<Vec<SymbolOp>>, op: SymbolOp) {
+    op.apply(D);
+    if let Some(operations) = recorded.as_mut() {
+        operations.push(op);
+    }
+}
+
+// Fused implementation for self.inverse().mul_symbols(symbols)
+// See section 5.4.2.1
+pub fn fused_inverse_mul_symbols<T: OctetMatrix>(
+    matrix: T,
+    symbols: Vec<Symbol>,
+    num_source_symbols: u32,
+) -> Option<Vec<Symbol>> {
+    IntermediateSymbolDecoder::new(matrix, symbols, num_source_symbols).execute()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        FirstPhaseRowSelectionStats, IntegrityCheck, IntermediateSymbolDecoder, SolverLimit,
+    };
+    use crate::constraint_matrix::generate_constraint_matrix;
+    use crate::matrix::DenseOctetMatrix;
+    use crate::matrix::OctetMatrix;
+    use crate::octet::Octet;
+    use crate::sparse_matrix::SparseOctetMatrix;
+    use crate::symbol::Symbol;
+    use crate::systematic_constants::extended_source_block_symbols;
+    use crate::systematic_constants::num_pi_symbols;
+    use rand::Rng;
+
+    #[test]
+    fn rows_with_non_zeros_index() {
+        let mut matrix = DenseOctetMatrix::new(4, 4, 0, 0, 0);
+        // Row i has i + 1 non-zeros, and row 3 has a value other than one
+        for row in 0..4 {
+            for col in 0..=row {
+                matrix.set(row, col, Octet::one());
+            }
+        }
+        matrix.set(3, 3, Octet::new(2));
+        let mut stats = FirstPhaseRowSelectionStats::new(&matrix, 0, 4, vec![false; 4]);
+        for r in 1..=4 {
+            assert_eq!(stats.rows_with_non_zeros(r), &[r - 1]);
+        }
+
+        stats.swap_rows(0, 2);
+        assert_eq!(stats.rows_with_non_zeros(1), &[2]);
+        assert_eq!(stats.rows_with_non_zeros(3), &[0]);
+        stats.eliminate_leading_value(0, &Octet::one());
+        let mut rows = stats.rows_with_non_zeros(2).to_vec();
+        rows.sort();
+        assert_eq!(rows, vec![0, 1]);
+
+        matrix.swap_rows(0, 2);
+        matrix.set(3, 0, Octet::zero());
+        stats.recompute_row(3, &matrix);
+        assert!(stats.rows_with_non_zeros(4).is_empty());
+        assert_eq!(stats.rows_with_non_zeros(3), &[3]);
+        assert_eq!(stats.first_phase_selection(0, 4, &matrix).0, Some(2));
+    }
+
+    #[test]
+    fn non_zero_columns_cache() {
+        let mut matrix = DenseOctetMatrix::new(2, 4, 0, 0, 0);
+        matrix.set(0, 2, Octet::one());
+        matrix.set(1, 1, Octet::one());
+        matrix.set(1, 3, Octet::one());
+        let mut stats = FirstPhaseRowSelectionStats::new(&matrix, 0, 4, vec![false; 2]);
+        assert_eq!(stats.non_zero_columns(0, &matrix), [2, 2]);
+        assert_eq!(stats.non_zero_columns(1, &matrix), [1, 3]);
+
+        // Stale entries are detected after columns are swapped
+        matrix.swap_columns(0, 3, 0);
+        assert_eq!(stats.non_zero_columns(1, &matrix), [0, 1]);
+        assert_eq!(stats.non_zero_columns(0, &matrix), [2, 2]);
+    }
+
+    #[test]
+    fn operations_per_symbol() {
+        for &(elements, expected_mul_ops, expected_add_ops) in
+            [(10, 35.0, 50.0), (100, 16.0, 35.0)].iter()
+        {
+            let num_symbols = extended_source_block_symbols(elements);
+            let indices: Vec<u32> = (0..num_symbols).collect();
+            let a = generate_constraint_matrix::<DenseOctetMatrix>(num_symbols, &indices);
+            let symbols = vec![Symbol::zero(1usize); a.width()];
+            let mut decoder = IntermediateSymbolDecoder::new(a, symbols, num_symbols);
+            decoder.execute();
+            assert!(
+                (decoder.get_symbol_mul_ops() as f64 / num_symbols as f64) < expected_mul_ops,
+                "mul ops per symbol = {}",
+                (decoder.get_symbol_mul_ops() as f64 / num_symbols as f64)
+            );
+            assert!(
+                (decoder.get_symbol_add_ops() as f64 / num_symbols as f64) < expected_add_ops,
+                "add ops per symbol = {}",
+                (decoder.get_symbol_add_ops() as f64 / num_symbols as f64)
+            );
+        }
+    }
+
+    #[test]
+    fn max_symbol_add_ops() {
+        let num_symbols = extended_source_block_symbols(100);
+        let indices: Vec<u32> = (0..num_symbols).collect();
+        let a = generate_constraint_matrix::<DenseOctetMatrix>(num_symbols, &indices);
+        let symbols = vec![Symbol::zero(1usize); a.width()];
+        let mut decoder = IntermediateSymbolDecoder::new(a, symbols, num_symbols);
+        decoder.set_max_symbol_add_ops(num_symbols);
+        assert!(decoder.execute().is_none());
+        assert!(decoder.aborted());
+        assert!(decoder.get_symbol_add_ops() > num_symbols);
+        // An aborted solver can't be resumed
+        assert!(decoder.execute().is_none());
+    }
+
+    #[test]
+    fn max_symbol_add_ops_after_second_phase() {
+        let num_symbols = extended_source_block_symbols(100);
+        let indices: Vec<u32> = (0..num_symbols).collect();
+        let a = generate_constraint_matrix::<DenseOctetMatrix>(num_symbols, &indices);
+        let symbols = vec![Symbol::zero(1usize); a.width()];
+
+        let mut decoder = IntermediateSymbolDecoder::new(a.clone(), symbols.clone(), num_symbols);
+        decoder.execute().unwrap();
+        let by_phase = decoder.get_symbol_add_ops_by_phase();
+        let after_second_phase = by_phase[0] + by_phase[1];
+        assert!(by_phase[2] > 2 * num_symbols);
+
+        // The budget runs out in the third phase, which stops there rather than running to
+        // completion
+        let mut decoder = IntermediateSymbolDecoder::new(a, symbols, num_symbols);
+        decoder.set_max_symbol_add_ops(after_second_phase + 1);
+        assert!(decoder.execute().is_none());
+        assert!(decoder.aborted());
+        assert!(decoder.get_symbol_add_ops() <= after_second_phase + 1 + num_symbols);
+    }
+
+    #[test]
+    fn max_symbol_add_ops_in_second_phase() {
+        let num_symbols = extended_source_block_symbols(100);
+        let indices: Vec<u32> = (0..num_symbols).collect();
+        let a = generate_constraint_matrix::<DenseOctetMatrix>(num_symbols, &indices);
+        let symbols = vec![Symbol::zero(1usize); a.width()];
+
+        let mut decoder = IntermediateSymbolDecoder::new(a.clone(), symbols.clone(), num_symbols);
+        decoder.execute().unwrap();
+        let by_phase = decoder.get_symbol_add_ops_by_phase();
+        let after_second_phase = by_phase[0] + by_phase[1];
+        assert!(by_phase[1] > 2 * a.height() as u32);
+
+        // The budget runs out half way through the second phase, which stops within a pivot of
+        // the limit
+        let limit = by_phase[0] + by_phase[1] / 2;
+        let mut decoder = IntermediateSymbolDecoder::new(a.clone(), symbols, num_symbols);
+        decoder.set_max_symbol_add_ops(limit);
+        assert!(decoder.execute().is_none());
+        assert!(decoder.aborted());
+        assert!(decoder.get_symbol_add_ops() <= limit + a.height() as u32);
+        assert!(decoder.get_symbol_add_ops() < after_second_phase);
+    }
+
+    #[test]
+    fn max_inactivated_columns() {
+        let num_symbols = extended_source_block_symbols(100);
+        let indices: Vec<u32> = (0..num_symbols).collect();
+        let a = generate_constraint_matrix::<DenseOctetMatrix>(num_symbols, &indices);
+        let symbols = vec![Symbol::zero(1usize); a.width()];
+        let pi_symbols = num_pi_symbols(num_symbols) as usize;
+
+        let mut decoder = IntermediateSymbolDecoder::new(a.clone(), symbols.clone(), num_symbols);
+        decoder.set_max_inactivated_columns(pi_symbols);
+        assert!(decoder.execute().is_none());
+        assert_eq!(decoder.exceeded_limit(), Some(SolverLimit::Density));
+
+        let mut decoder = IntermediateSymbolDecoder::new(a, symbols, num_symbols);
+        decoder.set_max_inactivated_columns(pi_symbols + 50);
+        assert!(decoder.execute().is_some());
+        assert!(!decoder.aborted());
+    }
+
+    #[test]
+    fn integrity_check() {
+        let num_symbols = extended_source_block_symbols(100);
+        let indices: Vec<u32> = (0..num_symbols).collect();
+        let a = generate_constraint_matrix::<SparseOctetMatrix>(num_symbols, &indices);
+        let symbols: Vec<Symbol> = (0..a.width())
+            .map(|i| Symbol::new(vec![i as u8; 4]))
+            .collect();
+        let mut results = vec![];
+        for &level in [
+            IntegrityCheck::Off,
+            IntegrityCheck::Cheap,
+            IntegrityCheck::Full,
+        ]
+        .iter()
+        {
+            let mut decoder =
+                IntermediateSymbolDecoder::new(a.clone(), symbols.clone(), num_symbols);
+            decoder.set_integrity_check(level);
+            results.push(decoder.execute().unwrap());
+        }
+        assert_eq!(results[0], results[1]);
+        assert_eq!(results[0], results[2]);
+    }
+
+    #[test]
+    #[should_panic(expected = "appears twice in the permutation")]
+    fn integrity_check_detects_corruption() {
+        let num_symbols = extended_source_block_symbols(100);
+        let indices: Vec<u32> = (0..num_symbols).collect();
+        let a = generate_constraint_matrix::<SparseOctetMatrix>(num_symbols, &indices);
+        let symbols = vec![Symbol::zero(1usize); a.width()];
+        let mut decoder = IntermediateSymbolDecoder::new(a, symbols, num_symbols);
+        decoder.set_integrity_check(IntegrityCheck::Cheap);
+        decoder.d[1] = decoder.d[0];
+        decoder.execute();
+    }
+
+    #[test]
+    fn permutations() {
+        let num_symbols = extended_source_block_symbols(100);
+        let indices: Vec<u32> = (0..num_symbols).collect();
+        let a = generate_constraint_matrix::<SparseOctetMatrix>(num_symbols, &indices);
+        let symbols = vec![Symbol::zero(1usize); a.width()];
+        let width = a.width();
+        let mut decoder = IntermediateSymbolDecoder::new(a.clone(), symbols.clone(), num_symbols);
+        assert!(decoder.execute().is_some());
+        // Not recorded
+        assert!(decoder.permutations().first_phase_pivots().is_empty());
+
+        let mut decoder = IntermediateSymbolDecoder::new(a, symbols, num_symbols);
+        decoder.record_permutations();
+        assert!(decoder.execute().is_some());
+        let permutations = decoder.permutations();
+
+        let is_permutation = |values: &[usize], len: usize| {
+            let mut sorted = values.to_vec();
+            sorted.sort_unstable();
+            sorted == (0..len).collect::<Vec<usize>>()
+        };
+        assert!(is_permutation(permutations.row_order(), width));
+        assert!(is_permutation(permutations.column_order(), width));
+        assert_eq!(permutations.physical_rows().len(), width);
+        assert_eq!(permutations.physical_columns().len(), width);
+        // Every column is either pivoted on in the first phase, or inactivated
+        let pivots = permutations.first_phase_pivots();
+        assert_eq!(pivots.len() + permutations.inactivated_columns(), width);
+        let inactivated: usize = pivots.iter().map(|(_, r)| r - 1).sum();
+        assert_eq!(
+            permutations.inactivated_columns(),
+            num_pi_symbols(num_symbols) as usize + inactivated
+        );
+        for (i, (row, _)) in pivots.iter().enumerate() {
+            assert_eq!(permutations.row_order()[i], *row);
+        }
+    }
+
+    #[test]
+    fn resume_after_first_phase_failure() {
+        let num_symbols = extended_source_block_symbols(10);
+        let indices: Vec<u32> = (0..(num_symbols + 40)).collect();
+        let a = generate_constraint_matrix::<DenseOctetMatrix>(num_symbols, &indices);
+        let mut intermediate_symbols = vec![];
+        for _ in 0..a.width() {
+            intermediate_symbols.push(Symbol::new(vec![rand::thread_rng().gen()]));
+        }
+        let encoded_symbol = |row: usize| {
+            let mut value = Octet::zero();
+            for (col, symbol) in intermediate_symbols.iter().enumerate() {
+                value += &a.get(row, col) * &Octet::new(symbol.as_bytes()[0]);
+            }
+            Symbol::new(vec![value.byte()])
+        };
+
+        // Hold back every row which has a non-zero in column 0, so that the first phase fails
+        let (held_back, initial): (Vec<usize>, Vec<usize>) =
+            (0..a.height()).partition(|&row| a.get(row, 0) != Octet::zero());
+        let copy_rows = |rows: &[usize]| {
+            let mut matrix = DenseOctetMatrix::new(rows.len(), a.width(), 0, 0, 0);
+            for (i, &row) in rows.iter().enumerate() {
+                for col in 0..a.width() {
+                    matrix.set(i, col, a.get(row, col));
+                }
+            }
+            matrix
+        };
+
+        let mut decoder = IntermediateSymbolDecoder::new(
+            copy_rows(&initial),
+            initial.iter().map(|&row| encoded_symbol(row)).collect(),
+            num_symbols,
+        );
+        assert_eq!(None, decoder.execute());
+
+        decoder.add_rows(
+            &copy_rows(&held_back),
+            held_back.iter().map(|&row| encoded_symbol(row)).collect(),
+        );
+        assert_eq!(Some(intermediate_symbols.clone()), decoder.execute());
+    }
+
+    #[cfg(feature = "failure_injection")]
+    #[test]
+    fn resume_after_second_phase_failure() {
+        use crate::failure_injection::InjectedFailure;
+
+        let num_symbols = extended_source_block_symbols(10);
+        let indices: Vec<u32> = (0..(num_symbols + 10)).collect();
+        let a = generate_constraint_matrix::<DenseOctetMatrix>(num_symbols, &indices);
+        let mut intermediate_symbols = vec![];
+        for _ in 0..a.width() {
+            intermediate_symbols.push(Symbol::new(vec![rand::thread_rng().gen()]));
+        }
+        let encoded_symbol = |row: usize| {
+            let mut value = Octet::zero();
+            for (col, symbol) in intermediate_symbols.iter().enumerate() {
+                value += &a.get(row, col) * &Octet::new(symbol.as_bytes()[0]);
+            }
+            Symbol::new(vec![value.byte()])
+        };
+        let copy_rows = |rows: &[usize]| {
+            let mut matrix = DenseOctetMatrix::new(rows.len(), a.width(), 0, 0, 0);
+            for (i, &row) in rows.iter().enumerate() {
+                for col in 0..a.width() {
+                    matrix.set(i, col, a.get(row, col));
+                }
+            }
+            matrix
+        };
+        let all_rows: Vec<usize> = (0..a.height()).collect();
+        let (initial, extra) = all_rows.split_at(a.width() + 2);
+
+        let mut fresh = IntermediateSymbolDecoder::new(
+            copy_rows(&all_rows),
+            all_rows.iter().map(|&row| encoded_symbol(row)).collect(),
+            num_symbols,
+        );
+        let expected = fresh.execute();
+        assert_eq!(Some(intermediate_symbols.clone()), expected);
+
+        let mut decoder = IntermediateSymbolDecoder::new(
+            copy_rows(initial),
+            initial.iter().map(|&row| encoded_symbol(row)).collect(),
+            num_symbols,
+        );
+        decoder.set_injected_failure(Some(InjectedFailure::SingularMatrix));
+        assert_eq!(None, decoder.execute());
+        assert!(!decoder.aborted());
+
+        // The first phase is not repeated, and the added rows take part in the second
+        decoder.set_injected_failure(None);
+        decoder.add_rows(
+            &copy_rows(extra),
+            extra.iter().map(|&row| encoded_symbol(row)).collect(),
+        );
+        assert_eq!(expected, decoder.execute());
+    }
+}
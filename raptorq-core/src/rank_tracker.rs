@@ -0,0 +1,80 @@
+use crate::util::div_ceil;
+use alloc::vec;
+use alloc::vec::Vec;
+use serde::{Deserialize, Serialize};
+
+// Tracks the rank of a set of binary rows, by keeping them in row echelon form as they are added.
+//
+// All rows of the constraint matrix, except the H HDPC rows, are binary. Rows which are linearly
+// independent over GF(2) remain so over GF(256), because rank is unchanged by extending the
+// field, so the rank of the binary rows is exact, and the rank of the whole constraint matrix is
+// at most H more.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct BinaryRankTracker {
+    words: usize,
+    // Indexed by the lowest column which is one in the reduced row
+    pivots: Vec<Option<Vec<u64>>>,
+    rank: usize,
+}
+
+impl BinaryRankTracker {
+    pub fn new(width: usize) -> BinaryRankTracker {
+        BinaryRankTracker {
+            words: div_ceil(width as u64, 64) as usize,
+            pivots: vec![None; width],
+            rank: 0,
+        }
+    }
+
+    pub fn rank(&self) -> usize {
+        self.rank
+    }
+
+    // Adds the row with ones in the given columns, and returns whether it was linearly
+    // independent of the previously added rows
+    pub fn add_row(&mut self, columns: &[usize]) -> bool {
+        let mut row = vec![0u64; self.words];
+        for &col in columns.iter() {
+            row[col / 64] ^= 1 << (col % 64);
+        }
+        let mut word = 0;
+        while word < self.words {
+            if row[word] == 0 {
+                word += 1;
+                continue;
+            }
+            let col = word * 64 + row[word].trailing_zeros() as usize;
+            match self.pivots[col] {
+                Some(ref pivot) => {
+                    // The pivot has no ones before col, so only the remaining words change
+                    for (value, pivot_value) in row[word..].iter_mut().zip(pivot[word..].iter()) {
+                        *value ^= pivot_value;
+                    }
+                }
+                None => {
+                    self.pivots[col] = Some(row);
+                    self.rank += 1;
+                    return true;
+                }
+            }
+        }
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::rank_tracker::BinaryRankTracker;
+
+    #[test]
+    fn rank() {
+        let mut tracker = BinaryRankTracker::new(130);
+        assert!(tracker.add_row(&[0, 64, 129]));
+        assert!(tracker.add_row(&[64, 100]));
+        // Sum of the first two rows
+        assert!(!tracker.add_row(&[0, 100, 129]));
+        assert!(!tracker.add_row(&[]));
+        assert!(tracker.add_row(&[129]));
+        assert_eq!(3, tracker.rank());
+    }
+}
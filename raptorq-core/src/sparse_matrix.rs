@@ -0,0 +1,947 @@
+use crate::iterators::{BorrowedKeyIter, OctetIter};
+use crate::matrix::{mul_add_tiled, OctetMatrix};
+use crate::octet::Octet;
+use crate::octets::{add_assign, mulassign_scalar};
+use crate::octets::{count_ones_and_nonzeros, fused_addassign_mul_scalar};
+use crate::sparse_vec::{SparseOctetVec, SparseValuelessVec};
+use crate::util::get_both_indices;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::cmp::{max, min};
+use serde::{Deserialize, Serialize};
+
+// While the column index is enabled, a sparse row is converted to dense storage once more than
+// 1/DENSE_ROW_DIVISOR of the sparse columns are nonzero, and a dense row back to sparse storage
+// once fewer than half that many are. This bounds the cost of each operation on a row which fills
+// up during elimination, while the gap avoids converting the same row back and forth
+const DENSE_ROW_DIVISOR: usize = 8;
+
+// Stores a matrix in sparse representation, with an optional dense block for the right most columns,
+// and optional dense rows.
+// The logical storage is as follows:
+// |---------------------------------------|
+// |  sparse rows             | (optional) |
+// |--------------------------| dense      |
+// |  (optional) dense rows   | columns    |
+// |---------------------------------------|
+// The physical ids of rows are, and dense columns are read first before the rows:
+// |--------------------------|
+// |  sparse rows             |
+// |--------------------------|
+// |  (optional) dense rows   |
+// |--------------------------|
+// Rows move between the two while the column index is enabled, see DENSE_ROW_DIVISOR
+#[derive(Clone, Debug, PartialEq, PartialOrd, Eq, Ord, Serialize, Deserialize, Hash)]
+pub struct SparseOctetMatrix {
+    height: usize,
+    width: usize,
+    sparse_elements: Vec<SparseOctetVec>,
+    // Optional dense rows. These have physical indices starting at sparse_elements.len()
+    // Note: these rows use logical column indices, not physical
+    dense_rows: Vec<Vec<u8>>,
+    // Number of nonzero values in each of dense_rows
+    dense_row_nonzeros: Vec<usize>,
+    // Note these are stored with the right-most element first in the vec.
+    // That is, for a matrix with width 10 and num_dense 3, the last three will be stored in these
+    // Vecs, and will be in the order: [9, 8, 7]
+    // There may be extra zeros padded at the end too, for efficiency
+    dense_elements: Vec<Vec<u8>>,
+    // Sparse vector indicating which rows may have a non-zero value in the given column
+    // Does not guarantee that the row has a non-zero value, since FMA may have added to zero
+    sparse_column_index: Vec<SparseValuelessVec>,
+    // Whether each physical column is in sparse_column_index. Only the columns of the V region are
+    // indexed by the solver, and the entries of the other columns are empty and never updated
+    indexed_columns: Vec<bool>,
+    // Mapping of logical row numbers to index in sparse_elements, dense_elements, and sparse_column_index
+    logical_row_to_physical: Vec<usize>,
+    physical_row_to_logical: Vec<usize>,
+    logical_col_to_physical: Vec<usize>,
+    physical_col_to_logical: Vec<usize>,
+    column_index_disabled: bool,
+    num_dense_columns: usize,
+}
+
+impl SparseOctetMatrix {
+    fn is_column_indexed(&self, physical_col: usize) -> bool {
+        !self.column_index_disabled && self.indexed_columns[physical_col]
+    }
+
+    fn sparse_width(&self) -> usize {
+        self.width - self.num_dense_columns
+    }
+
+    // Swaps the mappings and dense columns of two physical rows, but not their other storage
+    fn swap_physical_rows(&mut self, physical_i: usize, physical_j: usize) {
+        let logical_i = self.physical_row_to_logical[physical_i];
+        let logical_j = self.physical_row_to_logical[physical_j];
+        self.physical_row_to_logical.swap(physical_i, physical_j);
+        self.logical_row_to_physical[logical_i] = physical_j;
+        self.logical_row_to_physical[logical_j] = physical_i;
+        self.dense_elements.swap(physical_i, physical_j);
+    }
+
+    // Adds the index entries of a sparse row, which has just moved to this physical index. This
+    // includes the frozen columns, since they are still read from the index by the solver
+    fn index_sparse_row(&mut self, physical_row: usize) {
+        if self.column_index_disabled {
+            return;
+        }
+        for (physical_col, _) in self.sparse_elements[physical_row].keys_values() {
+            if self.indexed_columns[*physical_col] {
+                self.sparse_column_index[*physical_col].insert(physical_row);
+            }
+        }
+        for logical_col in self.sparse_width()..self.width {
+            let physical_col = self.logical_col_to_physical[logical_col];
+            if self.indexed_columns[physical_col]
+                && self.dense_elements[physical_row][self.width - logical_col - 1] != 0
+            {
+                self.sparse_column_index[physical_col].insert(physical_row);
+            }
+        }
+    }
+
+    // Moves a sparse row to dense storage. It becomes the first dense row, and the last sparse row
+    // takes its place
+    fn convert_row_to_dense(&mut self, physical_row: usize) {
+        let last_sparse = self.sparse_elements.len() - 1;
+        if physical_row != last_sparse {
+            self.sparse_elements.swap(physical_row, last_sparse);
+            self.swap_physical_rows(physical_row, last_sparse);
+            // The index entries of the moved row are left in place, and ignored once it is dense
+            self.index_sparse_row(physical_row);
+        }
+        let sparse = self.sparse_elements.pop().unwrap();
+        let mut dense = vec![0; self.sparse_width()];
+        let mut nonzeros = 0;
+        for (physical_col, value) in sparse.keys_values() {
+            if *value != Octet::zero() {
+                dense[self.physical_col_to_logical[*physical_col]] = value.byte();
+                nonzeros += 1;
+            }
+        }
+        self.dense_rows.insert(0, dense);
+        self.dense_row_nonzeros.insert(0, nonzeros);
+    }
+
+    // Moves a dense row to sparse storage. It becomes the last sparse row, and the first dense row
+    // takes its place
+    fn convert_row_to_sparse(&mut self, dense_index: usize) {
+        let first_dense = self.sparse_elements.len();
+        if dense_index != 0 {
+            self.dense_rows.swap(0, dense_index);
+            self.dense_row_nonzeros.swap(0, dense_index);
+            self.swap_physical_rows(first_dense, first_dense + dense_index);
+        }
+        let dense = self.dense_rows.remove(0);
+        self.dense_row_nonzeros.remove(0);
+        let elements = dense
+            .iter()
+            .enumerate()
+            .filter(|(_, value)| **value != 0)
+            .map(|(logical_col, value)| {
+                (
+                    self.logical_col_to_physical[logical_col],
+                    Octet::new(*value),
+                )
+            })
+            .collect();
+        self.sparse_elements
+            .push(SparseOctetVec::from_elements(elements));
+        self.index_sparse_row(first_dense);
+    }
+
+    fn convert_row_to_dense_if_full(&mut self, physical_row: usize) {
+        if !self.column_index_disabled
+            && self.sparse_elements[physical_row].len() * DENSE_ROW_DIVISOR > self.sparse_width()
+        {
+            self.convert_row_to_dense(physical_row);
+        }
+    }
+}
+
+impl OctetMatrix for SparseOctetMatrix {
+    fn new(
+        height: usize,
+        width: usize,
+        trailing_dense_column_hint: usize,
+        start_dense_row_hint: usize,
+        num_dense_rows_hint: usize,
+    ) -> SparseOctetMatrix {
+        let mut col_mapping = vec![0; width];
+        let elements = vec![SparseOctetVec::with_capacity(10); height - num_dense_rows_hint];
+        let mut dense_rows = Vec::with_capacity(num_dense_rows_hint);
+        for _ in 0..num_dense_rows_hint {
+            dense_rows.push(vec![0; width - trailing_dense_column_hint]);
+        }
+        let mut logical_row_to_physical = vec![0; height];
+        let mut physical_row_to_logical = vec![0; height];
+        // HDPC rows are stored in dense format. They are in the middle (logically) in the matrix
+        for i in 0..start_dense_row_hint {
+            logical_row_to_physical[i] = i;
+            physical_row_to_logical[i] = i;
+        }
+        for i in start_dense_row_hint..(start_dense_row_hint + num_dense_rows_hint) {
+            logical_row_to_physical[i] = i - start_dense_row_hint + elements.len();
+            physical_row_to_logical[i - start_dense_row_hint + elements.len()] = i;
+        }
+        for i in (start_dense_row_hint + num_dense_rows_hint)..height {
+            logical_row_to_physical[i] = i - num_dense_rows_hint;
+            physical_row_to_logical[i - num_dense_rows_hint] = i;
+        }
+        let mut dense_elements = Vec::with_capacity(height);
+        for _ in 0..height {
+            dense_elements.push(vec![0; 2 * trailing_dense_column_hint]);
+        }
+        #[allow(clippy::needless_range_loop)]
+        for i in 0..width {
+            col_mapping[i] = i;
+        }
+        SparseOctetMatrix {
+            height,
+            width,
+            sparse_elements: elements,
+            dense_rows,
+            dense_row_nonzeros: vec![0; num_dense_rows_hint],
+            dense_elements,
+            sparse_column_index: vec![],
+            indexed_columns: vec![],
+            logical_row_to_physical,
+            physical_row_to_logical,
+            logical_col_to_physical: col_mapping.clone(),
+            physical_col_to_logical: col_mapping,
+            column_index_disabled: true,
+            num_dense_columns: trailing_dense_column_hint,
+        }
+    }
+
+    fn set(&mut self, i: usize, j: usize, value: Octet) {
+        let physical_i = self.logical_row_to_physical[i];
+        let physical_j = self.logical_col_to_physical[j];
+        if self.width - j <= self.num_dense_columns {
+            self.dense_elements[physical_i][self.width - j - 1] = value.byte();
+        } else if physical_i >= self.sparse_elements.len() {
+            let dense_index = physical_i - self.sparse_elements.len();
+            let previous = core::mem::replace(&mut self.dense_rows[dense_index][j], value.byte());
+            if previous == 0 && value != Octet::zero() {
+                self.dense_row_nonzeros[dense_index] += 1;
+            } else if previous != 0 && value == Octet::zero() {
+                self.dense_row_nonzeros[dense_index] -= 1;
+            }
+        } else {
+            let previous = self.sparse_elements[physical_i].insert(physical_j, value);
+            if previous.is_none() && self.is_column_indexed(physical_j) {
+                self.sparse_column_index[physical_j].insert(physical_i);
+            }
+            if previous.is_none() {
+                self.convert_row_to_dense_if_full(physical_i);
+            }
+        }
+    }
+
+    fn height(&self) -> usize {
+        self.height
+    }
+
+    fn width(&self) -> usize {
+        self.width
+    }
+
+    fn count_ones_and_nonzeros(
+        &self,
+        row: usize,
+        start_col: usize,
+        end_col: usize,
+    ) -> (usize, usize) {
+        let physical_row = self.logical_row_to_physical[row];
+        // Columns from first_dense_col onwards are stored in dense_elements
+        let first_dense_col = self.width - self.num_dense_columns;
+        let (mut ones, mut nonzeros) = if end_col > first_dense_col {
+            let dense_start = max(start_col, first_dense_col);
+            // Stored right-most first
+            count_ones_and_nonzeros(
+                &self.dense_elements[physical_row]
+                    [(self.width - end_col)..(self.width - dense_start)],
+            )
+        } else {
+            (0, 0)
+        };
+        let start_col = min(start_col, first_dense_col);
+        let end_col = min(end_col, first_dense_col);
+        if physical_row >= self.sparse_elements.len() {
+            let (sparse_ones, sparse_nonzeros) = count_ones_and_nonzeros(
+                &self.dense_rows[physical_row - self.sparse_elements.len()][start_col..end_col],
+            );
+            ones += sparse_ones;
+            nonzeros += sparse_nonzeros;
+        } else {
+            for (physical_col, value) in self.sparse_elements[physical_row].keys_values() {
+                let col = self.physical_col_to_logical[*physical_col];
+                if col >= start_col && col < end_col {
+                    if *value == Octet::one() {
+                        ones += 1;
+                    }
+                    if *value != Octet::zero() {
+                        nonzeros += 1;
+                    }
+                }
+            }
+        }
+        return (ones, nonzeros);
+    }
+
+    fn mul_assign_row(&mut self, row: usize, value: &Octet) {
+        let physical_row = self.logical_row_to_physical[row];
+        if physical_row >= self.sparse_elements.len() {
+            let dense_index = physical_row - self.sparse_elements.len();
+            mulassign_scalar(&mut self.dense_rows[dense_index], value);
+            if *value == Octet::zero() {
+                self.dense_row_nonzeros[dense_index] = 0;
+            }
+        } else {
+            self.sparse_elements[physical_row].mul_assign(value);
+        }
+        mulassign_scalar(
+            &mut self.dense_elements[physical_row][..self.num_dense_columns],
+            value,
+        );
+    }
+
+    fn get(&self, i: usize, j: usize) -> Octet {
+        let physical_i = self.logical_row_to_physical[i];
+        let physical_j = self.logical_col_to_physical[j];
+        if self.width - j <= self.num_dense_columns {
+            return Octet::new(self.dense_elements[physical_i][self.width - j - 1]);
+        } else if physical_i >= self.sparse_elements.len() {
+            return Octet::new(self.dense_rows[physical_i - self.sparse_elements.len()][j]);
+        } else {
+            return self.sparse_elements[physical_i]
+                .get(physical_j)
+                .unwrap_or(&Octet::zero())
+                .clone();
+        }
+    }
+
+    fn get_row_iter(&self, row: usize, start_col: usize, end_col: usize) -> OctetIter {
+        let physical_row = self.logical_row_to_physical[row];
+        // Columns from first_dense_col onwards are stored in dense_elements
+        let first_dense_col = self.width - self.num_dense_columns;
+        let row_start_col = min(start_col, first_dense_col);
+        let row_end_col = min(end_col, first_dense_col);
+        let iter = if physical_row >= self.sparse_elements.len() {
+            OctetIter::new_dense(
+                row_start_col,
+                row_end_col,
+                &self.dense_rows[physical_row - self.sparse_elements.len()],
+                row_start_col,
+            )
+        } else {
+            OctetIter::new_sparse(
+                row_start_col,
+                row_end_col,
+                &self.sparse_elements[physical_row],
+                &self.physical_col_to_logical,
+            )
+        };
+        if end_col > first_dense_col {
+            return iter.with_dense_columns(
+                max(start_col, first_dense_col),
+                end_col,
+                &self.dense_elements[physical_row],
+                self.width,
+            );
+        }
+        iter
+    }
+
+    fn get_col_index_iter(&self, col: usize, start_row: usize, end_row: usize) -> BorrowedKeyIter {
+        let physical_col = self.logical_col_to_physical[col];
+        assert!(self.is_column_indexed(physical_col));
+        BorrowedKeyIter::new_sparse(
+            &self.sparse_column_index[physical_col],
+            start_row,
+            end_row,
+            self.sparse_elements.len(),
+            self.height,
+            &self.physical_row_to_logical,
+        )
+    }
+
+    fn swap_rows(&mut self, i: usize, j: usize) {
+        let physical_i = self.logical_row_to_physical[i];
+        let physical_j = self.logical_row_to_physical[j];
+        self.logical_row_to_physical.swap(i, j);
+        self.physical_row_to_logical.swap(physical_i, physical_j);
+    }
+
+    fn swap_columns(&mut self, i: usize, j: usize, _: usize) {
+        if j >= self.width - self.num_dense_columns {
+            unimplemented!("It was assumed that this wouldn't be needed, because the method would only be called on the V section of matrix A");
+        }
+
+        let physical_i = self.logical_col_to_physical[i];
+        let physical_j = self.logical_col_to_physical[j];
+        self.logical_col_to_physical.swap(i, j);
+        self.physical_col_to_logical.swap(physical_i, physical_j);
+        // dense_rows uses logical col indices, so values need to be swapped
+        for row in self.dense_rows.iter_mut() {
+            row.swap(i, j);
+        }
+    }
+
+    fn apply_row_permutation(&mut self, permutation: &[usize]) {
+        assert_eq!(permutation.len(), self.height);
+        // Only the mapping changes, since rows are stored by their physical index
+        self.logical_row_to_physical = permutation
+            .iter()
+            .map(|&i| self.logical_row_to_physical[i])
+            .collect();
+        for (logical, &physical) in self.logical_row_to_physical.iter().enumerate() {
+            self.physical_row_to_logical[physical] = logical;
+        }
+    }
+
+    fn apply_column_permutation(&mut self, permutation: &[usize]) {
+        assert_eq!(permutation.len(), self.width);
+        let sparse_width = self.width - self.num_dense_columns;
+        if permutation[sparse_width..]
+            .iter()
+            .enumerate()
+            .any(|(j, &previous)| previous != sparse_width + j)
+        {
+            unimplemented!("It was assumed that this wouldn't be needed, because the method would only be called on the V section of matrix A");
+        }
+        self.logical_col_to_physical = permutation
+            .iter()
+            .map(|&j| self.logical_col_to_physical[j])
+            .collect();
+        for (logical, &physical) in self.logical_col_to_physical.iter().enumerate() {
+            self.physical_col_to_logical[physical] = logical;
+        }
+        // dense_rows uses logical col indices, so values need to be moved
+        for row in self.dense_rows.iter_mut() {
+            *row = permutation[..row.len()].iter().map(|&j| row[j]).collect();
+        }
+    }
+
+    fn enable_column_acccess_acceleration(&mut self) {
+        self.enable_partial_column_acccess_acceleration(0, self.width);
+    }
+
+    fn enable_partial_column_acccess_acceleration(&mut self, start_col: usize, end_col: usize) {
+        self.column_index_disabled = false;
+        self.indexed_columns = vec![false; self.width];
+        for logical_col in start_col..min(end_col, self.width - self.num_dense_columns) {
+            self.indexed_columns[self.logical_col_to_physical[logical_col]] = true;
+        }
+        // Count the entries of each column first, so that each is allocated once with exactly the
+        // capacity it needs, and the unindexed columns not at all
+        let mut counts = vec![0; self.width];
+        for elements in self.sparse_elements.iter() {
+            for (physical_col, _) in elements.keys_values() {
+                counts[*physical_col] += 1;
+            }
+        }
+        self.sparse_column_index = counts
+            .iter()
+            .zip(self.indexed_columns.iter())
+            .map(|(&count, &indexed)| {
+                SparseValuelessVec::with_capacity(if indexed { count } else { 0 })
+            })
+            .collect();
+        for (physical_row, elements) in self.sparse_elements.iter().enumerate() {
+            for (physical_col, _) in elements.keys_values() {
+                if self.indexed_columns[*physical_col] {
+                    self.sparse_column_index[*physical_col].insert_last(physical_row);
+                }
+            }
+        }
+        // Don't add dense rows to sparse column index because dense rows are always included by
+        // get_col_iter()
+    }
+
+    fn disable_column_acccess_acceleration(&mut self) {
+        self.column_index_disabled = true;
+        self.sparse_column_index.clear();
+        self.indexed_columns.clear();
+    }
+
+    fn hint_compact_dense_rows(&mut self) {
+        while !self.dense_rows.is_empty() {
+            self.convert_row_to_sparse(0);
+        }
+    }
+
+    fn hint_column_dense_and_frozen(&mut self, i: usize) {
+        assert_eq!(
+            self.width - self.num_dense_columns - 1,
+            i,
+            "Can only freeze the last sparse column"
+        );
+        assert!(self.is_column_indexed(self.logical_col_to_physical[i]));
+        self.num_dense_columns += 1;
+        for i in 0..self.dense_elements.len() {
+            if self.dense_elements[i].len() < self.num_dense_columns {
+                // Add 10 more zeros at a time to amortize the cost
+                self.dense_elements[i].extend_from_slice(&[0; 10]);
+            }
+        }
+        let physical_i = self.logical_col_to_physical[i];
+        for maybe_present_in_row in self.sparse_column_index[physical_i].keys() {
+            let physical_row = *maybe_present_in_row;
+            // Index entries of rows which are now dense are stale, and those are handled below
+            if physical_row >= self.sparse_elements.len() {
+                continue;
+            }
+            if let Some(value) = self.sparse_elements[physical_row].remove(physical_i) {
+                self.dense_elements[physical_row][self.num_dense_columns - 1] = value.byte();
+            }
+        }
+
+        for physical_row in self.sparse_elements.len()..self.height {
+            // The value is left in dense_rows, since the physical col isn't removed from
+            // row storage. The dense cols are consulted first when looking up a value.
+            let dense_index = physical_row - self.sparse_elements.len();
+            let value = self.dense_rows[dense_index][i];
+            self.dense_elements[physical_row][self.num_dense_columns - 1] = value;
+            self.dense_rows[dense_index][i] = 0;
+            if value != 0 {
+                self.dense_row_nonzeros[dense_index] -= 1;
+            }
+        }
+
+        // Freezing shrinks the sparse columns, so rows which have emptied out may be stored
+        // sparse again. Converting a row moves another one to its index, which is then checked
+        let mut dense_index = 0;
+        while dense_index < self.dense_rows.len() {
+            if self.dense_row_nonzeros[dense_index] * 2 * DENSE_ROW_DIVISOR < self.sparse_width() {
+                self.convert_row_to_sparse(dense_index);
+            } else {
+                dense_index += 1;
+            }
+        }
+    }
+
+    // other must be a rows x rows matrix
+    // sets self[0..rows][..] = X * self[0..rows][..]
+    fn mul_assign_submatrix(&mut self, other: &SparseOctetMatrix, rows: usize) {
+        assert_eq!(rows, other.height());
+        assert_eq!(rows, other.width());
+        assert!(rows <= self.height());
+        if other.num_dense_columns != 0 {
+            unimplemented!();
+        }
+        // The product is computed on sparse rows
+        self.hint_compact_dense_rows();
+        // Note: rows are logically indexed
+        let coefficients: Vec<Vec<(usize, Octet)>> = (0..rows)
+            .map(|row| {
+                let mut row_coefficients: Vec<(usize, Octet)> = other
+                    .get_row_iter(row, 0, rows)
+                    .filter(|(_, scalar)| *scalar != Octet::zero())
+                    .collect();
+                row_coefficients.sort_unstable_by_key(|(i, _)| *i);
+                row_coefficients
+            })
+            .collect();
+        let mut temp_sparse = vec![SparseOctetVec::with_capacity(10); rows];
+        for (row, row_coefficients) in coefficients.iter().enumerate() {
+            for (i, scalar) in row_coefficients.iter() {
+                let physical_i = self.logical_row_to_physical[*i];
+                temp_sparse[row].fma(&self.sparse_elements[physical_i], scalar);
+            }
+        }
+        let mut temp_dense = vec![vec![0; self.num_dense_columns]; rows];
+        let dense_elements = &self.dense_elements;
+        let logical_row_to_physical = &self.logical_row_to_physical;
+        let num_dense_columns = self.num_dense_columns;
+        mul_add_tiled(
+            &mut temp_dense,
+            |i| &dense_elements[logical_row_to_physical[i]][..num_dense_columns],
+            rows,
+            &coefficients,
+        );
+        for row in (0..rows).rev() {
+            let physical_row = self.logical_row_to_physical[row];
+            self.sparse_elements[physical_row] = temp_sparse.pop().unwrap();
+            self.dense_elements[physical_row] = temp_dense.pop().unwrap();
+            if !self.column_index_disabled {
+                for (col, _) in self.sparse_elements[physical_row].keys_values() {
+                    if self.indexed_columns[*col] {
+                        self.sparse_column_index[*col].insert(physical_row)
+                    }
+                }
+            }
+        }
+    }
+
+    fn fma_rows(&mut self, dest: usize, multiplicand: usize, scalar: &Octet) {
+        assert_ne!(dest, multiplicand);
+        let physical_dest = self.logical_row_to_physical[dest];
+        let physical_multiplicand = self.logical_row_to_physical[multiplicand];
+        // First handle the dense columns
+        let (dest_row, temp_row) = get_both_indices(
+            &mut self.dense_elements,
+            physical_dest,
+            physical_multiplicand,
+        );
+
+        if *scalar == Octet::one() {
+            add_assign(
+                &mut dest_row[..self.num_dense_columns],
+                &temp_row[..self.num_dense_columns],
+            );
+        } else {
+            fused_addassign_mul_scalar(
+                &mut dest_row[..self.num_dense_columns],
+                &temp_row[..self.num_dense_columns],
+                scalar,
+            );
+        }
+
+        // Then the sparse columns
+        let sparse_rows = self.sparse_elements.len();
+        if physical_dest >= sparse_rows && physical_multiplicand >= sparse_rows {
+            let dense_dest = physical_dest - sparse_rows;
+            let (dest_row, temp_row) = get_both_indices(
+                &mut self.dense_rows,
+                dense_dest,
+                physical_multiplicand - sparse_rows,
+            );
+            // Rows converted to dense storage may be shorter, when columns were frozen since
+            let len = min(dest_row.len(), temp_row.len());
+            if *scalar == Octet::one() {
+                add_assign(&mut dest_row[..len], &temp_row[..len]);
+            } else {
+                fused_addassign_mul_scalar(&mut dest_row[..len], &temp_row[..len], scalar);
+            }
+            self.dense_row_nonzeros[dense_dest] =
+                dest_row.iter().filter(|value| **value != 0).count();
+        } else if physical_multiplicand >= sparse_rows {
+            let logical_col_to_physical = &self.logical_col_to_physical;
+            let temp_row = SparseOctetVec::from_elements(
+                self.dense_rows[physical_multiplicand - sparse_rows]
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, value)| **value != 0)
+                    .map(|(logical_col, value)| {
+                        (logical_col_to_physical[logical_col], Octet::new(*value))
+                    })
+                    .collect(),
+            );
+            let new_columns = self.sparse_elements[physical_dest].fma(&temp_row, scalar);
+            if !self.column_index_disabled {
+                for new_col in new_columns {
+                    if self.indexed_columns[new_col] {
+                        self.sparse_column_index[new_col].insert(physical_dest);
+                    }
+                }
+            }
+            self.convert_row_to_dense_if_full(physical_dest);
+        } else if physical_dest >= sparse_rows {
+            let dense_dest = physical_dest - sparse_rows;
+            for (physical_col, multiplicand) in
+                self.sparse_elements[physical_multiplicand].keys_values()
+            {
+                if *multiplicand != Octet::zero() {
+                    let logical_col = self.physical_col_to_logical[*physical_col];
+                    let previous = Octet::new(self.dense_rows[dense_dest][logical_col]);
+                    let mut value = previous.clone();
+                    value.fma(multiplicand, scalar);
+                    self.dense_rows[dense_dest][logical_col] = value.byte();
+                    if previous == Octet::zero() && value != Octet::zero() {
+                        self.dense_row_nonzeros[dense_dest] += 1;
+                    } else if previous != Octet::zero() && value == Octet::zero() {
+                        self.dense_row_nonzeros[dense_dest] -= 1;
+                    }
+
+                    // Don't add to sparse column index because dense rows are always included by
+                    // get_col_iter()
+                }
+            }
+        } else {
+            let (dest_row, temp_row) = get_both_indices(
+                &mut self.sparse_elements,
+                physical_dest,
+                physical_multiplicand,
+            );
+
+            let new_columns = dest_row.fma(temp_row, scalar);
+            if !self.column_index_disabled {
+                for new_col in new_columns {
+                    if self.indexed_columns[new_col] {
+                        self.sparse_column_index[new_col].insert(physical_dest);
+                    }
+                }
+            }
+            self.convert_row_to_dense_if_full(physical_dest);
+        }
+    }
+
+    fn resize(&mut self, new_height: usize, new_width: usize) {
+        assert!(new_height <= self.height);
+        assert!(new_width <= self.width);
+        if !self.column_index_disabled {
+            unimplemented!(
+                "Resize should only be used in phase 2, after column indexing is no longer needed"
+            );
+        }
+        let mut new_sparse = vec![None; new_height];
+        let mut new_dense = vec![None; new_height];
+
+        let original_sparse_len = self.sparse_elements.len();
+        for i in (0..self.sparse_elements.len()).rev() {
+            let logical_row = self.physical_row_to_logical[i];
+            let sparse = self.sparse_elements.pop();
+            if logical_row < new_height {
+                new_sparse[logical_row] = sparse;
+            }
+        }
+
+        // TODO: it would be better to keep any dense rows that are retained stored dense
+        self.dense_row_nonzeros.clear();
+        for i in (0..self.dense_rows.len()).rev() {
+            let logical_row = self.physical_row_to_logical[i + original_sparse_len];
+            let dense_row = self.dense_rows.pop();
+            if logical_row < new_height {
+                let mut sparse = SparseOctetVec::with_capacity(10);
+                for (logical_col, value) in dense_row.unwrap().iter().enumerate() {
+                    let value = Octet::new(*value);
+                    if value != Octet::zero() {
+                        sparse.insert(self.logical_col_to_physical[logical_col], value);
+                    }
+                }
+                new_sparse[logical_row] = Some(sparse);
+            }
+        }
+
+        for i in (0..self.dense_elements.len()).rev() {
+            let logical_row = self.physical_row_to_logical[i];
+            let dense = self.dense_elements.pop();
+            if logical_row < new_height {
+                new_dense[logical_row] = dense;
+            }
+        }
+
+        self.logical_row_to_physical.truncate(new_height);
+        self.physical_row_to_logical.truncate(new_height);
+        for i in 0..new_height {
+            self.logical_row_to_physical[i] = i;
+            self.physical_row_to_logical[i] = i;
+        }
+        for row in new_sparse.drain(0..new_height) {
+            self.sparse_elements.push(row.unwrap());
+        }
+        for row in new_dense.drain(0..new_height) {
+            self.dense_elements.push(row.unwrap());
+        }
+
+        let mut columns_to_remove = self.width - new_width;
+        let dense_columns_to_remove = min(self.num_dense_columns, columns_to_remove);
+        // First remove from dense
+        for row in 0..self.dense_elements.len() {
+            self.dense_elements[row].truncate(self.num_dense_columns - dense_columns_to_remove);
+        }
+        columns_to_remove -= dense_columns_to_remove;
+
+        // Next remove sparse columns
+        if columns_to_remove > 0 {
+            let physical_to_logical = &self.physical_col_to_logical;
+            for row in 0..self.sparse_elements.len() {
+                // Current number of sparse columns - number to remove
+                let sparse_width = self.width - self.num_dense_columns - columns_to_remove;
+                self.sparse_elements[row]
+                    .retain(|(col, _)| physical_to_logical[*col] < sparse_width);
+            }
+        }
+        self.num_dense_columns -= dense_columns_to_remove;
+
+        self.height = new_height;
+        self.width = new_width;
+        self.shrink_dense_storage();
+    }
+
+    fn shrink_dense_storage(&mut self) {
+        // Rows are preallocated, and grown in chunks, as columns become dense. Anything beyond
+        // num_dense_columns is never read
+        for row in self.dense_elements.iter_mut() {
+            row.truncate(self.num_dense_columns);
+            row.shrink_to_fit();
+        }
+    }
+
+    fn add_rows(&mut self, rows: usize) {
+        // New rows are stored sparse. Dense rows are physically stored after the sparse rows, so
+        // they need to be moved down
+        let first_new_physical = self.sparse_elements.len();
+        for physical_row in self.logical_row_to_physical.iter_mut() {
+            if *physical_row >= first_new_physical {
+                *physical_row += rows;
+            }
+        }
+        for i in 0..rows {
+            let logical_row = self.height + i;
+            self.sparse_elements.push(SparseOctetVec::with_capacity(10));
+            self.dense_elements
+                .insert(first_new_physical + i, vec![0; self.num_dense_columns]);
+            self.logical_row_to_physical.push(first_new_physical + i);
+            self.physical_row_to_logical
+                .insert(first_new_physical + i, logical_row);
+        }
+        self.height += rows;
+    }
+
+    fn storage_permutations(&self) -> (Vec<usize>, Vec<usize>) {
+        (
+            self.logical_row_to_physical.clone(),
+            self.logical_col_to_physical.clone(),
+        )
+    }
+
+    fn verify(&self) {
+        if self.column_index_disabled {
+            return;
+        }
+        for row in 0..self.sparse_elements.len() {
+            for (col, value) in self.sparse_elements[row].keys_values() {
+                if *value != Octet::zero() && self.indexed_columns[*col] {
+                    assert!(self.sparse_column_index[*col].exists(row));
+                }
+            }
+        }
+        // Dense rows are always included, so any index entries of them are ignored. They may
+        // have some, from before they were converted to dense storage
+        for (row, nonzeros) in self.dense_rows.iter().zip(self.dense_row_nonzeros.iter()) {
+            assert_eq!(row.iter().filter(|value| **value != 0).count(), *nonzeros);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::matrix::{DenseOctetMatrix, OctetMatrix};
+    use crate::octet::Octet;
+    use crate::sparse_matrix::SparseOctetMatrix;
+    use crate::sparse_vec::SparseValuelessVec;
+
+    #[test]
+    fn shrink_dense_storage() {
+        let mut matrix = SparseOctetMatrix::new(10, 10, 4, 0, 0);
+        for i in 0..10 {
+            matrix.set(i, i, Octet::new(i as u8 + 1));
+        }
+        let original = matrix.clone();
+        assert_eq!(matrix.dense_elements[0].len(), 8);
+
+        matrix.shrink_dense_storage();
+        for row in matrix.dense_elements.iter() {
+            assert_eq!(row.capacity(), 4);
+        }
+        for i in 0..10 {
+            for j in 0..10 {
+                assert_eq!(matrix.get(i, j), original.get(i, j));
+            }
+        }
+
+        matrix.resize(6, 6);
+        for row in matrix.dense_elements.iter() {
+            assert!(row.capacity() <= 4);
+        }
+    }
+
+    #[test]
+    fn partial_column_access_acceleration() {
+        let mut matrix = SparseOctetMatrix::new(10, 10, 2, 0, 0);
+        for i in 0..10 {
+            matrix.set(i, i, Octet::one());
+            matrix.set(i, (i + 3) % 8, Octet::new(2));
+        }
+        matrix.enable_partial_column_acccess_acceleration(2, 6);
+        for col in 0..10 {
+            let physical_col = matrix.logical_col_to_physical[col];
+            assert_eq!(matrix.indexed_columns[physical_col], (2..6).contains(&col));
+        }
+        // Indexed columns stay indexed when swapped out of the range, and are kept up to date
+        matrix.swap_columns(3, 7, 0);
+        matrix.fma_rows(9, 0, &Octet::one());
+        for col in [2, 4, 5, 7].iter() {
+            let rows: Vec<usize> = matrix.get_col_index_iter(*col, 0, 10).collect();
+            for row in 0..10 {
+                if matrix.get(row, *col) != Octet::zero() {
+                    assert!(rows.contains(&row));
+                }
+            }
+        }
+        assert!(matrix.sparse_column_index[matrix.logical_col_to_physical[3]].is_empty());
+    }
+
+    #[test]
+    #[should_panic]
+    fn verify_detects_stale_column_index() {
+        let mut matrix = SparseOctetMatrix::new(10, 10, 2, 0, 0);
+        for i in 0..10 {
+            matrix.set(i, i, Octet::one());
+        }
+        matrix.enable_partial_column_acccess_acceleration(0, 8);
+        matrix.verify();
+        // Corrupt the index of a column with a non-zero
+        let physical_col = matrix.logical_col_to_physical[4];
+        matrix.sparse_column_index[physical_col] = SparseValuelessVec::with_capacity(1);
+        matrix.verify();
+    }
+
+    #[test]
+    fn hybrid_row_storage() {
+        let mut dense = DenseOctetMatrix::new(10, 300, 0, 0, 0);
+        let mut sparse = SparseOctetMatrix::new(10, 300, 2, 0, 0);
+        // 38 non-zeros in total, which is more than an eighth of the 298 sparse columns
+        for col in 0..10 {
+            dense.set(0, col, Octet::new(col as u8 + 1));
+            sparse.set(0, col, Octet::new(col as u8 + 1));
+        }
+        for col in 270..298 {
+            dense.set(1, col, Octet::new(3));
+            sparse.set(1, col, Octet::new(3));
+        }
+        dense.set(2, 11, Octet::one());
+        sparse.set(2, 11, Octet::one());
+        sparse.enable_partial_column_acccess_acceleration(0, 298);
+
+        dense.fma_rows(0, 1, &Octet::one());
+        sparse.fma_rows(0, 1, &Octet::one());
+        assert_eq!(sparse.dense_rows.len(), 1);
+        // A dense multiplicand, into a sparse row which then fills up too
+        dense.fma_rows(2, 0, &Octet::new(5));
+        sparse.fma_rows(2, 0, &Octet::new(5));
+        assert_eq!(sparse.dense_rows.len(), 2);
+        // And into a dense row
+        dense.fma_rows(0, 2, &Octet::one());
+        sparse.fma_rows(0, 2, &Octet::one());
+        assert_eq!(sparse.dense_row_nonzeros, vec![39, 39]);
+
+        for col in [0, 11, 280].iter() {
+            // The index may include rows which are zero in the column, but each only once
+            let mut rows: Vec<usize> = sparse.get_col_index_iter(*col, 0, 10).collect();
+            rows.sort_unstable();
+            rows.dedup();
+            assert_eq!(rows.len(), sparse.get_col_index_iter(*col, 0, 10).count());
+            for row in 0..10 {
+                if dense.get(row, *col) != Octet::zero() {
+                    assert!(rows.contains(&row));
+                }
+            }
+        }
+
+        // The rows empty out as their columns are frozen, and are stored sparse again
+        for col in (270..298).rev() {
+            dense.hint_column_dense_and_frozen(col);
+            sparse.hint_column_dense_and_frozen(col);
+        }
+        assert!(sparse.dense_rows.is_empty());
+        for row in 0..10 {
+            for col in 0..300 {
+                assert_eq!(sparse.get(row, col), dense.get(row, col));
+            }
+        }
+    }
+}
@@ -1,6 +1,7 @@
 use crate::octet::Octet;
+use alloc::vec;
+use alloc::vec::Vec;
 use serde::{Deserialize, Serialize};
-use std::cmp::Ordering;
 
 #[derive(Clone, Debug, PartialEq, PartialOrd, Eq, Ord, Serialize, Deserialize, Hash)]
 pub struct SparseOctetVec {
@@ -15,6 +16,11 @@ impl SparseOctetVec {
         }
     }
 
+    pub fn from_elements(mut elements: Vec<(usize, Octet)>) -> SparseOctetVec {
+        elements.sort_unstable_by_key(|(index, _)| *index);
+        SparseOctetVec { elements }
+    }
+
     // Returns the internal index into self.elements matching key i, or the index
     // at which it can be inserted (maintaining sorted order)
     fn key_to_internal_index(&self, i: usize) -> Result<usize, usize> {
@@ -25,6 +31,10 @@ impl SparseOctetVec {
         self.elements.len()
     }
 
+    pub fn is_empty(&self) -> bool {
+        self.elements.is_empty()
+    }
+
     pub fn get_by_raw_index(&self, i: usize) -> &(usize, Octet) {
         &self.elements[i]
     }
@@ -54,56 +64,70 @@ impl SparseOctetVec {
             return vec![];
         }
 
-        let mut result = Vec::with_capacity(self.elements.len() + other.elements.len());
-        let mut self_iter = self.elements.iter();
-        let mut other_iter = other.elements.iter();
-        let mut self_entry = self_iter.next();
-        let mut other_entry = other_iter.next();
-
-        let mut new_columns = Vec::with_capacity(10);
-        loop {
-            if let Some((self_col, self_value)) = self_entry {
-                if let Some((other_col, other_value)) = other_entry {
-                    match self_col.cmp(&other_col) {
-                        Ordering::Less => {
-                            if *self_value != Octet::zero() {
-                                result.push((*self_col, self_value.clone()));
-                            }
-                            self_entry = self_iter.next();
-                        }
-                        Ordering::Equal => {
-                            let value = self_value + &(other_value * scalar);
-                            if value != Octet::zero() {
-                                result.push((*self_col, value));
-                            }
-                            self_entry = self_iter.next();
-                            other_entry = other_iter.next();
-                        }
-                        Ordering::Greater => {
-                            if *other_value != Octet::zero() {
-                                new_columns.push(*other_col);
-                                result.push((*other_col, other_value * scalar));
-                            }
-                            other_entry = other_iter.next();
-                        }
-                    }
-                } else {
-                    if *self_value != Octet::zero() {
-                        result.push((*self_col, self_value.clone()));
-                    }
-                    self_entry = self_iter.next();
-                }
-            } else if let Some((other_col, other_value)) = other_entry {
-                if *other_value != Octet::zero() {
-                    new_columns.push(*other_col);
-                    result.push((*other_col, other_value * scalar));
-                }
-                other_entry = other_iter.next();
+        // Fast path for rows whose columns don't overlap, which is common early in the first
+        // phase, in which case the scaled values of other are all new columns
+        if let (Some((self_last, _)), Some((other_first, _))) =
+            (self.elements.last(), other.elements.first())
+        {
+            if self_last < other_first {
+                let appended = self.elements.len();
+                self.elements.extend(
+                    other
+                        .elements
+                        .iter()
+                        .filter(|(_, value)| *value != Octet::zero())
+                        .map(|(col, value)| (*col, value * scalar)),
+                );
+                return self.elements[appended..]
+                    .iter()
+                    .map(|(col, _)| *col)
+                    .collect();
+            }
+        }
+
+        // Merge in place, from the back, after growing self by the number of new columns, so that
+        // every entry is moved at most once and no other buffer is needed
+        let mut new_entries = 0;
+        let mut self_index = 0;
+        for (other_col, other_value) in other.elements.iter() {
+            if *other_value == Octet::zero() {
+                continue;
+            }
+            while self_index < self.elements.len() && self.elements[self_index].0 < *other_col {
+                self_index += 1;
+            }
+            if self_index == self.elements.len() || self.elements[self_index].0 != *other_col {
+                new_entries += 1;
+            }
+        }
+
+        let mut self_end = self.elements.len();
+        let mut write = self_end + new_entries;
+        self.elements.resize(write, (0, Octet::zero()));
+        let mut new_columns = Vec::with_capacity(new_entries);
+        for (other_col, other_value) in other.elements.iter().rev() {
+            if *other_value == Octet::zero() {
+                continue;
+            }
+            while self_end > 0 && self.elements[self_end - 1].0 > *other_col {
+                self_end -= 1;
+                write -= 1;
+                self.elements.swap(self_end, write);
+            }
+            write -= 1;
+            if self_end > 0 && self.elements[self_end - 1].0 == *other_col {
+                self_end -= 1;
+                let value = &self.elements[self_end].1 + &(other_value * scalar);
+                self.elements[write] = (*other_col, value);
             } else {
-                break;
+                new_columns.push(*other_col);
+                self.elements[write] = (*other_col, other_value * scalar);
             }
         }
-        self.elements = result;
+        // The remaining entries of self precede all of other, and are already in place
+        debug_assert_eq!(self_end, write);
+        self.elements.retain(|(_, value)| *value != Octet::zero());
+        new_columns.reverse();
 
         return new_columns;
     }
@@ -136,10 +160,14 @@ impl SparseOctetVec {
         self.elements.iter()
     }
 
-    pub fn insert(&mut self, i: usize, value: Octet) {
+    // Returns the value which was overwritten, if any
+    pub fn insert(&mut self, i: usize, value: Octet) -> Option<Octet> {
         match self.key_to_internal_index(i) {
-            Ok(index) => self.elements[index] = (i, value),
-            Err(index) => self.elements.insert(index, (i, value)),
+            Ok(index) => Some(core::mem::replace(&mut self.elements[index].1, value)),
+            Err(index) => {
+                self.elements.insert(index, (i, value));
+                None
+            }
         }
     }
 }
@@ -167,7 +195,10 @@ impl SparseValuelessVec {
         self.elements.len()
     }
 
-    #[cfg(debug_assertions)]
+    pub fn is_empty(&self) -> bool {
+        self.elements.is_empty()
+    }
+
     pub fn exists(&self, i: usize) -> bool {
         self.key_to_internal_index(i).is_ok()
     }
@@ -266,4 +297,64 @@ mod tests {
             );
         }
     }
+
+    #[test]
+    fn sparse_vec_fma_random() {
+        let size = 32;
+        for _ in 0..100 {
+            let mut dense1 = vec![Octet::zero(); size];
+            let mut dense2 = vec![Octet::zero(); size];
+            let mut sparse1 = SparseOctetVec::with_capacity(size);
+            let mut sparse2 = SparseOctetVec::with_capacity(size);
+            for _ in 0..(size / 2) {
+                let i = rand::thread_rng().gen_range(0, size);
+                let value = Octet::new(rand::thread_rng().gen_range(1, 256) as u8);
+                dense1[i] = value.clone();
+                sparse1.insert(i, value.clone());
+                let j = rand::thread_rng().gen_range(0, size);
+                dense2[j] = value.clone();
+                sparse2.insert(j, value);
+            }
+            let scalar = Octet::new(rand::thread_rng().gen_range(1, 256) as u8);
+            let new_columns = sparse1.fma(&sparse2, &scalar);
+            let expected_new_columns: Vec<usize> = (0..size)
+                .filter(|i| dense1[*i] == Octet::zero() && dense2[*i] != Octet::zero())
+                .collect();
+            assert_eq!(new_columns, expected_new_columns);
+            for i in 0..size {
+                let expected = &dense1[i] + &(&scalar * &dense2[i]);
+                assert_eq!(sparse1.get(i).cloned().unwrap_or(Octet::zero()), expected);
+            }
+            assert!(sparse1
+                .keys_values()
+                .all(|(_, value)| *value != Octet::zero()));
+        }
+    }
+
+    #[test]
+    fn sparse_vec_fma_disjoint() {
+        let mut low = SparseOctetVec::with_capacity(8);
+        let mut high = SparseOctetVec::with_capacity(8);
+        for i in 0..4 {
+            low.insert(i, Octet::new(i as u8 + 1));
+            high.insert(i + 4, Octet::new(i as u8 + 5));
+        }
+
+        let mut appended = low.clone();
+        assert_eq!(appended.fma(&high, &Octet::new(3)), vec![4, 5, 6, 7]);
+        let mut prepended = high.clone();
+        assert_eq!(prepended.fma(&low, &Octet::new(3)), vec![0, 1, 2, 3]);
+        for i in 0..4 {
+            let scaled = low.get(i).unwrap() * &Octet::new(3);
+            assert_eq!(appended.get(i), low.get(i));
+            assert_eq!(prepended.get(i), Some(&scaled));
+        }
+        for i in 4..8 {
+            let scaled = high.get(i).unwrap() * &Octet::new(3);
+            assert_eq!(appended.get(i), Some(&scaled));
+            assert_eq!(prepended.get(i), high.get(i));
+        }
+        let keys: Vec<usize> = prepended.keys_values().map(|(col, _)| *col).collect();
+        assert_eq!(keys, (0..8).collect::<Vec<usize>>());
+    }
 }
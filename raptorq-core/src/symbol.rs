@@ -2,8 +2,11 @@ use crate::octet::Octet;
 use crate::octets::add_assign;
 use crate::octets::fused_addassign_mul_scalar;
 use crate::octets::mulassign_scalar;
+use crate::util::get_both_indices;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::ops::AddAssign;
 use serde::{Deserialize, Serialize};
-use std::ops::AddAssign;
 
 /// Elementary unit of data, for encoding/decoding purposes.
 #[derive(Clone, Debug, PartialEq, PartialOrd, Eq, Ord, Serialize, Deserialize, Hash)]
@@ -50,6 +53,48 @@ impl Symbol {
     }
 }
 
+impl AsRef<[u8]> for Symbol {
+    fn as_ref(&self) -> &[u8] {
+        &self.value
+    }
+}
+
+/// An operation on a vector of symbols, by index. The solver can record the operations it
+/// performs, so that they can be replayed on other symbols, see
+/// `IntermediateSymbolDecoder::record_operations()`
+#[derive(Clone, Debug, PartialEq, PartialOrd, Eq, Ord, Serialize, Deserialize, Hash)]
+pub enum SymbolOp {
+    AddAssign {
+        dest: usize,
+        src: usize,
+    },
+    MulAssign {
+        dest: usize,
+        scalar: Octet,
+    },
+    FusedAddAssignMul {
+        dest: usize,
+        src: usize,
+        scalar: Octet,
+    },
+}
+
+impl SymbolOp {
+    pub fn apply(&self, symbols: &mut Vec<Symbol>) {
+        match self {
+            SymbolOp::AddAssign { dest, src } => {
+                let (dest, src) = get_both_indices(symbols, *dest, *src);
+                *dest += src;
+            }
+            SymbolOp::MulAssign { dest, scalar } => symbols[*dest].mulassign_scalar(scalar),
+            SymbolOp::FusedAddAssignMul { dest, src, scalar } => {
+                let (dest, src) = get_both_indices(symbols, *dest, *src);
+                dest.fused_addassign_mul_scalar(src, scalar);
+            }
+        }
+    }
+}
+
 impl<'a> AddAssign<&'a Symbol> for Symbol {
     fn add_assign(&mut self, other: &'a Symbol) {
         add_assign(&mut self.value, &other.value);
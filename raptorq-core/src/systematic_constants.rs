@@ -1047,11 +1047,68 @@ pub fn calculate_p1(source_block_symbols: u32) -> u32 {
     unreachable!();
 }
 
+// Checks that the parameters derived for a source block of the given size are those of the table
+// in section 5.6, and satisfy the constraints of sections 5.3.3.3 and 5.6. Returns the name of
+// the first parameter which does not
+pub fn validate_derived_parameters(source_block_symbols: u32) -> Result<(), &'static str> {
+    if source_block_symbols == 0 || source_block_symbols > MAX_SOURCE_SYMBOLS_PER_BLOCK {
+        return Err("K");
+    }
+    // K' is the smallest value in the table which is at least K. See section 5.3.1
+    let row = SYSTEMATIC_INDICES_AND_PARAMETERS
+        .iter()
+        .position(|&(block_size, _, _, _, _)| block_size >= source_block_symbols)
+        .ok_or("K'")?;
+    let (block_size, j, s, h, w) = SYSTEMATIC_INDICES_AND_PARAMETERS[row];
+    if extended_source_block_symbols(source_block_symbols) != block_size
+        || (row > 0 && SYSTEMATIC_INDICES_AND_PARAMETERS[row - 1].0 >= source_block_symbols)
+    {
+        return Err("K'");
+    }
+    if systematic_index(source_block_symbols) != j {
+        return Err("J");
+    }
+    if num_ldpc_symbols(source_block_symbols) != s || !is_prime(s) {
+        return Err("S");
+    }
+    if num_hdpc_symbols(source_block_symbols) != h || h == 0 {
+        return Err("H");
+    }
+    let l = num_intermediate_symbols(source_block_symbols);
+    if num_lt_symbols(source_block_symbols) != w || !is_prime(w) || w >= l {
+        return Err("W");
+    }
+    // P1 is the smallest prime which is at least P. See section 5.3.3.3
+    let p = num_pi_symbols(source_block_symbols);
+    let p1 = calculate_p1(source_block_symbols);
+    if P1_TABLE[row].0 != block_size || p1 < p || !is_prime(p1) || (p..p1).any(is_prime) {
+        return Err("P1");
+    }
+    Ok(())
+}
+
+fn is_prime(n: u32) -> bool {
+    if n < 2 {
+        return false;
+    }
+    let mut divisor = 2;
+    while divisor * divisor <= n {
+        if n / divisor * divisor == n {
+            return false;
+        }
+        divisor += 1;
+    }
+    true
+}
+
 #[cfg(test)]
 mod tests {
     use crate::systematic_constants::num_ldpc_symbols;
     use crate::systematic_constants::num_lt_symbols;
     use crate::systematic_constants::{calculate_p1, num_pi_symbols, MAX_SOURCE_SYMBOLS_PER_BLOCK};
+    use crate::systematic_constants::{
+        validate_derived_parameters, SYSTEMATIC_INDICES_AND_PARAMETERS,
+    };
 
     #[test]
     fn all_prime() {
@@ -1077,4 +1134,17 @@ mod tests {
             assert_eq!(p1, calculate_p1(i));
         }
     }
+
+    #[test]
+    fn derived_parameters() {
+        for &(block_size, _, _, _, _) in SYSTEMATIC_INDICES_AND_PARAMETERS.iter() {
+            assert_eq!(Ok(()), validate_derived_parameters(block_size));
+            assert_eq!(Ok(()), validate_derived_parameters(block_size - 1));
+        }
+        assert_eq!(Err("K"), validate_derived_parameters(0));
+        assert_eq!(
+            Err("K"),
+            validate_derived_parameters(MAX_SOURCE_SYMBOLS_PER_BLOCK + 1)
+        );
+    }
 }
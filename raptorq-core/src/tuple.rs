@@ -0,0 +1,57 @@
+use crate::rng::rand;
+use core::cmp::min;
+
+// Deg[v] as defined in section 5.3.5.2
+pub fn deg(v: u32, lt_symbols: u32) -> u32 {
+    assert!(v < 1048576);
+    let f: [u32; 31] = [
+        0, 5243, 529531, 704294, 791675, 844104, 879057, 904023, 922747, 937311, 948962, 958494,
+        966438, 973160, 978921, 983914, 988283, 992138, 995565, 998631, 1001391, 1003887, 1006157,
+        1008229, 1010129, 1011876, 1013490, 1014983, 1016370, 1017662, 1048576,
+    ];
+
+    #[allow(clippy::needless_range_loop)]
+    for d in 1..f.len() {
+        if v < f[d] {
+            return min(d as u32, lt_symbols - 2);
+        }
+    }
+    unreachable!();
+}
+
+// Tuple[K', X] as defined in section 5.3.5.4
+#[allow(non_snake_case, clippy::many_single_char_names)]
+pub fn intermediate_tuple(
+    internal_symbol_id: u32,
+    lt_symbols: u32,
+    systematic_index: u32,
+    p1: u32,
+) -> (u32, u32, u32, u32, u32, u32) {
+    let J = systematic_index;
+    let W = lt_symbols;
+    let P1 = p1;
+
+    let mut A = 53591 + J * 997;
+
+    if A % 2 == 0 {
+        A += 1;
+    }
+
+    let B = 10267 * (J + 1);
+    let y: u32 = ((B as u64 + internal_symbol_id as u64 * A as u64) % 4294967296) as u32;
+    let v = rand(y, 0u32, 1048576);
+    let d = deg(v, W);
+    let a = 1 + rand(y, 1u32, W - 1);
+    let b = rand(y, 2u32, W);
+
+    let d1 = if d < 4 {
+        2 + rand(internal_symbol_id, 3u32, 2)
+    } else {
+        2
+    };
+
+    let a1 = 1 + rand(internal_symbol_id, 4u32, P1 - 1);
+    let b1 = rand(internal_symbol_id, 5u32, P1);
+
+    (d, a, b, d1, a1, b1)
+}
@@ -1,3 +1,5 @@
+use alloc::vec::Vec;
+
 pub fn get_both_indices<T>(vector: &mut Vec<T>, i: usize, j: usize) -> (&mut T, &mut T) {
     debug_assert_ne!(i, j);
     debug_assert!(i < vector.len());
@@ -10,3 +12,13 @@ pub fn get_both_indices<T>(vector: &mut Vec<T>, i: usize, j: usize) -> (&mut T,
         return (&mut last[0], &mut first[j]);
     }
 }
+
+// Equivalent to u64::div_ceil(), which requires a newer toolchain than the minimum supported
+pub fn div_ceil(numerator: u64, denominator: u64) -> u64 {
+    let quotient = numerator / denominator;
+    if quotient * denominator == numerator {
+        quotient
+    } else {
+        quotient + 1
+    }
+}
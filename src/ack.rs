@@ -0,0 +1,173 @@
+use serde::{Deserialize, Serialize};
+use std::cmp::min;
+
+// Bytes needed for one bit per block
+fn bitmap_length(blocks: usize) -> usize {
+    (blocks >> 3) + if blocks & 7 == 0 { 0 } else { 1 }
+}
+
+/// A selective acknowledgement, sent by a receiver to summarize which source blocks it has
+/// decoded, and how many more symbols it needs for each of the others, so that the sender can stop
+/// sending symbols for decoded blocks. See `Decoder::ack_frame()` and
+/// `Encoder::repair_packets_for_ack()`.
+///
+/// It is serialized as the number of source blocks (one byte), a bitmap with a bit set for each
+/// decoded block (most significant bit first, padded to a whole byte), and the number of symbols
+/// still needed for each block which has not been decoded, as a 16-bit unsigned integer.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct BlockAckFrame {
+    // Zero for blocks which have been decoded
+    symbols_needed: Vec<u32>,
+}
+
+impl BlockAckFrame {
+    /// `symbols_needed` contains, for each source block, the number of additional symbols needed
+    /// to decode it, which is zero once it has been decoded
+    pub fn new(symbols_needed: Vec<u32>) -> BlockAckFrame {
+        assert!(symbols_needed.len() <= 255);
+        BlockAckFrame { symbols_needed }
+    }
+
+    pub fn num_blocks(&self) -> usize {
+        self.symbols_needed.len()
+    }
+
+    pub fn is_decoded(&self, source_block_number: u8) -> bool {
+        self.symbols_needed[source_block_number as usize] == 0
+    }
+
+    pub fn all_decoded(&self) -> bool {
+        self.symbols_needed.iter().all(|needed| *needed == 0)
+    }
+
+    /// Returns the number of additional symbols the receiver needs to decode the block
+    pub fn symbols_needed(&self, source_block_number: u8) -> u32 {
+        self.symbols_needed[source_block_number as usize]
+    }
+
+    pub fn serialize(&self) -> Vec<u8> {
+        let blocks = self.symbols_needed.len();
+        let mut result = vec![0; 1 + bitmap_length(blocks)];
+        result[0] = blocks as u8;
+        for (block, needed) in self.symbols_needed.iter().enumerate() {
+            if *needed == 0 {
+                result[1 + block / 8] |= 0x80 >> (block % 8);
+            } else {
+                let needed = min(*needed, 0xFFFF) as u16;
+                result.push((needed >> 8) as u8);
+                result.push((needed & 0xFF) as u8);
+            }
+        }
+        result
+    }
+
+    /// Returns None if the frame is malformed
+    pub fn deserialize(data: &[u8]) -> Option<BlockAckFrame> {
+        let blocks = *data.first()? as usize;
+        let bitmap = data.get(1..(1 + bitmap_length(blocks)))?;
+        let mut counts = data[(1 + bitmap.len())..].chunks(2);
+        let mut symbols_needed = Vec::with_capacity(blocks);
+        for block in 0..blocks {
+            if bitmap[block / 8] & (0x80 >> (block % 8)) != 0 {
+                symbols_needed.push(0);
+            } else {
+                let count = counts.next()?;
+                if count.len() != 2 {
+                    return None;
+                }
+                let needed = (u32::from(count[0]) << 8) + u32::from(count[1]);
+                // Zero would be ambiguous with a decoded block
+                if needed == 0 {
+                    return None;
+                }
+                symbols_needed.push(needed);
+            }
+        }
+        if counts.next().is_some() {
+            return None;
+        }
+        Some(BlockAckFrame { symbols_needed })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{BlockAckFrame, Decoder, Encoder, MAX_REPAIR_PACKETS_PER_ACK};
+
+    #[test]
+    fn serialization() {
+        let frame = BlockAckFrame::new(vec![0, 3, 0, 0, 0, 0, 0, 0, 100_000, 1]);
+        let serialized = frame.serialize();
+        assert_eq!(serialized.len(), 1 + 2 + 3 * 2);
+        let deserialized = BlockAckFrame::deserialize(&serialized).unwrap();
+        assert!(deserialized.is_decoded(0));
+        assert_eq!(deserialized.symbols_needed(1), 3);
+        // Saturated
+        assert_eq!(deserialized.symbols_needed(8), 65535);
+
+        assert!(BlockAckFrame::deserialize(&serialized[..serialized.len() - 1]).is_none());
+        assert!(BlockAckFrame::deserialize(&[]).is_none());
+        assert_eq!(
+            BlockAckFrame::deserialize(&BlockAckFrame::new(vec![]).serialize()),
+            Some(BlockAckFrame::new(vec![]))
+        );
+    }
+
+    #[test]
+    fn feedback_loop() {
+        let data = vec![5; 10_000];
+        let encoder = Encoder::with_defaults(&data, 500);
+        let mut decoder = Decoder::new(encoder.get_config());
+
+        // Half of the source packets are lost
+        for packet in encoder.get_encoded_packets(0).into_iter().step_by(2) {
+            decoder.decode(packet);
+        }
+        let ack = BlockAckFrame::deserialize(&decoder.ack_frame().serialize()).unwrap();
+        assert!(!ack.all_decoded());
+
+        let mut result = None;
+        for packet in encoder.repair_packets_for_ack(&ack, 0, 2) {
+            result = decoder.decode(packet);
+        }
+        assert_eq!(result.unwrap(), data);
+        assert!(decoder.ack_frame().all_decoded());
+        assert!(encoder
+            .repair_packets_for_ack(&decoder.ack_frame(), 100, 2)
+            .is_empty());
+    }
+
+    #[test]
+    fn mismatched_ack() {
+        let data = vec![5; 10_000];
+        let encoder = Encoder::with_defaults(&data, 500);
+        assert_eq!(encoder.get_block_encoders().len(), 1);
+        // An acknowledgement of a different object, with more blocks
+        let ack = BlockAckFrame::new(vec![3, 3]);
+        assert!(encoder.repair_packets_for_ack(&ack, 0, 2).is_empty());
+    }
+
+    #[test]
+    fn forged_ack() {
+        let data = vec![5; 10_000];
+        let encoder = Encoder::with_defaults(&data, 500);
+        let source_symbols = encoder.get_block_encoders()[0].source_packets().len();
+        // The most symbols which an acknowledgement can claim to need
+        let ack = BlockAckFrame::deserialize(&[1, 0, 0xFF, 0xFF]).unwrap();
+        assert_eq!(ack.symbols_needed(0), 0xFFFF);
+        assert_eq!(
+            encoder.repair_packets_for_ack(&ack, 0, 2).len(),
+            source_symbols + 2
+        );
+
+        // Each of many blocks is clamped, and so is the total
+        let data = vec![5; 255 * 10 * 8];
+        let encoder = Encoder::new(
+            &data,
+            crate::ObjectTransmissionInformation::new(data.len() as u64, 8, 255, 1, 8),
+        );
+        let ack = BlockAckFrame::new(vec![0xFFFF; 255]);
+        let packets = encoder.repair_packets_for_ack(&ack, 0, 300);
+        assert_eq!(packets.len(), MAX_REPAIR_PACKETS_PER_ACK);
+    }
+}
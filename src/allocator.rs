@@ -0,0 +1,48 @@
+use std::cmp::max;
+use std::fmt;
+use std::sync::Arc;
+
+/// Hooks which control where the memory for per-block decoder state is allocated. For example,
+/// on multi-socket machines an implementation can place the symbols of a source block on the
+/// NUMA node of the thread which is handling that block.
+pub trait AllocatorHooks: Send + Sync {
+    /// Returns an empty buffer, with a capacity of at least `capacity` bytes, in which a symbol of
+    /// the given source block will be stored. This is called on the thread which is processing
+    /// the block. The buffer is freed through the global allocator, so it must have been allocated
+    /// by it, although the implementation may choose how its pages are placed.
+    fn allocate_symbol(&self, source_block_number: u8, capacity: usize) -> Vec<u8>;
+}
+
+// Allows decoders to hold hooks while still deriving the standard traits. Two handles are only
+// equal if they refer to the same hooks.
+#[derive(Clone)]
+pub(crate) struct AllocatorHandle(pub(crate) Arc<dyn AllocatorHooks>);
+
+impl AllocatorHandle {
+    pub(crate) fn copy_symbol(
+        &self,
+        source_block_number: u8,
+        data: &[u8],
+        capacity: usize,
+    ) -> Vec<u8> {
+        let mut buffer = self
+            .0
+            .allocate_symbol(source_block_number, max(data.len(), capacity));
+        buffer.extend_from_slice(data);
+        buffer
+    }
+}
+
+impl PartialEq for AllocatorHandle {
+    fn eq(&self, other: &AllocatorHandle) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for AllocatorHandle {}
+
+impl fmt::Debug for AllocatorHandle {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "AllocatorHandle")
+    }
+}
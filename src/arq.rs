@@ -0,0 +1,81 @@
+use crate::stats::{packets_needed, success_probability};
+
+/// What a receiver should do next about a source block which it has not decoded, as decided by a
+/// `HybridArqPolicy`
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum RecoveryAction {
+    /// Wait for the packets already in flight, which are likely enough to decode the block
+    Wait,
+    /// Request retransmission of the source symbols with these ESIs
+    RetransmitSource(Vec<u32>),
+    /// Request this many more repair packets
+    RequestRepair(u32),
+}
+
+/// Decides, for a source block which a receiver has not decoded, between requesting
+/// retransmission of its missing source symbols (ARQ), and waiting for, or requesting more, repair
+/// packets (FEC).
+///
+/// The reliability budget is the probability with which the block must decode once the packets in
+/// flight, and those requested, have been sent. It is estimated from the packet loss rate of the
+/// channel, and the decoding failure probabilities of RFC 6330: about 1% with exactly as many
+/// symbols as needed, and a hundred times less with each further one. When the packets in flight
+/// meet the budget, the receiver waits. Otherwise, if only a few source symbols are missing, they
+/// are retransmitted, which avoids the decoding failure probability and the cost of decoding.
+/// Otherwise, just enough repair packets are requested to meet the budget.
+#[derive(Clone, Debug, PartialEq)]
+pub struct HybridArqPolicy {
+    loss_rate: f64,
+    target_success: f64,
+    max_retransmissions: usize,
+}
+
+impl HybridArqPolicy {
+    /// `loss_rate` is the fraction of packets lost by the channel, and `target_success` the
+    /// reliability budget. Missing source symbols are retransmitted if there are at most
+    /// `max_retransmissions` of them.
+    pub fn new(loss_rate: f64, target_success: f64, max_retransmissions: usize) -> HybridArqPolicy {
+        assert!((0.0..1.0).contains(&loss_rate));
+        assert!(target_success > 0.0 && target_success < 1.0);
+        HybridArqPolicy {
+            loss_rate,
+            target_success,
+            max_retransmissions,
+        }
+    }
+
+    pub fn set_loss_rate(&mut self, loss_rate: f64) {
+        assert!((0.0..1.0).contains(&loss_rate));
+        self.loss_rate = loss_rate;
+    }
+
+    /// Probability that a block which needs `symbols_needed` more symbols decodes once `sent`
+    /// more packets have been sent
+    pub fn success_probability(&self, symbols_needed: u32, sent: u32) -> f64 {
+        success_probability(symbols_needed, sent, self.loss_rate)
+    }
+
+    /// Decides what to do about a block which needs `symbols_needed` more symbols, whose source
+    /// symbols with the ESIs in `missing_source` have not been received, and for which `in_flight`
+    /// packets have been sent, or requested, but not yet received
+    pub fn decide(
+        &self,
+        missing_source: &[u32],
+        symbols_needed: u32,
+        in_flight: u32,
+    ) -> RecoveryAction {
+        if self.success_probability(symbols_needed, in_flight) >= self.target_success {
+            return RecoveryAction::Wait;
+        }
+        if missing_source.len() <= self.max_retransmissions {
+            return RecoveryAction::RetransmitSource(missing_source.to_vec());
+        }
+        let sent = packets_needed(
+            symbols_needed,
+            self.loss_rate,
+            self.target_success,
+            in_flight + 1,
+        );
+        RecoveryAction::RequestRepair(sent - in_flight)
+    }
+}
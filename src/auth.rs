@@ -0,0 +1,198 @@
+use crate::base::EncodingPacket;
+use crate::base::EncodingPacketRef;
+#[cfg(feature = "security")]
+use crate::base::PayloadId;
+
+// Compares in time that depends only on the lengths, so that the timing doesn't reveal how much of
+// a forged tag was correct. The result is read back through a volatile load, so that the compiler
+// can't turn the loop into one which exits at the first difference
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    let mut difference = 0u8;
+    for (x, y) in a.iter().zip(b.iter()) {
+        difference |= x ^ y;
+    }
+    // SAFETY: `difference` is a valid, aligned, initialized local
+    unsafe { std::ptr::read_volatile(&difference) == 0 }
+}
+
+/// Encrypts the symbols of packets, for `PacketAuthenticator::seal()` and
+/// `PacketAuthenticator::open()`. The payload id is unique within an object, and so can serve as
+/// the nonce, as long as the key is unique per object.
+#[cfg(feature = "security")]
+pub trait PacketCipher {
+    fn encrypt(&self, payload_id: &PayloadId, data: &mut [u8]);
+
+    fn decrypt(&self, payload_id: &PayloadId, data: &mut [u8]);
+}
+
+/// Authenticates packets with a keyed BLAKE3 MAC, so that a receiver can discard forged packets
+/// before their symbols enter the decoder. A single forged symbol would otherwise corrupt the
+/// entire decoded object. The key must be shared out-of-band, and should be unique per object.
+#[derive(Clone)]
+pub struct PacketAuthenticator {
+    key: [u8; 32],
+}
+
+impl PacketAuthenticator {
+    /// Number of bytes appended to each packet
+    pub const TAG_SIZE: usize = 16;
+
+    pub fn new(key: [u8; 32]) -> PacketAuthenticator {
+        PacketAuthenticator { key }
+    }
+
+    fn tag(&self, data: &[u8]) -> [u8; PacketAuthenticator::TAG_SIZE] {
+        let hash = blake3::keyed_hash(&self.key, data);
+        let mut tag = [0; PacketAuthenticator::TAG_SIZE];
+        tag.copy_from_slice(&hash.as_bytes()[..PacketAuthenticator::TAG_SIZE]);
+        tag
+    }
+
+    /// Serializes the packet, followed by its tag
+    pub fn sign(&self, packet: &EncodingPacket) -> Vec<u8> {
+        let mut serialized = packet.serialize();
+        let tag = self.tag(&serialized);
+        serialized.extend_from_slice(&tag);
+        serialized
+    }
+
+    /// Verifies the tag of a packet serialized by `sign()`, and returns the packet without copying
+    /// it, for use with `Decoder::decode_borrowed()`. Returns None if the packet is malformed or
+    /// its tag does not match.
+    pub fn verify<'a>(&self, data: &'a [u8]) -> Option<EncodingPacketRef<'a>> {
+        if data.len() < 4 + PacketAuthenticator::TAG_SIZE {
+            return None;
+        }
+        let (packet, tag) = data.split_at(data.len() - PacketAuthenticator::TAG_SIZE);
+        if !constant_time_eq(&self.tag(packet), tag) {
+            return None;
+        }
+        Some(EncodingPacket::deserialize_borrowed(packet))
+    }
+
+    /// Encrypts the symbol of the packet, and then signs it like `sign()`. Receivers verify the
+    /// tag before decrypting anything (encrypt-then-MAC), see `open()`.
+    #[cfg(feature = "security")]
+    pub fn seal(&self, packet: &EncodingPacket, cipher: &dyn PacketCipher) -> Vec<u8> {
+        let mut data = packet.data().to_vec();
+        cipher.encrypt(packet.payload_id(), &mut data);
+        self.sign(&EncodingPacket::new(packet.payload_id().clone(), data))
+    }
+
+    /// Verifies and decrypts a packet serialized by `seal()`. Returns None if the packet is
+    /// malformed or its tag does not match, in which case nothing is decrypted.
+    #[cfg(feature = "security")]
+    pub fn open(&self, data: &[u8], cipher: &dyn PacketCipher) -> Option<EncodingPacket> {
+        let (payload_id, mut data) = self.verify(data)?.to_packet().split();
+        cipher.decrypt(&payload_id, &mut data);
+        Some(EncodingPacket::new(payload_id, data))
+    }
+}
+
+impl Drop for PacketAuthenticator {
+    // Clears the key, so that it doesn't linger in freed memory
+    fn drop(&mut self) {
+        for byte in self.key.iter_mut() {
+            // SAFETY: `byte` is a valid, aligned reference into the key
+            unsafe { std::ptr::write_volatile(byte, 0) };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Decoder, Encoder, PacketAuthenticator};
+    use rand::Rng;
+
+    #[test]
+    fn round_trip() {
+        let mut data: Vec<u8> = vec![0; 10_000];
+        for byte in data.iter_mut() {
+            *byte = rand::thread_rng().gen();
+        }
+        let authenticator = PacketAuthenticator::new(rand::thread_rng().gen());
+        let encoder = Encoder::with_defaults(&data, 500);
+        let mut packets: Vec<Vec<u8>> = encoder
+            .get_encoded_packets(5)
+            .iter()
+            .map(|packet| authenticator.sign(packet))
+            .collect();
+
+        // Forge the first few packets
+        for packet in packets.iter_mut().take(3) {
+            packet[10] ^= 1;
+        }
+
+        let mut decoder = Decoder::new(encoder.get_config());
+        let mut result = None;
+        let mut rejected = 0;
+        for packet in packets.iter() {
+            match authenticator.verify(packet) {
+                Some(packet) => result = decoder.decode_borrowed(packet),
+                None => rejected += 1,
+            }
+        }
+        assert_eq!(rejected, 3);
+        assert_eq!(result.unwrap(), data);
+    }
+
+    #[test]
+    fn constant_time_eq() {
+        assert!(super::constant_time_eq(&[], &[]));
+        assert!(super::constant_time_eq(&[1, 2, 3], &[1, 2, 3]));
+        assert!(!super::constant_time_eq(&[1, 2, 3], &[1, 2, 4]));
+        assert!(!super::constant_time_eq(&[0, 2, 3], &[1, 2, 3]));
+        assert!(!super::constant_time_eq(&[1, 2], &[1, 2, 3]));
+    }
+
+    #[test]
+    fn wrong_key() {
+        let signer = PacketAuthenticator::new([1; 32]);
+        let verifier = PacketAuthenticator::new([2; 32]);
+        let encoder = Encoder::with_defaults(&[3; 1000], 64);
+        for packet in encoder.get_encoded_packets(1) {
+            let signed = signer.sign(&packet);
+            assert_eq!(signer.verify(&signed).unwrap().to_packet(), packet);
+            assert!(verifier.verify(&signed).is_none());
+            assert!(signer.verify(&signed[..10]).is_none());
+        }
+    }
+
+    #[cfg(feature = "security")]
+    #[test]
+    fn sealed_packets() {
+        use crate::{EncodingPacket, PacketCipher, PayloadId};
+
+        // Not a real cipher, only enough to check that the symbols are transformed
+        struct XorCipher(u8);
+
+        impl PacketCipher for XorCipher {
+            fn encrypt(&self, payload_id: &PayloadId, data: &mut [u8]) {
+                for byte in data.iter_mut() {
+                    *byte ^= self.0 ^ payload_id.encoding_symbol_id() as u8;
+                }
+            }
+
+            fn decrypt(&self, payload_id: &PayloadId, data: &mut [u8]) {
+                self.encrypt(payload_id, data);
+            }
+        }
+
+        let authenticator = PacketAuthenticator::new([7; 32]);
+        let cipher = XorCipher(0x5A);
+        let encoder = Encoder::with_defaults(&[3; 1000], 64);
+        for packet in encoder.get_encoded_packets(1) {
+            let mut sealed = authenticator.seal(&packet, &cipher);
+            assert_ne!(
+                EncodingPacket::deserialize(&sealed[..sealed.len() - 16]).data(),
+                packet.data()
+            );
+            assert_eq!(authenticator.open(&sealed, &cipher).unwrap(), packet);
+            sealed[5] ^= 1;
+            assert!(authenticator.open(&sealed, &cipher).is_none());
+        }
+    }
+}
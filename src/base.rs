@@ -1,7 +1,10 @@
-use crate::rng::rand;
-use crate::systematic_constants::SYSTEMATIC_INDICES_AND_PARAMETERS;
+use crate::systematic_constants::{
+    validate_derived_parameters, MAX_SOURCE_SYMBOLS_PER_BLOCK, SYSTEMATIC_INDICES_AND_PARAMETERS,
+};
+use crate::util::div_ceil;
 use serde::{Deserialize, Serialize};
-use std::cmp::min;
+use std::fmt;
+use std::ops::AddAssign;
 
 // As defined in section 3.2
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, PartialOrd, Eq, Ord, Hash)]
@@ -22,9 +25,15 @@ impl PayloadId {
 
     #[allow(clippy::trivially_copy_pass_by_ref)]
     pub fn deserialize(data: &[u8; 4]) -> PayloadId {
+        PayloadId::from_bytes(*data)
+    }
+
+    /// Same as `deserialize()`, but usable in constant expressions. Every value is valid, as the
+    /// encoding symbol id is 24 bits on the wire.
+    pub const fn from_bytes(data: [u8; 4]) -> PayloadId {
         PayloadId {
             source_block_number: data[0],
-            encoding_symbol_id: ((data[1] as u32) << 16) + ((data[2] as u32) << 8) + data[3] as u32,
+            encoding_symbol_id: ((data[1] as u32) << 16) | ((data[2] as u32) << 8) | data[3] as u32,
         }
     }
 
@@ -89,8 +98,190 @@ impl EncodingPacket {
     pub fn split(self) -> (PayloadId, Vec<u8>) {
         (self.payload_id, self.data)
     }
+
+    /// Deserializes a packet without copying its payload, which instead borrows from `data`.
+    /// This allows a receiver to pass packets straight from its receive buffer to a decoder,
+    /// which only copies the payload if it retains the packet.
+    pub fn deserialize_borrowed(data: &[u8]) -> EncodingPacketRef<'_> {
+        let payload_data = [data[0], data[1], data[2], data[3]];
+        EncodingPacketRef {
+            payload_id: PayloadId::deserialize(&payload_data),
+            data: &data[4..],
+        }
+    }
+
+    /// Same as `deserialize_borrowed()`, but returns None rather than panicking if `data` is too
+    /// short to contain a payload id
+    pub fn try_deserialize_borrowed(data: &[u8]) -> Option<EncodingPacketRef<'_>> {
+        if data.len() < 4 {
+            return None;
+        }
+        Some(EncodingPacket::deserialize_borrowed(data))
+    }
+}
+
+/// An `EncodingPacket` whose payload is borrowed. See `EncodingPacket::deserialize_borrowed()`.
+#[derive(Clone, Debug, PartialEq, PartialOrd, Eq, Ord, Hash)]
+pub struct EncodingPacketRef<'a> {
+    pub(crate) payload_id: PayloadId,
+    pub(crate) data: &'a [u8],
+}
+
+impl<'a> EncodingPacketRef<'a> {
+    pub fn new(payload_id: PayloadId, data: &'a [u8]) -> EncodingPacketRef<'a> {
+        EncodingPacketRef { payload_id, data }
+    }
+
+    /// Retrieves packet payload ID.
+    pub fn payload_id(&self) -> &PayloadId {
+        &self.payload_id
+    }
+
+    /// Retrieves packet payload.
+    pub fn data(&self) -> &'a [u8] {
+        self.data
+    }
+
+    /// Copies the payload into an owned packet.
+    pub fn to_packet(&self) -> EncodingPacket {
+        EncodingPacket::new(self.payload_id.clone(), self.data.to_vec())
+    }
+}
+
+/// A source packet whose symbol is borrowed from the object being encoded, rather than copied.
+/// See `Encoder::source_packets_borrowed()`.
+///
+/// The serialized packet is the header, then the data, then `padding()` zero bytes, which only the
+/// last source packet of an object can have. These can be sent without assembling the packet, for
+/// example as `IoSlice`s with a vectored write.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct SourcePacketRef<'a> {
+    payload_id: PayloadId,
+    data: &'a [u8],
+    padding: usize,
+}
+
+impl<'a> SourcePacketRef<'a> {
+    pub(crate) fn new(
+        payload_id: PayloadId,
+        data: &'a [u8],
+        padding: usize,
+    ) -> SourcePacketRef<'a> {
+        SourcePacketRef {
+            payload_id,
+            data,
+            padding,
+        }
+    }
+
+    pub fn payload_id(&self) -> &PayloadId {
+        &self.payload_id
+    }
+
+    /// The serialized payload id
+    pub fn header(&self) -> [u8; 4] {
+        self.payload_id.serialize()
+    }
+
+    /// The part of the symbol which is in the object
+    pub fn data(&self) -> &'a [u8] {
+        self.data
+    }
+
+    /// Number of zero bytes which complete the symbol
+    pub fn padding(&self) -> usize {
+        self.padding
+    }
+
+    /// Copies the symbol, including its padding, into an owned packet
+    pub fn to_packet(&self) -> EncodingPacket {
+        let mut data = Vec::with_capacity(self.data.len() + self.padding);
+        data.extend_from_slice(self.data);
+        data.resize(self.data.len() + self.padding, 0);
+        EncodingPacket::new(self.payload_id.clone(), data)
+    }
 }
 
+// The maximum transfer length, of 40 bits less a multiple of the maximum symbol size. See section
+// 4.4.1.2
+pub(crate) const MAX_TRANSFER_LENGTH: u64 = 946270874880;
+
+/// Returned when an `ObjectTransmissionInformation` describes an object which cannot be decoded.
+/// As the OTI is usually received from the sender, this must be handled rather than trusted.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ConfigError {
+    /// The symbol size, number of source blocks, number of sub-blocks, or symbol alignment is zero
+    ZeroParameter,
+    /// More sub-blocks than a symbol can be split into, or sub-blocks with a symbol size which is
+    /// not a multiple of the symbol alignment
+    UnsupportedSubBlocks,
+    /// A source block would contain more than the 56403 source symbols allowed by section 5.1.2
+    SourceBlockTooLarge { source_block_symbols: u64 },
+    /// The number of symbols, or the size of the object in memory, is not representable
+    Overflow,
+    /// The object is larger than the maximum transfer length of section 4.4.1.2
+    TransferLengthTooLarge { transfer_length: u64 },
+    /// A parameter of the object, or one derived from it, does not conform to RFC 6330. Only
+    /// returned in strict mode. See `ObjectTransmissionInformation::check_strict()`
+    NonCompliant {
+        parameter: &'static str,
+        source_block_symbols: u32,
+    },
+    /// The object has no source block with the given number, or the source block has no
+    /// sub-block with the given number
+    NoSuchBlock {
+        source_block_number: u8,
+        sub_block_number: u16,
+    },
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConfigError::ZeroParameter => {
+                write!(
+                    f,
+                    "zero symbol size, source blocks, sub-blocks or alignment"
+                )
+            }
+            ConfigError::UnsupportedSubBlocks => {
+                write!(f, "symbols can not be split into that many sub-blocks")
+            }
+            ConfigError::SourceBlockTooLarge {
+                source_block_symbols,
+            } => write!(
+                f,
+                "source block of {} symbols exceeds the maximum of {}",
+                source_block_symbols, MAX_SOURCE_SYMBOLS_PER_BLOCK
+            ),
+            ConfigError::Overflow => write!(f, "object size overflows"),
+            ConfigError::TransferLengthTooLarge { transfer_length } => write!(
+                f,
+                "transfer length of {} bytes exceeds the maximum of {}",
+                transfer_length, MAX_TRANSFER_LENGTH
+            ),
+            ConfigError::NonCompliant {
+                parameter,
+                source_block_symbols,
+            } => write!(
+                f,
+                "parameter {} is not compliant, for a source block of {} symbols",
+                parameter, source_block_symbols
+            ),
+            ConfigError::NoSuchBlock {
+                source_block_number,
+                sub_block_number,
+            } => write!(
+                f,
+                "no sub-block {} of source block {}",
+                sub_block_number, source_block_number
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
 // As defined in section 3.3.2 and 3.3.3
 #[derive(Clone, Debug, PartialEq, PartialOrd, Eq, Ord, Serialize, Deserialize, Hash)]
 pub struct ObjectTransmissionInformation {
@@ -109,7 +300,7 @@ impl ObjectTransmissionInformation {
         sub_blocks: u16,
         alignment: u8,
     ) -> ObjectTransmissionInformation {
-        assert!(transfer_length <= 946270874880);
+        assert!(transfer_length <= MAX_TRANSFER_LENGTH);
         assert_eq!(symbol_size % alignment as u16, 0);
         ObjectTransmissionInformation {
             transfer_length,
@@ -121,15 +312,22 @@ impl ObjectTransmissionInformation {
     }
 
     pub fn deserialize(data: &[u8; 12]) -> ObjectTransmissionInformation {
+        ObjectTransmissionInformation::from_bytes(*data)
+    }
+
+    /// Same as `deserialize()`, but usable in constant expressions, e.g. for a configuration
+    /// which is fixed when the receiver is built. The result is not validated, see
+    /// `Decoder::try_new()`.
+    pub const fn from_bytes(data: [u8; 12]) -> ObjectTransmissionInformation {
         ObjectTransmissionInformation {
             transfer_length: ((data[0] as u64) << 32)
-                + ((data[1] as u64) << 24)
-                + ((data[2] as u64) << 16)
-                + ((data[3] as u64) << 8)
-                + (data[4] as u64),
-            symbol_size: ((data[6] as u16) << 8) + data[7] as u16,
+                | ((data[1] as u64) << 24)
+                | ((data[2] as u64) << 16)
+                | ((data[3] as u64) << 8)
+                | (data[4] as u64),
+            symbol_size: ((data[6] as u16) << 8) | data[7] as u16,
             num_source_blocks: data[8],
-            num_sub_blocks: ((data[9] as u16) << 8) + data[10] as u16,
+            num_sub_blocks: ((data[9] as u16) << 8) | data[10] as u16,
             symbol_alignment: data[11],
         }
     }
@@ -171,6 +369,192 @@ impl ObjectTransmissionInformation {
         self.symbol_alignment
     }
 
+    // Every sub-symbol must be a non-zero multiple of the alignment. See section 4.4.1.2
+    pub(crate) fn check_sub_blocks(&self) -> Result<(), ConfigError> {
+        if self.num_sub_blocks > 1 {
+            let alignment = u16::from(self.symbol_alignment);
+            if alignment == 0
+                || self.symbol_size / alignment * alignment != self.symbol_size
+                || self.num_sub_blocks > self.symbol_size / alignment
+            {
+                return Err(ConfigError::UnsupportedSubBlocks);
+            }
+        }
+        Ok(())
+    }
+
+    // Returns the sizes in bytes of the sub-symbols of each of the N sub-blocks, which together
+    // make up a symbol. See section 4.4.1.2
+    pub(crate) fn sub_symbol_sizes(&self) -> Vec<usize> {
+        if self.num_sub_blocks == 1 {
+            // The symbol size need not be a multiple of the alignment
+            return vec![self.symbol_size as usize];
+        }
+        let alignment = u32::from(self.symbol_alignment);
+        let (tl, ts, nl, ns) = partition(
+            u32::from(self.symbol_size) / alignment,
+            u32::from(self.num_sub_blocks),
+        );
+        let mut sizes = vec![(tl * alignment) as usize; nl as usize];
+        sizes.extend(vec![(ts * alignment) as usize; ns as usize]);
+        sizes
+    }
+
+    /// Returns the number of zero bytes which pad the last symbol of the last source block, after
+    /// the end of the object. See section 4.4.1.2. Returns 0 if the symbol size is zero, which an
+    /// OTI received from the sender may contain, though no object can be decoded with it.
+    pub fn padding(&self) -> u16 {
+        let symbol_size = u64::from(self.symbol_size);
+        if symbol_size == 0 {
+            return 0;
+        }
+        ((symbol_size - self.transfer_length % symbol_size) % symbol_size) as u16
+    }
+
+    /// Returns the minimum number of packets needed to decode the object, i.e. the number of source
+    /// symbols, excluding the padding symbols of section 5.3.1. Decoding from exactly this many
+    /// packets fails with a small probability, see `Encoder::total_packets_for_overhead()`.
+    /// Returns 0 if the symbol size is zero, as no number of packets decodes such an object.
+    pub fn min_packets(&self) -> u64 {
+        if self.symbol_size == 0 {
+            return 0;
+        }
+        div_ceil(self.transfer_length, u64::from(self.symbol_size))
+    }
+
+    /// Returns the source block partitioning (KL, KS, ZL, ZS) of section 4.4.1.2, checking that
+    /// every size derived from it fits in memory
+    pub(crate) fn checked_block_partition(&self) -> Result<(u32, u32, u32, u32), ConfigError> {
+        if self.symbol_size == 0
+            || self.num_source_blocks == 0
+            || self.num_sub_blocks == 0
+            || self.symbol_alignment == 0
+        {
+            return Err(ConfigError::ZeroParameter);
+        }
+        self.check_sub_blocks()?;
+        let symbol_size = u64::from(self.symbol_size);
+        let kt = self
+            .transfer_length
+            .checked_add(symbol_size - 1)
+            .ok_or(ConfigError::Overflow)?
+            / symbol_size;
+        if kt > 0xFFFF_FFFF {
+            return Err(ConfigError::Overflow);
+        }
+        let total_length = kt.checked_mul(symbol_size).ok_or(ConfigError::Overflow)?;
+        if total_length as usize as u64 != total_length {
+            return Err(ConfigError::Overflow);
+        }
+        let (kl, ks, zl, zs) = partition(kt as u32, self.num_source_blocks);
+        if kl > MAX_SOURCE_SYMBOLS_PER_BLOCK {
+            return Err(ConfigError::SourceBlockTooLarge {
+                source_block_symbols: u64::from(kl),
+            });
+        }
+        Ok((kl, ks, zl, zs))
+    }
+
+    /// Validates the parameters for strict compliance with RFC 6330: in addition to the checks of
+    /// `Decoder::try_new()`, the symbol size must be a multiple of the alignment, every source
+    /// block must contain at least one symbol, and all the parameters derived for each source
+    /// block size (K', J, S, H, W and P1) are checked against the tables and constraints of the
+    /// RFC.
+    pub fn check_strict(&self) -> Result<(), ConfigError> {
+        let (kl, ks, zl, zs) = self.checked_block_partition()?;
+        let alignment = u16::from(self.symbol_alignment);
+        if alignment == 0 || self.symbol_size / alignment * alignment != self.symbol_size {
+            return Err(ConfigError::NonCompliant {
+                parameter: "Al",
+                source_block_symbols: kl,
+            });
+        }
+        // See section 4.4.1.2
+        for &(block_symbols, blocks) in [(kl, zl), (ks, zs)].iter() {
+            if blocks == 0 {
+                continue;
+            }
+            if block_symbols == 0 {
+                return Err(ConfigError::NonCompliant {
+                    parameter: "Z",
+                    source_block_symbols: block_symbols,
+                });
+            }
+            validate_derived_parameters(block_symbols).map_err(|parameter| {
+                ConfigError::NonCompliant {
+                    parameter,
+                    source_block_symbols: block_symbols,
+                }
+            })?;
+        }
+        Ok(())
+    }
+
+    /// Serializes the parameters to a JSON object, for out-of-band signaling.
+    ///
+    /// The object has exactly the following fields, all unsigned integers:
+    ///
+    /// ```json
+    /// {
+    ///   "transfer_length": 1000000,
+    ///   "symbol_size": 1400,
+    ///   "num_source_blocks": 1,
+    ///   "num_sub_blocks": 1,
+    ///   "symbol_alignment": 8
+    /// }
+    /// ```
+    ///
+    /// The field names and ranges correspond to F, T, Z, N and Al in section 3.3.2.
+    #[cfg(feature = "json")]
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap()
+    }
+
+    /// Parses parameters produced by [`to_json`](#method.to_json), or an equivalent object
+    /// produced by another implementation. Unknown fields are rejected, as are values which
+    /// violate the constraints of section 3.3.2.
+    #[cfg(feature = "json")]
+    pub fn from_json(json: &str) -> Result<ObjectTransmissionInformation, serde_json::Error> {
+        use serde::de::Error;
+
+        #[derive(Deserialize)]
+        #[serde(deny_unknown_fields)]
+        struct Schema {
+            transfer_length: u64,
+            symbol_size: u16,
+            num_source_blocks: u8,
+            num_sub_blocks: u16,
+            symbol_alignment: u8,
+        }
+
+        let parsed: Schema = serde_json::from_str(json)?;
+        if parsed.transfer_length > 946270874880 {
+            return Err(serde_json::Error::custom(
+                "transfer_length exceeds 946270874880",
+            ));
+        }
+        if parsed.symbol_alignment == 0 || parsed.symbol_size % parsed.symbol_alignment as u16 != 0
+        {
+            return Err(serde_json::Error::custom(
+                "symbol_size must be a multiple of symbol_alignment",
+            ));
+        }
+        if parsed.symbol_size == 0 || parsed.num_source_blocks == 0 || parsed.num_sub_blocks == 0 {
+            return Err(serde_json::Error::custom(
+                "symbol_size, num_source_blocks and num_sub_blocks must be non-zero",
+            ));
+        }
+        let oti = ObjectTransmissionInformation {
+            transfer_length: parsed.transfer_length,
+            symbol_size: parsed.symbol_size,
+            num_source_blocks: parsed.num_source_blocks,
+            num_sub_blocks: parsed.num_sub_blocks,
+            symbol_alignment: parsed.symbol_alignment,
+        };
+        oti.check_sub_blocks().map_err(serde_json::Error::custom)?;
+        Ok(oti)
+    }
+
     pub fn with_defaults(
         transfer_length: u64,
         max_packet_size: u16,
@@ -214,6 +598,124 @@ impl ObjectTransmissionInformation {
     }
 }
 
+/// Breakdown of the heap memory, in bytes, retained by an encoder or decoder.
+///
+/// Matrices are only allocated for the duration of a single encode or decode call, and so are
+/// not included.
+#[derive(Clone, Debug, Default, PartialEq, PartialOrd, Eq, Ord, Serialize, Deserialize, Hash)]
+pub struct MemoryUsage {
+    source_symbols: usize,
+    repair_symbols: usize,
+    intermediate_symbols: usize,
+    decoded_data: usize,
+    indices: usize,
+}
+
+impl MemoryUsage {
+    pub(crate) fn new(
+        source_symbols: usize,
+        repair_symbols: usize,
+        intermediate_symbols: usize,
+        decoded_data: usize,
+        indices: usize,
+    ) -> MemoryUsage {
+        MemoryUsage {
+            source_symbols,
+            repair_symbols,
+            intermediate_symbols,
+            decoded_data,
+            indices,
+        }
+    }
+
+    /// Bytes used by source symbols, either retained for encoding or received for decoding.
+    pub fn source_symbols(&self) -> usize {
+        self.source_symbols
+    }
+
+    /// Bytes used by received repair symbols which are buffered until they can be decoded.
+    pub fn repair_symbols(&self) -> usize {
+        self.repair_symbols
+    }
+
+    /// Bytes used by intermediate symbols, from which repair symbols are generated.
+    pub fn intermediate_symbols(&self) -> usize {
+        self.intermediate_symbols
+    }
+
+    /// Bytes used by source blocks which have already been decoded.
+    pub fn decoded_data(&self) -> usize {
+        self.decoded_data
+    }
+
+    /// Bytes used by bookkeeping of which symbols have been received.
+    pub fn indices(&self) -> usize {
+        self.indices
+    }
+
+    pub fn total(&self) -> usize {
+        self.source_symbols
+            + self.repair_symbols
+            + self.intermediate_symbols
+            + self.decoded_data
+            + self.indices
+    }
+}
+
+impl AddAssign for MemoryUsage {
+    fn add_assign(&mut self, other: MemoryUsage) {
+        self.source_symbols += other.source_symbols;
+        self.repair_symbols += other.repair_symbols;
+        self.intermediate_symbols += other.intermediate_symbols;
+        self.decoded_data += other.decoded_data;
+        self.indices += other.indices;
+    }
+}
+
+// Rearranges a source block from its sub-blocks, one after another, into symbols which are each the
+// concatenation of one sub-symbol from every sub-block, as in section 4.4.1.2. Encoding and
+// decoding whole symbols is then the same as encoding and decoding every sub-block separately,
+// since all the sub-blocks use the same encoding symbol ids, but each operation touches one
+// contiguous symbol rather than N separate sub-symbols.
+pub(crate) fn interleave_sub_blocks(block: &[u8], sub_symbol_sizes: &[usize]) -> Vec<u8> {
+    let symbol_size: usize = sub_symbol_sizes.iter().sum();
+    let num_symbols = block.len() / symbol_size;
+    let mut result = vec![0; block.len()];
+    let mut sub_block_offset = 0;
+    let mut sub_symbol_offset = 0;
+    for &sub_symbol_size in sub_symbol_sizes.iter() {
+        for i in 0..num_symbols {
+            let from = sub_block_offset + i * sub_symbol_size;
+            let to = i * symbol_size + sub_symbol_offset;
+            result[to..(to + sub_symbol_size)]
+                .copy_from_slice(&block[from..(from + sub_symbol_size)]);
+        }
+        sub_block_offset += num_symbols * sub_symbol_size;
+        sub_symbol_offset += sub_symbol_size;
+    }
+    result
+}
+
+// Inverse of interleave_sub_blocks()
+pub(crate) fn deinterleave_sub_blocks(symbols: &[u8], sub_symbol_sizes: &[usize]) -> Vec<u8> {
+    let symbol_size: usize = sub_symbol_sizes.iter().sum();
+    let num_symbols = symbols.len() / symbol_size;
+    let mut result = vec![0; symbols.len()];
+    let mut sub_block_offset = 0;
+    let mut sub_symbol_offset = 0;
+    for &sub_symbol_size in sub_symbol_sizes.iter() {
+        for i in 0..num_symbols {
+            let from = i * symbol_size + sub_symbol_offset;
+            let to = sub_block_offset + i * sub_symbol_size;
+            result[to..(to + sub_symbol_size)]
+                .copy_from_slice(&symbols[from..(from + sub_symbol_size)]);
+        }
+        sub_block_offset += num_symbols * sub_symbol_size;
+        sub_symbol_offset += sub_symbol_size;
+    }
+    result
+}
+
 // Partition[I, J] function, as defined in section 4.4.1.2
 pub fn partition<TI, TJ>(i: TI, j: TJ) -> (u32, u32, u32, u32)
 where
@@ -228,65 +730,57 @@ where
     (il, is, jl, js)
 }
 
-// Deg[v] as defined in section 5.3.5.2
-pub fn deg(v: u32, lt_symbols: u32) -> u32 {
-    assert!(v < 1048576);
-    let f: [u32; 31] = [
-        0, 5243, 529531, 704294, 791675, 844104, 879057, 904023, 922747, 937311, 948962, 958494,
-        966438, 973160, 978921, 983914, 988283, 992138, 995565, 998631, 1001391, 1003887, 1006157,
-        1008229, 1010129, 1011876, 1013490, 1014983, 1016370, 1017662, 1048576,
-    ];
+#[cfg(test)]
+mod tests {
+    use crate::base::{deinterleave_sub_blocks, interleave_sub_blocks, partition};
+    use crate::{
+        ConfigError, Decoder, Encoder, EncodingPacket, ObjectTransmissionInformation, PayloadId,
+    };
+    use proptest::prelude::*;
+    use rand::seq::SliceRandom;
+    use rand::Rng;
 
-    #[allow(clippy::needless_range_loop)]
-    for d in 1..f.len() {
-        if v < f[d] {
-            return min(d as u32, lt_symbols - 2);
+    proptest! {
+        #[test]
+        fn payload_id_round_trip(source_block_number: u8, encoding_symbol_id in 0u32..(1 << 24)) {
+            let payload_id = PayloadId::new(source_block_number, encoding_symbol_id);
+            let serialized = payload_id.serialize();
+            prop_assert_eq!(PayloadId::deserialize(&serialized), payload_id.clone());
+            prop_assert_eq!(PayloadId::from_bytes(serialized), payload_id);
+            prop_assert_eq!(serialized[0], source_block_number);
         }
-    }
-    unreachable!();
-}
-
-// Tuple[K', X] as defined in section 5.3.5.4
-#[allow(non_snake_case, clippy::many_single_char_names)]
-pub fn intermediate_tuple(
-    internal_symbol_id: u32,
-    lt_symbols: u32,
-    systematic_index: u32,
-    p1: u32,
-) -> (u32, u32, u32, u32, u32, u32) {
-    let J = systematic_index;
-    let W = lt_symbols;
-    let P1 = p1;
-
-    let mut A = 53591 + J * 997;
-
-    if A % 2 == 0 {
-        A += 1;
-    }
-
-    let B = 10267 * (J + 1);
-    let y: u32 = ((B as u64 + internal_symbol_id as u64 * A as u64) % 4294967296) as u32;
-    let v = rand(y, 0u32, 1048576);
-    let d = deg(v, W);
-    let a = 1 + rand(y, 1u32, W - 1);
-    let b = rand(y, 2u32, W);
-
-    let d1 = if d < 4 {
-        2 + rand(internal_symbol_id, 3u32, 2)
-    } else {
-        2
-    };
 
-    let a1 = 1 + rand(internal_symbol_id, 4u32, P1 - 1);
-    let b1 = rand(internal_symbol_id, 5u32, P1);
+        #[test]
+        fn payload_id_wire_format(data: [u8; 4]) {
+            // Every 4 byte header is a valid payload id, which serializes back to the same bytes
+            let payload_id = PayloadId::deserialize(&data);
+            prop_assert!(payload_id.encoding_symbol_id() < (1 << 24));
+            prop_assert_eq!(payload_id.serialize(), data);
+        }
 
-    (d, a, b, d1, a1, b1)
-}
+        #[test]
+        fn oti_round_trip(
+            transfer_length in 0u64..=946270874880,
+            symbol_size: u16,
+            source_blocks: u8,
+            sub_blocks: u16,
+        ) {
+            let config =
+                ObjectTransmissionInformation::new(transfer_length, symbol_size, source_blocks, sub_blocks, 1);
+            let serialized = config.serialize();
+            prop_assert_eq!(serialized[5], 0);
+            prop_assert_eq!(ObjectTransmissionInformation::deserialize(&serialized), config);
+        }
 
-#[cfg(test)]
-mod tests {
-    use crate::{EncodingPacket, ObjectTransmissionInformation, PayloadId};
-    use rand::Rng;
+        #[test]
+        fn partition_invariants(i: u32, j in 1u32..=u32::from(u16::MAX)) {
+            let (il, is, jl, js) = partition(i, j);
+            prop_assert_eq!(jl + js, j);
+            prop_assert_eq!(u64::from(il) * u64::from(jl) + u64::from(is) * u64::from(js), u64::from(i));
+            prop_assert!(il == is || il == is + 1);
+            prop_assert!(jl < j || il == is);
+        }
+    }
 
     #[test]
     fn payload_id_serialization() {
@@ -309,6 +803,118 @@ mod tests {
         assert_eq!(deserialized, packet);
     }
 
+    #[test]
+    fn encoding_packet_borrowed_deserialization() {
+        let payload_id = PayloadId::new(
+            rand::thread_rng().gen(),
+            rand::thread_rng().gen_range(0, 256 * 256 * 256),
+        );
+        let packet = EncodingPacket::new(payload_id, vec![rand::thread_rng().gen(); 8]);
+        let serialized = packet.serialize();
+        let borrowed = EncodingPacket::deserialize_borrowed(&serialized);
+        assert_eq!(borrowed.payload_id(), packet.payload_id());
+        assert_eq!(borrowed.data(), packet.data());
+        assert_eq!(borrowed.to_packet(), packet);
+    }
+
+    #[test]
+    fn const_construction() {
+        const PAYLOAD_ID: PayloadId = PayloadId::from_bytes([3, 0xFF, 0, 1]);
+        const OTI: ObjectTransmissionInformation =
+            ObjectTransmissionInformation::from_bytes([0, 0, 0, 3, 0xE8, 0, 0, 64, 1, 0, 1, 8]);
+        assert_eq!(PAYLOAD_ID, PayloadId::new(3, 0xFF0001));
+        assert_eq!(OTI, ObjectTransmissionInformation::new(1000, 64, 1, 1, 8));
+
+        assert_eq!(EncodingPacket::try_deserialize_borrowed(&[1, 2, 3]), None);
+        let packet = EncodingPacket::try_deserialize_borrowed(&[3, 0xFF, 0, 1]).unwrap();
+        assert_eq!(packet.payload_id(), &PAYLOAD_ID);
+        assert!(packet.data().is_empty());
+    }
+
+    #[test]
+    fn sub_block_interleaving() {
+        // T = 40 and Al = 8 split into N = 3 sub-blocks of 16, 16 and 8 bytes
+        let oti = ObjectTransmissionInformation::new(200, 40, 1, 3, 8);
+        let sub_symbol_sizes = oti.sub_symbol_sizes();
+        assert_eq!(vec![16, 16, 8], sub_symbol_sizes);
+
+        let block: Vec<u8> = (0..200).map(|i| i as u8).collect();
+        let symbols = interleave_sub_blocks(&block, &sub_symbol_sizes);
+        // The second symbol is the second sub-symbol of each of the sub-blocks, which are 80, 80
+        // and 40 bytes
+        assert_eq!(block[16..32], symbols[40..56]);
+        assert_eq!(block[96..112], symbols[56..72]);
+        assert_eq!(block[168..176], symbols[72..80]);
+        assert_eq!(block, deinterleave_sub_blocks(&symbols, &sub_symbol_sizes));
+    }
+
+    #[test]
+    fn sub_blocks_round_trip() {
+        let mut data: Vec<u8> = vec![0; 10_000];
+        rand::thread_rng().fill(&mut data[..]);
+        let oti = ObjectTransmissionInformation::new(data.len() as u64, 64, 2, 3, 8);
+        let encoder = Encoder::new(&data, oti.clone());
+        assert!(encoder.encodes(&data));
+
+        let mut packets = encoder.get_encoded_packets(10);
+        packets.shuffle(&mut rand::thread_rng());
+        packets.truncate(packets.len() - 5);
+        let mut decoder = Decoder::new(oti);
+        let mut result = None;
+        for packet in packets {
+            result = result.or_else(|| decoder.decode(packet));
+        }
+        assert_eq!(Some(data), result);
+
+        // Sub-symbols must be at least the alignment
+        let oti = ObjectTransmissionInformation::new(1000, 64, 1, 9, 8);
+        assert_eq!(
+            Some(ConfigError::UnsupportedSubBlocks),
+            Decoder::try_new(oti).err()
+        );
+    }
+
+    #[test]
+    fn padding() {
+        assert_eq!(
+            ObjectTransmissionInformation::new(1000, 64, 1, 1, 8).padding(),
+            24
+        );
+        assert_eq!(
+            ObjectTransmissionInformation::new(1024, 64, 1, 1, 8).padding(),
+            0
+        );
+        assert_eq!(
+            ObjectTransmissionInformation::new(1, 64, 1, 1, 8).padding(),
+            63
+        );
+        // Zero symbol size
+        let config =
+            ObjectTransmissionInformation::from_bytes([0, 0, 0, 3, 232, 0, 0, 0, 1, 0, 1, 8]);
+        assert_eq!(config.symbol_size(), 0);
+        assert_eq!(config.padding(), 0);
+    }
+
+    #[test]
+    fn min_packets() {
+        assert_eq!(
+            ObjectTransmissionInformation::new(1000, 64, 1, 1, 8).min_packets(),
+            16
+        );
+        assert_eq!(
+            ObjectTransmissionInformation::new(1024, 64, 3, 1, 8).min_packets(),
+            16
+        );
+        assert_eq!(
+            ObjectTransmissionInformation::new(0, 64, 1, 1, 8).min_packets(),
+            0
+        );
+        // Zero symbol size
+        let config =
+            ObjectTransmissionInformation::from_bytes([0, 0, 0, 3, 232, 0, 0, 0, 1, 0, 1, 8]);
+        assert_eq!(config.min_packets(), 0);
+    }
+
     #[test]
     fn oti_serialization() {
         let oti = ObjectTransmissionInformation::with_defaults(
@@ -318,4 +924,75 @@ mod tests {
         let deserialized = ObjectTransmissionInformation::deserialize(&oti.serialize());
         assert_eq!(deserialized, oti);
     }
+
+    #[test]
+    #[cfg(feature = "json")]
+    fn oti_json() {
+        let oti = ObjectTransmissionInformation::with_defaults(
+            rand::thread_rng().gen_range(0, 946270874880),
+            rand::thread_rng().gen_range(8, 65535),
+        );
+        let deserialized = ObjectTransmissionInformation::from_json(&oti.to_json()).unwrap();
+        assert_eq!(deserialized, oti);
+
+        let misaligned = r#"{"transfer_length": 10, "symbol_size": 9, "num_source_blocks": 1,
+                             "num_sub_blocks": 1, "symbol_alignment": 8}"#;
+        assert!(ObjectTransmissionInformation::from_json(misaligned).is_err());
+
+        let json = |symbol_size: u16, source_blocks: u8, sub_blocks: u16| {
+            format!(
+                r#"{{"transfer_length": 10, "symbol_size": {}, "num_source_blocks": {},
+                    "num_sub_blocks": {}, "symbol_alignment": 8}}"#,
+                symbol_size, source_blocks, sub_blocks
+            )
+        };
+        assert!(ObjectTransmissionInformation::from_json(&json(16, 1, 2)).is_ok());
+        assert!(ObjectTransmissionInformation::from_json(&json(0, 1, 1)).is_err());
+        assert!(ObjectTransmissionInformation::from_json(&json(16, 0, 1)).is_err());
+        assert!(ObjectTransmissionInformation::from_json(&json(16, 1, 0)).is_err());
+        // More sub-blocks than T / Al
+        assert!(ObjectTransmissionInformation::from_json(&json(16, 1, 3)).is_err());
+    }
+
+    #[test]
+    fn maximal_field_values() {
+        let oti = |transfer_length: u64, symbol_size: u16, source_blocks: u8, sub_blocks: u16| {
+            let mut data = ObjectTransmissionInformation::new(0, 8, 1, 1, 8).serialize();
+            data[0..5].copy_from_slice(&transfer_length.to_be_bytes()[3..]);
+            data[6..8].copy_from_slice(&symbol_size.to_be_bytes());
+            data[8] = source_blocks;
+            data[9..11].copy_from_slice(&sub_blocks.to_be_bytes());
+            ObjectTransmissionInformation::deserialize(&data)
+        };
+        let max_transfer_length = (1 << 40) - 1;
+
+        assert_eq!(
+            Decoder::try_new(ObjectTransmissionInformation::deserialize(&[0xFF; 12])).err(),
+            Some(ConfigError::UnsupportedSubBlocks)
+        );
+        assert_eq!(
+            Decoder::try_new(oti(max_transfer_length, 0, 1, 1)).err(),
+            Some(ConfigError::ZeroParameter)
+        );
+        assert_eq!(
+            Decoder::try_new(oti(max_transfer_length, 8, 0, 1)).err(),
+            Some(ConfigError::ZeroParameter)
+        );
+        assert_eq!(
+            Decoder::try_new(oti(max_transfer_length, 1, 255, 1)).err(),
+            Some(ConfigError::Overflow)
+        );
+        assert_eq!(
+            Decoder::try_new(oti(max_transfer_length, 0xFFFF, 255, 1)).err(),
+            Some(ConfigError::SourceBlockTooLarge {
+                source_block_symbols: 65795
+            })
+        );
+        assert_eq!(
+            Decoder::try_new(oti(max_transfer_length, 0xFFFF, 1, 1)).err(),
+            Some(ConfigError::SourceBlockTooLarge {
+                source_block_symbols: 16777473
+            })
+        );
+    }
 }
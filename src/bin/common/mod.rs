@@ -0,0 +1,91 @@
+// The datagrams exchanged by raptorq-send and raptorq-recv. Each begins with a byte which
+// identifies its kind:
+//   CONFIG (sender to receiver): the 12 byte OTI of the file. Repeated until it is acknowledged
+//   DATA (sender to receiver): a serialized EncodingPacket
+//   ACK (receiver to sender): a serialized BlockAckFrame, sent in reply to CONFIG, whenever
+//     the sender has been quiet for RETRY_INTERVAL, and in reply to anything once the file is
+//     decoded
+
+// Each binary only uses part of this
+#![allow(dead_code)]
+
+use raptorq::{
+    BlockAckFrame, Config, EncodingPacket, EncodingPacketRef, ObjectTransmissionInformation,
+};
+use std::net::SocketAddr;
+use std::time::Duration;
+
+const CONFIG: u8 = 1;
+const DATA: u8 = 2;
+const ACK: u8 = 3;
+
+// Ethernet MTU, less the IPv6 and UDP headers
+const MAX_DATAGRAM: u16 = 1500 - 40 - 8;
+pub const RECEIVE_BUFFER_SIZE: usize = 65536;
+// How long either side waits for a datagram before repeating itself
+pub const RETRY_INTERVAL: Duration = Duration::from_millis(200);
+// How long either side waits without receiving anything, before giving up
+pub const IDLE_TIMEOUT: Duration = Duration::from_secs(10);
+
+pub enum Message<'a> {
+    Config(ObjectTransmissionInformation),
+    Data(EncodingPacketRef<'a>),
+    Ack(BlockAckFrame),
+}
+
+impl<'a> Message<'a> {
+    pub fn serialize(&self) -> Vec<u8> {
+        match self {
+            Message::Config(config) => {
+                let mut serialized = vec![CONFIG];
+                serialized.extend_from_slice(&config.serialize());
+                serialized
+            }
+            Message::Data(packet) => {
+                let mut serialized = vec![DATA];
+                serialized.extend_from_slice(&packet.payload_id().serialize());
+                serialized.extend_from_slice(packet.data());
+                serialized
+            }
+            Message::Ack(ack) => {
+                let mut serialized = vec![ACK];
+                serialized.extend_from_slice(&ack.serialize());
+                serialized
+            }
+        }
+    }
+
+    // Returns None for malformed datagrams, which are ignored
+    pub fn deserialize(data: &'a [u8]) -> Option<Message<'a>> {
+        let (kind, body) = data.split_first()?;
+        match *kind {
+            CONFIG if body.len() == 12 => {
+                let mut config = [0; 12];
+                config.copy_from_slice(body);
+                Some(Message::Config(ObjectTransmissionInformation::deserialize(
+                    &config,
+                )))
+            }
+            DATA => EncodingPacket::try_deserialize_borrowed(body).map(Message::Data),
+            ACK => BlockAckFrame::deserialize(body).map(Message::Ack),
+            _ => None,
+        }
+    }
+}
+
+// Leaves room in each datagram for the kind byte
+pub fn config() -> Config {
+    Config::new(MAX_DATAGRAM - 1, 8192)
+}
+
+pub fn parse_address(address: &str) -> SocketAddr {
+    match address.parse() {
+        Ok(address) => address,
+        Err(_) => fail(&format!("invalid address: {}", address)),
+    }
+}
+
+pub fn fail(message: &str) -> ! {
+    eprintln!("{}", message);
+    std::process::exit(1);
+}
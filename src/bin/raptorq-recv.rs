@@ -0,0 +1,96 @@
+// Receives a file from raptorq-send over UDP. See common/mod.rs for the protocol.
+mod common;
+
+use crate::common::{fail, parse_address, Message};
+use crate::common::{IDLE_TIMEOUT, RECEIVE_BUFFER_SIZE, RETRY_INTERVAL};
+use raptorq::Decoder;
+use std::io;
+use std::net::{SocketAddr, UdpSocket};
+use std::time::Instant;
+use std::{env, fs};
+
+fn send_ack(socket: &UdpSocket, decoder: &Decoder, sender: SocketAddr) {
+    // Losses are handled by the sender retrying
+    let _ = socket.send_to(&Message::Ack(decoder.ack_frame()).serialize(), sender);
+}
+
+fn is_timeout(error: &io::Error) -> bool {
+    error.kind() == io::ErrorKind::WouldBlock || error.kind() == io::ErrorKind::TimedOut
+}
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    if args.len() != 3 {
+        fail("usage: raptorq-recv <address> <output file>");
+    }
+    let address = parse_address(&args[1]);
+    let socket =
+        UdpSocket::bind(address).unwrap_or_else(|error| fail(&format!("{}: {}", address, error)));
+    socket.set_read_timeout(Some(RETRY_INTERVAL)).unwrap();
+    let mut buffer = vec![0; RECEIVE_BUFFER_SIZE];
+
+    // Waits indefinitely for a sender, and then only accepts datagrams from it
+    let mut transfer: Option<(SocketAddr, Decoder)> = None;
+    let mut last_received = Instant::now();
+    let mut received = 0;
+    let data = loop {
+        let (length, from) = match socket.recv_from(&mut buffer) {
+            Ok(datagram) => datagram,
+            Err(ref error) if is_timeout(error) => {
+                if let Some((sender, ref decoder)) = transfer {
+                    if last_received.elapsed() > IDLE_TIMEOUT {
+                        fail("sender stopped sending");
+                    }
+                    send_ack(&socket, decoder, sender);
+                }
+                continue;
+            }
+            Err(error) => fail(&format!("receive failed: {}", error)),
+        };
+        let message = match Message::deserialize(&buffer[..length]) {
+            Some(message) => message,
+            None => continue,
+        };
+        match transfer {
+            Some((sender, _)) if sender != from => continue,
+            Some(_) => {}
+            None => match message {
+                Message::Config(ref config) => match Decoder::try_new(config.clone()) {
+                    Ok(decoder) => transfer = Some((from, decoder)),
+                    Err(error) => {
+                        eprintln!("rejected transfer from {}: {}", from, error);
+                        continue;
+                    }
+                },
+                _ => continue,
+            },
+        }
+        last_received = Instant::now();
+        let (sender, decoder) = transfer.as_mut().unwrap();
+        match message {
+            Message::Config(_) => send_ack(&socket, decoder, *sender),
+            Message::Data(packet) => {
+                received += 1;
+                if let Some(data) = decoder.decode_borrowed(packet) {
+                    break data;
+                }
+            }
+            Message::Ack(_) => {}
+        }
+    };
+    fs::write(&args[2], &data).unwrap_or_else(|error| fail(&format!("{}: {}", args[2], error)));
+    println!("received {} bytes in {} packets", data.len(), received);
+
+    // Acknowledges whatever else the sender sends, until it stops, in case the acknowledgements
+    // that the file was decoded are lost
+    let (sender, decoder) = transfer.unwrap();
+    send_ack(&socket, &decoder, sender);
+    socket.set_read_timeout(Some(5 * RETRY_INTERVAL)).unwrap();
+    loop {
+        match socket.recv_from(&mut buffer) {
+            Ok((_, from)) if from == sender => send_ack(&socket, &decoder, sender),
+            Ok(_) => {}
+            Err(_) => break,
+        }
+    }
+}
@@ -0,0 +1,169 @@
+// Sends a file to raptorq-recv over UDP. See common/mod.rs for the protocol.
+mod common;
+
+use crate::common::{config, fail, parse_address, Message};
+use crate::common::{IDLE_TIMEOUT, RECEIVE_BUFFER_SIZE, RETRY_INTERVAL};
+use raptorq::{BlockAckFrame, Encoder, EncodingPacket, EncodingPacketRef};
+use std::io;
+use std::net::UdpSocket;
+use std::time::{Duration, Instant};
+use std::{env, fs, thread};
+
+const DEFAULT_PACKETS_PER_SECOND: u32 = 10_000;
+// Repair packets sent for each block in a round, beyond the number the receiver still needs, so
+// that a round usually completes the block despite some losses
+const EXTRA_PACKETS_PER_BLOCK: u32 = 4;
+// Longest burst the pacer allows, to catch up after the sender was delayed
+const MAX_BURST: Duration = Duration::from_millis(10);
+
+// Sends packets at a fixed rate, so that the sender does not overrun the network or the receiver,
+// which would only cause more losses
+struct Pacer {
+    interval: Duration,
+    next: Instant,
+}
+
+impl Pacer {
+    fn new(packets_per_second: u32) -> Pacer {
+        Pacer {
+            interval: Duration::from_secs(1) / packets_per_second,
+            next: Instant::now(),
+        }
+    }
+
+    fn wait(&mut self) {
+        let now = Instant::now();
+        if self.next > now {
+            thread::sleep(self.next - now);
+        } else if now - self.next > MAX_BURST {
+            self.next = now;
+        }
+        self.next += self.interval;
+    }
+}
+
+fn send(socket: &UdpSocket, message: &Message) {
+    if let Err(error) = socket.send(&message.serialize()) {
+        // Also reported when an earlier datagram was refused, which the retries handle
+        if error.kind() != io::ErrorKind::ConnectionRefused {
+            fail(&format!("send failed: {}", error));
+        }
+    }
+}
+
+fn send_packets(socket: &UdpSocket, pacer: &mut Pacer, packets: &[EncodingPacket]) {
+    for packet in packets {
+        pacer.wait();
+        let packet = EncodingPacketRef::new(packet.payload_id().clone(), packet.data());
+        send(socket, &Message::Data(packet));
+    }
+}
+
+// Returns the last acknowledgement received within `wait`, if any
+fn receive_ack(socket: &UdpSocket, buffer: &mut [u8], wait: Duration) -> Option<BlockAckFrame> {
+    let deadline = Instant::now() + wait;
+    let mut ack = None;
+    loop {
+        let now = Instant::now();
+        if now >= deadline {
+            return ack;
+        }
+        socket.set_read_timeout(Some(deadline - now)).unwrap();
+        match socket.recv(buffer) {
+            Ok(length) => {
+                if let Some(Message::Ack(frame)) = Message::deserialize(&buffer[..length]) {
+                    ack = Some(frame);
+                }
+            }
+            Err(ref error)
+                if error.kind() == io::ErrorKind::WouldBlock
+                    || error.kind() == io::ErrorKind::TimedOut => {}
+            // The receiver is not listening yet
+            Err(ref error) if error.kind() == io::ErrorKind::ConnectionRefused => {
+                thread::sleep(deadline - now);
+            }
+            Err(error) => fail(&format!("receive failed: {}", error)),
+        }
+    }
+}
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    if args.len() != 3 && args.len() != 4 {
+        fail("usage: raptorq-send <file> <address> [packets per second]");
+    }
+    let data = fs::read(&args[1]).unwrap_or_else(|error| fail(&format!("{}: {}", args[1], error)));
+    let address = parse_address(&args[2]);
+    let packets_per_second = match args.get(3) {
+        Some(rate) => match rate.parse() {
+            Ok(rate) if rate > 0 => rate,
+            _ => fail(&format!("invalid rate: {}", rate)),
+        },
+        None => DEFAULT_PACKETS_PER_SECOND,
+    };
+
+    let local_address = if address.is_ipv4() {
+        "0.0.0.0:0"
+    } else {
+        "[::]:0"
+    };
+    let socket = UdpSocket::bind(local_address)
+        .and_then(|socket| socket.connect(address).map(|_| socket))
+        .unwrap_or_else(|error| fail(&format!("{}: {}", address, error)));
+    let encoder = Encoder::with_config(&data, &config());
+    let mut buffer = vec![0; RECEIVE_BUFFER_SIZE];
+    let start = Instant::now();
+
+    // Handshake, which tells the receiver the transmission parameters
+    let mut ack = loop {
+        if start.elapsed() > IDLE_TIMEOUT {
+            fail("no response from receiver");
+        }
+        send(&socket, &Message::Config(encoder.get_config()));
+        if let Some(ack) = receive_ack(&socket, &mut buffer, RETRY_INTERVAL) {
+            break ack;
+        }
+    };
+
+    let mut pacer = Pacer::new(packets_per_second);
+    let source_packets = encoder.get_encoded_packets(0);
+    let mut sent = source_packets.len();
+    send_packets(&socket, &mut pacer, &source_packets);
+
+    // Rounds of repair packets for the blocks which the receiver has not decoded, until it has
+    // decoded all of them. The receiver acknowledges when the sender goes quiet
+    let mut next_repair_symbol_id = 0;
+    let mut last_ack = Instant::now();
+    loop {
+        match receive_ack(&socket, &mut buffer, 2 * RETRY_INTERVAL) {
+            Some(latest) => {
+                ack = latest;
+                last_ack = Instant::now();
+            }
+            None if last_ack.elapsed() > IDLE_TIMEOUT => fail("receiver stopped responding"),
+            None => {}
+        }
+        if ack.all_decoded() {
+            break;
+        }
+        let packets =
+            encoder.repair_packets_for_ack(&ack, next_repair_symbol_id, EXTRA_PACKETS_PER_BLOCK);
+        let most_needed = (0..ack.num_blocks())
+            .map(|block| ack.symbols_needed(block as u8))
+            .max()
+            .unwrap_or(0);
+        next_repair_symbol_id += most_needed + EXTRA_PACKETS_PER_BLOCK;
+        sent += packets.len();
+        send_packets(&socket, &mut pacer, &packets);
+    }
+
+    let elapsed = start.elapsed();
+    let elapsed = elapsed.as_secs() as f64 + elapsed.subsec_millis() as f64 * 0.001;
+    println!(
+        "sent {} bytes in {} packets ({} source) in {:.3}secs",
+        data.len(),
+        sent,
+        source_packets.len(),
+        elapsed
+    );
+}
@@ -0,0 +1,85 @@
+use std::collections::{BTreeMap, HashMap};
+
+/// Counters of a repair symbol cache, see `Encoder::set_repair_cache_capacity()`
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct RepairCacheStats {
+    hits: u64,
+    misses: u64,
+}
+
+impl RepairCacheStats {
+    /// Number of repair symbols which were returned from the cache
+    pub fn hits(&self) -> u64 {
+        self.hits
+    }
+
+    /// Number of repair symbols which had to be generated
+    pub fn misses(&self) -> u64 {
+        self.misses
+    }
+
+    /// Fraction of lookups which were hits, or zero if there have been none
+    pub fn hit_rate(&self) -> f64 {
+        let lookups = self.hits + self.misses;
+        if lookups == 0 {
+            0.0
+        } else {
+            self.hits as f64 / lookups as f64
+        }
+    }
+}
+
+// Repair symbols keyed by (SBN, ESI), which evicts the least recently used symbol once it holds
+// `capacity` of them
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct RepairSymbolCache {
+    capacity: usize,
+    // Symbol and the time it was last used, for each key
+    symbols: HashMap<(u8, u32), (Vec<u8>, u64)>,
+    // Key last used at each time, oldest first
+    recency: BTreeMap<u64, (u8, u32)>,
+    now: u64,
+    stats: RepairCacheStats,
+}
+
+impl RepairSymbolCache {
+    pub(crate) fn new(capacity: usize) -> RepairSymbolCache {
+        assert!(capacity > 0);
+        RepairSymbolCache {
+            capacity,
+            symbols: HashMap::new(),
+            recency: BTreeMap::new(),
+            now: 0,
+            stats: RepairCacheStats::default(),
+        }
+    }
+
+    pub(crate) fn stats(&self) -> &RepairCacheStats {
+        &self.stats
+    }
+
+    // Returns the cached symbol with the given key, or generates and caches it
+    pub(crate) fn get_or_insert_with<F: FnOnce() -> Vec<u8>>(
+        &mut self,
+        key: (u8, u32),
+        generate: F,
+    ) -> &[u8] {
+        self.now += 1;
+        let now = self.now;
+        if let Some((_, last_used)) = self.symbols.get_mut(&key) {
+            self.stats.hits += 1;
+            self.recency.remove(last_used);
+            *last_used = now;
+        } else {
+            self.stats.misses += 1;
+            if self.symbols.len() == self.capacity {
+                let (&oldest, _) = self.recency.iter().next().unwrap();
+                let evicted = self.recency.remove(&oldest).unwrap();
+                self.symbols.remove(&evicted);
+            }
+            self.symbols.insert(key, (generate(), now));
+        }
+        self.recency.insert(now, key);
+        &self.symbols[&key].0
+    }
+}
@@ -0,0 +1,160 @@
+use crate::base::EncodingPacket;
+use crate::decoder::SourceBlockDecoder;
+use crate::encoder::SourceBlockEncoder;
+use crate::util::div_ceil;
+use std::time::{Duration, Instant};
+
+// Representative symbol sizes: a small constrained link, an IPv6 minimum MTU sized packet, and an
+// Ethernet sized packet
+const SYMBOL_SIZES: [u16; 3] = [64, 512, 1280];
+// Small enough that calibration is quick, but large enough that the solver dominates
+const SOURCE_SYMBOLS: u32 = 256;
+
+/// Encode and decode throughput for one configuration. See `calibrate()`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ThroughputMeasurement {
+    symbol_size: u16,
+    source_symbols: u32,
+    encode_bytes_per_second: f64,
+    decode_bytes_per_second: f64,
+}
+
+impl ThroughputMeasurement {
+    pub fn symbol_size(&self) -> u16 {
+        self.symbol_size
+    }
+
+    pub fn source_symbols(&self) -> u32 {
+        self.source_symbols
+    }
+
+    /// Bytes of source data per second, for computing the intermediate symbols of a block and
+    /// generating a tenth of its size in repair symbols
+    pub fn encode_bytes_per_second(&self) -> f64 {
+        self.encode_bytes_per_second
+    }
+
+    /// Bytes of source data per second, for decoding a block of which a tenth of the source
+    /// symbols were lost
+    pub fn decode_bytes_per_second(&self) -> f64 {
+        self.decode_bytes_per_second
+    }
+}
+
+/// Results of `calibrate()`
+#[derive(Clone, Debug, PartialEq)]
+pub struct Calibration {
+    measurements: Vec<ThroughputMeasurement>,
+    threads: usize,
+}
+
+impl Calibration {
+    /// One measurement per symbol size, in increasing order of symbol size
+    pub fn measurements(&self) -> &[ThroughputMeasurement] {
+        &self.measurements
+    }
+
+    /// Number of threads that the `parallel` feature spreads decoding across, or 1 if it is
+    /// disabled
+    pub fn threads(&self) -> usize {
+        self.threads
+    }
+
+    /// The measured symbol size with the highest decode throughput. Note that larger symbols
+    /// are only preferable if the transport can carry them without fragmentation
+    pub fn best_symbol_size(&self) -> u16 {
+        self.measurements
+            .iter()
+            .max_by(|a, b| {
+                a.decode_bytes_per_second
+                    .partial_cmp(&b.decode_bytes_per_second)
+                    .unwrap()
+            })
+            .unwrap()
+            .symbol_size
+    }
+}
+
+/// Measures encode and decode throughput for a few representative configurations on the current
+/// machine. This takes on the order of tens of milliseconds in an optimized build, and is intended
+/// to be run once at startup to pick the symbol size and parallelism.
+pub fn calibrate() -> Calibration {
+    let measurements = SYMBOL_SIZES
+        .iter()
+        .map(|&symbol_size| measure(symbol_size, SOURCE_SYMBOLS))
+        .collect();
+    Calibration {
+        measurements,
+        threads: threads(),
+    }
+}
+
+fn measure(symbol_size: u16, source_symbols: u32) -> ThroughputMeasurement {
+    let length = symbol_size as usize * source_symbols as usize;
+    let data: Vec<u8> = (0..length).map(|i| (i * 31 + 7) as u8).collect();
+    let lost = div_ceil(u64::from(source_symbols), 10) as u32;
+
+    let start = Instant::now();
+    let encoder = SourceBlockEncoder::new(0, symbol_size, &data);
+    let repair = encoder.repair_packets(0, lost + 2);
+    let encode_time = start.elapsed();
+
+    // Lose every tenth source symbol
+    let packets: Vec<EncodingPacket> = encoder
+        .source_packets()
+        .into_iter()
+        .enumerate()
+        .filter(|(i, _)| i % 10 != 0)
+        .map(|(_, packet)| packet)
+        .chain(repair)
+        .collect();
+    let start = Instant::now();
+    let mut decoder = SourceBlockDecoder::new(0, symbol_size, length as u64);
+    let result = decoder.decode(packets);
+    let decode_time = start.elapsed();
+    // This can only fail with negligible probability, and then merely skews the measurement
+    debug_assert!(result.is_some());
+
+    ThroughputMeasurement {
+        symbol_size,
+        source_symbols,
+        encode_bytes_per_second: bytes_per_second(length, encode_time),
+        decode_bytes_per_second: bytes_per_second(length, decode_time),
+    }
+}
+
+fn bytes_per_second(bytes: usize, elapsed: Duration) -> f64 {
+    // Avoid dividing by zero on platforms with a coarse clock
+    let seconds = elapsed.as_secs_f64().max(1e-9);
+    bytes as f64 / seconds
+}
+
+#[cfg(feature = "parallel")]
+fn threads() -> usize {
+    rayon::current_num_threads()
+}
+
+#[cfg(not(feature = "parallel"))]
+fn threads() -> usize {
+    1
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::calibrate;
+
+    #[test]
+    fn calibration() {
+        let calibration = calibrate();
+        assert_eq!(3, calibration.measurements().len());
+        for measurement in calibration.measurements() {
+            assert!(measurement.encode_bytes_per_second() > 0.0);
+            assert!(measurement.decode_bytes_per_second() > 0.0);
+        }
+        assert!(calibration
+            .measurements()
+            .iter()
+            .any(|measurement| measurement.symbol_size() == calibration.best_symbol_size()));
+        assert!(calibration.threads() >= 1);
+    }
+}
@@ -0,0 +1,285 @@
+use crate::base::ConfigError;
+use crate::base::EncodingPacket;
+use crate::base::ObjectTransmissionInformation;
+use crate::decoder::Decoder;
+use crate::encoder::Encoder;
+use serde::{Deserialize, Serialize};
+use std::io;
+
+// The default maximum length of a decompressed object. See
+// `CompressedDecoder::set_max_uncompressed_length()`
+const DEFAULT_MAX_UNCOMPRESSED_LENGTH: u64 = 1024 * 1024 * 1024;
+
+/// Compression applied to an object before it is encoded
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Compression {
+    None,
+    Lz4,
+    /// Zstandard, with the given compression level
+    Zstd(i32),
+}
+
+impl Compression {
+    fn compress(self, data: &[u8]) -> Vec<u8> {
+        match self {
+            Compression::None => data.to_vec(),
+            Compression::Lz4 => lz4_flex::compress(data),
+            // Compressing from memory into memory can't fail
+            Compression::Zstd(level) => zstd::bulk::compress(data, level).unwrap(),
+        }
+    }
+
+    // The uncompressed length is received from the sender, and is the capacity which is allocated
+    // for the output, so it is checked against the maximum first
+    fn decompress(
+        self,
+        data: &[u8],
+        uncompressed_length: u64,
+        max_uncompressed_length: u64,
+    ) -> io::Result<Vec<u8>> {
+        if uncompressed_length > max_uncompressed_length {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "uncompressed length exceeds the maximum",
+            ));
+        }
+        let uncompressed_length = uncompressed_length as usize;
+        let result = match self {
+            Compression::None => Ok(data.to_vec()),
+            Compression::Lz4 => lz4_flex::decompress(data, uncompressed_length)
+                .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error)),
+            Compression::Zstd(_) => zstd::bulk::decompress(data, uncompressed_length),
+        }?;
+        if result.len() != uncompressed_length {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "decompressed length does not match the object information",
+            ));
+        }
+        Ok(result)
+    }
+}
+
+/// The `ObjectTransmissionInformation` of a compressed object, extended with the compression
+/// algorithm and the length of the uncompressed object. Like the OTI, this must be transmitted to
+/// the receiver over a reliable channel.
+///
+/// It is serialized as the 12 byte OTI, followed by a one byte algorithm identifier (0 = none,
+/// 1 = LZ4 block format, 2 = Zstandard), and the uncompressed length as a 40-bit unsigned integer.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct CompressedObjectInformation {
+    config: ObjectTransmissionInformation,
+    compression: Compression,
+    uncompressed_length: u64, // Limited to u40
+}
+
+impl CompressedObjectInformation {
+    pub fn config(&self) -> ObjectTransmissionInformation {
+        self.config.clone()
+    }
+
+    pub fn compression(&self) -> Compression {
+        self.compression
+    }
+
+    pub fn uncompressed_length(&self) -> u64 {
+        self.uncompressed_length
+    }
+
+    pub fn serialize(&self) -> [u8; 18] {
+        let mut result = [0; 18];
+        result[..12].copy_from_slice(&self.config.serialize());
+        result[12] = match self.compression {
+            Compression::None => 0,
+            Compression::Lz4 => 1,
+            Compression::Zstd(_) => 2,
+        };
+        for i in 0..5 {
+            result[13 + i] = (self.uncompressed_length >> (8 * (4 - i))) as u8;
+        }
+        result
+    }
+
+    /// Returns None if the compression algorithm is unknown
+    pub fn deserialize(data: &[u8; 18]) -> Option<CompressedObjectInformation> {
+        let mut config = [0; 12];
+        config.copy_from_slice(&data[..12]);
+        let compression = match data[12] {
+            0 => Compression::None,
+            1 => Compression::Lz4,
+            // The level is only needed for compression
+            2 => Compression::Zstd(0),
+            _ => return None,
+        };
+        let uncompressed_length = data[13..]
+            .iter()
+            .fold(0, |length, byte| (length << 8) + u64::from(*byte));
+        Some(CompressedObjectInformation {
+            config: ObjectTransmissionInformation::deserialize(&config),
+            compression,
+            uncompressed_length,
+        })
+    }
+}
+
+/// Compresses the object, and creates an encoder for the compressed data, as with
+/// `Encoder::with_defaults()`
+pub fn encode_compressed(
+    data: &[u8],
+    maximum_transmission_unit: u16,
+    compression: Compression,
+) -> (Encoder, CompressedObjectInformation) {
+    assert!(data.len() as u64 <= 946270874880);
+    let encoder = Encoder::with_defaults(&compression.compress(data), maximum_transmission_unit);
+    let info = CompressedObjectInformation {
+        config: encoder.get_config(),
+        compression,
+        uncompressed_length: data.len() as u64,
+    };
+    (encoder, info)
+}
+
+/// Decodes an object created by `encode_compressed()`, and decompresses it
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct CompressedDecoder {
+    decoder: Decoder,
+    compression: Compression,
+    uncompressed_length: u64,
+    max_uncompressed_length: u64,
+}
+
+impl CompressedDecoder {
+    /// Returns an error if the object information is invalid. It is usually received from the
+    /// sender, so it is validated as with `Decoder::try_new()`.
+    pub fn new(info: CompressedObjectInformation) -> Result<CompressedDecoder, ConfigError> {
+        Ok(CompressedDecoder {
+            decoder: Decoder::try_new(info.config)?,
+            compression: info.compression,
+            uncompressed_length: info.uncompressed_length,
+            max_uncompressed_length: DEFAULT_MAX_UNCOMPRESSED_LENGTH,
+        })
+    }
+
+    /// Sets the maximum length of the decompressed object, by default 1 GiB. The uncompressed
+    /// length is received from the sender, and memory for it is allocated before decompressing,
+    /// so objects which claim to be longer fail to decompress rather than being allocated.
+    pub fn set_max_uncompressed_length(&mut self, max_uncompressed_length: u64) {
+        self.max_uncompressed_length = max_uncompressed_length;
+    }
+
+    /// Returns the decompressed object once it has been decoded. Decompression fails if the
+    /// sender used different object information, or if the uncompressed length exceeds the
+    /// maximum
+    pub fn decode(&mut self, packet: EncodingPacket) -> Option<io::Result<Vec<u8>>> {
+        let compression = self.compression;
+        let uncompressed_length = self.uncompressed_length;
+        let max_uncompressed_length = self.max_uncompressed_length;
+        self.decoder.decode(packet).map(|compressed| {
+            compression.decompress(&compressed, uncompressed_length, max_uncompressed_length)
+        })
+    }
+
+    /// Access to the underlying decoder, e.g. for configuration
+    pub fn decoder_mut(&mut self) -> &mut Decoder {
+        &mut self.decoder
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        encode_compressed, CompressedDecoder, CompressedObjectInformation, Compression, ConfigError,
+    };
+    use rand::Rng;
+
+    #[test]
+    fn round_trip() {
+        // Compressible, but not trivially
+        let mut data: Vec<u8> = vec![0; 100_000];
+        for byte in data.iter_mut() {
+            *byte = rand::thread_rng().gen_range(0, 4);
+        }
+        for &compression in [Compression::None, Compression::Lz4, Compression::Zstd(3)].iter() {
+            let (encoder, info) = encode_compressed(&data, 1280, compression);
+            assert_eq!(info.uncompressed_length(), data.len() as u64);
+            if compression != Compression::None {
+                assert!(info.config().transfer_length() < data.len() as u64);
+            }
+
+            let info = CompressedObjectInformation::deserialize(&info.serialize()).unwrap();
+            let mut decoder = CompressedDecoder::new(info).unwrap();
+            let mut result = None;
+            for packet in encoder.get_encoded_packets(5).into_iter().skip(5) {
+                result = decoder.decode(packet);
+                if result.is_some() {
+                    break;
+                }
+            }
+            assert_eq!(result.unwrap().unwrap(), data);
+        }
+    }
+
+    #[test]
+    fn length_mismatch() {
+        let data = vec![7; 10_000];
+        let (encoder, info) = encode_compressed(&data, 1280, Compression::Lz4);
+        let mut serialized = info.serialize();
+        serialized[17] += 1;
+        let mut decoder =
+            CompressedDecoder::new(CompressedObjectInformation::deserialize(&serialized).unwrap())
+                .unwrap();
+        let mut result = None;
+        for packet in encoder.get_encoded_packets(0) {
+            result = decoder.decode(packet);
+        }
+        assert!(result.unwrap().is_err());
+
+        serialized[12] = 3;
+        assert!(CompressedObjectInformation::deserialize(&serialized).is_none());
+    }
+
+    #[test]
+    fn invalid_config() {
+        let data = vec![7; 10_000];
+        let (_, info) = encode_compressed(&data, 1280, Compression::Lz4);
+        let mut serialized = info.serialize();
+        // Zero symbol size
+        serialized[6] = 0;
+        serialized[7] = 0;
+        let info = CompressedObjectInformation::deserialize(&serialized).unwrap();
+        assert_eq!(
+            CompressedDecoder::new(info).err(),
+            Some(ConfigError::ZeroParameter)
+        );
+    }
+
+    #[test]
+    fn forged_uncompressed_length() {
+        let data = vec![7; 10_000];
+        for &compression in [Compression::Lz4, Compression::Zstd(3)].iter() {
+            let (encoder, info) = encode_compressed(&data, 1280, compression);
+            // The maximum 40-bit length
+            let mut serialized = info.serialize();
+            for byte in serialized[13..].iter_mut() {
+                *byte = 0xFF;
+            }
+            let mut decoder = CompressedDecoder::new(
+                CompressedObjectInformation::deserialize(&serialized).unwrap(),
+            )
+            .unwrap();
+            let mut result = None;
+            for packet in encoder.get_encoded_packets(0) {
+                result = decoder.decode(packet);
+            }
+            assert!(result.unwrap().is_err());
+
+            let mut decoder = CompressedDecoder::new(info).unwrap();
+            decoder.set_max_uncompressed_length(data.len() as u64 - 1);
+            let mut result = None;
+            for packet in encoder.get_encoded_packets(0) {
+                result = decoder.decode(packet);
+            }
+            assert!(result.unwrap().is_err());
+        }
+    }
+}
@@ -0,0 +1,162 @@
+use crate::base::ObjectTransmissionInformation;
+use crate::util::div_ceil;
+
+// Size of the FEC Payload ID. See section 3.2
+const PAYLOAD_ID_SIZE: u16 = 4;
+const ALIGNMENT: u8 = 8;
+
+// Ethernet MTU, less the IP and UDP headers
+const UDP_IPV4_MAX_PAYLOAD: u16 = 1500 - 20 - 8;
+const UDP_IPV6_MAX_PAYLOAD: u16 = 1500 - 40 - 8;
+// Maximum ATT payload with the Data Length Extension (LE Data Length of 251 bytes)
+const BLE_MAX_PAYLOAD: u16 = 244;
+
+/// Encoding parameters chosen for a particular transport.
+///
+/// Packets produced with these parameters fit in a single datagram of the transport, and source
+/// blocks are kept small enough that a block can be decoded before a lossy link drops too many of
+/// its packets.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Config {
+    symbol_size: u16,
+    max_source_block_symbols: u32,
+}
+
+impl Config {
+    /// `max_packet_size` includes the 4 byte payload id, and is rounded down to a whole number of
+    /// aligned symbols
+    pub fn new(max_packet_size: u16, max_source_block_symbols: u32) -> Config {
+        assert!(max_packet_size >= PAYLOAD_ID_SIZE + u16::from(ALIGNMENT));
+        assert!(max_source_block_symbols > 0);
+        assert!(max_source_block_symbols <= 56403);
+        let symbol_size =
+            (max_packet_size - PAYLOAD_ID_SIZE) / u16::from(ALIGNMENT) * u16::from(ALIGNMENT);
+        Config {
+            symbol_size,
+            max_source_block_symbols,
+        }
+    }
+
+    /// UDP over IPv4 on an Ethernet-sized path
+    pub fn for_udp_ipv4() -> Config {
+        Config::new(UDP_IPV4_MAX_PAYLOAD, 8192)
+    }
+
+    /// UDP over IPv6 on an Ethernet-sized path
+    pub fn for_udp_ipv6() -> Config {
+        Config::new(UDP_IPV6_MAX_PAYLOAD, 8192)
+    }
+
+    /// QUIC DATAGRAM frames, where `max_datagram` is the largest datagram payload the connection
+    /// currently allows
+    pub fn for_quic_datagram(max_datagram: u16) -> Config {
+        Config::new(max_datagram, 8192)
+    }
+
+    /// Bluetooth Low Energy. Blocks are kept small, since the link is slow and losses are bursty
+    pub fn for_ble() -> Config {
+        Config::new(BLE_MAX_PAYLOAD, 1024)
+    }
+
+    pub fn symbol_size(&self) -> u16 {
+        self.symbol_size
+    }
+
+    pub fn max_source_block_symbols(&self) -> u32 {
+        self.max_source_block_symbols
+    }
+
+    /// Length in bytes of a full source block
+    pub fn block_length(&self) -> u64 {
+        u64::from(self.symbol_size) * u64::from(self.max_source_block_symbols)
+    }
+
+    /// Returns the transmission parameters of an object, in the mode where the block structure is
+    /// implied by this configuration, rather than signaled with an OTI. Every source block is
+    /// full, so the object is zero padded up to a whole number of blocks. See
+    /// `Encoder::with_implied_config()`
+    pub(crate) fn implied_object_transmission_information(
+        &self,
+        transfer_length: u64,
+    ) -> ObjectTransmissionInformation {
+        let source_blocks = div_ceil(transfer_length, self.block_length()).max(1);
+        assert!(
+            source_blocks <= 255,
+            "Object is too large for this configuration"
+        );
+        ObjectTransmissionInformation::new(
+            source_blocks * self.block_length(),
+            self.symbol_size,
+            source_blocks as u8,
+            1,
+            ALIGNMENT,
+        )
+    }
+
+    /// Returns the transmission parameters for an object of the given length, using as few source
+    /// blocks as the block limit allows
+    pub fn object_transmission_information(
+        &self,
+        transfer_length: u64,
+    ) -> ObjectTransmissionInformation {
+        let source_symbols = div_ceil(transfer_length, u64::from(self.symbol_size));
+        let source_blocks = div_ceil(source_symbols, u64::from(self.max_source_block_symbols));
+        assert!(
+            source_blocks <= 255,
+            "Object is too large for this configuration"
+        );
+        ObjectTransmissionInformation::new(
+            transfer_length,
+            self.symbol_size,
+            source_blocks.max(1) as u8,
+            1,
+            ALIGNMENT,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Config, Decoder, Encoder};
+    use rand::Rng;
+
+    #[test]
+    fn presets() {
+        assert_eq!(1464, Config::for_udp_ipv4().symbol_size());
+        assert_eq!(1448, Config::for_udp_ipv6().symbol_size());
+        assert_eq!(1192, Config::for_quic_datagram(1200).symbol_size());
+        assert_eq!(240, Config::for_ble().symbol_size());
+        for config in [
+            Config::for_udp_ipv4(),
+            Config::for_udp_ipv6(),
+            Config::for_quic_datagram(1350),
+            Config::for_ble(),
+        ]
+        .iter()
+        {
+            assert_eq!(0, config.symbol_size() % 8);
+            let oti = config.object_transmission_information(0);
+            assert_eq!(1, oti.source_blocks());
+        }
+    }
+
+    #[test]
+    fn multiple_blocks_round_trip() {
+        let config = Config::for_ble();
+        let mut data: Vec<u8> = vec![0; 2500 * 240 + 17];
+        for byte in data.iter_mut() {
+            *byte = rand::thread_rng().gen();
+        }
+        let encoder = Encoder::with_config(&data, &config);
+        let oti = encoder.get_config();
+        assert_eq!(3, oti.source_blocks());
+
+        let mut decoder = Decoder::new(oti);
+        let mut result = None;
+        for packet in encoder.get_encoded_packets(0) {
+            assert!(packet.data().len() + 4 <= 244);
+            result = decoder.decode(packet);
+        }
+        assert_eq!(Some(data), result);
+    }
+}
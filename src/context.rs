@@ -0,0 +1,124 @@
+use crate::symbol::Symbol;
+
+/// Scratch buffers which can be reused across many decoding operations, so that they are not
+/// reallocated for each one. This holds the buffers of the symbols passed through the solver, and
+/// the indices of the received symbols. When decoding many small blocks, the same context should
+/// be passed to each call of `SourceBlockDecoder::decode_with_ctx()`.
+///
+/// A context is not tied to a symbol size, but buffers are only reused for symbols of the size
+/// they were allocated for.
+#[derive(Debug, Default)]
+pub struct WorkContext {
+    symbol_buffers: Vec<Vec<u8>>,
+    symbols: Vec<Symbol>,
+    indices: Vec<u32>,
+}
+
+impl WorkContext {
+    pub fn new() -> WorkContext {
+        WorkContext::default()
+    }
+
+    /// Returns the number of symbol buffers available for reuse
+    pub fn pooled_symbols(&self) -> usize {
+        self.symbol_buffers.len()
+    }
+
+    /// Releases all the retained buffers
+    pub fn clear(&mut self) {
+        self.symbol_buffers = vec![];
+        self.symbols = vec![];
+        self.indices = vec![];
+    }
+
+    // Returns a symbol with a copy of the given data
+    pub(crate) fn symbol_from(&mut self, data: &[u8]) -> Symbol {
+        match self.take_buffer(data.len()) {
+            Some(mut buffer) => {
+                buffer.copy_from_slice(data);
+                Symbol::new(buffer)
+            }
+            None => Symbol::new(data.to_vec()),
+        }
+    }
+
+    pub(crate) fn zero_symbol(&mut self, symbol_size: usize) -> Symbol {
+        match self.take_buffer(symbol_size) {
+            Some(mut buffer) => {
+                for byte in buffer.iter_mut() {
+                    *byte = 0;
+                }
+                Symbol::new(buffer)
+            }
+            None => Symbol::zero(symbol_size),
+        }
+    }
+
+    pub(crate) fn recycle_symbol(&mut self, symbol: Symbol) {
+        self.symbol_buffers.push(symbol.into_bytes());
+    }
+
+    // Retains the buffers of the symbols, and the vector itself, to be returned by
+    // `take_symbols()`
+    pub(crate) fn recycle(&mut self, mut symbols: Vec<Symbol>) {
+        self.symbol_buffers
+            .extend(symbols.drain(..).map(Symbol::into_bytes));
+        if symbols.capacity() > self.symbols.capacity() {
+            self.symbols = symbols;
+        }
+    }
+
+    // The returned vector is empty
+    pub(crate) fn take_symbols(&mut self) -> Vec<Symbol> {
+        std::mem::take(&mut self.symbols)
+    }
+
+    // The returned vector is empty, and should be returned with `return_indices()`
+    pub(crate) fn take_indices(&mut self) -> Vec<u32> {
+        let mut indices = std::mem::take(&mut self.indices);
+        indices.clear();
+        indices
+    }
+
+    pub(crate) fn return_indices(&mut self, indices: Vec<u32>) {
+        self.indices = indices;
+    }
+
+    fn take_buffer(&mut self, length: usize) -> Option<Vec<u8>> {
+        // Buffers of a different size are discarded, since the symbol size rarely changes
+        while let Some(buffer) = self.symbol_buffers.pop() {
+            if buffer.len() == length {
+                return Some(buffer);
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{SourceBlockDecoder, SourceBlockEncoder, WorkContext};
+    use rand::Rng;
+
+    #[test]
+    fn reuse_across_blocks() {
+        let symbol_size = 16;
+        let elements = 30 * symbol_size;
+        let mut ctx = WorkContext::new();
+        for _ in 0..3 {
+            let mut data: Vec<u8> = vec![0; elements];
+            rand::thread_rng().fill(&mut data[..]);
+            let encoder = SourceBlockEncoder::new(0, symbol_size as u16, &data);
+            let mut packets = encoder.source_packets();
+            packets.remove(3);
+            packets.extend(encoder.repair_packets(0, 3));
+
+            let mut decoder = SourceBlockDecoder::new(0, symbol_size as u16, elements as u64);
+            assert_eq!(Some(data), decoder.decode_with_ctx(packets, &mut ctx));
+            assert!(ctx.pooled_symbols() > 0);
+        }
+
+        ctx.clear();
+        assert_eq!(0, ctx.pooled_symbols());
+    }
+}
@@ -1,37 +1,107 @@
-use crate::base::intermediate_tuple;
-use crate::base::partition;
+use crate::ack::BlockAckFrame;
+use crate::allocator::{AllocatorHandle, AllocatorHooks};
+use crate::base::deinterleave_sub_blocks;
+use crate::base::ConfigError;
 use crate::base::EncodingPacket;
+use crate::base::EncodingPacketRef;
+use crate::base::MemoryUsage;
 use crate::base::ObjectTransmissionInformation;
+use crate::base::PayloadId;
 use crate::constraint_matrix::enc_indices;
-use crate::constraint_matrix::generate_constraint_matrix;
+use crate::constraint_matrix::{encoding_row, ldpc_rows};
+use crate::constraint_matrix::{generate_constraint_matrix, generate_encoding_rows};
+use crate::context::WorkContext;
+use crate::encoder::quick_repair_indices;
+#[cfg(feature = "security")]
+use crate::encoder::SourceBlockEncoder;
 use crate::encoder::SPARSE_MATRIX_THRESHOLD;
+#[cfg(feature = "failure_injection")]
+use crate::failure_injection::InjectedFailure;
+use crate::framing::read_framed_packet;
+#[cfg(feature = "async")]
+use crate::framing::read_framed_packet_async;
+use crate::framing::write_framed_packet;
 use crate::matrix::{DenseOctetMatrix, OctetMatrix};
-use crate::pi_solver::fused_inverse_mul_symbols;
+use crate::octets::add_assign;
+use crate::pi_solver::{
+    IntegrityCheck, IntermediateSymbolDecoder, SolverLimit, SolverPermutations,
+};
+use crate::rank_tracker::BinaryRankTracker;
 use crate::sparse_matrix::SparseOctetMatrix;
 use crate::symbol::Symbol;
 use crate::systematic_constants::num_hdpc_symbols;
+use crate::systematic_constants::num_intermediate_symbols;
 use crate::systematic_constants::num_ldpc_symbols;
 use crate::systematic_constants::{
     calculate_p1, extended_source_block_symbols, num_lt_symbols, num_pi_symbols, systematic_index,
 };
+use crate::tuple::intermediate_tuple;
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::cmp::{max, min};
 use std::collections::HashSet;
+use std::io;
+use std::io::{Read, Write};
+use std::ops::Range;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Inconsistencies detected by a decoder. In lenient mode they are tolerated, see
+/// `Decoder::set_lenient()`. Otherwise non-zero padding fails decoding, see `Decoder::failed()`.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub enum DecodeWarning {
+    /// A symbol was received whose length did not match the symbol size. It was zero padded or
+    /// truncated to the symbol size.
+    SymbolLengthMismatch {
+        source_block_number: u8,
+        encoding_symbol_id: u32,
+        length: usize,
+    },
+    /// The padding after the declared transfer length contained non-zero bytes, which indicates
+    /// that the sender used a different transfer length. In lenient mode the data was truncated to
+    /// the declared transfer length, otherwise decoding failed.
+    NonZeroPadding { non_zero_bytes: usize },
+}
 
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
 pub struct Decoder {
     config: ObjectTransmissionInformation,
     block_decoders: Vec<SourceBlockDecoder>,
     blocks: Vec<Option<Vec<u8>>>,
+    // Fields added since the first release default when missing, so that decoders serialized by
+    // it can be deserialized
+    #[serde(default)]
+    acceptance_window: Option<usize>,
+    #[serde(default)]
+    lenient: bool,
+    #[serde(default)]
+    strict: bool,
+    #[serde(default)]
+    warnings: Vec<DecodeWarning>,
+    // Set when the padding is not zero outside lenient mode, after which no result is returned
+    #[serde(default)]
+    failed: bool,
+    #[serde(default)]
+    block_timeout: Option<Duration>,
+    // For each block, the number of symbols received, and when that last changed. Only tracked
+    // while a block timeout is set
+    #[serde(skip)]
+    block_progress: Vec<Option<(u32, Instant)>>,
 }
 
 impl Decoder {
     pub fn new(config: ObjectTransmissionInformation) -> Decoder {
-        let kt = (config.transfer_length() as f64 / config.symbol_size() as f64).ceil() as u32;
-        let (kl, ks, zl, zs) = partition(kt, config.source_blocks());
+        Decoder::try_new(config).unwrap()
+    }
 
-        // TODO: support subblocks
-        assert_eq!(1, config.sub_blocks());
-        //        let (tl, ts, nl, ns) = partition((config.symbol_size() / config.alignment() as u16) as u32, config.sub_blocks());
+    /// Same as `new()`, but returns an error rather than panicking, or allocating an unbounded
+    /// amount of memory, if the configuration is invalid. Use this when the configuration was
+    /// received from an untrusted sender. Memory for the symbols of a source block is only
+    /// allocated once they are received.
+    pub fn try_new(config: ObjectTransmissionInformation) -> Result<Decoder, ConfigError> {
+        let (kl, ks, zl, zs) = config.checked_block_partition()?;
 
         let mut decoders = vec![];
         for i in 0..zl {
@@ -44,17 +114,237 @@ impl Decoder {
 
         for i in 0..zs {
             decoders.push(SourceBlockDecoder::new(
-                i as u8,
+                (zl + i) as u8,
                 config.symbol_size(),
                 u64::from(ks) * u64::from(config.symbol_size()),
             ));
         }
 
-        Decoder {
+        Ok(Decoder {
             config,
             block_decoders: decoders,
             blocks: vec![None; (zl + zs) as usize],
+            acceptance_window: None,
+            lenient: false,
+            strict: false,
+            warnings: vec![],
+            failed: false,
+            block_timeout: None,
+            block_progress: vec![],
+        })
+    }
+
+    /// Same as `try_new()`, but in strict mode, for applications which must certify
+    /// interoperability. The configuration, and every parameter derived from it, is validated
+    /// against RFC 6330 (see `ObjectTransmissionInformation::check_strict()`), and received
+    /// packets which do not conform are discarded, rather than decoded. Lenient mode cannot be
+    /// enabled on a strict decoder.
+    pub fn try_new_strict(config: ObjectTransmissionInformation) -> Result<Decoder, ConfigError> {
+        config.check_strict()?;
+        let mut decoder = Decoder::try_new(config)?;
+        decoder.strict = true;
+        for block_decoder in decoder.block_decoders.iter_mut() {
+            block_decoder.set_strict(true);
+        }
+        Ok(decoder)
+    }
+
+    /// Returns the number of packets which were discarded because they were malformed. See
+    /// `SourceBlockDecoder::rejected_symbols()`
+    pub fn rejected_packets(&self) -> u32 {
+        self.block_decoders
+            .iter()
+            .map(|block_decoder| block_decoder.rejected_symbols())
+            .sum()
+    }
+
+    /// Enables lenient mode, for interoperability with senders which are known to be slightly
+    /// non-compliant. Rather than failing, the decoder repairs symbols with the wrong length and
+    /// ignores non-zero padding, and records each inconsistency, which can be retrieved with
+    /// `warnings()`.
+    pub fn set_lenient(&mut self, lenient: bool) {
+        assert!(
+            !(lenient && self.strict),
+            "lenient mode is not allowed in strict mode"
+        );
+        self.lenient = lenient;
+        for block_decoder in self.block_decoders.iter_mut() {
+            block_decoder.set_lenient(lenient);
+        }
+    }
+
+    /// Returns the inconsistencies which have been detected so far. Outside lenient mode, only
+    /// non-zero padding is recorded, which fails decoding.
+    pub fn warnings(&self) -> Vec<DecodeWarning> {
+        let mut warnings: Vec<DecodeWarning> = self
+            .block_decoders
+            .iter()
+            .flat_map(|block_decoder| block_decoder.warnings().iter().cloned())
+            .collect();
+        warnings.extend(self.warnings.iter().cloned());
+        warnings
+    }
+
+    /// Allocates the buffers for received symbols through the given hooks, rather than leaving
+    /// them wherever the packets were allocated. Received symbols are then always copied into a
+    /// buffer from the hooks. Hooks are not serialized, and must be set again on a deserialized
+    /// decoder.
+    pub fn set_allocator_hooks(&mut self, hooks: Arc<dyn AllocatorHooks>) {
+        for block_decoder in self.block_decoders.iter_mut() {
+            block_decoder.set_allocator_hooks(hooks.clone());
+        }
+    }
+
+    #[cfg(feature = "security")]
+    /// See `SourceBlockDecoder::set_pollution_detection()`
+    pub fn set_pollution_detection(&mut self, enabled: bool) {
+        for block_decoder in self.block_decoders.iter_mut() {
+            block_decoder.set_pollution_detection(enabled);
+        }
+    }
+
+    #[cfg(feature = "security")]
+    /// Returns, for each source block, the result of the consistency check. See
+    /// `SourceBlockDecoder::pollution_detected()`.
+    pub fn pollution_detected(&self) -> Vec<Option<bool>> {
+        self.block_decoders
+            .iter()
+            .map(|block_decoder| block_decoder.pollution_detected())
+            .collect()
+    }
+
+    #[cfg(feature = "security")]
+    /// See `SourceBlockDecoder::set_pollution_recovery()`
+    pub fn set_pollution_recovery(&mut self, max_attempts: u32) {
+        for block_decoder in self.block_decoders.iter_mut() {
+            block_decoder.set_pollution_recovery(max_attempts);
+        }
+    }
+
+    #[cfg(feature = "security")]
+    /// Returns, for each source block, the ESI of the symbol which was excluded to recover from
+    /// pollution. See `SourceBlockDecoder::excluded_symbol()`.
+    pub fn excluded_symbols(&self) -> Vec<Option<u32>> {
+        self.block_decoders
+            .iter()
+            .map(|block_decoder| block_decoder.excluded_symbol())
+            .collect()
+    }
+
+    /// See `SourceBlockDecoder::set_complexity_limit()`
+    pub fn set_complexity_limit(&mut self, factor: u32) {
+        for block_decoder in self.block_decoders.iter_mut() {
+            block_decoder.set_complexity_limit(factor);
+        }
+    }
+
+    /// See `SourceBlockDecoder::set_max_density()`
+    pub fn set_max_density(&mut self, percent: u32) {
+        for block_decoder in self.block_decoders.iter_mut() {
+            block_decoder.set_max_density(percent);
+        }
+    }
+
+    /// See `SourceBlockDecoder::set_integrity_check()`
+    pub fn set_integrity_check(&mut self, level: IntegrityCheck) {
+        for block_decoder in self.block_decoders.iter_mut() {
+            block_decoder.set_integrity_check(level);
+        }
+    }
+
+    /// Returns, for each source block, the limit which caused its most recent decoding attempt to
+    /// be aborted, if any. See `SourceBlockDecoder::exceeded_limit()`.
+    pub fn exceeded_limits(&self) -> Vec<Option<SolverLimit>> {
+        self.block_decoders
+            .iter()
+            .map(|block_decoder| block_decoder.exceeded_limit())
+            .collect()
+    }
+
+    /// See `SourceBlockDecoder::set_injected_failure()`
+    #[cfg(feature = "failure_injection")]
+    pub fn set_injected_failure(&mut self, failure: Option<InjectedFailure>) {
+        for block_decoder in self.block_decoders.iter_mut() {
+            block_decoder.set_injected_failure(failure);
+        }
+    }
+
+    /// Only accept packets for the first `blocks` source blocks which have not been decoded yet.
+    /// Packets for any later block are discarded, which bounds the number of blocks for which
+    /// symbols are buffered at any one time. By default all blocks are accepted.
+    pub fn set_acceptance_window(&mut self, blocks: usize) {
+        assert!(blocks > 0);
+        self.acceptance_window = Some(blocks);
+    }
+
+    fn accepts(&self, block_number: usize) -> bool {
+        if block_number >= self.blocks.len() || self.blocks[block_number].is_some() {
+            return false;
+        }
+        if let Some(window) = self.acceptance_window {
+            let preceding_undecoded = self.blocks[..block_number]
+                .iter()
+                .filter(|block| block.is_none())
+                .count();
+            return preceding_undecoded < window;
+        }
+        true
+    }
+
+    /// Sets how long a source block may go without receiving a new symbol, before `gc()` discards
+    /// the symbols received for it. None, the default, disables this.
+    pub fn set_block_timeout(&mut self, timeout: Option<Duration>) {
+        self.block_timeout = timeout;
+        self.block_progress = vec![None; self.blocks.len()];
+        if timeout.is_some() {
+            for block_number in 0..self.blocks.len() {
+                self.track_progress(block_number);
+            }
+        }
+    }
+
+    fn track_progress(&mut self, block_number: usize) {
+        if self.block_timeout.is_none() {
+            return;
+        }
+        // Not serialized, so it is rebuilt after deserialization
+        if self.block_progress.len() != self.blocks.len() {
+            self.block_progress = vec![None; self.blocks.len()];
         }
+        let received = self.block_decoders[block_number].received_symbols();
+        if received == 0 || self.blocks[block_number].is_some() {
+            self.block_progress[block_number] = None;
+            return;
+        }
+        match self.block_progress[block_number] {
+            Some((previous, _)) if previous == received => {}
+            _ => self.block_progress[block_number] = Some((received, Instant::now())),
+        }
+    }
+
+    /// Discards the symbols received for each source block which has not been decoded, and has not
+    /// received a new symbol within the timeout set by `set_block_timeout()`. The block can still
+    /// be decoded from packets received afterwards. This bounds the memory held by a long running
+    /// receiver for transfers which were abandoned by their sender. Returns the number of blocks
+    /// discarded.
+    pub fn gc(&mut self) -> usize {
+        let timeout = match self.block_timeout {
+            Some(timeout) => timeout,
+            None => return 0,
+        };
+        let now = Instant::now();
+        let mut discarded = 0;
+        for block_number in 0..self.blocks.len() {
+            self.track_progress(block_number);
+            if let Some((_, last_progress)) = self.block_progress[block_number] {
+                if now.duration_since(last_progress) >= timeout {
+                    self.block_decoders[block_number].discard_received_symbols();
+                    self.block_progress[block_number] = None;
+                    discarded += 1;
+                }
+            }
+        }
+        discarded
     }
 
     #[cfg(any(test, feature = "benchmarking"))]
@@ -64,33 +354,249 @@ impl Decoder {
         }
     }
 
+    /// See `SourceBlockDecoder::set_min_overhead_mode()`
+    pub fn set_min_overhead_mode(&mut self, enabled: bool) {
+        for block_decoder in self.block_decoders.iter_mut() {
+            block_decoder.set_min_overhead_mode(enabled);
+        }
+    }
+
     pub fn decode(&mut self, packet: EncodingPacket) -> Option<Vec<u8>> {
+        self.add_new_packet(packet);
+        self.get_result()
+    }
+
+    /// Same as `decode()`, except that the payload is only copied if the packet is retained.
+    /// Packets for blocks which have already been decoded, or are outside the acceptance window,
+    /// and duplicate packets are discarded without copying.
+    pub fn decode_borrowed(&mut self, packet: EncodingPacketRef) -> Option<Vec<u8>> {
+        self.add_new_packet_borrowed(packet);
+        self.get_result()
+    }
+
+    /// Same as `decode()`, for a quick repair packet. See `SourceBlockDecoder::decode_quick_repair()`
+    pub fn decode_quick_repair(&mut self, packet: EncodingPacket) -> Option<Vec<u8>> {
         let block_number = packet.payload_id.source_block_number() as usize;
-        if self.blocks[block_number].is_none() {
-            self.blocks[block_number] = self.block_decoders[block_number].decode(vec![packet]);
+        if self.accepts(block_number) {
+            self.blocks[block_number] =
+                self.block_decoders[block_number].decode_quick_repair(vec![packet]);
+            self.block_updated(block_number);
+            self.track_progress(block_number);
         }
-        for block in self.blocks.iter() {
-            if block.is_none() {
-                return None;
+        self.get_result()
+    }
+
+    /// Reads packets framed by `write_framed_packet()` from the reader, until the object is
+    /// decoded. Returns `Ok(None)` if the reader ends before enough packets have been received.
+    /// Nothing beyond the frame which completed decoding is consumed from the reader.
+    pub fn decode_from<R: Read>(&mut self, mut reader: R) -> io::Result<Option<Vec<u8>>> {
+        while let Some(packet) = read_framed_packet(&mut reader)? {
+            if let Some(result) = self.decode(packet) {
+                return Ok(Some(result));
             }
         }
+        Ok(None)
+    }
 
-        let mut result = vec![];
-        for block in self.blocks.iter() {
-            result.extend(block.clone().unwrap());
+    /// Same as `decode_from()`, for an asynchronous reader
+    #[cfg(feature = "async")]
+    pub async fn decode_from_async<R: futures::io::AsyncRead + Unpin>(
+        &mut self,
+        mut reader: R,
+    ) -> io::Result<Option<Vec<u8>>> {
+        while let Some(packet) = read_framed_packet_async(&mut reader).await? {
+            if let Some(result) = self.decode(packet) {
+                return Ok(Some(result));
+            }
         }
-        result.truncate(self.config.transfer_length() as usize);
-        Some(result)
+        Ok(None)
     }
 
     pub fn add_new_packet(&mut self, packet: EncodingPacket) {
         let block_number = packet.payload_id.source_block_number() as usize;
-        if self.blocks[block_number].is_none() {
+        if self.accepts(block_number) {
             self.blocks[block_number] = self.block_decoders[block_number].decode(vec![packet]);
+            self.block_updated(block_number);
+            self.track_progress(block_number);
+        }
+    }
+
+    /// See `decode_borrowed()`
+    pub fn add_new_packet_borrowed(&mut self, packet: EncodingPacketRef) {
+        let block_number = packet.payload_id.source_block_number() as usize;
+        if self.accepts(block_number) {
+            self.blocks[block_number] =
+                self.block_decoders[block_number].decode_borrowed(vec![packet]);
+            self.block_updated(block_number);
+            self.track_progress(block_number);
+        }
+    }
+
+    /// Same as `decode()`, except that the payload is retained without being copied, so that the
+    /// application can also forward or consume it without the bytes existing twice in memory.
+    /// See `SourceBlockDecoder::decode_shared()`
+    pub fn decode_shared(&mut self, payload_id: PayloadId, payload: Arc<[u8]>) -> Option<Vec<u8>> {
+        self.add_new_packet_shared(payload_id, payload);
+        self.get_result()
+    }
+
+    /// See `decode_shared()`
+    pub fn add_new_packet_shared(&mut self, payload_id: PayloadId, payload: Arc<[u8]>) {
+        let block_number = payload_id.source_block_number() as usize;
+        if self.accepts(block_number) {
+            self.blocks[block_number] =
+                self.block_decoders[block_number].decode_shared(vec![(payload_id, payload)]);
+            self.block_updated(block_number);
+            self.track_progress(block_number);
+        }
+    }
+
+    /// Adds a batch of packets, which may belong to any number of source blocks. With the
+    /// `parallel` feature, the blocks which received packets are decoded concurrently on the rayon
+    /// thread pool, so for objects with many source blocks it is preferable to pass packets in
+    /// batches, rather than one at a time to `add_new_packet()`.
+    pub fn add_new_packets<T: IntoIterator<Item = EncodingPacket>>(&mut self, packets: T) {
+        let mut block_packets: Vec<Vec<EncodingPacket>> = vec![vec![]; self.blocks.len()];
+        for packet in packets {
+            let block_number = packet.payload_id.source_block_number() as usize;
+            if self.accepts(block_number) {
+                block_packets[block_number].push(packet);
+            }
+        }
+        let updated: Vec<usize> = block_packets
+            .iter()
+            .enumerate()
+            .filter(|(_, packets)| !packets.is_empty())
+            .map(|(block_number, _)| block_number)
+            .collect();
+        self.decode_blocks(block_packets);
+        if updated.last() == Some(&(self.blocks.len() - 1)) {
+            self.block_updated(self.blocks.len() - 1);
+        }
+        for block_number in updated {
+            self.track_progress(block_number);
+        }
+    }
+
+    #[cfg(not(feature = "parallel"))]
+    fn decode_blocks(&mut self, block_packets: Vec<Vec<EncodingPacket>>) {
+        self.block_decoders
+            .iter_mut()
+            .zip(self.blocks.iter_mut())
+            .zip(block_packets)
+            .filter(|(_, packets)| !packets.is_empty())
+            .for_each(|((block_decoder, block), packets)| *block = block_decoder.decode(packets));
+    }
+
+    #[cfg(feature = "parallel")]
+    fn decode_blocks(&mut self, block_packets: Vec<Vec<EncodingPacket>>) {
+        self.block_decoders
+            .par_iter_mut()
+            .zip(self.blocks.par_iter_mut())
+            .zip(block_packets)
+            .filter(|(_, packets)| !packets.is_empty())
+            .for_each(|((block_decoder, block), packets)| *block = block_decoder.decode(packets));
+    }
+
+    fn block_updated(&mut self, block_number: usize) {
+        if block_number == self.blocks.len() - 1 {
+            self.check_padding();
+        }
+    }
+
+    // The zero padding of the object is all at the end of the last block. Non-zero padding means
+    // that the peers disagree on the transfer length, so rather than silently truncating the
+    // data this fails, unless in lenient mode. The padding is received from the sender, so this
+    // must not panic
+    fn check_padding(&mut self) {
+        if let Some(last_block) = self.blocks.last().unwrap() {
+            let last_block = self.block_data(last_block);
+            let padding = self.config.padding() as usize;
+            let non_zero_bytes = last_block[(last_block.len() - padding)..]
+                .iter()
+                .filter(|byte| **byte != 0)
+                .count();
+            if non_zero_bytes > 0 {
+                self.failed = !self.lenient;
+                self.warnings
+                    .push(DecodeWarning::NonZeroPadding { non_zero_bytes });
+            }
+        }
+    }
+
+    /// Returns the packets needed to resume decoding later, when it is abandoned before the object
+    /// could be decoded: the source symbols of each decoded block, and the symbols received for
+    /// each of the others. Adding them to a new `Decoder` for the same object restores the progress,
+    /// after which symbols from another session, such as a second broadcast pass, can be added.
+    pub fn salvage_packets(&self) -> Vec<EncodingPacket> {
+        let symbol_size = self.config.symbol_size() as usize;
+        let mut packets = vec![];
+        for (block_number, block) in self.blocks.iter().enumerate() {
+            match block {
+                Some(block) => {
+                    packets.extend(block.chunks(symbol_size).enumerate().map(|(esi, symbol)| {
+                        EncodingPacket::new(
+                            PayloadId::new(block_number as u8, esi as u32),
+                            symbol.to_vec(),
+                        )
+                    }))
+                }
+                None => packets.extend(self.block_decoders[block_number].received_packets()),
+            }
+        }
+        packets
+    }
+
+    /// Writes the packets of `salvage_packets()` as frames, which can be persisted, and later
+    /// read into a new decoder with `decode_from()`. State from several sessions can be merged
+    /// by reading all of it into the same decoder.
+    pub fn write_salvage<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        for packet in self.salvage_packets() {
+            write_framed_packet(writer, &packet)?;
+        }
+        Ok(())
+    }
+
+    /// Returns an acknowledgement summarizing the decoding progress, to send back to the sender
+    pub fn ack_frame(&self) -> BlockAckFrame {
+        BlockAckFrame::new(
+            self.block_decoders
+                .iter()
+                .map(|block_decoder| block_decoder.symbols_needed())
+                .collect(),
+        )
+    }
+
+    /// Returns, for each source block, the number of symbols beyond K which were needed to
+    /// decode it. Blocks which have not been decoded yet are reported as None.
+    pub fn extra_symbols_consumed(&self) -> Vec<Option<u32>> {
+        self.block_decoders
+            .iter()
+            .map(|block_decoder| block_decoder.extra_symbols_consumed())
+            .collect()
+    }
+
+    pub fn memory_usage(&self) -> MemoryUsage {
+        let mut usage = MemoryUsage::default();
+        for block_decoder in self.block_decoders.iter() {
+            usage += block_decoder.memory_usage();
         }
+        let decoded_data = self.blocks.iter().flatten().map(|block| block.len()).sum();
+        usage += MemoryUsage::new(0, 0, 0, decoded_data, 0);
+        usage
+    }
+
+    /// Returns true if decoding failed because the padding of the last symbol is not zero, which
+    /// means that the transfer length is likely incorrect. `get_result()` then returns None, and
+    /// `warnings()` contains the number of non-zero bytes. Lenient mode tolerates this instead.
+    pub fn failed(&self) -> bool {
+        self.failed
     }
 
     pub fn get_result(&self) -> Option<Vec<u8>> {
+        if self.failed {
+            return None;
+        }
         for block in self.blocks.iter() {
             if block.is_none() {
                 return None;
@@ -99,11 +605,170 @@ impl Decoder {
 
         let mut result = vec![];
         for block in self.blocks.iter() {
-            result.extend(block.clone().unwrap());
+            result.extend_from_slice(&self.block_data(block.as_ref().unwrap()));
         }
         result.truncate(self.config.transfer_length() as usize);
         Some(result)
     }
+
+    // Returns the data of a decoded source block in the order of the object, i.e. with its
+    // sub-blocks one after another
+    fn block_data<'a>(&self, block: &'a [u8]) -> Cow<'a, [u8]> {
+        if self.config.sub_blocks() > 1 {
+            Cow::Owned(deinterleave_sub_blocks(
+                block,
+                &self.config.sub_symbol_sizes(),
+            ))
+        } else {
+            Cow::Borrowed(block)
+        }
+    }
+}
+
+// Upper bound on the number of symbol additions per extended source symbol, when solving for
+// the intermediate symbols. Measured values are between 30 and 45 for K' from 10 to 10,000
+const TYPICAL_SYMBOL_ADD_OPS: u32 = 50;
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+enum PartialSolver {
+    Dense(Box<IntermediateSymbolDecoder<DenseOctetMatrix>>),
+    Sparse(Box<IntermediateSymbolDecoder<SparseOctetMatrix>>),
+}
+
+// The payload of a received symbol
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+enum ReceivedPayload {
+    Owned(Vec<u8>),
+    // Retained without copying. See `SourceBlockDecoder::decode_shared()`
+    Shared(Arc<[u8]>),
+}
+
+impl ReceivedPayload {
+    fn as_bytes(&self) -> &[u8] {
+        match self {
+            ReceivedPayload::Owned(data) => data,
+            ReceivedPayload::Shared(data) => data,
+        }
+    }
+
+    fn to_symbol(&self) -> Symbol {
+        Symbol::new(self.as_bytes().to_vec())
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+struct ReceivedRepairSymbol {
+    payload_id: PayloadId,
+    payload: ReceivedPayload,
+}
+
+// A quick repair symbol which has not recovered a source symbol yet, from which the source
+// symbols received so far have been subtracted. See `SourceBlockDecoder::decode_quick_repair()`
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+struct QuickRepairSymbol {
+    // The source symbols which have not been received
+    indices: Vec<u32>,
+    data: Vec<u8>,
+}
+
+// The state of a failed decoding attempt, so that a retry only needs to add the symbols which were
+// received since
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+struct RetryState {
+    solver: PartialSolver,
+    pending_esis: Vec<u32>,
+}
+
+/// Decodes a single sub-block of a source block (see section 4.4.1.2), from the sub-symbols of the
+/// received packets which belong to it. Each sub-block is encoded independently of the others, so
+/// receivers with limited working memory can decode the sub-blocks of a source block one at a
+/// time, holding only the sub-symbols of one sub-block, rather than whole symbols. Each sub-block
+/// is a contiguous range of the object, see `object_range()`.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SubBlockDecoder {
+    decoder: SourceBlockDecoder,
+    // Position of the sub-symbols of this sub-block in each symbol
+    sub_symbol_offset: usize,
+    sub_symbol_size: usize,
+    object_range: Range<u64>,
+}
+
+impl SubBlockDecoder {
+    /// Returns an error if `config` is invalid, see `Decoder::try_new()`, or if there is no such
+    /// source block or sub-block.
+    pub fn new(
+        config: &ObjectTransmissionInformation,
+        source_block_number: u8,
+        sub_block_number: u16,
+    ) -> Result<SubBlockDecoder, ConfigError> {
+        let (kl, ks, zl, zs) = config.checked_block_partition()?;
+        if u32::from(source_block_number) >= zl + zs || sub_block_number >= config.sub_blocks() {
+            return Err(ConfigError::NoSuchBlock {
+                source_block_number,
+                sub_block_number,
+            });
+        }
+        let symbol_size = u64::from(config.symbol_size());
+        let sbn = u64::from(source_block_number);
+        let (block_start, block_symbols) = if sbn < u64::from(zl) {
+            (sbn * u64::from(kl) * symbol_size, kl)
+        } else {
+            let large_blocks = u64::from(zl) * u64::from(kl);
+            let block = sbn - u64::from(zl);
+            ((large_blocks + block * u64::from(ks)) * symbol_size, ks)
+        };
+        let sub_symbol_sizes = config.sub_symbol_sizes();
+        let sub_symbol_offset: usize = sub_symbol_sizes[..sub_block_number as usize].iter().sum();
+        let sub_symbol_size = sub_symbol_sizes[sub_block_number as usize];
+        // The sub-blocks of a source block are one after another in the object
+        let start = block_start + u64::from(block_symbols) * sub_symbol_offset as u64;
+        let end = start + u64::from(block_symbols) * sub_symbol_size as u64;
+        let transfer_length = config.transfer_length();
+        Ok(SubBlockDecoder {
+            decoder: SourceBlockDecoder::new(
+                source_block_number,
+                sub_symbol_size as u16,
+                u64::from(block_symbols) * sub_symbol_size as u64,
+            ),
+            sub_symbol_offset,
+            sub_symbol_size,
+            object_range: min(start, transfer_length)..min(end, transfer_length),
+        })
+    }
+
+    /// The range of the object which this sub-block contains, and which `decode()` returns. It is
+    /// empty for sub-blocks which only contain padding.
+    pub fn object_range(&self) -> Range<u64> {
+        self.object_range.clone()
+    }
+
+    /// Returns the number of packets which were discarded because they were malformed: packets
+    /// too short to contain the sub-symbol of this sub-block, and sub-symbols rejected as by
+    /// `SourceBlockDecoder::rejected_symbols()`
+    pub fn rejected_packets(&self) -> u32 {
+        self.decoder.rejected_symbols()
+    }
+
+    /// Adds the sub-symbol of a packet, which is ignored if it is from another source block, and
+    /// returns the data of the sub-block once it has been decoded. Packets too short to contain
+    /// the sub-symbol are discarded, and counted by `rejected_packets()`.
+    pub fn decode(&mut self, packet: &EncodingPacket) -> Option<Vec<u8>> {
+        if packet.payload_id().source_block_number() != self.decoder.source_block_id {
+            return None;
+        }
+        let end = self.sub_symbol_offset + self.sub_symbol_size;
+        if packet.data().len() < end {
+            self.decoder.rejected_symbols += 1;
+            return None;
+        }
+        let sub_symbol = EncodingPacketRef::new(
+            packet.payload_id().clone(),
+            &packet.data()[self.sub_symbol_offset..end],
+        );
+        let mut result = self.decoder.decode_borrowed(vec![sub_symbol])?;
+        result.truncate((self.object_range.end - self.object_range.start) as usize);
+        Some(result)
+    }
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
@@ -111,12 +776,42 @@ pub struct SourceBlockDecoder {
     source_block_id: u8,
     symbol_size: u16,
     source_block_symbols: u32,
-    source_symbols: Vec<Option<Symbol>>,
-    repair_packets: Vec<EncodingPacket>,
+    // Indexed by ESI. Empty until the first source symbol is received, so that a decoder costs
+    // little memory before any of its symbols arrive
+    source_symbols: Vec<Option<ReceivedPayload>>,
+    repair_packets: Vec<ReceivedRepairSymbol>,
+    quick_repair_symbols: Vec<QuickRepairSymbol>,
     received_source_symbols: u32,
     received_esi: HashSet<u32>,
     decoded: bool,
+    extra_symbols_consumed: Option<u32>,
+    lenient: bool,
+    strict: bool,
+    rejected_symbols: u32,
+    warnings: Vec<DecodeWarning>,
+    last_solve_symbol_ops: Option<(u32, u32)>,
+    min_overhead_mode: bool,
+    retry_state: Option<RetryState>,
     sparse_threshold: u32,
+    #[serde(skip)]
+    allocator_hooks: Option<AllocatorHandle>,
+    #[cfg(feature = "security")]
+    pollution_detection: bool,
+    #[cfg(feature = "security")]
+    pollution_detected: Option<bool>,
+    #[cfg(feature = "security")]
+    pollution_recovery_attempts: u32,
+    #[cfg(feature = "security")]
+    excluded_symbol: Option<u32>,
+    complexity_limit: Option<u32>,
+    max_density: Option<u32>,
+    exceeded_limit: Option<SolverLimit>,
+    integrity_check: IntegrityCheck,
+    record_permutations: bool,
+    permutations: Option<SolverPermutations>,
+    rank_tracker: Option<BinaryRankTracker>,
+    #[cfg(feature = "failure_injection")]
+    injected_failure: Option<InjectedFailure>,
 }
 
 impl SourceBlockDecoder {
@@ -130,41 +825,302 @@ impl SourceBlockDecoder {
             source_block_id,
             symbol_size,
             source_block_symbols: source_symbols,
-            source_symbols: vec![None; source_symbols as usize],
+            source_symbols: vec![],
             repair_packets: vec![],
+            quick_repair_symbols: vec![],
             received_source_symbols: 0,
             received_esi,
             decoded: false,
+            extra_symbols_consumed: None,
+            lenient: false,
+            strict: false,
+            rejected_symbols: 0,
+            warnings: vec![],
+            last_solve_symbol_ops: None,
+            min_overhead_mode: false,
+            retry_state: None,
             sparse_threshold: SPARSE_MATRIX_THRESHOLD,
+            allocator_hooks: None,
+            #[cfg(feature = "security")]
+            pollution_detection: false,
+            #[cfg(feature = "security")]
+            pollution_detected: None,
+            #[cfg(feature = "security")]
+            pollution_recovery_attempts: 0,
+            #[cfg(feature = "security")]
+            excluded_symbol: None,
+            complexity_limit: None,
+            max_density: None,
+            exceeded_limit: None,
+            integrity_check: IntegrityCheck::default(),
+            record_permutations: false,
+            permutations: None,
+            rank_tracker: None,
+            #[cfg(feature = "failure_injection")]
+            injected_failure: None,
+        }
+    }
+
+    /// Enables tracking of the linear independence of the received symbols as they arrive, which
+    /// makes `fully_specified()` accurate. This costs an elimination step per received symbol,
+    /// and retains up to L^2 / 8 bytes, where L is the number of intermediate symbols.
+    pub fn set_independence_tracking(&mut self, enabled: bool) {
+        if !enabled {
+            self.rank_tracker = None;
+            return;
+        }
+        if self.rank_tracker.is_some() {
+            return;
+        }
+        let mut tracker =
+            BinaryRankTracker::new(num_intermediate_symbols(self.source_block_symbols) as usize);
+        for row in ldpc_rows(self.source_block_symbols) {
+            tracker.add_row(&row);
+        }
+        // Includes the padding symbols
+        for &esi in self.received_esi.iter() {
+            tracker.add_row(&encoding_row(self.source_block_symbols, esi));
+        }
+        self.rank_tracker = Some(tracker);
+    }
+
+    /// Returns whether the received symbols specify the block, so that decoding will succeed.
+    ///
+    /// With independence tracking (see `set_independence_tracking()`), false is exact: the
+    /// received symbols are known to be insufficient. True means that the binary part of the
+    /// constraint matrix has sufficient rank, and that decoding only fails in the rare case that
+    /// the HDPC rows do not complete it. Without tracking, this only checks that at least K'
+    /// symbols have been received, which is when a decoding attempt is made.
+    pub fn fully_specified(&self) -> bool {
+        if self.decoded {
+            return true;
+        }
+        let intermediate_symbols = num_intermediate_symbols(self.source_block_symbols) as usize;
+        match self.rank_tracker {
+            Some(ref tracker) => {
+                tracker.rank() + num_hdpc_symbols(self.source_block_symbols) as usize
+                    >= intermediate_symbols
+            }
+            None => {
+                self.received_esi.len() as u32
+                    >= extended_source_block_symbols(self.source_block_symbols)
+            }
         }
     }
 
+    /// See `Decoder::set_lenient()`
+    pub fn set_lenient(&mut self, lenient: bool) {
+        assert!(
+            !(lenient && self.strict),
+            "lenient mode is not allowed in strict mode"
+        );
+        self.lenient = lenient;
+    }
+
+    /// In strict mode, symbols which do not conform to RFC 6330 are discarded: those whose length
+    /// is not the symbol size, and those with the ESI of a padding symbol, which is never sent.
+    /// See `Decoder::try_new_strict()`
+    pub fn set_strict(&mut self, strict: bool) {
+        assert!(
+            !(strict && self.lenient),
+            "lenient mode is not allowed in strict mode"
+        );
+        self.strict = strict;
+    }
+
+    /// Returns the number of symbols which were discarded because they were malformed: symbols
+    /// with the ESI or ISI of a padding symbol, and symbols whose length does not match the
+    /// symbol size, unless in lenient mode
+    pub fn rejected_symbols(&self) -> u32 {
+        self.rejected_symbols
+    }
+
+    pub fn warnings(&self) -> &[DecodeWarning] {
+        &self.warnings
+    }
+
+    /// See `Decoder::set_allocator_hooks()`
+    pub fn set_allocator_hooks(&mut self, hooks: Arc<dyn AllocatorHooks>) {
+        self.allocator_hooks = Some(AllocatorHandle(hooks));
+    }
+
+    fn block_length(&self) -> usize {
+        self.source_block_symbols as usize * self.symbol_size as usize
+    }
+
     #[cfg(any(test, feature = "benchmarking"))]
     pub fn set_sparse_threshold(&mut self, value: u32) {
         self.sparse_threshold = value;
     }
 
-    fn try_pi_decode(
+    #[cfg(feature = "security")]
+    /// Checks that the received symbols are consistent with each other when the block is decoded,
+    /// which detects that some symbol was corrupted, e.g. by a pollution attack, even if packets
+    /// are not authenticated. This requires more than K symbols to have been received, because
+    /// any K symbols (from which the block can be decoded) are trivially consistent, and costs an
+    /// additional encoding of each received symbol.
+    pub fn set_pollution_detection(&mut self, enabled: bool) {
+        self.pollution_detection = enabled;
+    }
+
+    #[cfg(feature = "security")]
+    /// Returns whether the consistency check found that some received symbol was corrupted, in
+    /// which case the decoded data should not be trusted. Returns None if the block has not been
+    /// decoded, pollution detection is disabled, or too few symbols were received to check.
+    pub fn pollution_detected(&self) -> Option<bool> {
+        self.pollution_detected
+    }
+
+    #[cfg(feature = "security")]
+    /// When pollution is detected, tries to recover by decoding again without one of the received
+    /// symbols at a time, until the remaining symbols are consistent. This recovers from a single
+    /// corrupted symbol, if at least K + 2 symbols were received. Each attempt costs a full
+    /// decode, so at most `max_attempts` are made. Requires pollution detection to be enabled.
+    pub fn set_pollution_recovery(&mut self, max_attempts: u32) {
+        self.pollution_recovery_attempts = max_attempts;
+    }
+
+    #[cfg(feature = "security")]
+    /// Returns the ESI of the symbol which was excluded to recover from pollution, or None if
+    /// no recovery was needed or it was unsuccessful. See `set_pollution_recovery()`.
+    pub fn excluded_symbol(&self) -> Option<u32> {
+        self.excluded_symbol
+    }
+
+    /// Bounds the worst case cost of each decoding attempt, for systems with latency requirements.
+    /// Attempts which perform more than `factor` times the symbol additions of a typical decode
+    /// (about 50 per extended source symbol) are aborted, and treated as failed. The budget is
+    /// checked throughout solving, so the symbol operations of each attempt cost at most
+    /// O(factor * K' * T), plus the operations on the constraint matrix, which do not depend on T.
+    /// An aborted block is retried when further symbols arrive, which usually avoids the
+    /// pathological case.
+    pub fn set_complexity_limit(&mut self, factor: u32) {
+        assert!(factor > 0);
+        self.complexity_limit = Some(factor);
+    }
+
+    /// Returns true if the most recent decoding attempt was aborted, because it exceeded the
+    /// limit set by `set_complexity_limit()`
+    pub fn complexity_limit_exceeded(&self) -> bool {
+        self.exceeded_limit == Some(SolverLimit::SymbolAddOps)
+    }
+
+    /// Bounds the density of the constraint matrix during each decoding attempt, which protects
+    /// against sets of symbols crafted to make decoding quadratic in K. Attempts which inactivate
+    /// more than `percent` percent of the LT symbols, in addition to the PI symbols, are aborted
+    /// and treated as failed. Decoding well formed input inactivates only a few, so a limit of
+    /// 10 percent or more does not affect it in practice.
+    pub fn set_max_density(&mut self, percent: u32) {
+        assert!(percent <= 100);
+        self.max_density = Some(percent);
+    }
+
+    /// Sets how thoroughly the solver checks its invariants in each decoding attempt, which
+    /// catches memory corruption and bugs early, rather than returning wrong data. A failed check
+    /// panics. `IntegrityCheck::Cheap` adds O(L) work per attempt, so can be enabled in production,
+    /// while `IntegrityCheck::Full` is quadratic. Defaults to `IntegrityCheck::Full` in debug
+    /// builds, and `IntegrityCheck::Off` otherwise.
+    pub fn set_integrity_check(&mut self, level: IntegrityCheck) {
+        self.integrity_check = level;
+    }
+
+    /// Returns the limit which caused the most recent decoding attempt to be aborted, if any. See
+    /// `set_complexity_limit()` and `set_max_density()`.
+    pub fn exceeded_limit(&self) -> Option<SolverLimit> {
+        self.exceeded_limit
+    }
+
+    /// Records the row and column permutations chosen by the solver in each decoding attempt, for
+    /// analysis of pivoting and inactivation patterns. See `permutations()`.
+    pub fn set_record_permutations(&mut self, enabled: bool) {
+        self.record_permutations = enabled;
+        if !enabled {
+            self.permutations = None;
+        }
+    }
+
+    /// Returns the permutations chosen by the solver in the most recent decoding attempt which
+    /// needed it, if recording is enabled. See `set_record_permutations()`.
+    pub fn permutations(&self) -> Option<&SolverPermutations> {
+        self.permutations.as_ref()
+    }
+
+    /// Forces every subsequent decoding attempt which needs the solver to fail, until cleared by
+    /// passing None. See `InjectedFailure`.
+    #[cfg(feature = "failure_injection")]
+    pub fn set_injected_failure(&mut self, failure: Option<InjectedFailure>) {
+        self.injected_failure = failure;
+    }
+
+    /// Retains the partially solved state of a failed decoding attempt, so that retrying with
+    /// additional symbols only requires eliminating the rows for those symbols, rather than
+    /// starting over. This is intended for receivers which attempt decoding with little or no
+    /// overhead beyond K symbols, at the cost of holding that state until the block is decoded.
+    ///
+    /// Note that in this mode the outcome may depend on the order in which the symbols received
+    /// after the first failed attempt arrived.
+    pub fn set_min_overhead_mode(&mut self, enabled: bool) {
+        self.min_overhead_mode = enabled;
+        if !enabled {
+            self.retry_state = None;
+        }
+    }
+
+    fn try_pi_decode<T: OctetMatrix>(
         &mut self,
-        constraint_matrix: impl OctetMatrix,
-        symbols: Vec<Symbol>,
+        mut solver: IntermediateSymbolDecoder<T>,
+        retain: fn(IntermediateSymbolDecoder<T>) -> PartialSolver,
+        ctx: &mut WorkContext,
     ) -> Option<Vec<u8>> {
-        let intermediate_symbols = match fused_inverse_mul_symbols(
-            constraint_matrix,
-            symbols,
-            self.source_block_symbols,
-        ) {
-            None => return None,
+        if let Some(factor) = self.complexity_limit {
+            let num_extended_symbols = extended_source_block_symbols(self.source_block_symbols);
+            solver.set_max_symbol_add_ops(
+                factor
+                    .saturating_mul(TYPICAL_SYMBOL_ADD_OPS)
+                    .saturating_mul(num_extended_symbols),
+            );
+        }
+        if let Some(percent) = self.max_density {
+            let lt_symbols = num_lt_symbols(self.source_block_symbols) as usize;
+            solver.set_max_inactivated_columns(
+                num_pi_symbols(self.source_block_symbols) as usize
+                    + lt_symbols * percent as usize / 100,
+            );
+        }
+        solver.set_integrity_check(self.integrity_check);
+        if self.record_permutations {
+            solver.record_permutations();
+        }
+        #[cfg(feature = "failure_injection")]
+        solver.set_injected_failure(self.injected_failure);
+        let solved = solver.execute();
+        self.last_solve_symbol_ops =
+            Some((solver.get_symbol_add_ops(), solver.get_symbol_mul_ops()));
+        self.exceeded_limit = solver.exceeded_limit();
+        if self.record_permutations {
+            self.permutations = Some(solver.permutations());
+        }
+        let intermediate_symbols = match solved {
+            None => {
+                // An aborted solver is left in an inconsistent state, so can't be resumed
+                if self.min_overhead_mode && !solver.aborted() {
+                    self.retry_state = Some(RetryState {
+                        solver: retain(solver),
+                        pending_esis: vec![],
+                    });
+                }
+                return None;
+            }
             Some(s) => s,
         };
 
-        let mut result = vec![];
+        let mut result = Vec::with_capacity(self.block_length());
         let lt_symbols = num_lt_symbols(self.source_block_symbols);
         let pi_symbols = num_pi_symbols(self.source_block_symbols);
         let sys_index = systematic_index(self.source_block_symbols);
         let p1 = calculate_p1(self.source_block_symbols);
         for i in 0..self.source_block_symbols as usize {
-            if let Some(ref symbol) = self.source_symbols[i] {
+            if let Some(Some(symbol)) = self.source_symbols.get(i) {
                 result.extend(symbol.as_bytes())
             } else {
                 let rebuilt = self.rebuild_source_symbol(
@@ -174,99 +1130,606 @@ impl SourceBlockDecoder {
                     pi_symbols,
                     sys_index,
                     p1,
+                    ctx,
                 );
                 result.extend(rebuilt.as_bytes());
+                ctx.recycle_symbol(rebuilt);
             }
         }
+        ctx.recycle(intermediate_symbols);
 
-        self.decoded = true;
+        #[cfg(feature = "security")]
+        let result = self.check_pollution(result);
+        self.mark_decoded();
         return Some(result);
     }
 
-    pub fn decode<T: IntoIterator<Item = EncodingPacket>>(
-        &mut self,
-        packets: T,
-    ) -> Option<Vec<u8>> {
-        for packet in packets {
-            assert_eq!(
-                self.source_block_id,
-                packet.payload_id.source_block_number()
-            );
-
+    #[cfg(feature = "security")]
+    // Runs the consistency check, if enabled, and if it fails tries to recover by excluding one
+    // received symbol at a time
+    fn check_pollution(&mut self, block: Vec<u8>) -> Vec<u8> {
+        if !self.pollution_detection {
+            return block;
+        }
+        self.pollution_detected = self.check_consistency(&block);
+        if self.pollution_detected != Some(true) {
+            return block;
+        }
+
+        let received = self.received_packets();
+
+        for excluded in 0..min(received.len(), self.pollution_recovery_attempts as usize) {
+            let mut decoder = SourceBlockDecoder::new(
+                self.source_block_id,
+                self.symbol_size,
+                self.block_length() as u64,
+            );
+            decoder.sparse_threshold = self.sparse_threshold;
+            decoder.complexity_limit = self.complexity_limit;
+            decoder.max_density = self.max_density;
+            decoder.integrity_check = self.integrity_check;
+            decoder.set_pollution_detection(true);
+            let attempt = decoder.decode(
+                received
+                    .iter()
+                    .enumerate()
+                    .filter(|(i, _)| *i != excluded)
+                    .map(|(_, packet)| packet.clone()),
+            );
+            if let (Some(recovered), Some(false)) = (attempt, decoder.pollution_detected()) {
+                self.excluded_symbol = Some(received[excluded].payload_id.encoding_symbol_id());
+                return recovered;
+            }
+        }
+        block
+    }
+
+    #[cfg(feature = "security")]
+    // Re-encodes the decoded block, and compares each received symbol with the encoded one. If
+    // the received symbols are consistent with each other, they are all reproduced. Otherwise, no
+    // source block can reproduce all of them, so there is at least one mismatch.
+    fn check_consistency(&self, block: &[u8]) -> Option<bool> {
+        let received = self.received_source_symbols + self.repair_packets.len() as u32;
+        if received <= self.source_block_symbols {
+            return None;
+        }
+        // The received source symbols are part of the decoded block, so only the repair symbols
+        // need to be compared
+        let encoder = SourceBlockEncoder::new(self.source_block_id, self.symbol_size, block);
+        let num_extended_symbols = extended_source_block_symbols(self.source_block_symbols);
+        for packet in self.repair_packets.iter() {
+            let repair_symbol_id = packet.payload_id.encoding_symbol_id() - num_extended_symbols;
+            if encoder.repair_packets(repair_symbol_id, 1)[0].data() != packet.payload.as_bytes() {
+                return Some(true);
+            }
+        }
+        Some(false)
+    }
+
+    fn mark_decoded(&mut self) {
+        if !self.decoded {
+            let received = self.received_source_symbols + self.repair_packets.len() as u32;
+            self.extra_symbols_consumed = Some(received - self.source_block_symbols);
+        }
+        self.decoded = true;
+        self.retry_state = None;
+        self.quick_repair_symbols = vec![];
+    }
+
+    fn received_symbols(&self) -> u32 {
+        self.received_source_symbols + self.repair_packets.len() as u32
+    }
+
+    /// Returns the symbols received so far, source symbols first, in order of their ESI. These
+    /// can be added to another decoder for the same block, for example after a restart. See
+    /// `Decoder::write_salvage()`.
+    pub fn received_packets(&self) -> Vec<EncodingPacket> {
+        let mut received: Vec<EncodingPacket> = self
+            .source_symbols
+            .iter()
+            .enumerate()
+            .filter_map(|(esi, symbol)| {
+                symbol.as_ref().map(|symbol| {
+                    EncodingPacket::new(
+                        PayloadId::new(self.source_block_id, esi as u32),
+                        symbol.as_bytes().to_vec(),
+                    )
+                })
+            })
+            .collect();
+        received.extend(self.repair_packets.iter().map(|packet| {
+            EncodingPacket::new(
+                packet.payload_id.clone(),
+                packet.payload.as_bytes().to_vec(),
+            )
+        }));
+        received
+    }
+
+    /// Discards the symbols received so far, which frees their memory, while retaining the
+    /// settings of the decoder. The block must not have been decoded. See `Decoder::gc()`.
+    pub fn discard_received_symbols(&mut self) {
+        assert!(!self.decoded);
+        self.source_symbols = vec![];
+        self.repair_packets = vec![];
+        self.quick_repair_symbols = vec![];
+        self.received_source_symbols = 0;
+        self.received_esi = (self.source_block_symbols
+            ..extended_source_block_symbols(self.source_block_symbols))
+            .collect();
+        self.retry_state = None;
+        if self.rank_tracker.is_some() {
+            self.rank_tracker = None;
+            self.set_independence_tracking(true);
+        }
+    }
+
+    /// Returns the number of additional symbols which are needed to decode the block, assuming
+    /// that decoding succeeds with K symbols, or zero if it has been decoded. If decoding already
+    /// failed with K or more symbols, at least one more is needed.
+    pub fn symbols_needed(&self) -> u32 {
+        if self.decoded {
+            return 0;
+        }
+        let received = self.received_source_symbols + self.repair_packets.len() as u32;
+        max(1, self.source_block_symbols.saturating_sub(received))
+    }
+
+    /// Returns the number of additional linearly independent symbols which are needed to decode
+    /// the block, from the rank of the constraint matrix of the symbols received so far. Unlike
+    /// `symbols_needed()`, this accounts for received symbols which are linearly dependent on the
+    /// others, so zero means that decoding can succeed. This is as expensive as a dense decode,
+    /// and is intended for diagnostics.
+    pub fn rank_deficiency(&self) -> u32 {
+        if self.decoded {
+            return 0;
+        }
+        let num_extended_symbols = extended_source_block_symbols(self.source_block_symbols);
+        let mut encoded_indices: Vec<u32> = self
+            .source_symbols
+            .iter()
+            .enumerate()
+            .filter(|(_, symbol)| symbol.is_some())
+            .map(|(i, _)| i as u32)
+            .collect();
+        encoded_indices.extend(self.source_block_symbols..num_extended_symbols);
+        encoded_indices.extend(
+            self.repair_packets
+                .iter()
+                .map(|packet| packet.payload_id.encoding_symbol_id()),
+        );
+        // The constraint matrix can only be generated with at least K' symbols, so fill up with
+        // placeholder repair symbols, whose rows are then removed
+        let height = (num_ldpc_symbols(self.source_block_symbols)
+            + num_hdpc_symbols(self.source_block_symbols)) as usize
+            + encoded_indices.len();
+        let next_esi = encoded_indices
+            .iter()
+            .map(|&esi| esi + 1)
+            .fold(num_extended_symbols, max);
+        let placeholders = num_extended_symbols.saturating_sub(encoded_indices.len() as u32);
+        encoded_indices.extend(next_esi..(next_esi + placeholders));
+        let mut constraint_matrix = generate_constraint_matrix::<DenseOctetMatrix>(
+            self.source_block_symbols,
+            &encoded_indices,
+        );
+        let width = constraint_matrix.width();
+        constraint_matrix.resize(height, width);
+        (width - constraint_matrix.rank()) as u32
+    }
+
+    /// Returns the number of symbols beyond K (the number of source symbols) which were
+    /// received before the block could be decoded, or None if it has not been decoded yet.
+    pub fn extra_symbols_consumed(&self) -> Option<u32> {
+        self.extra_symbols_consumed
+    }
+
+    /// Returns the number of symbol additions and multiplications performed by the most recent
+    /// attempt to solve for the intermediate symbols, or None if no attempt has been made
+    pub fn last_solve_symbol_ops(&self) -> Option<(u32, u32)> {
+        self.last_solve_symbol_ops
+    }
+
+    pub fn memory_usage(&self) -> MemoryUsage {
+        let source_symbols = self
+            .source_symbols
+            .iter()
+            .flatten()
+            .map(|symbol| symbol.as_bytes().len())
+            .sum();
+        let repair_symbols = self
+            .repair_packets
+            .iter()
+            .map(|packet| packet.payload.as_bytes().len())
+            .chain(
+                self.quick_repair_symbols
+                    .iter()
+                    .map(|symbol| symbol.data.len()),
+            )
+            .sum();
+        let indices = self.received_esi.capacity() * std::mem::size_of::<u32>();
+        MemoryUsage::new(source_symbols, repair_symbols, 0, 0, indices)
+    }
+
+    /// Adds the given packets, and attempts to decode the block.
+    ///
+    /// The outcome of a decoding attempt depends only on the set of symbols received, not on the
+    /// order in which they arrived.
+    pub fn decode<T: IntoIterator<Item = EncodingPacket>>(
+        &mut self,
+        packets: T,
+    ) -> Option<Vec<u8>> {
+        for packet in packets {
             let (payload_id, payload) = packet.split();
-            let num_extended_symbols = extended_source_block_symbols(self.source_block_symbols);
-            if self.received_esi.insert(payload_id.encoding_symbol_id()) {
-                if payload_id.encoding_symbol_id() >= num_extended_symbols {
-                    // Repair symbol
-                    self.repair_packets
-                        .push(EncodingPacket::new(payload_id, payload));
-                } else {
-                    // Check that this is not an extended symbol (which aren't explicitly sent)
-                    assert!(payload_id.encoding_symbol_id() < self.source_block_symbols);
-                    // Source symbol
-                    self.source_symbols[payload_id.encoding_symbol_id() as usize] =
-                        Some(Symbol::new(payload));
-                    self.received_source_symbols += 1;
+            self.add_symbol(payload_id, Cow::Owned(payload));
+        }
+        self.try_decode()
+    }
+
+    /// Same as `decode()`, except that the scratch buffers for solving are taken from `ctx`, and
+    /// returned to it afterwards, so that repeatedly decoding blocks with the same symbol size
+    /// doesn't reallocate them. See `WorkContext`
+    pub fn decode_with_ctx<T: IntoIterator<Item = EncodingPacket>>(
+        &mut self,
+        packets: T,
+        ctx: &mut WorkContext,
+    ) -> Option<Vec<u8>> {
+        for packet in packets {
+            let (payload_id, payload) = packet.split();
+            self.add_symbol(payload_id, Cow::Owned(payload));
+        }
+        self.try_decode_with_ctx(ctx)
+    }
+
+    /// Same as `decode()`, except that payloads are only copied if they are retained, i.e. not
+    /// for duplicate packets.
+    pub fn decode_borrowed<'a, T: IntoIterator<Item = EncodingPacketRef<'a>>>(
+        &mut self,
+        packets: T,
+    ) -> Option<Vec<u8>> {
+        for packet in packets {
+            self.add_symbol(packet.payload_id, Cow::Borrowed(packet.data));
+        }
+        self.try_decode()
+    }
+
+    /// Same as `decode()`, except that payloads are retained by reference, rather than copied.
+    /// This allows an application which relays the received symbols, and also decodes them, to
+    /// keep a single copy of each payload in memory. Allocator hooks are not used for shared
+    /// payloads, and in lenient mode payloads whose length does not match the symbol size are
+    /// still copied.
+    pub fn decode_shared<T: IntoIterator<Item = (PayloadId, Arc<[u8]>)>>(
+        &mut self,
+        packets: T,
+    ) -> Option<Vec<u8>> {
+        for (payload_id, payload) in packets {
+            self.add_shared_symbol(payload_id, payload);
+        }
+        self.try_decode()
+    }
+
+    /// Same as `decode()`, except that symbols are identified by their internal symbol id (ISI),
+    /// rather than by a payload id. Source symbols have ISIs in [0, K), and repair symbols in
+    /// [K', ...). The padding symbols in [K, K') are implicitly zero, so symbols with those ISIs
+    /// are discarded, and counted by `rejected_symbols()`.
+    pub fn decode_by_isi<T: IntoIterator<Item = (u32, Vec<u8>)>>(
+        &mut self,
+        symbols: T,
+    ) -> Option<Vec<u8>> {
+        let num_extended_symbols = extended_source_block_symbols(self.source_block_symbols);
+        for (isi, symbol) in symbols {
+            if isi >= self.source_block_symbols && isi < num_extended_symbols {
+                self.rejected_symbols += 1;
+                continue;
+            }
+            // Repair symbols are numbered from K', so their ESI is equal to their ISI
+            let payload_id = PayloadId::new(self.source_block_id, isi);
+            self.add_symbol(payload_id, Cow::Owned(symbol));
+        }
+        self.try_decode()
+    }
+
+    /// Adds quick repair packets (see `SourceBlockEncoder::quick_repair_packets()`), and attempts
+    /// to decode the block. Each recovers a missing source symbol once all of the other source
+    /// symbols it was generated from have been received, including ones received later, or
+    /// recovered by other quick repair packets. Packets whose length does not match the symbol
+    /// size are rejected.
+    pub fn decode_quick_repair<T: IntoIterator<Item = EncodingPacket>>(
+        &mut self,
+        packets: T,
+    ) -> Option<Vec<u8>> {
+        for packet in packets {
+            let (payload_id, data) = packet.split();
+            assert_eq!(self.source_block_id, payload_id.source_block_number());
+            if data.len() != self.symbol_size as usize {
+                self.rejected_symbols += 1;
+                continue;
+            }
+            if !self.decoded {
+                self.quick_repair_symbols.push(QuickRepairSymbol {
+                    indices: quick_repair_indices(
+                        self.source_block_symbols,
+                        payload_id.encoding_symbol_id(),
+                    ),
+                    data,
+                });
+            }
+        }
+        self.try_decode()
+    }
+
+    // Subtracts the received source symbols from the quick repair symbols, and stores each one
+    // which is left with a single source symbol as that symbol. Repeats until none are recovered,
+    // since each recovered symbol may complete others.
+    fn peel_quick_repair_symbols(&mut self) {
+        loop {
+            let mut recovered = vec![];
+            let source_symbols = &self.source_symbols;
+            for symbol in self.quick_repair_symbols.iter_mut() {
+                let QuickRepairSymbol { indices, data } = symbol;
+                indices.retain(|&esi| match source_symbols.get(esi as usize) {
+                    Some(Some(source_symbol)) => {
+                        add_assign(data, source_symbol.as_bytes());
+                        false
+                    }
+                    _ => true,
+                });
+                if indices.len() == 1 {
+                    recovered.push((indices[0], std::mem::take(data)));
                 }
             }
+            self.quick_repair_symbols
+                .retain(|symbol| symbol.indices.len() > 1);
+            if recovered.is_empty() {
+                return;
+            }
+            for (esi, data) in recovered {
+                // Duplicates, if several recovered the same symbol, are discarded
+                let payload_id = PayloadId::new(self.source_block_id, esi);
+                self.add_symbol(payload_id, Cow::Owned(data));
+            }
+        }
+    }
+
+    fn add_symbol(&mut self, payload_id: PayloadId, payload: Cow<[u8]>) {
+        if !self.accept_symbol(&payload_id, payload.len()) {
+            return;
+        }
+        let mut payload = match self.allocator_hooks {
+            Some(ref hooks) => {
+                hooks.copy_symbol(self.source_block_id, &payload, self.symbol_size as usize)
+            }
+            None => payload.into_owned(),
+        };
+        if self.lenient {
+            payload.resize(self.symbol_size as usize, 0);
+        }
+        self.store_symbol(payload_id, ReceivedPayload::Owned(payload));
+    }
+
+    fn add_shared_symbol(&mut self, payload_id: PayloadId, payload: Arc<[u8]>) {
+        if !self.accept_symbol(&payload_id, payload.len()) {
+            return;
+        }
+        if self.lenient && payload.len() != self.symbol_size as usize {
+            let mut payload = payload.to_vec();
+            payload.resize(self.symbol_size as usize, 0);
+            self.store_symbol(payload_id, ReceivedPayload::Owned(payload));
+        } else {
+            self.store_symbol(payload_id, ReceivedPayload::Shared(payload));
+        }
+    }
+
+    // Returns false if the symbol is a duplicate, or is malformed. Symbols are received from the
+    // sender, so malformed ones are rejected rather than panicking: padding symbols are never
+    // sent, and symbols of the wrong length can only be repaired in lenient mode
+    fn accept_symbol(&mut self, payload_id: &PayloadId, length: usize) -> bool {
+        assert_eq!(self.source_block_id, payload_id.source_block_number());
+
+        let esi = payload_id.encoding_symbol_id();
+        let padding = esi >= self.source_block_symbols
+            && esi < extended_source_block_symbols(self.source_block_symbols);
+        if padding || (length != self.symbol_size as usize && !self.lenient) {
+            self.rejected_symbols += 1;
+            return false;
         }
 
+        // Duplicates are discarded without a warning, so that a sender which retransmits a
+        // symbol of the wrong length does not grow the warnings without bound
+        if !self.received_esi.insert(payload_id.encoding_symbol_id()) {
+            return false;
+        }
+        if self.lenient && length != self.symbol_size as usize {
+            self.warnings.push(DecodeWarning::SymbolLengthMismatch {
+                source_block_number: payload_id.source_block_number(),
+                encoding_symbol_id: payload_id.encoding_symbol_id(),
+                length,
+            });
+        }
+        true
+    }
+
+    fn store_symbol(&mut self, payload_id: PayloadId, payload: ReceivedPayload) {
+        let num_extended_symbols = extended_source_block_symbols(self.source_block_symbols);
+        if let Some(ref mut tracker) = self.rank_tracker {
+            tracker.add_row(&encoding_row(
+                self.source_block_symbols,
+                payload_id.encoding_symbol_id(),
+            ));
+        }
+        if payload_id.encoding_symbol_id() >= num_extended_symbols {
+            // Repair symbol. These are kept ordered by ESI, because their order determines
+            // the pivoting of the solver
+            let index = self
+                .repair_packets
+                .binary_search_by_key(&payload_id.encoding_symbol_id(), |packet| {
+                    packet.payload_id.encoding_symbol_id()
+                })
+                .unwrap_err();
+            if let Some(ref mut retry_state) = self.retry_state {
+                retry_state
+                    .pending_esis
+                    .push(payload_id.encoding_symbol_id());
+            }
+            self.repair_packets.insert(
+                index,
+                ReceivedRepairSymbol {
+                    payload_id,
+                    payload,
+                },
+            );
+        } else {
+            // Check that this is not an extended symbol (which aren't explicitly sent)
+            assert!(payload_id.encoding_symbol_id() < self.source_block_symbols);
+            // Source symbol
+            if let Some(ref mut retry_state) = self.retry_state {
+                retry_state
+                    .pending_esis
+                    .push(payload_id.encoding_symbol_id());
+            }
+            if self.source_symbols.is_empty() {
+                self.source_symbols = vec![None; self.source_block_symbols as usize];
+            }
+            self.source_symbols[payload_id.encoding_symbol_id() as usize] = Some(payload);
+            self.received_source_symbols += 1;
+        }
+    }
+
+    fn try_decode(&mut self) -> Option<Vec<u8>> {
+        self.try_decode_with_ctx(&mut WorkContext::new())
+    }
+
+    fn try_decode_with_ctx(&mut self, ctx: &mut WorkContext) -> Option<Vec<u8>> {
+        if !self.quick_repair_symbols.is_empty() {
+            self.peel_quick_repair_symbols();
+        }
         let num_extended_symbols = extended_source_block_symbols(self.source_block_symbols);
         if self.received_source_symbols == self.source_block_symbols {
-            let result = self
-                .source_symbols
-                .iter()
-                .cloned()
-                .map(|symbol| symbol.unwrap().into_bytes())
-                .flatten()
-                .collect();
+            let mut result = Vec::with_capacity(self.block_length());
+            for symbol in self.source_symbols.iter() {
+                result.extend_from_slice(symbol.as_ref().unwrap().as_bytes());
+            }
 
-            self.decoded = true;
+            #[cfg(feature = "security")]
+            let result = self.check_pollution(result);
+            self.mark_decoded();
             return Some(result);
         }
 
         if self.received_esi.len() as u32 >= num_extended_symbols {
-            let s = num_ldpc_symbols(self.source_block_symbols) as usize;
-            let h = num_hdpc_symbols(self.source_block_symbols) as usize;
-
-            let mut encoded_indices = vec![];
-            // See section 5.3.3.4.2. There are S + H zero symbols to start the D vector
-            let mut d = vec![Symbol::zero(self.symbol_size); s + h];
-            for (i, source) in self.source_symbols.iter().enumerate() {
-                if let Some(symbol) = source {
-                    encoded_indices.push(i as u32);
-                    d.push(symbol.clone());
+            let solver = match self.retry_state.take() {
+                Some(retry_state) => self.extend_solver(retry_state),
+                None => self.new_solver(ctx),
+            };
+            return match solver {
+                PartialSolver::Dense(solver) => {
+                    let retain = |solver| PartialSolver::Dense(Box::new(solver));
+                    self.try_pi_decode(*solver, retain, ctx)
                 }
-            }
+                PartialSolver::Sparse(solver) => {
+                    let retain = |solver| PartialSolver::Sparse(Box::new(solver));
+                    self.try_pi_decode(*solver, retain, ctx)
+                }
+            };
+        }
+        None
+    }
 
-            // Append the extended padding symbols
-            for i in self.source_block_symbols..num_extended_symbols {
-                encoded_indices.push(i);
-                d.push(Symbol::zero(self.symbol_size));
-            }
+    fn new_solver(&self, ctx: &mut WorkContext) -> PartialSolver {
+        let num_extended_symbols = extended_source_block_symbols(self.source_block_symbols);
+        let s = num_ldpc_symbols(self.source_block_symbols) as usize;
+        let h = num_hdpc_symbols(self.source_block_symbols) as usize;
+        let symbol_size = self.symbol_size as usize;
 
-            for repair_packet in self.repair_packets.iter() {
-                encoded_indices.push(repair_packet.payload_id.encoding_symbol_id());
-                d.push(Symbol::new(repair_packet.data.clone()));
+        let mut encoded_indices = ctx.take_indices();
+        let mut d = ctx.take_symbols();
+        // See section 5.3.3.4.2. There are S + H zero symbols to start the D vector
+        for _ in 0..(s + h) {
+            d.push(ctx.zero_symbol(symbol_size));
+        }
+        for (i, source) in self.source_symbols.iter().enumerate() {
+            if let Some(symbol) = source {
+                encoded_indices.push(i as u32);
+                d.push(ctx.symbol_from(symbol.as_bytes()));
             }
+        }
+
+        // Append the extended padding symbols
+        for i in self.source_block_symbols..num_extended_symbols {
+            encoded_indices.push(i);
+            d.push(ctx.zero_symbol(symbol_size));
+        }
+
+        for repair_packet in self.repair_packets.iter() {
+            encoded_indices.push(repair_packet.payload_id.encoding_symbol_id());
+            d.push(ctx.symbol_from(repair_packet.payload.as_bytes()));
+        }
+
+        let solver = if num_extended_symbols >= self.sparse_threshold {
+            let constraint_matrix = generate_constraint_matrix::<SparseOctetMatrix>(
+                self.source_block_symbols,
+                &encoded_indices,
+            );
+            PartialSolver::Sparse(Box::new(IntermediateSymbolDecoder::new(
+                constraint_matrix,
+                d,
+                self.source_block_symbols,
+            )))
+        } else {
+            let constraint_matrix = generate_constraint_matrix::<DenseOctetMatrix>(
+                self.source_block_symbols,
+                &encoded_indices,
+            );
+            PartialSolver::Dense(Box::new(IntermediateSymbolDecoder::new(
+                constraint_matrix,
+                d,
+                self.source_block_symbols,
+            )))
+        };
+        ctx.return_indices(encoded_indices);
+        solver
+    }
 
-            if extended_source_block_symbols(self.source_block_symbols) >= self.sparse_threshold {
-                let constraint_matrix = generate_constraint_matrix::<SparseOctetMatrix>(
-                    self.source_block_symbols,
-                    &encoded_indices,
+    // Adds the symbols received since the failed attempt that retry_state was saved from
+    fn extend_solver(&self, retry_state: RetryState) -> PartialSolver {
+        let RetryState {
+            mut solver,
+            pending_esis,
+        } = retry_state;
+        match solver {
+            PartialSolver::Dense(ref mut solver) => self.add_pending_rows(solver, &pending_esis),
+            PartialSolver::Sparse(ref mut solver) => self.add_pending_rows(solver, &pending_esis),
+        }
+        solver
+    }
+
+    fn add_pending_rows<T: OctetMatrix>(
+        &self,
+        solver: &mut IntermediateSymbolDecoder<T>,
+        pending_esis: &[u32],
+    ) {
+        let mut symbols = Vec::with_capacity(pending_esis.len());
+        for &esi in pending_esis.iter() {
+            if esi < self.source_block_symbols {
+                symbols.push(
+                    self.source_symbols[esi as usize]
+                        .as_ref()
+                        .unwrap()
+                        .to_symbol(),
                 );
-                return self.try_pi_decode(constraint_matrix, d);
             } else {
-                let constraint_matrix = generate_constraint_matrix::<DenseOctetMatrix>(
-                    self.source_block_symbols,
-                    &encoded_indices,
-                );
-                return self.try_pi_decode(constraint_matrix, d);
+                let index = self
+                    .repair_packets
+                    .binary_search_by_key(&esi, |packet| packet.payload_id.encoding_symbol_id())
+                    .unwrap();
+                symbols.push(self.repair_packets[index].payload.to_symbol());
             }
         }
-        None
+        let rows = generate_encoding_rows::<T>(self.source_block_symbols, pending_esis);
+        solver.add_rows(&rows, symbols);
     }
 
+    #[allow(clippy::too_many_arguments)]
     fn rebuild_source_symbol(
         &self,
         intermediate_symbols: &[Symbol],
@@ -275,8 +1738,9 @@ impl SourceBlockDecoder {
         pi_symbols: u32,
         sys_index: u32,
         p1: u32,
+        ctx: &mut WorkContext,
     ) -> Symbol {
-        let mut rebuilt = Symbol::zero(self.symbol_size);
+        let mut rebuilt = ctx.zero_symbol(self.symbol_size as usize);
         let tuple = intermediate_tuple(source_symbol_id, lt_symbols, sys_index, p1);
 
         for i in enc_indices(tuple, lt_symbols, pi_symbols, p1) {
@@ -288,12 +1752,26 @@ impl SourceBlockDecoder {
 
 #[cfg(test)]
 mod codec_tests {
+    use crate::base::ConfigError;
+    use crate::systematic_constants::extended_source_block_symbols;
+    use crate::systematic_constants::SYSTEMATIC_INDICES_AND_PARAMETERS;
+    use crate::systematic_constants::{num_intermediate_symbols, num_pi_symbols};
+    use crate::util::div_ceil;
+    use crate::AllocatorHooks;
+    use crate::DecodeWarning;
     use crate::Decoder;
     use crate::Encoder;
+    use crate::EncodingPacket;
+    use crate::ObjectTransmissionInformation;
+    use crate::PayloadId;
+    use crate::SolverLimit;
     use crate::SourceBlockDecoder;
     use crate::SourceBlockEncoder;
+    use crate::SubBlockDecoder;
     use rand::seq::SliceRandom;
     use rand::Rng;
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
 
     #[test]
     fn random_erasure_dense() {
@@ -337,6 +1815,124 @@ mod codec_tests {
         assert_eq!(result.unwrap(), data);
     }
 
+    #[test]
+    #[cfg(feature = "json")]
+    fn deserialize_without_new_fields() {
+        let data = vec![7; 1000];
+        let config = ObjectTransmissionInformation::new(data.len() as u64, 64, 1, 1, 8);
+        let mut decoder = Decoder::new(config.clone());
+        let packets = Encoder::new(&data, config).get_encoded_packets(0);
+        assert_eq!(decoder.decode(packets[0].clone()), None);
+        // As serialized by the first release, which had none of these fields
+        let mut value = serde_json::to_value(&decoder).unwrap();
+        let fields = value.as_object_mut().unwrap();
+        for field in [
+            "acceptance_window",
+            "lenient",
+            "strict",
+            "warnings",
+            "failed",
+            "block_timeout",
+        ]
+        .iter()
+        {
+            fields.remove(*field).unwrap();
+        }
+        let mut deserialized: Decoder = serde_json::from_value(value).unwrap();
+        assert_eq!(deserialized, decoder);
+        let mut result = None;
+        for packet in packets[1..].iter() {
+            result = deserialized.decode(packet.clone()).or(result);
+        }
+        assert_eq!(result, Some(data));
+    }
+
+    #[test]
+    fn sub_block_decoder() {
+        let mut data: Vec<u8> = vec![0; 10_000];
+        rand::thread_rng().fill(&mut data[..]);
+        let config = ObjectTransmissionInformation::new(data.len() as u64, 64, 2, 3, 8);
+        let encoder = Encoder::new(&data, config.clone());
+        let mut packets = encoder.get_encoded_packets(10);
+        packets.shuffle(&mut rand::thread_rng());
+        packets.truncate(packets.len() - 5);
+
+        // Each sub-block is decoded on its own, into its place in the object
+        let mut result = vec![0; data.len()];
+        let mut decoded = 0;
+        for source_block in 0..2 {
+            for sub_block in 0..3 {
+                let mut decoder = SubBlockDecoder::new(&config, source_block, sub_block).unwrap();
+                let range = decoder.object_range();
+                let sub_block_data = packets
+                    .iter()
+                    .filter_map(|packet| decoder.decode(packet))
+                    .next()
+                    .unwrap();
+                assert_eq!(sub_block_data.len() as u64, range.end - range.start);
+                result[(range.start as usize)..(range.end as usize)]
+                    .copy_from_slice(&sub_block_data);
+                decoded += sub_block_data.len();
+            }
+        }
+        assert_eq!(decoded, data.len());
+        assert_eq!(result, data);
+
+        let config = ObjectTransmissionInformation::new(1000, 64, 1, 9, 8);
+        assert_eq!(
+            SubBlockDecoder::new(&config, 0, 0),
+            Err(ConfigError::UnsupportedSubBlocks)
+        );
+
+        // The source block and sub-block numbers are checked, rather than trusted
+        let config = ObjectTransmissionInformation::new(10_000, 64, 2, 3, 8);
+        for &(source_block, sub_block) in [(2, 0), (0, 3), (255, 65535)].iter() {
+            assert_eq!(
+                SubBlockDecoder::new(&config, source_block, sub_block),
+                Err(ConfigError::NoSuchBlock {
+                    source_block_number: source_block,
+                    sub_block_number: sub_block
+                })
+            );
+        }
+
+        // Packets too short to contain the sub-symbol are rejected
+        let mut decoder = SubBlockDecoder::new(&config, 0, 2).unwrap();
+        let packet = &encoder.get_encoded_packets(0)[0];
+        let truncated =
+            EncodingPacket::new(packet.payload_id().clone(), packet.data()[..50].to_vec());
+        assert_eq!(decoder.decode(&truncated), None);
+        assert_eq!(decoder.decode(&truncated), None);
+        assert_eq!(decoder.rejected_packets(), 2);
+        assert_eq!(decoder.decode(packet), None);
+        assert_eq!(decoder.rejected_packets(), 2);
+
+        // Zero alignment and a single sub-block, which the wire format allows
+        let config =
+            ObjectTransmissionInformation::from_bytes([0, 0, 0, 3, 232, 0, 0, 64, 1, 0, 1, 0]);
+        assert_eq!(
+            SubBlockDecoder::new(&config, 0, 0),
+            Err(ConfigError::ZeroParameter)
+        );
+        assert_eq!(
+            Decoder::try_new(config).err(),
+            Some(ConfigError::ZeroParameter)
+        );
+
+        // A single sub-block is the whole symbol, even if it is not a multiple of the alignment
+        let mut data: Vec<u8> = vec![0; 1000];
+        rand::thread_rng().fill(&mut data[..]);
+        let config =
+            ObjectTransmissionInformation::from_bytes([0, 0, 0, 3, 232, 0, 0, 10, 1, 0, 1, 4]);
+        let encoder = Encoder::new(&data, config.clone());
+        let mut decoder = SubBlockDecoder::new(&config, 0, 0).unwrap();
+        let mut result = None;
+        for packet in encoder.get_encoded_packets(0) {
+            result = decoder.decode(&packet).or(result);
+        }
+        assert_eq!(result, Some(data));
+    }
+
     #[test]
     fn round_trip_dense() {
         round_trip(99_999, 100, false);
@@ -387,6 +1983,43 @@ mod codec_tests {
         }
     }
 
+    // Solver bugs have historically only shown up for specific values of K', so this decodes a
+    // block of every size in the systematic constants table. Run with `make test_extended`
+    #[test]
+    #[ignore]
+    fn every_extended_source_block_size() {
+        for &(block_size, _, _, _, _) in SYSTEMATIC_INDICES_AND_PARAMETERS.iter() {
+            extended_source_block_size_round_trip(block_size as usize);
+        }
+    }
+
+    fn extended_source_block_size_round_trip(source_symbols: usize) {
+        let symbol_size = 8;
+        let elements = symbol_size * source_symbols;
+        let mut data: Vec<u8> = vec![0; elements];
+        rand::thread_rng().fill(&mut data[..]);
+        let encoder = SourceBlockEncoder::new(1, symbol_size as u16, &data);
+
+        // No overhead: all the source symbols
+        let mut decoder = SourceBlockDecoder::new(1, symbol_size as u16, elements as u64);
+        assert_eq!(Some(data.clone()), decoder.decode(encoder.source_packets()));
+
+        // Minimal overhead: a tenth of the source symbols replaced by repair symbols, plus two.
+        // This can theoretically fail with ~1/256^3 probability
+        let mut packets = encoder.source_packets();
+        packets.shuffle(&mut rand::thread_rng());
+        let lost = div_ceil(source_symbols as u64, 10) as usize;
+        packets.truncate(source_symbols - lost);
+        packets.extend(encoder.repair_packets(0, lost as u32 + 2));
+        let mut decoder = SourceBlockDecoder::new(1, symbol_size as u16, elements as u64);
+        assert_eq!(
+            Some(data),
+            decoder.decode(packets),
+            "Failed to decode K'={}",
+            source_symbols
+        );
+    }
+
     #[test]
     fn repair_dense() {
         repair(99_999);
@@ -422,4 +2055,840 @@ mod codec_tests {
 
         assert_eq!(result.unwrap(), data);
     }
+
+    #[test]
+    fn large_and_small_source_blocks() {
+        let symbol_size = 8;
+        let elements = 100 * symbol_size as usize;
+        let mut data: Vec<u8> = vec![0; elements];
+        rand::thread_rng().fill(&mut data[..]);
+        // 100 symbols in 3 source blocks: a large block of 34 symbols, and two small blocks of 33
+        let config = ObjectTransmissionInformation::new(elements as u64, symbol_size, 3, 1, 8);
+        let encoder = Encoder::new(&data, config.clone());
+
+        let mut packets = encoder.get_encoded_packets(2);
+        let mut block_numbers: Vec<u8> = packets
+            .iter()
+            .map(|packet| packet.payload_id().source_block_number())
+            .collect();
+        block_numbers.dedup();
+        assert_eq!(block_numbers, vec![0, 1, 2]);
+
+        // Lose a source symbol of each block, so that every block is decoded from a repair symbol
+        packets.retain(|packet| packet.payload_id().encoding_symbol_id() != 0);
+        packets.shuffle(&mut rand::thread_rng());
+        let mut decoder = Decoder::new(config);
+        let mut result = None;
+        for packet in packets {
+            result = decoder.decode(packet);
+        }
+        assert_eq!(result, Some(data));
+    }
+
+    #[test]
+    fn fully_specified() {
+        let symbol_size = 8;
+        let elements = 100 * symbol_size;
+        let mut data: Vec<u8> = vec![0; elements];
+        rand::thread_rng().fill(&mut data[..]);
+        let encoder = SourceBlockEncoder::new(0, symbol_size as u16, &data);
+        let mut decoder = SourceBlockDecoder::new(0, symbol_size as u16, elements as u64);
+        decoder.set_independence_tracking(true);
+
+        let mut packets = encoder.source_packets();
+        packets.truncate(90);
+        // Enable tracking after some symbols have already been received
+        let mut untracked = SourceBlockDecoder::new(0, symbol_size as u16, elements as u64);
+        assert_eq!(None, untracked.decode(packets.clone()));
+        untracked.set_independence_tracking(true);
+        assert_eq!(None, decoder.decode(packets));
+        assert!(!decoder.fully_specified());
+        assert!(!untracked.fully_specified());
+
+        let mut result = None;
+        for packet in encoder.repair_packets(0, 20) {
+            // Not being fully specified is exact, but the converse may rarely not be
+            if !decoder.fully_specified() {
+                assert!(decoder.rank_deficiency() > 0);
+            }
+            result = decoder.decode(vec![packet]);
+            if result.is_some() {
+                break;
+            }
+        }
+        assert_eq!(Some(data), result);
+        assert!(decoder.fully_specified());
+    }
+
+    #[test]
+    fn rank_deficiency() {
+        let symbol_size = 8;
+        let elements = 20 * symbol_size;
+        let mut data: Vec<u8> = vec![0; elements];
+        rand::thread_rng().fill(&mut data[..]);
+        let encoder = SourceBlockEncoder::new(0, symbol_size as u16, &data);
+        let mut decoder = SourceBlockDecoder::new(0, symbol_size as u16, elements as u64);
+        assert_eq!(20, decoder.rank_deficiency());
+
+        let mut packets = encoder.source_packets();
+        packets.truncate(15);
+        assert_eq!(None, decoder.decode(packets));
+        assert_eq!(5, decoder.rank_deficiency());
+        assert_eq!(5, decoder.symbols_needed());
+
+        let mut result = None;
+        for packet in encoder.repair_packets(0, 10) {
+            if decoder.rank_deficiency() == 0 {
+                break;
+            }
+            result = decoder.decode(vec![packet]);
+        }
+        assert_eq!(Some(data), result);
+        assert_eq!(0, decoder.rank_deficiency());
+    }
+
+    #[test]
+    fn strict_mode() {
+        let mut data: Vec<u8> = vec![0; 1000];
+        rand::thread_rng().fill(&mut data[..]);
+        let encoder = Encoder::with_defaults(&data, 64);
+        let mut decoder = Decoder::try_new_strict(encoder.get_config()).unwrap();
+
+        // Wrong length, and the ESI of a padding symbol
+        let mut truncated = encoder.get_encoded_packets(0)[0].clone();
+        truncated.data.pop();
+        let padding_esi = PayloadId::new(0, 1000 / 64 + 1);
+        assert_eq!(None, decoder.decode(truncated));
+        assert_eq!(
+            None,
+            decoder.decode(EncodingPacket::new(padding_esi, vec![0; 64]))
+        );
+        assert_eq!(2, decoder.rejected_packets());
+
+        let mut result = None;
+        for packet in encoder.get_encoded_packets(0) {
+            result = decoder.decode(packet);
+        }
+        assert_eq!(Some(data), result);
+        assert_eq!(2, decoder.rejected_packets());
+
+        // Symbol size is not a multiple of the alignment
+        let config =
+            ObjectTransmissionInformation::from_bytes([0, 0, 0, 3, 232, 0, 0, 10, 1, 0, 1, 4]);
+        assert_eq!(
+            Err(ConfigError::NonCompliant {
+                parameter: "Al",
+                source_block_symbols: 100,
+            }),
+            Decoder::try_new_strict(config.clone())
+        );
+        assert!(Decoder::try_new(config).is_ok());
+
+        // More source blocks than symbols
+        let config = ObjectTransmissionInformation::new(64, 64, 2, 1, 8);
+        assert_eq!(
+            Err(ConfigError::NonCompliant {
+                parameter: "Z",
+                source_block_symbols: 0,
+            }),
+            Decoder::try_new_strict(config)
+        );
+    }
+
+    #[test]
+    fn shared_payloads() {
+        let mut data: Vec<u8> = vec![0; 10_000];
+        rand::thread_rng().fill(&mut data[..]);
+        let encoder = Encoder::with_defaults(&data, 64);
+        let mut packets: Vec<(PayloadId, Arc<[u8]>)> = encoder
+            .get_encoded_packets(15)
+            .into_iter()
+            .map(|packet| {
+                let (payload_id, payload) = packet.split();
+                (payload_id, Arc::from(payload))
+            })
+            .collect();
+        packets.shuffle(&mut rand::thread_rng());
+        packets.truncate(packets.len() - 10);
+
+        let mut decoder = Decoder::new(encoder.get_config());
+        let (payload_id, payload) = packets.pop().unwrap();
+        assert_eq!(None, decoder.decode_shared(payload_id, payload.clone()));
+        // The decoder retains the same allocation, rather than a copy
+        assert_eq!(2, Arc::strong_count(&payload));
+
+        let mut result = None;
+        for (payload_id, payload) in packets {
+            result = decoder.decode_shared(payload_id, payload);
+        }
+        assert_eq!(Some(data), result);
+    }
+
+    #[test]
+    fn acceptance_window() {
+        let symbol_size = 8;
+        let elements = 20 * symbol_size;
+        let mut data: Vec<u8> = vec![0; elements];
+        for byte in data.iter_mut() {
+            *byte = rand::thread_rng().gen();
+        }
+
+        // Two blocks of 10 symbols each
+        let config =
+            ObjectTransmissionInformation::new(elements as u64, symbol_size as u16, 2, 1, 8);
+        let first = SourceBlockEncoder::new(0, symbol_size as u16, &data[..elements / 2]);
+        let second = SourceBlockEncoder::new(1, symbol_size as u16, &data[elements / 2..]);
+
+        let mut decoder = Decoder::new(config);
+        decoder.set_acceptance_window(1);
+        // The second block is outside the window, so these are discarded
+        for packet in second.source_packets() {
+            assert_eq!(decoder.decode(packet), None);
+        }
+        for packet in first.source_packets() {
+            assert_eq!(decoder.decode(packet), None);
+        }
+        assert_eq!(decoder.extra_symbols_consumed(), vec![Some(0), None]);
+
+        // Now that the first block is decoded, the window has advanced
+        let mut result = None;
+        for packet in second.source_packets() {
+            result = decoder.decode(packet);
+        }
+        assert_eq!(result.unwrap(), data);
+    }
+
+    #[test]
+    fn stale_block_gc() {
+        let data: Vec<u8> = (0..2048).map(|i| i as u8).collect();
+        let config = ObjectTransmissionInformation::new(data.len() as u64, 64, 2, 1, 8);
+        let encoder = Encoder::new(&data, config.clone());
+        let mut decoder = Decoder::new(config);
+        // Disabled by default
+        assert_eq!(decoder.gc(), 0);
+
+        decoder.set_block_timeout(Some(Duration::from_secs(3600)));
+        let blocks = encoder.get_encoded_packets_by_block(0);
+        decoder.add_new_packets(blocks[0].clone());
+        decoder.add_new_packet(blocks[1][0].clone());
+        assert_eq!(decoder.gc(), 0);
+        let usage = decoder.memory_usage().source_symbols();
+
+        // Every block which has not been decoded has gone too long without progress
+        decoder.set_block_timeout(Some(Duration::from_secs(0)));
+        assert_eq!(decoder.gc(), 1);
+        assert_eq!(decoder.memory_usage().source_symbols(), usage - 64);
+        assert_eq!(decoder.ack_frame().symbols_needed(1), 16);
+        assert_eq!(decoder.gc(), 0);
+
+        // The discarded block is decoded from packets received afterwards
+        let mut result = None;
+        for packet in blocks[1].iter() {
+            result = decoder.decode(packet.clone());
+        }
+        assert_eq!(result.unwrap(), data);
+    }
+
+    #[test]
+    fn quick_repair() {
+        let data: Vec<u8> = (0..6400).map(|i| (i * 13) as u8).collect();
+        let encoder = SourceBlockEncoder::new(0, 64, &data);
+        let lost = [3, 50, 51];
+        let source_packets: Vec<EncodingPacket> = encoder
+            .source_packets()
+            .into_iter()
+            .filter(|packet| !lost.contains(&packet.payload_id().encoding_symbol_id()))
+            .collect();
+
+        // The quick repair packets recover the lost source symbols, whether they arrive before or
+        // after the source packets
+        let mut decoder = SourceBlockDecoder::new(0, 64, data.len() as u64);
+        assert_eq!(
+            decoder.decode_quick_repair(encoder.quick_repair_packets(0, 30)),
+            None
+        );
+        assert_eq!(decoder.decode(source_packets.clone()).unwrap(), data);
+        assert_eq!(decoder.extra_symbols_consumed(), Some(0));
+
+        let mut decoder = SourceBlockDecoder::new(0, 64, data.len() as u64);
+        assert_eq!(decoder.decode(source_packets), None);
+        let result = decoder.decode_quick_repair(encoder.quick_repair_packets(0, 30));
+        assert_eq!(result.unwrap(), data);
+    }
+
+    #[test]
+    fn salvage() {
+        let data: Vec<u8> = (0..2048).map(|i| (i * 7) as u8).collect();
+        let config = ObjectTransmissionInformation::new(data.len() as u64, 64, 2, 2, 8);
+        let encoder = Encoder::new(&data, config.clone());
+        let blocks = encoder.get_encoded_packets_by_block(4);
+
+        // The first session decodes the first block, and receives half of the second
+        let mut decoder = Decoder::new(config.clone());
+        decoder.add_new_packets(blocks[0].iter().skip(2).cloned());
+        decoder.add_new_packets(blocks[1].iter().step_by(2).cloned());
+        assert_eq!(decoder.get_result(), None);
+        let mut salvage = vec![];
+        decoder.write_salvage(&mut salvage).unwrap();
+
+        // A later session resumes from the salvaged state, and adds the rest
+        let mut resumed = Decoder::new(config);
+        assert_eq!(resumed.decode_from(&salvage[..]).unwrap(), None);
+        assert_eq!(resumed.ack_frame(), decoder.ack_frame());
+        let mut result = None;
+        for packet in blocks[1].iter().skip(1).step_by(2) {
+            result = resumed.decode(packet.clone());
+        }
+        assert_eq!(result.unwrap(), data);
+    }
+
+    #[test]
+    fn memory_usage() {
+        let elements = 1024;
+        let mut data: Vec<u8> = vec![0; elements];
+        for byte in data.iter_mut() {
+            *byte = rand::thread_rng().gen();
+        }
+
+        let encoder = Encoder::with_defaults(&data, 64);
+        let usage = encoder.memory_usage();
+        assert_eq!(usage.source_symbols(), elements);
+        assert!(usage.intermediate_symbols() > elements);
+        assert_eq!(usage.repair_symbols(), 0);
+
+        let mut decoder = Decoder::new(encoder.get_config());
+        let mut packets = encoder.get_encoded_packets(2);
+        // Drop one source packet, and buffer a repair packet
+        packets.remove(0);
+        let repair = packets.pop().unwrap();
+        decoder.add_new_packet(repair);
+        decoder.add_new_packet(packets.pop().unwrap());
+        let usage = decoder.memory_usage();
+        assert_eq!(usage.repair_symbols(), 128);
+        assert_eq!(usage.decoded_data(), 0);
+
+        for packet in packets {
+            decoder.add_new_packet(packet);
+        }
+        assert_eq!(decoder.get_result().unwrap(), data);
+        assert_eq!(decoder.memory_usage().decoded_data(), elements);
+    }
+
+    #[test]
+    fn lenient() {
+        let elements = 1000;
+        let mut data: Vec<u8> = vec![0; elements];
+        for byte in data.iter_mut() {
+            *byte = rand::thread_rng().gen();
+        }
+        data[elements - 1] = 1;
+        let encoder = Encoder::with_defaults(&data, 64);
+
+        // The peer truncated the last symbol, rather than padding it, and declared a transfer
+        // length which is one byte too short
+        let config = encoder.get_config();
+        let config = ObjectTransmissionInformation::new(
+            config.transfer_length() - 1,
+            config.symbol_size(),
+            config.source_blocks(),
+            config.sub_blocks(),
+            config.symbol_alignment(),
+        );
+        let mut decoder = Decoder::new(config);
+        decoder.set_lenient(true);
+
+        let mut result = None;
+        // The truncated symbol arrives first, and is retransmitted, which only warns once
+        for packet in encoder.get_encoded_packets(0).into_iter().rev() {
+            let (payload_id, mut payload) = packet.split();
+            let mut copies = 1;
+            if payload_id.encoding_symbol_id() == 15 {
+                payload.truncate(elements % 64);
+                copies = 3;
+            }
+            for _ in 0..copies {
+                result = decoder.decode(EncodingPacket::new(payload_id.clone(), payload.clone()));
+            }
+        }
+        assert_eq!(result.unwrap()[..], data[..elements - 1]);
+        assert_eq!(
+            decoder.warnings(),
+            vec![
+                DecodeWarning::SymbolLengthMismatch {
+                    source_block_number: 0,
+                    encoding_symbol_id: 15,
+                    length: elements % 64
+                },
+                DecodeWarning::NonZeroPadding { non_zero_bytes: 1 }
+            ]
+        );
+    }
+
+    #[test]
+    fn add_new_packets() {
+        let blocks = 8;
+        let block_length = 200 * 64;
+        let mut data: Vec<u8> = vec![0; blocks * block_length];
+        for byte in data.iter_mut() {
+            *byte = rand::thread_rng().gen();
+        }
+        let mut packets = vec![];
+        for (i, block) in data.chunks(block_length).enumerate() {
+            let encoder = SourceBlockEncoder::new(i as u8, 64, block);
+            packets.extend(encoder.source_packets());
+            packets.extend(encoder.repair_packets(0, 20));
+        }
+        packets.shuffle(&mut rand::thread_rng());
+        // Drop some packets from every block, so that repair symbols are needed
+        packets.truncate(packets.len() - 80);
+
+        let config = ObjectTransmissionInformation::new(data.len() as u64, 64, blocks as u8, 1, 8);
+        let mut decoder = Decoder::new(config);
+        for batch in packets.chunks(200) {
+            assert_eq!(None, decoder.get_result());
+            decoder.add_new_packets(batch.to_vec());
+        }
+        assert_eq!(decoder.get_result().unwrap(), data);
+    }
+
+    #[test]
+    fn allocator_hooks() {
+        #[derive(Default)]
+        struct CountingHooks {
+            allocated: Mutex<Vec<u8>>,
+        }
+
+        impl AllocatorHooks for CountingHooks {
+            fn allocate_symbol(&self, source_block_number: u8, capacity: usize) -> Vec<u8> {
+                self.allocated.lock().unwrap().push(source_block_number);
+                Vec::with_capacity(capacity)
+            }
+        }
+
+        let mut data: Vec<u8> = vec![0; 10_000];
+        for byte in data.iter_mut() {
+            *byte = rand::thread_rng().gen();
+        }
+        let encoder = Encoder::with_defaults(&data, 500);
+        let hooks = Arc::new(CountingHooks::default());
+        let mut decoder = Decoder::new(encoder.get_config());
+        decoder.set_allocator_hooks(hooks.clone());
+
+        let packets = encoder.get_encoded_packets(1);
+        let mut result = None;
+        for packet in packets.iter().skip(1) {
+            result = decoder.decode(packet.clone());
+        }
+        assert_eq!(result.unwrap(), data);
+        assert_eq!(*hooks.allocated.lock().unwrap(), vec![0; packets.len() - 1]);
+    }
+
+    #[test]
+    #[cfg(feature = "security")]
+    fn pollution_detection() {
+        let elements = 10 * 1024;
+        let symbol_size = 64;
+        let source_symbols = elements / symbol_size;
+        let mut data: Vec<u8> = vec![0; elements];
+        for byte in data.iter_mut() {
+            *byte = rand::thread_rng().gen();
+        }
+        let encoder = SourceBlockEncoder::new(1, symbol_size as u16, &data);
+
+        let decode = |packets: Vec<EncodingPacket>| {
+            let mut decoder = SourceBlockDecoder::new(1, symbol_size as u16, elements as u64);
+            decoder.set_pollution_detection(true);
+            let result = decoder.decode(packets);
+            (result, decoder.pollution_detected())
+        };
+
+        let mut packets = encoder.source_packets();
+        packets.truncate(source_symbols - 3);
+        packets.extend(encoder.repair_packets(0, 6));
+        let (result, detected) = decode(packets.clone());
+        assert_eq!(result.unwrap(), data);
+        assert_eq!(detected, Some(false));
+
+        // Corrupting any one symbol is detected, whether or not it was needed to decode
+        for &index in [0, source_symbols - 4, source_symbols].iter() {
+            let mut polluted = packets.clone();
+            polluted[index].data[7] ^= 0x40;
+            assert_eq!(decode(polluted).1, Some(true));
+        }
+
+        // Too few symbols to check
+        let mut packets = encoder.source_packets();
+        packets.truncate(source_symbols - 3);
+        packets.extend(encoder.repair_packets(0, 3));
+        packets[0].data[7] ^= 0x40;
+        let (result, detected) = decode(packets);
+        if result.is_some() {
+            assert_eq!(detected, None);
+        }
+
+        // All source symbols, plus a corrupted repair symbol
+        let mut packets = encoder.source_packets();
+        let mut repair = encoder.repair_packets(0, 1);
+        repair[0].data[0] ^= 1;
+        packets.extend(repair);
+        let (result, detected) = decode(packets);
+        assert_eq!(result.unwrap(), data);
+        assert_eq!(detected, Some(true));
+    }
+
+    #[test]
+    #[cfg(feature = "security")]
+    fn pollution_recovery() {
+        let elements = 2 * 1024;
+        let symbol_size = 64;
+        let source_symbols = elements / symbol_size;
+        let mut data: Vec<u8> = vec![0; elements];
+        for byte in data.iter_mut() {
+            *byte = rand::thread_rng().gen();
+        }
+        let encoder = SourceBlockEncoder::new(1, symbol_size as u16, &data);
+
+        let mut packets = encoder.source_packets();
+        packets.truncate(source_symbols - 3);
+        packets.extend(encoder.repair_packets(0, 6));
+        for &index in [5, source_symbols + 1].iter() {
+            let mut polluted = packets.clone();
+            polluted[index].data[3] ^= 0x10;
+            let esi = polluted[index].payload_id.encoding_symbol_id();
+
+            let mut decoder = SourceBlockDecoder::new(1, symbol_size as u16, elements as u64);
+            decoder.set_pollution_detection(true);
+            decoder.set_pollution_recovery(packets.len() as u32);
+            assert_eq!(decoder.decode(polluted.clone()).unwrap(), data);
+            assert_eq!(decoder.pollution_detected(), Some(true));
+            assert_eq!(decoder.excluded_symbol(), Some(esi));
+
+            // Not enough attempts to reach the corrupted symbol
+            let mut decoder = SourceBlockDecoder::new(1, symbol_size as u16, elements as u64);
+            decoder.set_pollution_detection(true);
+            decoder.set_pollution_recovery(index as u32);
+            decoder.decode(polluted);
+            assert_eq!(decoder.pollution_detected(), Some(true));
+            assert_eq!(decoder.excluded_symbol(), None);
+        }
+    }
+
+    #[test]
+    fn complexity_limit() {
+        let elements = 10 * 1024;
+        let symbol_size = 64;
+        let mut data: Vec<u8> = vec![0; elements];
+        for byte in data.iter_mut() {
+            *byte = rand::thread_rng().gen();
+        }
+        let encoder = SourceBlockEncoder::new(1, symbol_size as u16, &data);
+        let mut packets = encoder.source_packets();
+        packets.truncate(packets.len() - 10);
+        packets.extend(encoder.repair_packets(0, 12));
+
+        let mut decoder = SourceBlockDecoder::new(1, symbol_size as u16, elements as u64);
+        decoder.set_complexity_limit(1);
+        assert_eq!(decoder.decode(packets.clone()).unwrap(), data);
+        assert!(!decoder.complexity_limit_exceeded());
+
+        // Ten times more repair symbols than needed, which are all eliminated, cost several times
+        // the symbol additions of a typical decode
+        let symbols = elements / symbol_size;
+        let packets = encoder.repair_packets(0, 10 * symbols as u32);
+        let mut decoder = SourceBlockDecoder::new(1, symbol_size as u16, elements as u64);
+        decoder.set_complexity_limit(1);
+        assert_eq!(decoder.decode(packets), None);
+        assert!(decoder.complexity_limit_exceeded());
+        assert_eq!(decoder.exceeded_limit(), Some(SolverLimit::SymbolAddOps));
+
+        // The block is retried when another symbol arrives, which succeeds within a higher limit
+        decoder.set_complexity_limit(100);
+        let packet = encoder.source_packets().remove(0);
+        assert_eq!(decoder.decode(vec![packet]).unwrap(), data);
+        assert!(!decoder.complexity_limit_exceeded());
+        assert_eq!(decoder.exceeded_limit(), None);
+    }
+
+    #[test]
+    fn max_density() {
+        let elements = 10 * 1024;
+        let symbol_size = 64;
+        let data: Vec<u8> = (0..elements).map(|i| i as u8).collect();
+        let encoder = SourceBlockEncoder::new(1, symbol_size as u16, &data);
+        // Only repair symbols, so that the first phase must inactivate columns
+        let packets = encoder.repair_packets(0, 170);
+
+        let mut decoder = SourceBlockDecoder::new(1, symbol_size as u16, elements as u64);
+        decoder.set_max_density(0);
+        assert_eq!(decoder.decode(packets.clone()), None);
+        assert_eq!(decoder.exceeded_limit(), Some(SolverLimit::Density));
+        assert!(!decoder.complexity_limit_exceeded());
+
+        let mut decoder = SourceBlockDecoder::new(1, symbol_size as u16, elements as u64);
+        decoder.set_max_density(10);
+        assert_eq!(decoder.decode(packets).unwrap(), data);
+        assert_eq!(decoder.exceeded_limit(), None);
+    }
+
+    #[test]
+    fn record_permutations() {
+        let elements = 10 * 1024;
+        let symbol_size = 64;
+        let data: Vec<u8> = (0..elements).map(|i| i as u8).collect();
+        let encoder = SourceBlockEncoder::new(1, symbol_size as u16, &data);
+        let mut packets = encoder.source_packets();
+        packets.truncate(packets.len() - 10);
+        packets.extend(encoder.repair_packets(0, 12));
+
+        let mut decoder = SourceBlockDecoder::new(1, symbol_size as u16, elements as u64);
+        decoder.set_record_permutations(true);
+        assert!(decoder.permutations().is_none());
+        assert_eq!(decoder.decode(packets).unwrap(), data);
+        let permutations = decoder.permutations().unwrap();
+        let intermediate_symbols = num_intermediate_symbols(160) as usize;
+        assert_eq!(permutations.column_order().len(), intermediate_symbols);
+        assert!(permutations.inactivated_columns() >= num_pi_symbols(160) as usize);
+        assert_eq!(
+            permutations.first_phase_pivots().len() + permutations.inactivated_columns(),
+            intermediate_symbols
+        );
+    }
+
+    #[cfg(feature = "failure_injection")]
+    #[test]
+    fn injected_failures() {
+        use crate::InjectedFailure;
+
+        let elements = 10 * 1024;
+        let symbol_size = 64;
+        let mut data: Vec<u8> = vec![0; elements];
+        for byte in data.iter_mut() {
+            *byte = rand::thread_rng().gen();
+        }
+        let encoder = Encoder::with_defaults(&data, symbol_size);
+        let mut packets = encoder.get_encoded_packets(20);
+        packets.remove(0);
+
+        for &failure in [
+            InjectedFailure::PivotSelection,
+            InjectedFailure::SingularMatrix,
+            InjectedFailure::Allocation,
+        ]
+        .iter()
+        {
+            let mut decoder = Decoder::new(encoder.get_config());
+            decoder.set_injected_failure(Some(failure));
+            for packet in packets.iter().take(packets.len() - 1) {
+                assert_eq!(decoder.decode(packet.clone()), None);
+            }
+            // The application falls back to requesting more symbols
+            decoder.set_injected_failure(None);
+            assert_eq!(
+                decoder.decode(packets.last().unwrap().clone()),
+                Some(data.clone())
+            );
+        }
+    }
+
+    #[test]
+    fn decode_by_isi() {
+        // K = 150, which is extended with padding symbols to K' = 153
+        let elements = 150 * 64;
+        let symbol_size = 64;
+        let source_symbols = (elements / symbol_size) as u32;
+        let mut data: Vec<u8> = vec![0; elements];
+        for byte in data.iter_mut() {
+            *byte = rand::thread_rng().gen();
+        }
+        let encoder = SourceBlockEncoder::new(1, symbol_size as u16, &data);
+        let k_prime = extended_source_block_symbols(source_symbols);
+        let isis = (5..source_symbols).chain(k_prime..(k_prime + 7));
+
+        let mut decoder = SourceBlockDecoder::new(1, symbol_size as u16, elements as u64);
+        // A padding ISI is discarded
+        let padding = (source_symbols, vec![1; symbol_size]);
+        assert_eq!(decoder.decode_by_isi(vec![padding]), None);
+        assert_eq!(decoder.rejected_symbols(), 1);
+        let result = decoder.decode_by_isi(isis.map(|isi| (isi, encoder.encode_isi(isi))));
+        assert_eq!(result.unwrap(), data);
+    }
+
+    #[test]
+    fn lazy_allocation() {
+        // The largest object which can be decoded, as claimed by an untrusted sender
+        let config = ObjectTransmissionInformation::new(255 * 56403 * 0xFFFF, 0xFFFF, 255, 1, 1);
+        let mut decoder = Decoder::try_new(config).unwrap();
+        assert!(decoder
+            .block_decoders
+            .iter()
+            .all(|block| block.source_symbols.is_empty()));
+
+        let packet = EncodingPacket::new(PayloadId::new(3, 0), vec![1; 0xFFFF]);
+        assert_eq!(decoder.decode(packet), None);
+        assert_eq!(decoder.block_decoders[3].source_symbols.len(), 56403);
+        assert!(decoder.block_decoders[4].source_symbols.is_empty());
+    }
+
+    #[test]
+    fn decode_borrowed() {
+        let mut data: Vec<u8> = vec![0; 10_000];
+        for byte in data.iter_mut() {
+            *byte = rand::thread_rng().gen();
+        }
+        let encoder = Encoder::with_defaults(&data, 500);
+        let packets: Vec<Vec<u8>> = encoder
+            .get_encoded_packets(10)
+            .iter()
+            .skip(5)
+            .map(|packet| packet.serialize())
+            .collect();
+
+        let mut decoder = Decoder::new(encoder.get_config());
+        let mut result = None;
+        for packet in packets.iter() {
+            // Duplicates are discarded
+            decoder.add_new_packet_borrowed(EncodingPacket::deserialize_borrowed(packet));
+            result = decoder.decode_borrowed(EncodingPacket::deserialize_borrowed(packet));
+            if result.is_some() {
+                break;
+            }
+        }
+        assert_eq!(result.unwrap(), data);
+    }
+
+    #[test]
+    fn non_zero_padding() {
+        let data = vec![1; 1000];
+        let encoder = Encoder::with_defaults(&data, 64);
+        let config = encoder.get_config();
+        // One byte too short
+        let config = ObjectTransmissionInformation::new(
+            config.transfer_length() - 1,
+            config.symbol_size(),
+            config.source_blocks(),
+            config.sub_blocks(),
+            config.symbol_alignment(),
+        );
+        let mut decoder = Decoder::new(config);
+        for packet in encoder.get_encoded_packets(0) {
+            assert_eq!(decoder.decode(packet), None);
+        }
+        assert!(decoder.failed());
+        assert_eq!(decoder.get_result(), None);
+        assert_eq!(
+            decoder.warnings(),
+            vec![DecodeWarning::NonZeroPadding { non_zero_bytes: 1 }]
+        );
+    }
+
+    #[test]
+    fn arrival_order_independent() {
+        let elements = 10 * 1024;
+        let symbol_size = 64;
+        let source_symbols = elements / symbol_size;
+        let mut data: Vec<u8> = vec![0; elements];
+        for byte in data.iter_mut() {
+            *byte = rand::thread_rng().gen();
+        }
+        let encoder = SourceBlockEncoder::new(1, symbol_size as u16, &data);
+
+        for _ in 0..10 {
+            // Exactly K symbols, so that decoding occasionally fails
+            let mut packets = encoder.source_packets();
+            packets.shuffle(&mut rand::thread_rng());
+            let lost = rand::thread_rng().gen_range(1, source_symbols);
+            packets.truncate(source_symbols - lost);
+            packets.extend(encoder.repair_packets(0, lost as u32));
+
+            let mut expected = None;
+            for _ in 0..5 {
+                packets.shuffle(&mut rand::thread_rng());
+                let mut decoder = SourceBlockDecoder::new(1, symbol_size as u16, elements as u64);
+                let mut result = None;
+                for packet in packets.iter() {
+                    result = decoder.decode(vec![packet.clone()]);
+                }
+                let outcome = (result, decoder.last_solve_symbol_ops());
+                assert_eq!(expected.get_or_insert(outcome.clone()), &outcome);
+            }
+        }
+    }
+
+    #[test]
+    fn min_overhead_mode_dense() {
+        min_overhead_mode(99_999);
+    }
+
+    #[test]
+    fn min_overhead_mode_sparse() {
+        min_overhead_mode(0);
+    }
+
+    fn min_overhead_mode(sparse_threshold: u32) {
+        let elements = 80;
+        let symbol_size = 8;
+        let source_symbols = elements / symbol_size;
+        let mut data: Vec<u8> = vec![0; elements];
+        for byte in data.iter_mut() {
+            *byte = rand::thread_rng().gen();
+        }
+        let encoder = SourceBlockEncoder::new(1, symbol_size as u16, &data);
+
+        // Whether a decoding attempt succeeds depends only on the ESIs received, so search for a
+        // set of exactly K symbols which fails to decode, to exercise the retry
+        let mut retried = 0;
+        for start in 0..200 {
+            let mut decoder = SourceBlockDecoder::new(1, symbol_size as u16, elements as u64);
+            decoder.set_sparse_threshold(sparse_threshold);
+            decoder.set_min_overhead_mode(true);
+            let mut packets = encoder.source_packets();
+            packets.truncate(source_symbols - 3);
+            packets.extend(encoder.repair_packets(start * 3, 3));
+            if decoder.decode(packets).is_some() {
+                continue;
+            }
+            retried += 1;
+
+            let mut result = None;
+            for packet in encoder.repair_packets(1000, 10) {
+                result = decoder.decode(vec![packet]);
+                if result.is_some() {
+                    break;
+                }
+            }
+            assert_eq!(result.unwrap(), data);
+        }
+        assert!(retried > 0);
+    }
+
+    #[test]
+    fn extra_symbols_consumed() {
+        let elements = 1024;
+        let mut data: Vec<u8> = vec![0; elements];
+        for byte in data.iter_mut() {
+            *byte = rand::thread_rng().gen();
+        }
+
+        let encoder = SourceBlockEncoder::new(1, 8, &data);
+        let mut decoder = SourceBlockDecoder::new(1, 8, elements as u64);
+        assert_eq!(decoder.extra_symbols_consumed(), None);
+
+        // Drop the first source symbol, so that exactly one repair symbol is needed beyond K
+        let mut packets = encoder.source_packets();
+        packets.remove(0);
+        assert_eq!(decoder.decode(packets), None);
+        assert_eq!(decoder.extra_symbols_consumed(), None);
+
+        let mut received = elements / 8 - 1;
+        for packet in encoder.repair_packets(0, 10) {
+            received += 1;
+            if decoder.decode(vec![packet]).is_some() {
+                break;
+            }
+        }
+        assert_eq!(
+            decoder.extra_symbols_consumed(),
+            Some((received - elements / 8) as u32)
+        );
+    }
 }
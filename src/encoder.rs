@@ -1,174 +1,1842 @@
-use crate::base::intermediate_tuple;
+use crate::ack::BlockAckFrame;
 use crate::base::partition;
+use crate::base::ConfigError;
 use crate::base::EncodingPacket;
+use crate::base::MemoryUsage;
 use crate::base::PayloadId;
+use crate::base::SourcePacketRef;
+use crate::base::MAX_TRANSFER_LENGTH;
+use crate::base::{deinterleave_sub_blocks, interleave_sub_blocks};
+use crate::cache::{RepairCacheStats, RepairSymbolCache};
+use crate::config::Config;
+use crate::constraint_matrix::enc_indices;
+use crate::constraint_matrix::for_each_enc_index;
 use crate::constraint_matrix::generate_constraint_matrix;
 use crate::matrix::DenseOctetMatrix;
+use crate::matrix::OctetMatrix;
+use crate::octets::add_assign;
 use crate::pi_solver::fused_inverse_mul_symbols;
+use crate::pi_solver::{IntermediateSymbolDecoder, SolverPermutations};
 use crate::sparse_matrix::SparseOctetMatrix;
-use crate::symbol::Symbol;
+use crate::store::PacketStore;
+use crate::symbol::{Symbol, SymbolOp};
 use crate::systematic_constants::extended_source_block_symbols;
 use crate::systematic_constants::num_hdpc_symbols;
 use crate::systematic_constants::num_intermediate_symbols;
 use crate::systematic_constants::num_ldpc_symbols;
 use crate::systematic_constants::num_lt_symbols;
 use crate::systematic_constants::num_pi_symbols;
-use crate::systematic_constants::{calculate_p1, systematic_index};
+use crate::systematic_constants::{calculate_p1, systematic_index, MAX_SOURCE_SYMBOLS_PER_BLOCK};
+use crate::tuple::intermediate_tuple;
+use crate::util::div_ceil;
 use crate::ObjectTransmissionInformation;
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::cmp::min;
+use std::fmt;
+#[cfg(feature = "mmap")]
+use std::fs::File;
+use std::io;
+use std::ops::Range;
+use std::sync::{mpsc, Arc};
+use std::thread;
 
 pub const SPARSE_MATRIX_THRESHOLD: u32 = 250;
 
+/// The most packets returned by `Encoder::repair_packets_for_ack()` for one acknowledgement
+pub const MAX_REPAIR_PACKETS_PER_ACK: usize = 1 << 16;
+
+// Encoding symbol ids are 24 bits. See section 3.2
+const ESI_BITS: u32 = 24;
+const ESI_MASK: u32 = (1 << ESI_BITS) - 1;
+
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
 pub struct Encoder {
     config: ObjectTransmissionInformation,
     blocks: Vec<SourceBlockEncoder>,
+    // Byte ranges of the object, [start, end), tagged with an importance
+    #[serde(default)]
+    importance: Vec<(u64, u64, u8)>,
+    #[serde(skip)]
+    repair_cache: Option<RepairSymbolCache>,
+}
+
+impl Encoder {
+    pub fn with_defaults(data: &[u8], maximum_transmission_unit: u16) -> Encoder {
+        let config = ObjectTransmissionInformation::with_defaults(
+            data.len() as u64,
+            maximum_transmission_unit,
+        );
+        Encoder::new(data, config)
+    }
+
+    /// Encodes with the parameters of a transport preset. See `Config`.
+    pub fn with_config(data: &[u8], config: &Config) -> Encoder {
+        Encoder::new(
+            data,
+            config.object_transmission_information(data.len() as u64),
+        )
+    }
+
+    /// Encodes in the closed-system mode, where the block structure is implied by a `Config`
+    /// negotiated out of band, so that receivers need no OTI. Every source block contains exactly
+    /// `config.max_source_block_symbols()` symbols, with the last one zero padded. Decode with an
+    /// `ImpliedConfigDecoder`
+    pub fn with_implied_config(data: &[u8], config: &Config) -> Encoder {
+        let oti = config.implied_object_transmission_information(data.len() as u64);
+        let mut padded = Vec::with_capacity(oti.transfer_length() as usize);
+        padded.extend_from_slice(data);
+        padded.resize(oti.transfer_length() as usize, 0);
+        Encoder::new(&padded, oti)
+    }
+
+    /// Same as `new()`, but the source symbols are not copied: each source block encoder holds a
+    /// reference to `data`, and borrows its source symbols from it. This halves the memory held
+    /// for large objects, since otherwise both the object and the copy are held. Blocks whose
+    /// symbols are not contiguous in the object, because it is split into sub-blocks or because
+    /// the last symbol is zero padded, still hold a copy.
+    pub fn new_shared(data: Arc<[u8]>, config: ObjectTransmissionInformation) -> Encoder {
+        Encoder::with_shared_object(SharedObject::Memory(data), config)
+    }
+
+    /// Same as `new_shared()`, but the object is the content of `file`, which is memory mapped.
+    /// The source symbols are read from the mapping when they are needed, so they are paged in
+    /// lazily, and can be paged out again, rather than copied. The intermediate symbols of every
+    /// block are still solved for up front and held in memory, and they are slightly larger than
+    /// the object, as is the copy held of blocks which are split into sub-blocks or zero padded.
+    /// So this does not bound resident memory by itself: `FileEncoder` does, by solving for the
+    /// intermediate symbols of a few blocks at a time. Returns an error if the file can not be
+    /// mapped, or its length is not the transfer length.
+    ///
+    /// # Safety
+    ///
+    /// The file must not be modified, or truncated, while the encoder, or any encoder of its
+    /// blocks, exists.
+    #[cfg(feature = "mmap")]
+    pub unsafe fn from_file(
+        file: &File,
+        config: ObjectTransmissionInformation,
+    ) -> io::Result<Encoder> {
+        Ok(Encoder::with_shared_object(
+            map_file(file, &config)?,
+            config,
+        ))
+    }
+
+    fn with_shared_object(data: SharedObject, config: ObjectTransmissionInformation) -> Encoder {
+        assert_eq!(config.transfer_length(), data.as_bytes().len() as u64);
+        config.check_sub_blocks().unwrap();
+        let blocks = source_block_ranges(&config)
+            .into_iter()
+            .enumerate()
+            .map(|(i, range)| shared_block_encoder(i as u8, &data, range, &config))
+            .collect();
+        Encoder {
+            config,
+            blocks,
+            importance: vec![],
+            repair_cache: None,
+        }
+    }
+
+    /// Encodes with the given parameters, whose transfer length must be the length of `data`. With
+    /// the `parallel` feature, the source blocks are encoded concurrently on the rayon thread pool
+    pub fn new(data: &[u8], config: ObjectTransmissionInformation) -> Encoder {
+        let blocks = encode_blocks(&source_blocks(data, &config), config.symbol_size());
+        Encoder {
+            config,
+            blocks,
+            importance: vec![],
+            repair_cache: None,
+        }
+    }
+
+    /// Returns an `EncoderBuilder`, for encoding with explicitly chosen parameters
+    pub fn builder(symbol_size: u16) -> EncoderBuilder {
+        EncoderBuilder::new(symbol_size)
+    }
+
+    /// Returns a `StreamingEncoder`, which is given the object in pieces as it is produced, rather
+    /// than all at once. The transfer length of `config` must be the length of the whole object.
+    /// Panics if `config` can not be split into source blocks, see `StreamingEncoder::new()`.
+    pub fn streaming(config: ObjectTransmissionInformation) -> StreamingEncoder {
+        StreamingEncoder::new(config)
+    }
+
+    /// Encodes the object read from `reader`, whose length must be the transfer length of
+    /// `config`. The object is read one source block at a time, and each block is encoded once it
+    /// has been read, so at most one block of the object is buffered. The encoder still holds the
+    /// source and intermediate symbols of every block, which together are about twice the size of
+    /// the object, so objects larger than memory should be encoded with `read_blocks()` instead.
+    /// Returns an error of kind `UnexpectedEof` if the reader ends before the transfer length.
+    /// Panics if `config` can not be split into source blocks, see `StreamingEncoder::new()`.
+    pub fn from_reader<R: io::Read>(
+        reader: R,
+        config: ObjectTransmissionInformation,
+    ) -> io::Result<Encoder> {
+        let blocks = Encoder::read_blocks(reader, config.clone()).collect::<io::Result<_>>()?;
+        Ok(Encoder {
+            config,
+            blocks,
+            importance: vec![],
+            repair_cache: None,
+        })
+    }
+
+    /// Same as `from_reader()`, but rather than an encoder of the whole object, returns an
+    /// iterator over the encoders of its source blocks, each of which is read and encoded when it
+    /// is consumed. Only the block being read is buffered, and only the encoders which the caller
+    /// keeps are held, so objects larger than memory can be encoded by sending the packets of each
+    /// block before consuming the next one.
+    pub fn read_blocks<R: io::Read>(
+        reader: R,
+        config: ObjectTransmissionInformation,
+    ) -> BlockEncoderReader<R> {
+        config.checked_block_partition().unwrap();
+        BlockEncoderReader {
+            reader,
+            ranges: source_block_ranges(&config),
+            config,
+            next_block: 0,
+            buffer: vec![],
+        }
+    }
+
+    pub fn get_config(&self) -> ObjectTransmissionInformation {
+        self.config.clone()
+    }
+
+    pub fn get_encoded_packets(&self, repair_packets_per_block: u32) -> Vec<EncodingPacket> {
+        let mut packets = vec![];
+        for encoder in self.blocks.iter() {
+            packets.extend(encoder.source_packets());
+            packets.extend(encoder.repair_packets(0, repair_packets_per_block));
+        }
+        packets
+    }
+
+    /// Same as `get_encoded_packets()`, but the packets of each source block are generated
+    /// concurrently on the rayon thread pool. Generating repair packets dominates the cost of
+    /// sending large objects, and the blocks are independent of each other.
+    #[cfg(feature = "parallel")]
+    pub fn get_encoded_packets_parallel(
+        &self,
+        repair_packets_per_block: u32,
+    ) -> Vec<EncodingPacket> {
+        let block_packets: Vec<Vec<EncodingPacket>> = self
+            .blocks
+            .par_iter()
+            .map(|encoder| {
+                let mut packets = encoder.source_packets();
+                packets.extend(encoder.repair_packets(0, repair_packets_per_block));
+                packets
+            })
+            .collect();
+        block_packets.into_iter().flatten().collect()
+    }
+
+    /// Returns the source packets of every block, in the same order as `get_encoded_packets(0)`,
+    /// with their symbols borrowed from `data`, which must be the object this encoder was created
+    /// from. Sending these copies no payload, and they remain available after
+    /// `shrink_to_repair_only()`. Panics if the object is split into sub-blocks, since symbols
+    /// are then not contiguous in the object.
+    pub fn source_packets_borrowed<'a>(&self, data: &'a [u8]) -> Vec<SourcePacketRef<'a>> {
+        assert_eq!(self.config.transfer_length(), data.len() as u64);
+        assert_eq!(
+            self.config.sub_blocks(),
+            1,
+            "symbols of sub-blocks are not contiguous in the object"
+        );
+        let symbol_size = self.config.symbol_size() as usize;
+        let mut packets = vec![];
+        let mut offset = 0;
+        for block in self.blocks.iter() {
+            for esi in 0..block.source_block_symbols {
+                let end = min(offset + symbol_size, data.len());
+                packets.push(SourcePacketRef::new(
+                    PayloadId::new(block.source_block_id, esi),
+                    &data[min(offset, end)..end],
+                    offset + symbol_size - end,
+                ));
+                offset += symbol_size;
+            }
+        }
+        packets
+    }
+
+    /// Same as `get_encoded_packets()`, but grouped by source block: the i-th entry contains the
+    /// packets of source block number i. Useful for senders which send each block over a
+    /// different path.
+    pub fn get_encoded_packets_by_block(
+        &self,
+        repair_packets_per_block: u32,
+    ) -> Vec<Vec<EncodingPacket>> {
+        (0..self.blocks.len())
+            .map(|i| self.get_block_packets(i as u8, repair_packets_per_block))
+            .collect()
+    }
+
+    /// Returns the source packets of the given source block, followed by the given number of its
+    /// repair packets
+    pub fn get_block_packets(
+        &self,
+        source_block_number: u8,
+        repair_packets: u32,
+    ) -> Vec<EncodingPacket> {
+        let encoder = &self.blocks[source_block_number as usize];
+        let mut packets = encoder.source_packets();
+        packets.extend(encoder.repair_packets(0, repair_packets));
+        packets
+    }
+
+    /// Returns the total number of packets to send, so that every source block can be received
+    /// with the given overhead: its source symbols, plus that percentage of them rounded up, of
+    /// repair symbols
+    pub fn total_packets_for_overhead(&self, overhead_percent: u32) -> u64 {
+        self.blocks
+            .iter()
+            .map(|block| {
+                let source_symbols = u64::from(block.source_block_symbols);
+                source_symbols + div_ceil(source_symbols * u64::from(overhead_percent), 100)
+            })
+            .sum()
+    }
+
+    /// Tags the bytes of the object in `[start, end)` with an application defined importance, for
+    /// example to mark the I-frames of a video, so that `repair_packets_by_importance()` protects
+    /// the source blocks containing them better. Where ranges overlap, the highest importance
+    /// applies. Untagged bytes have an importance of zero. The range is clamped to the object, so
+    /// the part of it past the end of the object, or all of an empty range, is ignored.
+    pub fn set_importance(&mut self, start: u64, end: u64, importance: u8) {
+        let end = min(end, self.config.transfer_length());
+        if start < end {
+            self.importance.push((start, end, importance));
+        }
+    }
+
+    pub fn clear_importance(&mut self) {
+        self.importance = vec![];
+    }
+
+    /// Returns the importance of each source block, which is the highest importance of the ranges
+    /// which overlap it, or zero
+    pub fn block_importance(&self) -> Vec<u8> {
+        let symbol_size = u64::from(self.config.symbol_size());
+        let mut block_start = 0;
+        let mut result = vec![];
+        for block in self.blocks.iter() {
+            let block_end = block_start + u64::from(block.source_block_symbols) * symbol_size;
+            result.push(
+                self.importance
+                    .iter()
+                    .filter(|(start, end, _)| *start < block_end && *end > block_start)
+                    .map(|(_, _, importance)| *importance)
+                    .max()
+                    .unwrap_or(0),
+            );
+            block_start = block_end;
+        }
+        result
+    }
+
+    /// Returns `packets` repair packets, shared between the source blocks in proportion to their
+    /// number of source symbols multiplied by one plus their importance (see `set_importance()`),
+    /// starting from the given repair symbol id in each block. The packets of more important
+    /// blocks come first, so that they are sent even if transmission is cut short.
+    pub fn repair_packets_by_importance(
+        &self,
+        start_repair_symbol_id: u32,
+        packets: u32,
+    ) -> Vec<EncodingPacket> {
+        let importance = self.block_importance();
+        let weights: Vec<u64> = self
+            .blocks
+            .iter()
+            .zip(importance.iter())
+            .map(|(block, importance)| {
+                u64::from(block.source_block_symbols) * (1 + u64::from(*importance))
+            })
+            .collect();
+        let total_weight: u64 = weights.iter().sum();
+        let mut shares: Vec<u32> = weights
+            .iter()
+            .map(|weight| (u64::from(packets) * weight / total_weight) as u32)
+            .collect();
+        let mut order: Vec<usize> = (0..self.blocks.len()).collect();
+        order.sort_by_key(|&i| std::cmp::Reverse(importance[i]));
+        // The packets left over by rounding down go to the most important blocks
+        let mut remaining = packets - shares.iter().sum::<u32>();
+        for &i in order.iter().cycle() {
+            if remaining == 0 {
+                break;
+            }
+            shares[i] += 1;
+            remaining -= 1;
+        }
+        order
+            .iter()
+            .flat_map(|&i| self.blocks[i].repair_packets(start_repair_symbol_id, shares[i]))
+            .collect()
+    }
+
+    /// Same as `get_encoded_packets()`, and also inserts the packets into the store, so that they
+    /// can be resent with `resend()`
+    pub fn get_encoded_packets_stored(
+        &self,
+        repair_packets_per_block: u32,
+        store: &mut dyn PacketStore,
+    ) -> Vec<EncodingPacket> {
+        let packets = self.get_encoded_packets(repair_packets_per_block);
+        for packet in packets.iter() {
+            store.insert(packet.clone());
+        }
+        packets
+    }
+
+    /// Returns the packet with the given payload id, from the store if it is there, and otherwise
+    /// by generating it again. Returns None if the payload id is not valid for this object.
+    pub fn resend(
+        &self,
+        store: &dyn PacketStore,
+        payload_id: &PayloadId,
+    ) -> Option<EncodingPacket> {
+        if let Some(packet) = store.get(payload_id) {
+            return Some(packet);
+        }
+        let block = self.blocks.get(payload_id.source_block_number() as usize)?;
+        let esi = payload_id.encoding_symbol_id();
+        // Repair symbols are numbered from K', so their ESI is equal to their ISI
+        if esi >= block.source_block_symbols
+            && esi < extended_source_block_symbols(block.source_block_symbols)
+        {
+            return None;
+        }
+        Some(EncodingPacket::new(
+            payload_id.clone(),
+            block.encode_isi(esi),
+        ))
+    }
+
+    /// Returns the repair packets needed by a receiver which sent the given acknowledgement:
+    /// for each block it has not decoded, the number of symbols it still needs plus
+    /// `extra_packets_per_block`, starting from the given repair symbol id. Decoded blocks are
+    /// skipped. The acknowledgement is received from the peer, so if its number of blocks does
+    /// not match this object, it is ignored and no packets are returned. Otherwise no more
+    /// symbols are counted as needed for a block than it has source symbols, and at most
+    /// `MAX_REPAIR_PACKETS_PER_ACK` packets are returned in total, the rest of which are sent in
+    /// response to later acknowledgements.
+    pub fn repair_packets_for_ack(
+        &self,
+        ack: &BlockAckFrame,
+        start_repair_symbol_id: u32,
+        extra_packets_per_block: u32,
+    ) -> Vec<EncodingPacket> {
+        if ack.num_blocks() != self.blocks.len() {
+            return vec![];
+        }
+        let mut packets = vec![];
+        for (i, encoder) in self.blocks.iter().enumerate() {
+            if !ack.is_decoded(i as u8) {
+                let needed = min(ack.symbols_needed(i as u8), encoder.source_block_symbols)
+                    + extra_packets_per_block;
+                let remaining = (MAX_REPAIR_PACKETS_PER_ACK - packets.len()) as u32;
+                packets.extend(
+                    encoder
+                        .repair_packets_iter(start_repair_symbol_id)
+                        .take(min(needed, remaining) as usize),
+                );
+                if packets.len() == MAX_REPAIR_PACKETS_PER_ACK {
+                    break;
+                }
+            }
+        }
+        packets
+    }
+
+    /// See `SourceBlockEncoder::set_repair_esi_seed()`. Each block permutes its repair symbol ids
+    /// differently, even with the same seed.
+    pub fn set_repair_esi_seed(&mut self, seed: Option<u64>) {
+        for block in self.blocks.iter_mut() {
+            block.set_repair_esi_seed(seed);
+        }
+    }
+
+    /// Returns the repair packet with the given ESI of the given source block, like
+    /// `SourceBlockEncoder::repair_packet()`, but from the repair symbol cache, if there is one.
+    /// Returns None if there is no such block, or the ESI is not that of a repair symbol.
+    pub fn repair_packet(
+        &mut self,
+        source_block_number: u8,
+        encoding_symbol_id: u32,
+    ) -> Option<EncodingPacket> {
+        let block = self.blocks.get(source_block_number as usize)?;
+        let cache = match self.repair_cache.as_mut() {
+            Some(cache) => cache,
+            None => return block.repair_packet(encoding_symbol_id),
+        };
+        // Checked before the lookup, so that invalid ESIs are not counted as misses
+        if !block.is_repair_esi(encoding_symbol_id) {
+            return None;
+        }
+        let data = cache
+            .get_or_insert_with((source_block_number, encoding_symbol_id), || {
+                block.repair_packet_with_esi(encoding_symbol_id).split().1
+            })
+            .to_vec();
+        Some(EncodingPacket::new(
+            PayloadId::new(source_block_number, encoding_symbol_id),
+            data,
+        ))
+    }
+
+    /// Caches up to `capacity` of the repair symbols most recently returned by `repair_packet()`,
+    /// so that requests for the same symbol, which are common when retransmitting to several
+    /// lossy receivers, do not generate it again. The least recently used symbol is evicted when
+    /// the cache is full. A capacity of zero disables the cache. The cache is emptied, and its
+    /// statistics reset, by every call.
+    pub fn set_repair_cache_capacity(&mut self, capacity: usize) {
+        self.repair_cache = if capacity == 0 {
+            None
+        } else {
+            Some(RepairSymbolCache::new(capacity))
+        };
+    }
+
+    /// Returns the hits and misses of the repair symbol cache, if it is enabled
+    pub fn repair_cache_stats(&self) -> Option<RepairCacheStats> {
+        self.repair_cache
+            .as_ref()
+            .map(|cache| cache.stats().clone())
+    }
+
+    pub fn get_block_encoders(&self) -> &Vec<SourceBlockEncoder> {
+        &self.blocks
+    }
+
+    pub fn memory_usage(&self) -> MemoryUsage {
+        let mut usage = MemoryUsage::default();
+        for block in self.blocks.iter() {
+            usage += block.memory_usage();
+        }
+        usage
+    }
+
+    // Whether this encoder was created from exactly the given data
+    pub(crate) fn encodes(&self, data: &[u8]) -> bool {
+        if self.config.transfer_length() != data.len() as u64 {
+            return false;
+        }
+        let sub_symbol_sizes = self.config.sub_symbol_sizes();
+        let mut remaining = data;
+        for block in self.blocks.iter() {
+            let mut symbols = vec![];
+            for esi in 0..block.source_block_symbols {
+                symbols.extend_from_slice(&block.source_symbol(esi));
+            }
+            let block_data = if sub_symbol_sizes.len() > 1 {
+                deinterleave_sub_blocks(&symbols, &sub_symbol_sizes)
+            } else {
+                symbols
+            };
+            // The last block may be zero padded beyond the end of the data
+            let length = min(block_data.len(), remaining.len());
+            if block_data[..length] != remaining[..length] {
+                return false;
+            }
+            remaining = &remaining[length..];
+        }
+        true
+    }
+
+    /// See `SourceBlockEncoder::shrink_to_repair_only()`
+    pub fn shrink_to_repair_only(&mut self) {
+        for block in self.blocks.iter_mut() {
+            block.shrink_to_repair_only();
+        }
+    }
+}
+
+// Maps a file whose content is the object
+#[cfg(feature = "mmap")]
+unsafe fn map_file(
+    file: &File,
+    config: &ObjectTransmissionInformation,
+) -> io::Result<SharedObject> {
+    let mapping = memmap2::Mmap::map(file)?;
+    if mapping.len() as u64 != config.transfer_length() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "the length of the file is not the transfer length",
+        ));
+    }
+    Ok(SharedObject::Mapped(Arc::new(mapping)))
+}
+
+// Returns the encoder of the source block in the given range of a shared object, which borrows its
+// source symbols from the object if they are contiguous in it
+fn shared_block_encoder(
+    source_block_number: u8,
+    data: &SharedObject,
+    range: Range<usize>,
+    config: &ObjectTransmissionInformation,
+) -> SourceBlockEncoder {
+    let symbol_size = config.symbol_size();
+    if config.sub_blocks() == 1 && range.len() % symbol_size as usize == 0 {
+        SourceBlockEncoder::with_shared_object(
+            source_block_number,
+            symbol_size,
+            data.clone(),
+            range,
+        )
+    } else {
+        // Padding or interleaving needs a copy anyway
+        let block = source_block(&data.as_bytes()[range], config);
+        SourceBlockEncoder::new(source_block_number, symbol_size, &block)
+    }
+}
+
+// Returns the range of the object in each source block. That of the last block is shorter than the
+// block if its last symbol is zero padded
+fn source_block_ranges(config: &ObjectTransmissionInformation) -> Vec<Range<usize>> {
+    let symbol_size = config.symbol_size() as usize;
+    let kt = div_ceil(config.transfer_length(), u64::from(config.symbol_size())) as u32;
+    let (kl, ks, zl, zs) = partition(kt, config.source_blocks());
+    let mut start = 0;
+    let mut ranges = vec![];
+    for i in 0..(zl + zs) {
+        let block_symbols = if i < zl { kl } else { ks };
+        let end = start + block_symbols as usize * symbol_size;
+        ranges.push(start..min(end, config.transfer_length() as usize));
+        start = end;
+    }
+    ranges
+}
+
+// Splits an object into the data of its source blocks, as they are encoded: the last symbol is
+// zero padded, and the sub-blocks of each block are interleaved into whole symbols. Blocks which
+// need neither are borrowed from the object.
+fn source_blocks<'a>(data: &'a [u8], config: &ObjectTransmissionInformation) -> Vec<Cow<'a, [u8]>> {
+    assert_eq!(config.transfer_length(), data.len() as u64);
+    config.check_sub_blocks().unwrap();
+    source_block_ranges(config)
+        .into_iter()
+        .map(|range| source_block(&data[range], config))
+        .collect()
+}
+
+// Solves for the intermediate symbols of each source block, returned by source_blocks()
+#[cfg(not(feature = "parallel"))]
+fn encode_blocks(blocks: &[Cow<[u8]>], symbol_size: u16) -> Vec<SourceBlockEncoder> {
+    blocks
+        .iter()
+        .enumerate()
+        .map(|(i, block)| SourceBlockEncoder::new(i as u8, symbol_size, block))
+        .collect()
+}
+
+#[cfg(feature = "parallel")]
+fn encode_blocks(blocks: &[Cow<[u8]>], symbol_size: u16) -> Vec<SourceBlockEncoder> {
+    blocks
+        .par_iter()
+        .enumerate()
+        .map(|(i, block)| SourceBlockEncoder::new(i as u8, symbol_size, block))
+        .collect()
+}
+
+// Returns the data of a source block, given the part of the object which it contains. See
+// source_blocks()
+fn source_block<'a>(data: &'a [u8], config: &ObjectTransmissionInformation) -> Cow<'a, [u8]> {
+    let symbol_size = config.symbol_size() as usize;
+    let sub_symbol_sizes = config.sub_symbol_sizes();
+    let mut block = Cow::Borrowed(data);
+    if block.len() % symbol_size != 0 {
+        // Zero pad the last symbol. See third to last paragraph in section 4.4.1.2
+        let padded = div_ceil(block.len() as u64, symbol_size as u64) as usize * symbol_size;
+        block.to_mut().resize(padded, 0);
+    }
+    if sub_symbol_sizes.len() > 1 {
+        block = Cow::Owned(interleave_sub_blocks(&block, &sub_symbol_sizes));
+    }
+    block
+}
+
+/// Builds an `Encoder` with explicitly chosen parameters: the symbol size (T), number of source
+/// blocks (Z), number of sub-blocks (N) and symbol alignment (Al) of section 3.3.2. Unlike
+/// `ObjectTransmissionInformation::new()`, which panics on some inconsistent parameters and
+/// accepts others which fail later, the combination is validated against the limits of RFC 6330
+/// for the object being encoded, and a `ConfigError` is returned if it is not compliant.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct EncoderBuilder {
+    symbol_size: u16,
+    // The fewest blocks which the object fits in, if not set
+    source_blocks: Option<u8>,
+    sub_blocks: u16,
+    symbol_alignment: u8,
+}
+
+impl EncoderBuilder {
+    /// By default, the object is split into as few source blocks as possible, without sub-blocks,
+    /// and symbols are aligned to 8 bytes
+    pub fn new(symbol_size: u16) -> EncoderBuilder {
+        EncoderBuilder {
+            symbol_size,
+            source_blocks: None,
+            sub_blocks: 1,
+            symbol_alignment: 8,
+        }
+    }
+
+    pub fn source_blocks(mut self, source_blocks: u8) -> EncoderBuilder {
+        self.source_blocks = Some(source_blocks);
+        self
+    }
+
+    pub fn sub_blocks(mut self, sub_blocks: u16) -> EncoderBuilder {
+        self.sub_blocks = sub_blocks;
+        self
+    }
+
+    pub fn symbol_alignment(mut self, symbol_alignment: u8) -> EncoderBuilder {
+        self.symbol_alignment = symbol_alignment;
+        self
+    }
+
+    /// Returns the parameters for an object of `transfer_length` bytes, once they have been
+    /// validated, see `ObjectTransmissionInformation::check_strict()`
+    pub fn object_transmission_information(
+        &self,
+        transfer_length: u64,
+    ) -> Result<ObjectTransmissionInformation, ConfigError> {
+        if transfer_length > MAX_TRANSFER_LENGTH {
+            return Err(ConfigError::TransferLengthTooLarge { transfer_length });
+        }
+        if self.symbol_size == 0 || self.symbol_alignment == 0 {
+            return Err(ConfigError::ZeroParameter);
+        }
+        let alignment = u16::from(self.symbol_alignment);
+        if self.symbol_size / alignment * alignment != self.symbol_size {
+            return Err(ConfigError::NonCompliant {
+                parameter: "Al",
+                source_block_symbols: 0,
+            });
+        }
+        let source_blocks = match self.source_blocks {
+            Some(source_blocks) => source_blocks,
+            None => {
+                let symbols = div_ceil(transfer_length, u64::from(self.symbol_size));
+                let blocks = div_ceil(symbols, u64::from(MAX_SOURCE_SYMBOLS_PER_BLOCK)).max(1);
+                if blocks > 255 {
+                    return Err(ConfigError::SourceBlockTooLarge {
+                        source_block_symbols: div_ceil(symbols, 255),
+                    });
+                }
+                blocks as u8
+            }
+        };
+        let config = ObjectTransmissionInformation::new(
+            transfer_length,
+            self.symbol_size,
+            source_blocks,
+            self.sub_blocks,
+            self.symbol_alignment,
+        );
+        config.check_strict()?;
+        Ok(config)
+    }
+
+    pub fn build(&self, data: &[u8]) -> Result<Encoder, ConfigError> {
+        let config = self.object_transmission_information(data.len() as u64)?;
+        Ok(Encoder::new(data, config))
+    }
+}
+
+/// An encoder whose source packets are available as soon as it is created, while the intermediate
+/// symbols, which repair packets are generated from, are solved for on a background thread. This
+/// reduces the time to the first packet for latency sensitive transfers, since the solve takes
+/// most of the time of `Encoder::new()`, and the source packets are usually sent first anyway.
+#[derive(Debug)]
+pub struct DeferredEncoder {
+    config: ObjectTransmissionInformation,
+    blocks: Arc<Vec<Vec<u8>>>,
+    solved: mpsc::Receiver<Encoder>,
+    encoder: Option<Encoder>,
+}
+
+impl DeferredEncoder {
+    /// Copies the object, and starts solving for its intermediate symbols on a new thread. The
+    /// result is the same as `Encoder::new()`
+    pub fn new(data: &[u8], config: ObjectTransmissionInformation) -> DeferredEncoder {
+        let blocks: Arc<Vec<Vec<u8>>> = Arc::new(
+            source_blocks(data, &config)
+                .into_iter()
+                .map(|block| block.into_owned())
+                .collect(),
+        );
+        let (sender, solved) = mpsc::sync_channel(1);
+        let thread_blocks = blocks.clone();
+        let thread_config = config.clone();
+        thread::spawn(move || {
+            let blocks = thread_blocks
+                .iter()
+                .enumerate()
+                .map(|(i, block)| {
+                    SourceBlockEncoder::new(i as u8, thread_config.symbol_size(), block)
+                })
+                .collect();
+            // The DeferredEncoder may have been dropped, in which case nobody needs the result
+            let _ = sender.send(Encoder {
+                config: thread_config,
+                blocks,
+                importance: vec![],
+                repair_cache: None,
+            });
+        });
+        DeferredEncoder {
+            config,
+            blocks,
+            solved,
+            encoder: None,
+        }
+    }
+
+    pub fn get_config(&self) -> ObjectTransmissionInformation {
+        self.config.clone()
+    }
+
+    /// Returns the source packets of every block, in the same order as
+    /// `Encoder::get_encoded_packets(0)`, without waiting for the solve
+    pub fn source_packets(&self) -> Vec<EncodingPacket> {
+        let symbol_size = self.config.symbol_size() as usize;
+        let mut packets = vec![];
+        for (i, block) in self.blocks.iter().enumerate() {
+            packets.extend(block.chunks(symbol_size).enumerate().map(|(esi, symbol)| {
+                EncodingPacket::new(PayloadId::new(i as u8, esi as u32), symbol.to_vec())
+            }));
+        }
+        packets
+    }
+
+    /// Returns the given number of quick repair packets for each block, without waiting for the
+    /// solve. See `SourceBlockEncoder::quick_repair_packets()`
+    pub fn quick_repair_packets(
+        &self,
+        start_quick_repair_id: u32,
+        packets_per_block: u32,
+    ) -> Vec<EncodingPacket> {
+        let symbol_size = self.config.symbol_size() as usize;
+        let mut packets = vec![];
+        for (i, block) in self.blocks.iter().enumerate() {
+            let source_block_symbols = (block.len() / symbol_size) as u32;
+            for id in start_quick_repair_id..(start_quick_repair_id + packets_per_block) {
+                packets.push(quick_repair_packet(
+                    i as u8,
+                    source_block_symbols,
+                    symbol_size,
+                    id,
+                    |esi| {
+                        let start = esi as usize * symbol_size;
+                        Cow::Borrowed(&block[start..(start + symbol_size)])
+                    },
+                ));
+            }
+        }
+        packets
+    }
+
+    /// Whether the solve has finished, so that `encoder()` will not block
+    pub fn is_ready(&mut self) -> bool {
+        if self.encoder.is_none() {
+            self.encoder = self.solved.try_recv().ok();
+        }
+        self.encoder.is_some()
+    }
+
+    /// Returns the encoder, for repair packets, waiting for the solve to finish if necessary
+    pub fn encoder(&mut self) -> &Encoder {
+        if self.encoder.is_none() {
+            self.encoder = Some(self.solved.recv().expect("Encoder thread panicked"));
+        }
+        self.encoder.as_ref().unwrap()
+    }
+
+    /// Same as `encoder()`, but takes ownership of the encoder
+    pub fn into_encoder(mut self) -> Encoder {
+        self.encoder();
+        self.encoder.unwrap()
+    }
+}
+
+/// An encoder which is given the object in pieces, for example as it is read from a socket or a
+/// pipe, rather than as a single slice. The block structure depends only on the transfer length,
+/// so each source block is encoded as soon as all of its data has been pushed, and its packets are
+/// available while the rest of the object is still arriving. At most one source block of data is
+/// buffered.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StreamingEncoder {
+    config: ObjectTransmissionInformation,
+    // Number of source symbols of each block
+    block_symbols: Vec<u32>,
+    blocks: Vec<SourceBlockEncoder>,
+    // Data of the block after the last complete one
+    pending: Vec<u8>,
+    received: u64,
+}
+
+impl StreamingEncoder {
+    /// Panics if `config` can not be split into source blocks: if its symbol size, number of
+    /// source blocks or number of sub-blocks is zero, a source block would contain more than 56403
+    /// source symbols, or symbols can not be split into its sub-blocks. See `ConfigError`. Unlike
+    /// `ObjectTransmissionInformation::check_strict()`, this does not require it to otherwise
+    /// conform to RFC 6330.
+    pub fn new(config: ObjectTransmissionInformation) -> StreamingEncoder {
+        let (kl, ks, zl, zs) = config.checked_block_partition().unwrap();
+        let mut block_symbols = vec![kl; zl as usize];
+        block_symbols.extend(vec![ks; zs as usize]);
+        StreamingEncoder {
+            config,
+            block_symbols,
+            blocks: vec![],
+            pending: vec![],
+            received: 0,
+        }
+    }
+
+    pub fn get_config(&self) -> ObjectTransmissionInformation {
+        self.config.clone()
+    }
+
+    /// Number of bytes pushed so far
+    pub fn received(&self) -> u64 {
+        self.received
+    }
+
+    /// Whether the whole object has been pushed
+    pub fn is_complete(&self) -> bool {
+        self.blocks.len() == self.block_symbols.len()
+    }
+
+    /// Appends the next piece of the object, and encodes every source block which it completes.
+    /// Returns the number of blocks completed by this piece, which are the last ones of
+    /// `get_block_encoders()`. Panics if more data is pushed than the transfer length.
+    pub fn push(&mut self, mut data: &[u8]) -> usize {
+        assert!(
+            self.received + data.len() as u64 <= self.config.transfer_length(),
+            "More data was pushed than the transfer length"
+        );
+        self.received += data.len() as u64;
+        let symbol_size = self.config.symbol_size() as usize;
+        let completed = self.blocks.len();
+        while !self.is_complete() {
+            let block_length = self.block_symbols[self.blocks.len()] as usize * symbol_size;
+            let remaining = (self.config.transfer_length() - self.received) as usize + data.len();
+            // The last block is zero padded beyond the end of the object
+            let needed = min(block_length, self.pending.len() + remaining) - self.pending.len();
+            let taken = min(needed, data.len());
+            self.pending.extend_from_slice(&data[..taken]);
+            data = &data[taken..];
+            if taken < needed {
+                break;
+            }
+            self.pending.resize(block_length, 0);
+            self.encode_pending();
+        }
+        self.blocks.len() - completed
+    }
+
+    fn encode_pending(&mut self) {
+        let sub_symbol_sizes = self.config.sub_symbol_sizes();
+        let block = if sub_symbol_sizes.len() > 1 {
+            interleave_sub_blocks(&self.pending, &sub_symbol_sizes)
+        } else {
+            std::mem::take(&mut self.pending)
+        };
+        self.pending.clear();
+        self.blocks.push(SourceBlockEncoder::new(
+            self.blocks.len() as u8,
+            self.config.symbol_size(),
+            &block,
+        ));
+    }
+
+    /// Returns the encoders of the source blocks completed so far, in order
+    pub fn get_block_encoders(&self) -> &[SourceBlockEncoder] {
+        &self.blocks
+    }
+
+    /// Returns the encoder of the whole object. Panics if it has not all been pushed.
+    pub fn finalize(self) -> Encoder {
+        assert!(
+            self.is_complete(),
+            "Only {} of {} bytes were pushed",
+            self.received,
+            self.config.transfer_length()
+        );
+        Encoder {
+            config: self.config,
+            blocks: self.blocks,
+            importance: vec![],
+            repair_cache: None,
+        }
+    }
+}
+
+/// Iterator over the encoders of the source blocks of an object, which are read and encoded as
+/// they are consumed. After an error, it returns no more blocks. See `Encoder::read_blocks()`
+#[derive(Debug)]
+pub struct BlockEncoderReader<R> {
+    reader: R,
+    config: ObjectTransmissionInformation,
+    ranges: Vec<Range<usize>>,
+    next_block: usize,
+    // Data of the block being read
+    buffer: Vec<u8>,
+}
+
+impl<R: io::Read> Iterator for BlockEncoderReader<R> {
+    type Item = io::Result<SourceBlockEncoder>;
+
+    fn next(&mut self) -> Option<io::Result<SourceBlockEncoder>> {
+        let range = self.ranges.get(self.next_block)?.clone();
+        self.buffer.resize(range.len(), 0);
+        if let Err(error) = self.reader.read_exact(&mut self.buffer) {
+            self.next_block = self.ranges.len();
+            return Some(Err(error));
+        }
+        let block = source_block(&self.buffer, &self.config);
+        let encoder =
+            SourceBlockEncoder::new(self.next_block as u8, self.config.symbol_size(), &block);
+        self.next_block += 1;
+        Some(Ok(encoder))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.ranges.len() - self.next_block;
+        (0, Some(remaining))
+    }
+}
+
+/// Encodes the content of a memory mapped file, like `Encoder::from_file()`, but solves for the
+/// intermediate symbols of a source block only when its encoder is first requested, and holds the
+/// encoders of at most `capacity` blocks, dropping the least recently used one to make room.
+/// Resident memory is then bounded by the intermediate symbols of `capacity` blocks, plus the
+/// pages of the file which are mapped in, so objects larger than the memory of the machine can be
+/// encoded. A block which has been dropped is solved for again if it is requested again.
+#[cfg(feature = "mmap")]
+#[derive(Debug)]
+pub struct FileEncoder {
+    config: ObjectTransmissionInformation,
+    data: SharedObject,
+    ranges: Vec<Range<usize>>,
+    capacity: usize,
+    // Least recently used first
+    blocks: Vec<SourceBlockEncoder>,
+}
+
+#[cfg(feature = "mmap")]
+impl FileEncoder {
+    /// Returns an error if the file can not be mapped, or its length is not the transfer length.
+    /// Panics if `capacity` is zero.
+    ///
+    /// # Safety
+    ///
+    /// The file must not be modified, or truncated, while the encoder, or any encoder of its
+    /// blocks, exists.
+    pub unsafe fn new(
+        file: &File,
+        config: ObjectTransmissionInformation,
+        capacity: usize,
+    ) -> io::Result<FileEncoder> {
+        assert!(capacity > 0);
+        let data = map_file(file, &config)?;
+        config.check_sub_blocks().unwrap();
+        Ok(FileEncoder {
+            ranges: source_block_ranges(&config),
+            config,
+            data,
+            capacity,
+            blocks: vec![],
+        })
+    }
+
+    pub fn get_config(&self) -> ObjectTransmissionInformation {
+        self.config.clone()
+    }
+
+    /// Number of source blocks of the object
+    pub fn source_blocks(&self) -> usize {
+        self.ranges.len()
+    }
+
+    /// Returns the encoder of the given source block, from those held if it is one of them, and
+    /// otherwise by solving for its intermediate symbols. Panics if there is no such block.
+    pub fn block_encoder(&mut self, source_block_number: u8) -> &SourceBlockEncoder {
+        let held = self
+            .blocks
+            .iter()
+            .position(|block| block.source_block_id == source_block_number);
+        let block = match held {
+            Some(i) => self.blocks.remove(i),
+            None => {
+                if self.blocks.len() == self.capacity {
+                    self.blocks.remove(0);
+                }
+                shared_block_encoder(
+                    source_block_number,
+                    &self.data,
+                    self.ranges[source_block_number as usize].clone(),
+                    &self.config,
+                )
+            }
+        };
+        self.blocks.push(block);
+        self.blocks.last().unwrap()
+    }
+}
+
+// An object which source block encoders borrow their source symbols from. It is compared by
+// content, like a copy of the source symbols would be
+#[derive(Clone)]
+enum SharedObject {
+    Memory(Arc<[u8]>),
+    #[cfg(feature = "mmap")]
+    Mapped(Arc<memmap2::Mmap>),
+}
+
+impl SharedObject {
+    fn as_bytes(&self) -> &[u8] {
+        match self {
+            SharedObject::Memory(data) => data,
+            #[cfg(feature = "mmap")]
+            SharedObject::Mapped(mapping) => mapping,
+        }
+    }
+}
+
+impl fmt::Debug for SharedObject {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("SharedObject")
+            .field("length", &self.as_bytes().len())
+            .finish()
+    }
+}
+
+impl PartialEq for SharedObject {
+    fn eq(&self, other: &SharedObject) -> bool {
+        self.as_bytes() == other.as_bytes()
+    }
+}
+
+impl Eq for SharedObject {}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(from = "SerializedSourceBlockEncoder")]
+pub struct SourceBlockEncoder {
+    source_block_id: u8,
+    source_block_symbols: u32,
+    // Empty if the encoder has been shrunk to repair only, or the source symbols are shared
+    source_symbols: Vec<Symbol>,
+    // The object which the source symbols are borrowed from, and the offset of the block in it.
+    // It is not serialized, so deserialized encoders recompute the source symbols
+    #[serde(skip)]
+    shared_source: Option<(SharedObject, usize)>,
+    intermediate_symbols: Vec<Symbol>,
+    repair_esi_seed: Option<u64>,
+}
+
+// A serialized SourceBlockEncoder, including one serialized by an earlier release, which neither
+// recorded the number of source symbols, as it was always that of source_symbols, nor a seed
+#[derive(Deserialize)]
+struct SerializedSourceBlockEncoder {
+    source_block_id: u8,
+    // Zero if not recorded, as a block has at least one source symbol
+    #[serde(default)]
+    source_block_symbols: u32,
+    source_symbols: Vec<Symbol>,
+    intermediate_symbols: Vec<Symbol>,
+    #[serde(default)]
+    repair_esi_seed: Option<u64>,
+}
+
+impl From<SerializedSourceBlockEncoder> for SourceBlockEncoder {
+    fn from(serialized: SerializedSourceBlockEncoder) -> SourceBlockEncoder {
+        let source_block_symbols = if serialized.source_block_symbols == 0 {
+            serialized.source_symbols.len() as u32
+        } else {
+            serialized.source_block_symbols
+        };
+        SourceBlockEncoder {
+            source_block_id: serialized.source_block_id,
+            source_block_symbols,
+            source_symbols: serialized.source_symbols,
+            shared_source: None,
+            intermediate_symbols: serialized.intermediate_symbols,
+            repair_esi_seed: serialized.repair_esi_seed,
+        }
+    }
+}
+
+impl SourceBlockEncoder {
+    pub fn new(source_block_id: u8, symbol_size: u16, data: &[u8]) -> SourceBlockEncoder {
+        assert_eq!(data.len() % symbol_size as usize, 0);
+        let source_symbols: Vec<Symbol> = data
+            .chunks(symbol_size as usize)
+            .map(|x| Symbol::new(Vec::from(x)))
+            .collect();
+        let intermediate_symbols = gen_intermediate_symbols(
+            &source_symbols,
+            symbol_size as usize,
+            SPARSE_MATRIX_THRESHOLD,
+        );
+        SourceBlockEncoder {
+            source_block_id,
+            source_block_symbols: source_symbols.len() as u32,
+            source_symbols,
+            shared_source: None,
+            intermediate_symbols,
+            repair_esi_seed: None,
+        }
+    }
+
+    /// Same as `new()`, but performs the symbol operations of `plan`, rather than solving the
+    /// constraint matrix. Panics if the plan is for a different number of extended source symbols.
+    pub fn with_encoding_plan(
+        source_block_id: u8,
+        symbol_size: u16,
+        data: &[u8],
+        plan: &SourceBlockEncodingPlan,
+    ) -> SourceBlockEncoder {
+        assert_eq!(data.len() % symbol_size as usize, 0);
+        let source_symbols: Vec<Symbol> = data
+            .chunks(symbol_size as usize)
+            .map(|x| Symbol::new(Vec::from(x)))
+            .collect();
+        let intermediate_symbols = plan.intermediate_symbols(&source_symbols, symbol_size as usize);
+        SourceBlockEncoder {
+            source_block_id,
+            source_block_symbols: source_symbols.len() as u32,
+            source_symbols,
+            shared_source: None,
+            intermediate_symbols,
+            repair_esi_seed: None,
+        }
+    }
+
+    /// Same as `new()` for the block in the given range of `data`, but rather than copying the
+    /// source symbols, holds a reference to `data` and borrows them from it
+    pub fn new_shared(
+        source_block_id: u8,
+        symbol_size: u16,
+        data: Arc<[u8]>,
+        range: Range<usize>,
+    ) -> SourceBlockEncoder {
+        SourceBlockEncoder::with_shared_object(
+            source_block_id,
+            symbol_size,
+            SharedObject::Memory(data),
+            range,
+        )
+    }
+
+    fn with_shared_object(
+        source_block_id: u8,
+        symbol_size: u16,
+        data: SharedObject,
+        range: Range<usize>,
+    ) -> SourceBlockEncoder {
+        let block = &data.as_bytes()[range.clone()];
+        assert_eq!(block.len() % symbol_size as usize, 0);
+        let source_symbols: Vec<&[u8]> = block.chunks(symbol_size as usize).collect();
+        let intermediate_symbols = gen_intermediate_symbols(
+            &source_symbols,
+            symbol_size as usize,
+            SPARSE_MATRIX_THRESHOLD,
+        );
+        let source_block_symbols = source_symbols.len() as u32;
+        SourceBlockEncoder {
+            source_block_id,
+            source_block_symbols,
+            source_symbols: vec![],
+            shared_source: Some((data, range.start)),
+            intermediate_symbols,
+            repair_esi_seed: None,
+        }
+    }
+
+    /// Creates the encoder of a block of `source_block_symbols` source symbols from its
+    /// intermediate symbols, as returned by `intermediate_symbols()`, without solving for them
+    /// again. This lets senders cache them, for example on disk, and resume generating repair
+    /// symbols later. Source symbols are recomputed when they are needed, as after
+    /// `shrink_to_repair_only()`. Panics if the number of intermediate symbols is not the one of
+    /// the block, or they differ in size.
+    ///
+    /// The intermediate symbols only determine the number of extended source symbols (K'), so
+    /// the caller must cache `source_block_symbols` along with them. Blocks with the same K' have
+    /// the same number of intermediate symbols. A number of source symbols smaller than that of
+    /// the block is detected, since the source symbols which it would count as padding are not
+    /// zero, and panics, unless they happen to be zero. A larger one can not be detected, and the
+    /// encoder then returns padding symbols as source packets.
+    pub fn from_intermediate_symbols<T: AsRef<[u8]>>(
+        source_block_id: u8,
+        source_block_symbols: u32,
+        intermediate_symbols: &[T],
+    ) -> SourceBlockEncoder {
+        let expected = num_intermediate_symbols(source_block_symbols) as usize;
+        assert_eq!(
+            intermediate_symbols.len(),
+            expected,
+            "A block of {} source symbols has {} intermediate symbols",
+            source_block_symbols,
+            expected
+        );
+        let symbol_size = intermediate_symbols[0].as_ref().len();
+        assert!(
+            intermediate_symbols
+                .iter()
+                .all(|symbol| symbol.as_ref().len() == symbol_size),
+            "Intermediate symbols differ in size"
+        );
+        let extended_source_symbols = extended_source_block_symbols(source_block_symbols);
+        assert!(
+            (source_block_symbols..extended_source_symbols).all(|isi| {
+                encode_repair_symbol(intermediate_symbols, extended_source_symbols, isi)
+                    .iter()
+                    .all(|byte| *byte == 0)
+            }),
+            "The intermediate symbols are not those of a block of {} source symbols",
+            source_block_symbols
+        );
+        SourceBlockEncoder {
+            source_block_id,
+            source_block_symbols,
+            source_symbols: vec![],
+            shared_source: None,
+            intermediate_symbols: intermediate_symbols
+                .iter()
+                .map(|symbol| Symbol::new(symbol.as_ref().to_vec()))
+                .collect(),
+            repair_esi_seed: None,
+        }
+    }
+
+    /// With a seed, repair symbols are emitted with pseudo-random ESIs, rather than sequentially
+    /// from K'. Repair symbol id `i` in `repair_packets()` maps to a distinct ESI, as determined
+    /// by the seed, so senders with different seeds which start from the same repair symbol id
+    /// send mostly different symbols, and the ESIs of the packets sent do not reveal how many
+    /// have been sent. Receivers need no configuration, since every ESI identifies its symbol.
+    pub fn set_repair_esi_seed(&mut self, seed: Option<u64>) {
+        self.repair_esi_seed = seed;
+    }
+
+    // Returns the ESI of the given repair symbol id
+    fn repair_esi(&self, repair_symbol_id: u32) -> u32 {
+        let extended_source_symbols = extended_source_block_symbols(self.source_block_symbols);
+        match self.repair_esi_seed {
+            None => extended_source_symbols + repair_symbol_id,
+            Some(seed) => {
+                let repair_symbols = self.max_repair_symbols();
+                assert!(repair_symbol_id < repair_symbols);
+                let keys = permutation_keys(seed, self.source_block_id);
+                // Cycle walk the permutation of all 24 bit values, until it lands in the range
+                // of repair symbol ids, which makes it a permutation of that range
+                let mut id = repair_symbol_id;
+                loop {
+                    id = permute_esi_bits(id, &keys);
+                    if id < repair_symbols {
+                        return extended_source_symbols + id;
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn memory_usage(&self) -> MemoryUsage {
+        let source_symbols = self
+            .source_symbols
+            .iter()
+            .map(|symbol| symbol.as_bytes().len())
+            .sum();
+        let intermediate_symbols = self
+            .intermediate_symbols
+            .iter()
+            .map(|symbol| symbol.as_bytes().len())
+            .sum();
+        MemoryUsage::new(source_symbols, 0, intermediate_symbols, 0, 0)
+    }
+
+    /// Drops the copy of the source symbols, which are not needed to generate repair symbols,
+    /// roughly halving the memory held by senders which only transmit repair symbols, such as
+    /// data carousels. Source symbols can still be generated afterwards, but are then
+    /// recomputed from the intermediate symbols. A shared object is released.
+    pub fn shrink_to_repair_only(&mut self) {
+        self.source_symbols = vec![];
+        self.shared_source = None;
+    }
+
+    fn source_symbol(&self, esi: u32) -> Cow<'_, [u8]> {
+        if let Some((data, offset)) = self.shared_source.as_ref() {
+            let symbol_size = self.intermediate_symbols[0].as_bytes().len();
+            let start = offset + esi as usize * symbol_size;
+            Cow::Borrowed(&data.as_bytes()[start..(start + symbol_size)])
+        } else if self.source_symbols.is_empty() {
+            Cow::Owned(encode_repair_symbol(
+                &self.intermediate_symbols(),
+                extended_source_block_symbols(self.source_block_symbols),
+                esi,
+            ))
+        } else {
+            Cow::Borrowed(self.source_symbols[esi as usize].as_bytes())
+        }
+    }
+
+    /// Returns the encoding symbol with the given internal symbol id (ISI). ISIs in [0, K)
+    /// are the source symbols, and repair symbols start at K'.
+    pub fn encode_isi(&self, internal_symbol_id: u32) -> Vec<u8> {
+        if internal_symbol_id < self.source_block_symbols {
+            return self.source_symbol(internal_symbol_id).into_owned();
+        }
+        encode_repair_symbol(
+            &self.intermediate_symbols(),
+            extended_source_block_symbols(self.source_block_symbols),
+            internal_symbol_id,
+        )
+    }
+
+    /// Returns the intermediate symbols of the block, for use with `encode_repair_symbol()`, or
+    /// `from_intermediate_symbols()`
+    pub fn intermediate_symbols(&self) -> Vec<&[u8]> {
+        self.intermediate_symbols
+            .iter()
+            .map(|symbol| symbol.as_bytes())
+            .collect()
+    }
+
+    pub fn source_packets(&self) -> Vec<EncodingPacket> {
+        (0..self.source_block_symbols)
+            .map(|esi| {
+                EncodingPacket::new(
+                    PayloadId::new(self.source_block_id, esi),
+                    self.source_symbol(esi).into_owned(),
+                )
+            })
+            .collect()
+    }
+
+    // See section 5.3.4
+    pub fn repair_packets(&self, start_repair_symbol_id: u32, packets: u32) -> Vec<EncodingPacket> {
+        (start_repair_symbol_id..(start_repair_symbol_id + packets))
+            .map(|repair_symbol_id| self.repair_packet_with_id(repair_symbol_id))
+            .collect()
+    }
+
+    /// Same as `repair_packets()`, but generates each packet only when it is needed, for as many
+    /// as there are repair symbol ids, so that senders such as data carousels which send repair
+    /// packets until told to stop do not need to choose how many to generate ahead of time
+    pub fn repair_packets_iter(&self, start_repair_symbol_id: u32) -> RepairPacketIter<'_> {
+        RepairPacketIter {
+            encoder: self,
+            next_repair_symbol_id: start_repair_symbol_id,
+        }
+    }
+
+    // Number of distinct repair symbol ids, which is the number of ESIs above the padding symbols
+    fn max_repair_symbols(&self) -> u32 {
+        ESI_MASK + 1 - extended_source_block_symbols(self.source_block_symbols)
+    }
+
+    /// Returns the repair packet with the given encoding symbol id, without generating any
+    /// others, for example to answer a NACK for that ESI. Returns None if the ESI is not that of
+    /// a repair symbol, since it is usually received from the peer.
+    pub fn repair_packet(&self, encoding_symbol_id: u32) -> Option<EncodingPacket> {
+        if !self.is_repair_esi(encoding_symbol_id) {
+            return None;
+        }
+        Some(self.repair_packet_with_esi(encoding_symbol_id))
+    }
+
+    // Whether the ESI is that of a repair symbol, rather than a source or padding symbol, and fits
+    // in 24 bits
+    fn is_repair_esi(&self, encoding_symbol_id: u32) -> bool {
+        encoding_symbol_id >= extended_source_block_symbols(self.source_block_symbols)
+            && encoding_symbol_id <= ESI_MASK
+    }
+
+    fn repair_packet_with_id(&self, repair_symbol_id: u32) -> EncodingPacket {
+        self.repair_packet_with_esi(self.repair_esi(repair_symbol_id))
+    }
+
+    fn repair_packet_with_esi(&self, esi: u32) -> EncodingPacket {
+        let tuple = intermediate_tuple(
+            esi,
+            num_lt_symbols(self.source_block_symbols),
+            systematic_index(self.source_block_symbols),
+            calculate_p1(self.source_block_symbols),
+        );
+        EncodingPacket::new(
+            PayloadId::new(self.source_block_id, esi),
+            enc(self.source_block_symbols, &self.intermediate_symbols, tuple).into_bytes(),
+        )
+    }
+
+    /// Returns quick repair packets, which are each the sum of a few source symbols, as chosen by
+    /// the LT tuple generator, rather than of intermediate symbols. They are much cheaper to
+    /// generate than repair packets, and are available from a `DeferredEncoder` before the
+    /// intermediate symbols have been solved for, but a decoder can only use them to recover
+    /// a source symbol when all of the others in the sum have been received, so they repair few
+    /// losses. The ESI field of the payload id holds the quick repair id, so they are not valid
+    /// RaptorQ packets, and must be passed to `SourceBlockDecoder::decode_quick_repair()`, never
+    /// to `decode()`.
+    pub fn quick_repair_packets(
+        &self,
+        start_quick_repair_id: u32,
+        packets: u32,
+    ) -> Vec<EncodingPacket> {
+        let symbol_size = self.intermediate_symbols[0].as_bytes().len();
+        (start_quick_repair_id..(start_quick_repair_id + packets))
+            .map(|id| {
+                quick_repair_packet(
+                    self.source_block_id,
+                    self.source_block_symbols,
+                    symbol_size,
+                    id,
+                    |esi| self.source_symbol(esi),
+                )
+            })
+            .collect()
+    }
+
+    /// Writes the packet of the repair symbol with the given encoding symbol id to the start of
+    /// `buffer`: the serialized payload id, followed by the symbol, which is computed in place
+    /// rather than in a newly allocated symbol. Returns the number of bytes written, which is the
+    /// symbol size plus 4. Panics if `buffer` is too small, or if the ESI is not that of a repair
+    /// symbol.
+    pub fn write_repair_symbol(&self, encoding_symbol_id: u32, buffer: &mut [u8]) -> usize {
+        match self.try_write_repair_symbol(encoding_symbol_id, buffer) {
+            Ok(length) => length,
+            Err(error) => panic!("{}", error),
+        }
+    }
+
+    /// Same as `write_repair_symbol()`, but returns an error rather than panicking. This neither
+    /// panics nor allocates, so repair symbols can be generated where both are forbidden once the
+    /// encoder has been created. Only this method makes that guarantee: creating the encoder, its
+    /// other methods, and decoding may still panic and allocate.
+    pub fn try_write_repair_symbol(
+        &self,
+        encoding_symbol_id: u32,
+        buffer: &mut [u8],
+    ) -> Result<usize, RepairSymbolError> {
+        if !self.is_repair_esi(encoding_symbol_id) {
+            return Err(RepairSymbolError::NotRepairSymbol { encoding_symbol_id });
+        }
+        let payload_id = PayloadId::new(self.source_block_id, encoding_symbol_id);
+        let length = 4 + self.intermediate_symbols[0].as_bytes().len();
+        if buffer.len() < length {
+            return Err(RepairSymbolError::BufferTooSmall { required: length });
+        }
+        buffer[..4].copy_from_slice(&payload_id.serialize());
+
+        let lt_symbols = num_lt_symbols(self.source_block_symbols);
+        let p1 = calculate_p1(self.source_block_symbols);
+        let tuple = intermediate_tuple(
+            encoding_symbol_id,
+            lt_symbols,
+            systematic_index(self.source_block_symbols),
+            p1,
+        );
+        let symbol = &mut buffer[4..length];
+        let mut first = true;
+        for_each_enc_index(
+            tuple,
+            lt_symbols,
+            num_pi_symbols(self.source_block_symbols),
+            p1,
+            |i| {
+                if first {
+                    symbol.copy_from_slice(self.intermediate_symbols[i].as_bytes());
+                    first = false;
+                } else {
+                    add_assign(symbol, self.intermediate_symbols[i].as_bytes());
+                }
+            },
+        );
+        Ok(length)
+    }
+}
+
+/// Returned by `SourceBlockEncoder::try_write_repair_symbol()`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RepairSymbolError {
+    /// The ESI is that of a source or padding symbol, or is more than 24 bits
+    NotRepairSymbol { encoding_symbol_id: u32 },
+    /// The buffer is smaller than the packet, which needs `required` bytes
+    BufferTooSmall { required: usize },
 }
 
-impl Encoder {
-    pub fn with_defaults(data: &[u8], maximum_transmission_unit: u16) -> Encoder {
-        let config = ObjectTransmissionInformation::with_defaults(
-            data.len() as u64,
-            maximum_transmission_unit,
-        );
+impl fmt::Display for RepairSymbolError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RepairSymbolError::NotRepairSymbol { encoding_symbol_id } => write!(
+                f,
+                "{} is not the ESI of a repair symbol",
+                encoding_symbol_id
+            ),
+            RepairSymbolError::BufferTooSmall { .. } => {
+                write!(f, "Buffer is smaller than a packet")
+            }
+        }
+    }
+}
+
+impl std::error::Error for RepairSymbolError {}
 
-        let kt = (config.transfer_length() as f64 / config.symbol_size() as f64).ceil() as u32;
-        let (kl, ks, zl, zs) = partition(kt, config.source_blocks());
+/// Iterator over the repair packets of a source block, which are generated as they are consumed.
+/// See `SourceBlockEncoder::repair_packets_iter()`
+#[derive(Clone, Debug)]
+pub struct RepairPacketIter<'a> {
+    encoder: &'a SourceBlockEncoder,
+    next_repair_symbol_id: u32,
+}
 
-        // TODO: support subblocks
-        assert_eq!(1, config.sub_blocks());
-        //        let (tl, ts, nl, ns) = partition((config.symbol_size() / config.alignment() as u16) as u32, config.sub_blocks());
+impl<'a> Iterator for RepairPacketIter<'a> {
+    type Item = EncodingPacket;
 
-        let mut data_index = 0;
-        let mut blocks = vec![];
-        for i in 0..zl {
-            let offset = kl as usize * config.symbol_size() as usize;
-            blocks.push(SourceBlockEncoder::new(
-                i as u8,
-                config.symbol_size(),
-                &data[data_index..(data_index + offset)],
-            ));
-            data_index += offset;
+    fn next(&mut self) -> Option<EncodingPacket> {
+        if self.next_repair_symbol_id >= self.encoder.max_repair_symbols() {
+            return None;
         }
+        let packet = self
+            .encoder
+            .repair_packet_with_id(self.next_repair_symbol_id);
+        self.next_repair_symbol_id += 1;
+        Some(packet)
+    }
 
-        for i in 0..zs {
-            let offset = ks as usize * config.symbol_size() as usize;
-            if data_index + offset <= data.len() {
-                blocks.push(SourceBlockEncoder::new(
-                    i as u8,
-                    config.symbol_size(),
-                    &data[data_index..(data_index + offset)],
-                ));
-            } else {
-                // Should only be possible when Kt * T > F. See third to last paragraph in section 4.4.1.2
-                assert!(kt as usize * config.symbol_size() as usize > data.len());
-                // Zero pad the last symbol
-                let mut padded = Vec::from(&data[data_index..]);
-                padded.extend(vec![
-                    0;
-                    kt as usize * config.symbol_size() as usize - data.len()
-                ]);
-                blocks.push(SourceBlockEncoder::new(
-                    i as u8,
-                    config.symbol_size(),
-                    &padded,
-                ));
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self
+            .encoder
+            .max_repair_symbols()
+            .saturating_sub(self.next_repair_symbol_id) as usize;
+        (remaining, Some(remaining))
+    }
+}
+
+// Returns the ESIs of the source symbols which are added together to form the given quick repair
+// symbol. These follow the tuple generator of section 5.3.5.4, as for the repair symbol with the
+// same id, but step through the K source symbols, rather than the W LT intermediate symbols.
+// Source symbols which are visited twice cancel out.
+pub(crate) fn quick_repair_indices(source_block_symbols: u32, quick_repair_id: u32) -> Vec<u32> {
+    let k = source_block_symbols;
+    if k == 1 {
+        return vec![0];
+    }
+    let (d, a, b, _, _, _) = intermediate_tuple(
+        extended_source_block_symbols(k) + quick_repair_id,
+        num_lt_symbols(k),
+        systematic_index(k),
+        calculate_p1(k),
+    );
+    let step = 1 + a % (k - 1);
+    let mut esi = b % k;
+    let mut indices: Vec<u32> = vec![];
+    for _ in 0..d {
+        match indices.binary_search(&esi) {
+            Ok(i) => {
+                indices.remove(i);
             }
-            data_index += offset;
+            Err(i) => indices.insert(i, esi),
         }
-
-        Encoder { config, blocks }
+        esi = (esi + step) % k;
     }
+    indices
+}
 
-    pub fn get_config(&self) -> ObjectTransmissionInformation {
-        self.config.clone()
+// Adds together the source symbols of the given quick repair symbol, where `source_symbol` returns
+// the source symbol with the given ESI
+fn quick_repair_packet<'a, F: Fn(u32) -> Cow<'a, [u8]>>(
+    source_block_id: u8,
+    source_block_symbols: u32,
+    symbol_size: usize,
+    quick_repair_id: u32,
+    source_symbol: F,
+) -> EncodingPacket {
+    let mut symbol = vec![0; symbol_size];
+    for esi in quick_repair_indices(source_block_symbols, quick_repair_id) {
+        add_assign(&mut symbol, &source_symbol(esi));
     }
+    EncodingPacket::new(PayloadId::new(source_block_id, quick_repair_id), symbol)
+}
 
-    pub fn get_encoded_packets(&self, repair_packets_per_block: u32) -> Vec<EncodingPacket> {
-        let mut packets = vec![];
-        for encoder in self.blocks.iter() {
-            packets.extend(encoder.source_packets());
-            packets.extend(encoder.repair_packets(0, repair_packets_per_block));
-        }
-        packets
+// Derives the round keys of the ESI permutation of a source block from the seed
+fn permutation_keys(seed: u64, source_block_number: u8) -> [u32; 3] {
+    // SplitMix64
+    let mut state = seed ^ u64::from(source_block_number).wrapping_mul(0x9E37_79B9_7F4A_7C15);
+    let mut keys = [0; 3];
+    for key in keys.iter_mut() {
+        state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        *key = (z ^ (z >> 31)) as u32;
     }
+    keys
+}
 
-    pub fn get_block_encoders(&self) -> &Vec<SourceBlockEncoder> {
-        &self.blocks
+// A permutation of 24 bit values. Each step is invertible modulo 2^24: xor with a key,
+// multiplication by an odd constant, and xor with a right shift of itself.
+fn permute_esi_bits(mut value: u32, keys: &[u32; 3]) -> u32 {
+    for &key in keys.iter() {
+        value ^= key & ESI_MASK;
+        value = value.wrapping_mul(0x9E_3779 | 1) & ESI_MASK;
+        value ^= value >> 13;
     }
+    value
 }
 
+/// The symbol operations which solve for the intermediate symbols of a source block. They depend
+/// only on the number of extended source symbols (K'), so a plan can be generated once and reused
+/// for every block with the same K', which skips the solving of the constraint matrix, and only
+/// performs the symbol operations. This speeds up senders which encode many blocks or objects of
+/// the same shape. See `SourceBlockEncoder::with_encoding_plan()`.
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
-pub struct SourceBlockEncoder {
-    source_block_id: u8,
-    source_symbols: Vec<Symbol>,
-    intermediate_symbols: Vec<Symbol>,
+pub struct SourceBlockEncodingPlan {
+    extended_source_symbols: u32,
+    operations: Vec<SymbolOp>,
+    // Index in D of each intermediate symbol, once the operations have been performed
+    intermediate_symbol_order: Vec<usize>,
 }
 
-impl SourceBlockEncoder {
-    pub fn new(source_block_id: u8, symbol_size: u16, data: &[u8]) -> SourceBlockEncoder {
-        assert_eq!(data.len() % symbol_size as usize, 0);
-        let source_symbols: Vec<Symbol> = data
-            .chunks(symbol_size as usize)
-            .map(|x| Symbol::new(Vec::from(x)))
-            .collect();
-        let intermediate_symbols = gen_intermediate_symbols(
-            &source_symbols,
-            symbol_size as usize,
-            SPARSE_MATRIX_THRESHOLD,
-        );
-        SourceBlockEncoder {
-            source_block_id,
-            source_symbols,
-            intermediate_symbols,
+impl SourceBlockEncodingPlan {
+    /// Generates the plan for blocks with `source_symbols` source symbols, which is also the plan
+    /// for any other number of source symbols with the same K'
+    #[allow(non_snake_case)]
+    pub fn generate(source_symbols: u32) -> SourceBlockEncodingPlan {
+        let extended_source_symbols = extended_source_block_symbols(source_symbols);
+        // Symbols of zero length, so that only the operations performed on them are of interest
+        let D = constraint_symbols(&vec![[0; 0]; extended_source_symbols as usize], 0);
+        let indices: Vec<u32> = (0..extended_source_symbols).collect();
+        let (operations, permutations) = if extended_source_symbols >= SPARSE_MATRIX_THRESHOLD {
+            let A =
+                generate_constraint_matrix::<SparseOctetMatrix>(extended_source_symbols, &indices);
+            record_operations(IntermediateSymbolDecoder::new(
+                A,
+                D,
+                extended_source_symbols,
+            ))
+        } else {
+            let A =
+                generate_constraint_matrix::<DenseOctetMatrix>(extended_source_symbols, &indices);
+            record_operations(IntermediateSymbolDecoder::new(
+                A,
+                D,
+                extended_source_symbols,
+            ))
+        };
+        // See end of section 5.4.2.1
+        let mut intermediate_symbol_order = vec![0; permutations.column_order().len()];
+        for (column, row) in permutations
+            .column_order()
+            .iter()
+            .zip(permutations.row_order())
+        {
+            intermediate_symbol_order[*column] = *row;
+        }
+        SourceBlockEncodingPlan {
+            extended_source_symbols,
+            operations,
+            intermediate_symbol_order,
         }
     }
 
-    pub fn source_packets(&self) -> Vec<EncodingPacket> {
-        let mut esi: i32 = -1;
-        self.source_symbols
+    /// K', the number of extended source symbols of the blocks which this plan encodes
+    pub fn extended_source_symbols(&self) -> u32 {
+        self.extended_source_symbols
+    }
+
+    #[allow(non_snake_case)]
+    fn intermediate_symbols<T: AsRef<[u8]>>(
+        &self,
+        source_block: &[T],
+        symbol_size: usize,
+    ) -> Vec<Symbol> {
+        assert_eq!(
+            extended_source_block_symbols(source_block.len() as u32),
+            self.extended_source_symbols,
+            "The plan is for {} extended source symbols",
+            self.extended_source_symbols
+        );
+        let mut D = constraint_symbols(source_block, symbol_size);
+        for operation in self.operations.iter() {
+            operation.apply(&mut D);
+        }
+        let mut removable_D: Vec<Option<Symbol>> = D.into_iter().map(Some).collect();
+        self.intermediate_symbol_order
             .iter()
-            .map(|symbol| {
-                esi += 1;
-                EncodingPacket::new(
-                    PayloadId::new(self.source_block_id, esi as u32),
-                    symbol.as_bytes().to_vec(),
-                )
-            })
+            .map(|index| removable_D[*index].take().unwrap())
             .collect()
     }
+}
 
-    // See section 5.3.4
-    pub fn repair_packets(&self, start_repair_symbol_id: u32, packets: u32) -> Vec<EncodingPacket> {
-        let start_encoding_symbol_id = start_repair_symbol_id
-            + extended_source_block_symbols(self.source_symbols.len() as u32);
-        let mut result = vec![];
-        let lt_symbols = num_lt_symbols(self.source_symbols.len() as u32);
-        let sys_index = systematic_index(self.source_symbols.len() as u32);
-        let p1 = calculate_p1(self.source_symbols.len() as u32);
-        for i in 0..packets {
-            let tuple = intermediate_tuple(start_encoding_symbol_id + i, lt_symbols, sys_index, p1);
-            result.push(EncodingPacket::new(
-                PayloadId::new(self.source_block_id, start_encoding_symbol_id + i),
-                enc(
-                    self.source_symbols.len() as u32,
-                    &self.intermediate_symbols,
-                    tuple,
-                )
-                .into_bytes(),
-            ));
-        }
-        result
+fn record_operations<T: OctetMatrix>(
+    mut decoder: IntermediateSymbolDecoder<T>,
+) -> (Vec<SymbolOp>, SolverPermutations) {
+    decoder.record_operations();
+    decoder
+        .execute()
+        .expect("The constraint matrix of a source block is always solvable");
+    (
+        decoder.take_recorded_operations().unwrap(),
+        decoder.permutations(),
+    )
+}
+
+/// Generates the encoding symbol with the given internal symbol id (ISI) from the intermediate
+/// symbols of a source block, without any other encoder state. This allows senders which keep the
+/// intermediate symbols (see `SourceBlockEncoder::intermediate_symbols()`) in shared storage to
+/// generate any symbol on demand. `extended_source_block_symbols` is K', and
+/// `intermediate_symbols` must contain the L intermediate symbols for it.
+///
+/// See section 5.3.5.3
+pub fn encode_repair_symbol<T: AsRef<[u8]>>(
+    intermediate_symbols: &[T],
+    extended_source_block_symbols: u32,
+    internal_symbol_id: u32,
+) -> Vec<u8> {
+    let k_prime = extended_source_block_symbols;
+    assert_eq!(
+        k_prime,
+        crate::systematic_constants::extended_source_block_symbols(k_prime),
+        "{} is not a supported number of extended source symbols",
+        k_prime
+    );
+    assert_eq!(
+        intermediate_symbols.len(),
+        num_intermediate_symbols(k_prime) as usize
+    );
+    let lt_symbols = num_lt_symbols(k_prime);
+    let tuple = intermediate_tuple(
+        internal_symbol_id,
+        lt_symbols,
+        systematic_index(k_prime),
+        calculate_p1(k_prime),
+    );
+    let mut result = vec![0; intermediate_symbols[0].as_ref().len()];
+    for i in enc_indices(
+        tuple,
+        lt_symbols,
+        num_pi_symbols(k_prime),
+        calculate_p1(k_prime),
+    ) {
+        add_assign(&mut result, intermediate_symbols[i].as_ref());
     }
+    result
 }
 
-// See section 5.3.3.4
+// The symbols D of section 5.3.3.4, which are the source symbols preceded by the zero symbols of
+// the LDPC and HDPC constraints, and followed by the padding symbols
 #[allow(non_snake_case)]
-fn gen_intermediate_symbols(
-    source_block: &[Symbol],
-    symbol_size: usize,
-    sparse_threshold: u32,
-) -> Vec<Symbol> {
+fn constraint_symbols<T: AsRef<[u8]>>(source_block: &[T], symbol_size: usize) -> Vec<Symbol> {
     let L = num_intermediate_symbols(source_block.len() as u32);
     let S = num_ldpc_symbols(source_block.len() as u32);
     let H = num_hdpc_symbols(source_block.len() as u32);
@@ -179,13 +1847,25 @@ fn gen_intermediate_symbols(
         D.push(Symbol::zero(symbol_size));
     }
     for symbol in source_block {
-        D.push(symbol.clone());
+        D.push(Symbol::new(symbol.as_ref().to_vec()));
     }
     // Extend the source block with padding. See section 5.3.2
     for _ in 0..(extended_source_symbols as usize - source_block.len()) {
         D.push(Symbol::zero(symbol_size));
     }
     assert_eq!(D.len(), L as usize);
+    D
+}
+
+// See section 5.3.3.4
+#[allow(non_snake_case)]
+fn gen_intermediate_symbols<T: AsRef<[u8]>>(
+    source_block: &[T],
+    symbol_size: usize,
+    sparse_threshold: u32,
+) -> Vec<Symbol> {
+    let extended_source_symbols = extended_source_block_symbols(source_block.len() as u32);
+    let D = constraint_symbols(source_block, symbol_size);
 
     let indices: Vec<u32> = (0..extended_source_symbols).collect();
     if extended_source_symbols >= sparse_threshold {
@@ -240,18 +1920,30 @@ fn enc(
 
 #[cfg(test)]
 mod tests {
+    use proptest::prelude::*;
     use rand::Rng;
 
-    use crate::base::intermediate_tuple;
     use crate::encoder::enc;
+    use crate::encoder::encode_repair_symbol;
     use crate::encoder::gen_intermediate_symbols;
+    use crate::encoder::source_blocks;
     use crate::symbol::Symbol;
+    use crate::systematic_constants::extended_source_block_symbols;
     use crate::systematic_constants::num_lt_symbols;
     use crate::systematic_constants::num_pi_symbols;
     use crate::systematic_constants::{
         calculate_p1, num_ldpc_symbols, systematic_index, MAX_SOURCE_SYMBOLS_PER_BLOCK,
     };
-    use crate::{Encoder, EncodingPacket};
+    use crate::tuple::intermediate_tuple;
+    #[cfg(feature = "mmap")]
+    use crate::FileEncoder;
+    use crate::{
+        ConfigError, Decoder, DeferredEncoder, Encoder, EncodingPacket,
+        ObjectTransmissionInformation, RepairSymbolError, SourceBlockEncoder,
+        SourceBlockEncodingPlan,
+    };
+    use std::io::{IoSlice, Write};
+    use std::sync::Arc;
 
     const SYMBOL_SIZE: usize = 4;
     const NUM_SYMBOLS: u32 = 100;
@@ -355,6 +2047,64 @@ mod tests {
         padding_constraint(packet_size, padding_size, data_size);
     }
 
+    #[test]
+    fn stateless_repair_symbol() {
+        let data = gen_test_data(SYMBOL_SIZE * NUM_SYMBOLS as usize);
+        let encoder = SourceBlockEncoder::new(0, SYMBOL_SIZE as u16, &data);
+        let intermediate_symbols: Vec<Vec<u8>> = encoder
+            .intermediate_symbols()
+            .iter()
+            .map(|symbol| symbol.to_vec())
+            .collect();
+        let k_prime = extended_source_block_symbols(NUM_SYMBOLS);
+        for packet in encoder.repair_packets(0, 10) {
+            assert_eq!(
+                encode_repair_symbol(
+                    &intermediate_symbols,
+                    k_prime,
+                    packet.payload_id().encoding_symbol_id()
+                ),
+                packet.data()
+            );
+        }
+        // Source symbols can be generated too
+        assert_eq!(
+            encode_repair_symbol(&intermediate_symbols, k_prime, 3),
+            &data[(3 * SYMBOL_SIZE)..(4 * SYMBOL_SIZE)]
+        );
+    }
+
+    #[test]
+    fn shrink_to_repair_only() {
+        let data = gen_test_data(1000);
+        let mut encoder = Encoder::with_defaults(&data, 64);
+        let expected = encoder.get_encoded_packets(5);
+        let memory = encoder.memory_usage();
+        encoder.shrink_to_repair_only();
+        assert_eq!(encoder.memory_usage().source_symbols(), 0);
+        assert_eq!(
+            encoder.memory_usage().intermediate_symbols(),
+            memory.intermediate_symbols()
+        );
+        assert_eq!(encoder.get_encoded_packets(5), expected);
+        assert!(encoder.encodes(&data));
+    }
+
+    #[test]
+    fn total_packets_for_overhead() {
+        let data = gen_test_data(10_000);
+        let encoder = Encoder::with_defaults(&data, 64);
+        let min_packets = encoder.get_config().min_packets();
+        assert_eq!(min_packets, 157);
+        assert_eq!(encoder.total_packets_for_overhead(0), min_packets);
+        assert_eq!(encoder.total_packets_for_overhead(10), min_packets + 16);
+        assert_eq!(encoder.total_packets_for_overhead(100), 2 * min_packets);
+        assert_eq!(
+            encoder.get_encoded_packets(0).len() as u64,
+            encoder.total_packets_for_overhead(0)
+        );
+    }
+
     #[test]
     fn padding_constraint_42_bytes() {
         let packet_size: u16 = 1024;
@@ -382,4 +2132,634 @@ mod tests {
         assert_eq!(data_size + padding_size, padded_data.len());
         assert_eq!(data[..], padded_data[..data_size]);
     }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(32))]
+
+        #[test]
+        fn esi_isi_mapping(
+            source_symbols in 1u32..200,
+            seed in proptest::option::of(any::<u64>()),
+            start_repair_symbol_id in 0u32..10_000,
+        ) {
+            let data = vec![7; source_symbols as usize];
+            let mut block = SourceBlockEncoder::new(3, 1, &data);
+            block.set_repair_esi_seed(seed);
+            let extended_source_symbols = extended_source_block_symbols(source_symbols);
+
+            // Source ESIs are their ISIs, and repair ESIs are above the padding symbols
+            for (esi, packet) in block.source_packets().iter().enumerate() {
+                prop_assert_eq!(packet.payload_id().encoding_symbol_id(), esi as u32);
+                prop_assert_eq!(block.encode_isi(esi as u32), packet.data().to_vec());
+            }
+            let repair = block.repair_packets(start_repair_symbol_id, 20);
+            let mut esis = vec![];
+            for packet in repair.iter() {
+                let esi = packet.payload_id().encoding_symbol_id();
+                prop_assert!(esi >= extended_source_symbols && esi < (1 << 24));
+                prop_assert_eq!(packet.payload_id().source_block_number(), 3);
+                prop_assert_eq!(block.encode_isi(esi), packet.data().to_vec());
+                esis.push(esi);
+            }
+            esis.sort_unstable();
+            esis.dedup();
+            prop_assert_eq!(esis.len(), 20);
+        }
+
+        #[test]
+        fn block_partitioning(
+            transfer_length in 1usize..20_000,
+            symbol_size in 1u16..=64,
+            num_blocks in 1u8..=20,
+        ) {
+            // Every block must contain at least one symbol
+            prop_assume!(
+                num_blocks as usize <= (transfer_length + symbol_size as usize - 1) / symbol_size as usize
+            );
+            let data: Vec<u8> = (0..transfer_length).map(|i| i as u8).collect();
+            let config = ObjectTransmissionInformation::new(
+                transfer_length as u64,
+                symbol_size,
+                num_blocks,
+                1,
+                1,
+            );
+            let blocks = source_blocks(&data, &config);
+            prop_assert_eq!(blocks.len(), num_blocks as usize);
+            let symbol_size = symbol_size as usize;
+            let symbols: Vec<usize> = blocks.iter().map(|block| block.len() / symbol_size).collect();
+            // Blocks are a whole number of symbols, larger blocks first, differing by at most one
+            prop_assert!(blocks.iter().all(|block| block.len() % symbol_size == 0));
+            prop_assert!(symbols.windows(2).all(|pair| pair[0] >= pair[1]));
+            prop_assert!(symbols[0] - symbols[symbols.len() - 1] <= 1);
+            prop_assert_eq!(
+                symbols.iter().sum::<usize>(),
+                (transfer_length + symbol_size - 1) / symbol_size
+            );
+            let (kl, ks, zl, zs) = config.checked_block_partition().unwrap();
+            prop_assert_eq!(zl + zs, u32::from(num_blocks));
+            for (i, &count) in symbols.iter().enumerate() {
+                let expected = if (i as u32) < zl { kl } else { ks };
+                prop_assert_eq!(count as u32, expected);
+            }
+            // Concatenated, they are the object followed by zero padding
+            let joined: Vec<u8> = blocks.iter().flat_map(|block| block.iter().cloned()).collect();
+            prop_assert_eq!(&joined[..transfer_length], &data[..]);
+            prop_assert!(joined[transfer_length..].iter().all(|&byte| byte == 0));
+        }
+    }
+
+    #[test]
+    fn randomized_repair_esis() {
+        let data = gen_test_data(64 * NUM_SYMBOLS as usize);
+        let mut encoder = Encoder::with_defaults(&data, 64);
+        encoder.set_repair_esi_seed(Some(42));
+        let block = &encoder.get_block_encoders()[0];
+        let extended_source_symbols = extended_source_block_symbols(NUM_SYMBOLS);
+
+        let esis = |packets: Vec<EncodingPacket>| -> Vec<u32> {
+            packets
+                .iter()
+                .map(|packet| packet.payload_id().encoding_symbol_id())
+                .collect()
+        };
+        let mut repair_esis = esis(block.repair_packets(0, 1000));
+        // Continuing from a later repair symbol id gives the same sequence
+        assert_eq!(repair_esis[500..], esis(block.repair_packets(500, 500))[..]);
+        assert!(repair_esis
+            .iter()
+            .all(|&esi| esi >= extended_source_symbols && esi < (1 << 24)));
+        assert!(repair_esis.windows(2).any(|pair| pair[1] != pair[0] + 1));
+        repair_esis.sort_unstable();
+        repair_esis.dedup();
+        assert_eq!(1000, repair_esis.len());
+
+        let mut other = Encoder::with_defaults(&data, 64);
+        other.set_repair_esi_seed(Some(43));
+        assert_ne!(
+            esis(block.repair_packets(0, 10)),
+            esis(other.get_block_encoders()[0].repair_packets(0, 10))
+        );
+
+        // Decode from only repair symbols
+        let mut decoder = Decoder::new(encoder.get_config());
+        let mut result = None;
+        for packet in block.repair_packets(0, NUM_SYMBOLS + 10) {
+            result = result.or(decoder.decode(packet));
+        }
+        assert_eq!(Some(data), result);
+    }
+
+    #[test]
+    fn packets_by_block() {
+        let data = gen_test_data(64 * 300);
+        let config = ObjectTransmissionInformation::new(data.len() as u64, 64, 3, 1, 8);
+        let encoder = Encoder::new(&data, config);
+
+        let grouped = encoder.get_encoded_packets_by_block(5);
+        assert_eq!(3, grouped.len());
+        for (block_number, packets) in grouped.iter().enumerate() {
+            assert!(packets
+                .iter()
+                .all(|packet| packet.payload_id().source_block_number() == block_number as u8));
+            assert_eq!(
+                encoder.get_block_packets(block_number as u8, 5),
+                packets.clone()
+            );
+        }
+        let flattened: Vec<EncodingPacket> = grouped.into_iter().flatten().collect();
+        assert_eq!(encoder.get_encoded_packets(5), flattened);
+    }
+
+    #[test]
+    fn source_packets_borrowed() {
+        let data = gen_test_data(10_000);
+        let mut encoder = Encoder::with_defaults(&data, 96);
+        let expected = encoder.get_encoded_packets(0);
+        encoder.shrink_to_repair_only();
+        let packets = encoder.source_packets_borrowed(&data);
+        assert_eq!(packets.len(), expected.len());
+        for (packet, expected) in packets.iter().zip(expected.iter()) {
+            assert_eq!(&packet.to_packet(), expected);
+            let mut serialized = vec![];
+            let padding = vec![0; packet.padding()];
+            let header = packet.header();
+            let slices = [
+                IoSlice::new(&header),
+                IoSlice::new(packet.data()),
+                IoSlice::new(&padding),
+            ];
+            let written = serialized.write_vectored(&slices).unwrap();
+            assert_eq!(written, expected.serialize().len());
+            assert_eq!(serialized, expected.serialize());
+        }
+        assert!(packets.last().unwrap().padding() > 0);
+        // Borrowed from the object
+        let symbol_size = encoder.get_config().symbol_size() as usize;
+        assert_eq!(packets[1].data().as_ptr(), data[symbol_size..].as_ptr());
+    }
+
+    #[test]
+    fn shared_encoder() {
+        let data = gen_test_data(64 * 300 + 10);
+        let config = ObjectTransmissionInformation::new(data.len() as u64, 64, 3, 1, 8);
+        let expected = Encoder::new(&data, config.clone());
+        let shared: Arc<[u8]> = Arc::from(data);
+        let encoder = Encoder::new_shared(shared.clone(), config);
+        assert_eq!(
+            encoder.get_encoded_packets(5),
+            expected.get_encoded_packets(5)
+        );
+        assert!(encoder.encodes(&shared));
+        // All but the zero padded last block borrow their symbols
+        let blocks = encoder.get_block_encoders();
+        assert_eq!(Arc::strong_count(&shared), 3);
+        assert_eq!(blocks[0].memory_usage().source_symbols(), 0);
+        assert!(blocks[2].memory_usage().source_symbols() > 0);
+        assert_eq!(
+            blocks[1].source_symbol(2).as_ptr(),
+            shared[(64 * 101 + 2 * 64)..].as_ptr()
+        );
+
+        // With sub-blocks, symbols are copied
+        let config = ObjectTransmissionInformation::new(shared.len() as u64, 64, 3, 2, 8);
+        let encoder = Encoder::new_shared(shared.clone(), config.clone());
+        assert_eq!(Arc::strong_count(&shared), 3);
+        assert_eq!(encoder, Encoder::new(&shared, config));
+    }
+
+    #[test]
+    #[cfg(feature = "mmap")]
+    fn encode_from_file() {
+        let data = gen_test_data(64 * 300 + 10);
+        let path = std::env::temp_dir().join(format!("raptorq-mmap-{}", std::process::id()));
+        std::fs::write(&path, &data).unwrap();
+        let file = std::fs::File::open(&path).unwrap();
+        let config = ObjectTransmissionInformation::new(data.len() as u64, 64, 3, 1, 8);
+        let expected = Encoder::new(&data, config.clone());
+        let encoder = unsafe { Encoder::from_file(&file, config.clone()) }.unwrap();
+        assert_eq!(
+            encoder.get_encoded_packets(5),
+            expected.get_encoded_packets(5)
+        );
+        // All but the zero padded last block read their symbols from the file
+        let blocks = encoder.get_block_encoders();
+        assert_eq!(blocks[0].memory_usage().source_symbols(), 0);
+
+        let config = ObjectTransmissionInformation::new(data.len() as u64 + 1, 64, 3, 1, 8);
+        let error = unsafe { Encoder::from_file(&file, config) }.unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidInput);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    #[cfg(feature = "mmap")]
+    fn file_encoder() {
+        let data = gen_test_data(64 * 300 + 10);
+        let path = std::env::temp_dir().join(format!("raptorq-file-{}", std::process::id()));
+        std::fs::write(&path, &data).unwrap();
+        let file = std::fs::File::open(&path).unwrap();
+        let config = ObjectTransmissionInformation::new(data.len() as u64, 64, 3, 1, 8);
+        let expected = Encoder::new(&data, config.clone());
+        let mut encoder = unsafe { FileEncoder::new(&file, config, 2) }.unwrap();
+        assert_eq!(encoder.source_blocks(), 3);
+        for &sbn in [0, 1, 2, 0, 2].iter() {
+            let block = encoder.block_encoder(sbn);
+            let expected_block = &expected.get_block_encoders()[sbn as usize];
+            assert_eq!(block.source_packets(), expected_block.source_packets());
+            assert_eq!(
+                block.repair_packets(3, 5),
+                expected_block.repair_packets(3, 5)
+            );
+            assert!(encoder.blocks.len() <= 2);
+        }
+        // Block 1 was the least recently used
+        let held: Vec<u8> = encoder.blocks.iter().map(|b| b.source_block_id).collect();
+        assert_eq!(held, vec![0, 2]);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn from_intermediate_symbols() {
+        let data = gen_test_data(64 * 300);
+        let encoder = SourceBlockEncoder::new(2, 64, &data);
+        let cached: Vec<Vec<u8>> = encoder
+            .intermediate_symbols()
+            .iter()
+            .map(|symbol| symbol.to_vec())
+            .collect();
+        let resumed = SourceBlockEncoder::from_intermediate_symbols(2, 300, &cached);
+        assert_eq!(resumed.repair_packets(7, 20), encoder.repair_packets(7, 20));
+        assert_eq!(resumed.source_packets(), encoder.source_packets());
+    }
+
+    #[test]
+    #[should_panic(expected = "A block of 200 source symbols has")]
+    fn from_intermediate_symbols_mismatch() {
+        let data = gen_test_data(64 * 300);
+        let encoder = SourceBlockEncoder::new(2, 64, &data);
+        SourceBlockEncoder::from_intermediate_symbols(2, 200, &encoder.intermediate_symbols());
+    }
+
+    #[test]
+    #[should_panic(expected = "are not those of a block of 297 source symbols")]
+    fn from_intermediate_symbols_same_extended_source_symbols() {
+        // 297 and 300 source symbols are both extended to 301
+        assert_eq!(
+            extended_source_block_symbols(297),
+            extended_source_block_symbols(300)
+        );
+        let data = gen_test_data(64 * 300);
+        let encoder = SourceBlockEncoder::new(2, 64, &data);
+        SourceBlockEncoder::from_intermediate_symbols(2, 297, &encoder.intermediate_symbols());
+    }
+
+    #[test]
+    fn encoding_plan() {
+        // Dense and sparse constraint matrices
+        for &source_symbols in [18, 300].iter() {
+            let plan = SourceBlockEncodingPlan::generate(source_symbols);
+            let extended_source_symbols = extended_source_block_symbols(source_symbols);
+            assert_eq!(plan.extended_source_symbols(), extended_source_symbols);
+            // The plan is reused for every number of source symbols with the same K'
+            for symbols in [source_symbols, extended_source_symbols].iter() {
+                let data = gen_test_data(*symbols as usize * 32);
+                let encoder = SourceBlockEncoder::with_encoding_plan(3, 32, &data, &plan);
+                assert_eq!(encoder, SourceBlockEncoder::new(3, 32, &data));
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "The plan is for 20 extended source symbols")]
+    fn encoding_plan_mismatch() {
+        let plan = SourceBlockEncodingPlan::generate(20);
+        SourceBlockEncoder::with_encoding_plan(0, 32, &gen_test_data(21 * 32), &plan);
+    }
+
+    #[test]
+    fn repair_cache() {
+        let data = gen_test_data(64 * 100);
+        let config = ObjectTransmissionInformation::new(data.len() as u64, 64, 2, 1, 8);
+        let mut encoder = Encoder::new(&data, config);
+        let expected = encoder.get_block_encoders()[1].repair_packet(60);
+        assert_eq!(encoder.repair_packet(1, 60), expected);
+        assert_eq!(encoder.repair_cache_stats(), None);
+
+        encoder.set_repair_cache_capacity(2);
+        for _ in 0..3 {
+            assert_eq!(encoder.repair_packet(1, 60), expected);
+        }
+        encoder.repair_packet(0, 60);
+        // Evicts (1, 60), which was used less recently than (0, 60)
+        encoder.repair_packet(0, 61);
+        encoder.repair_packet(0, 60);
+        assert_eq!(encoder.repair_packet(1, 60), expected);
+        let stats = encoder.repair_cache_stats().unwrap();
+        assert_eq!(stats.hits(), 3);
+        assert_eq!(stats.misses(), 4);
+        assert!((stats.hit_rate() - 3.0 / 7.0).abs() < 1e-9);
+
+        // The source block number and ESI are received from the peer
+        assert_eq!(encoder.repair_packet(2, 60), None);
+        assert_eq!(encoder.repair_packet(1, 0), None);
+        assert_eq!(encoder.repair_cache_stats().unwrap().misses(), 4);
+    }
+
+    #[test]
+    fn encoder_builder() {
+        let data = gen_test_data(10_000);
+        let encoder = Encoder::builder(64)
+            .source_blocks(2)
+            .sub_blocks(2)
+            .symbol_alignment(4)
+            .build(&data)
+            .unwrap();
+        let config = ObjectTransmissionInformation::new(data.len() as u64, 64, 2, 2, 4);
+        assert_eq!(encoder, Encoder::new(&data, config));
+
+        // As few source blocks as possible by default
+        let config = Encoder::builder(8)
+            .object_transmission_information(8 * 56403 + 1)
+            .unwrap();
+        assert_eq!(config.source_blocks(), 2);
+        assert_eq!(config.symbol_alignment(), 8);
+
+        assert_eq!(
+            Encoder::builder(60).build(&data),
+            Err(ConfigError::NonCompliant {
+                parameter: "Al",
+                source_block_symbols: 0
+            })
+        );
+        assert_eq!(
+            Encoder::builder(64).sub_blocks(0).build(&data),
+            Err(ConfigError::ZeroParameter)
+        );
+        assert_eq!(
+            Encoder::builder(64).sub_blocks(9).build(&data),
+            Err(ConfigError::UnsupportedSubBlocks)
+        );
+        assert_eq!(
+            Encoder::builder(8)
+                .source_blocks(1)
+                .object_transmission_information(8 * 56404),
+            Err(ConfigError::SourceBlockTooLarge {
+                source_block_symbols: 56404
+            })
+        );
+        assert_eq!(
+            Encoder::builder(8).object_transmission_information(8 * 56403 * 256),
+            Err(ConfigError::SourceBlockTooLarge {
+                source_block_symbols: 56625
+            })
+        );
+        assert_eq!(
+            Encoder::builder(64).object_transmission_information(1 << 40),
+            Err(ConfigError::TransferLengthTooLarge {
+                transfer_length: 1 << 40
+            })
+        );
+    }
+
+    #[test]
+    fn deferred_encoder() {
+        let data = gen_test_data(10_000);
+        // With sub-blocks, and a zero padded last symbol
+        let config = ObjectTransmissionInformation::new(data.len() as u64, 64, 2, 2, 8);
+        let expected = Encoder::new(&data, config.clone());
+        let mut deferred = DeferredEncoder::new(&data, config);
+        assert_eq!(deferred.source_packets(), expected.get_encoded_packets(0));
+        let quick_repair: Vec<EncodingPacket> = expected
+            .get_block_encoders()
+            .iter()
+            .flat_map(|block| block.quick_repair_packets(5, 3))
+            .collect();
+        assert_eq!(deferred.quick_repair_packets(5, 3), quick_repair);
+        assert_eq!(deferred.encoder(), &expected);
+        assert!(deferred.is_ready());
+        assert_eq!(deferred.into_encoder(), expected);
+    }
+
+    #[test]
+    fn streaming_encoder() {
+        let data = gen_test_data(10_000);
+        // With sub-blocks, and a zero padded last symbol
+        let config = ObjectTransmissionInformation::new(data.len() as u64, 64, 3, 2, 8);
+        let expected = Encoder::new(&data, config.clone());
+        let mut streaming = Encoder::streaming(config);
+        let mut completed = 0;
+        for chunk in data.chunks(1000) {
+            completed += streaming.push(chunk);
+            assert_eq!(streaming.get_block_encoders().len(), completed);
+            assert_eq!(
+                streaming.get_block_encoders()[..],
+                expected.get_block_encoders()[..completed]
+            );
+        }
+        assert_eq!(completed, 3);
+        assert!(streaming.is_complete());
+        assert_eq!(streaming.received(), data.len() as u64);
+        assert_eq!(streaming.finalize(), expected);
+    }
+
+    #[test]
+    fn from_reader() {
+        let data = gen_test_data(10_000);
+        let config = ObjectTransmissionInformation::new(data.len() as u64, 64, 3, 2, 8);
+        let expected = Encoder::new(&data, config.clone());
+        assert_eq!(
+            Encoder::from_reader(&data[..], config.clone()).unwrap(),
+            expected
+        );
+        let error = Encoder::from_reader(&data[..9000], config.clone()).unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::UnexpectedEof);
+
+        // One block at a time
+        let mut blocks = Encoder::read_blocks(&data[..], config.clone());
+        for expected_block in expected.get_block_encoders() {
+            assert_eq!(&blocks.next().unwrap().unwrap(), expected_block);
+        }
+        assert!(blocks.next().is_none());
+        let mut blocks = Encoder::read_blocks(&data[..9000], config);
+        assert!(blocks.next().unwrap().is_ok());
+        assert!(blocks.next().unwrap().is_ok());
+        assert!(blocks.next().unwrap().is_err());
+        assert!(blocks.next().is_none());
+    }
+
+    #[test]
+    fn repair_packets_by_importance() {
+        let data = gen_test_data(4 * 64 * 100);
+        let config = ObjectTransmissionInformation::new(data.len() as u64, 64, 4, 1, 1);
+        let mut encoder = Encoder::new(&data, config);
+        assert_eq!(encoder.block_importance(), vec![0; 4]);
+        // Without importance, the packets are shared evenly
+        let packets = encoder.repair_packets_by_importance(0, 40);
+        assert_eq!(packets.len(), 40);
+        for block in 0..4 {
+            let count = packets
+                .iter()
+                .filter(|packet| packet.payload_id().source_block_number() == block)
+                .count();
+            assert_eq!(count, 10);
+        }
+
+        // A range within the third block, and one which straddles the first two
+        encoder.set_importance(2 * 6400 + 100, 2 * 6400 + 200, 3);
+        encoder.set_importance(6000, 6500, 1);
+        assert_eq!(encoder.block_importance(), vec![1, 1, 3, 0]);
+        let packets = encoder.repair_packets_by_importance(0, 61);
+        assert_eq!(packets.len(), 61);
+        let blocks: Vec<u8> = packets
+            .iter()
+            .map(|packet| packet.payload_id().source_block_number())
+            .collect();
+        // Weights of 2, 2, 4 and 1, with the leftover packets going to the most important blocks
+        let mut expected = vec![2; 28];
+        expected.extend(vec![0; 14]);
+        expected.extend(vec![1; 13]);
+        expected.extend(vec![3; 6]);
+        assert_eq!(blocks, expected);
+        let expected_packets = encoder.get_block_encoders()[2].repair_packets(0, 28);
+        assert_eq!(packets[..28], expected_packets[..]);
+
+        encoder.clear_importance();
+        assert_eq!(encoder.block_importance(), vec![0; 4]);
+
+        // Ranges are clamped to the object
+        encoder.set_importance(3 * 6400 + 100, 1 << 40, 2);
+        encoder.set_importance(6000, 5000, 5);
+        assert_eq!(encoder.block_importance(), vec![0, 0, 0, 2]);
+    }
+
+    #[test]
+    #[cfg(feature = "json")]
+    fn deserialize_without_importance() {
+        let data = gen_test_data(64 * 100);
+        let config = ObjectTransmissionInformation::new(data.len() as u64, 64, 1, 1, 1);
+        let encoder = Encoder::new(&data, config);
+        // As serialized before importance was added
+        let mut value = serde_json::to_value(&encoder).unwrap();
+        value.as_object_mut().unwrap().remove("importance");
+        let deserialized: Encoder = serde_json::from_value(value).unwrap();
+        assert_eq!(deserialized, encoder);
+    }
+
+    #[test]
+    #[cfg(feature = "json")]
+    fn deserialize_source_block_encoder_without_new_fields() {
+        let data = gen_test_data(64 * 100);
+        let encoder = SourceBlockEncoder::new(3, 64, &data);
+        // As serialized before the number of source symbols and the seed were recorded
+        let mut value = serde_json::to_value(&encoder).unwrap();
+        let fields = value.as_object_mut().unwrap();
+        fields.remove("source_block_symbols").unwrap();
+        fields.remove("repair_esi_seed").unwrap();
+        let deserialized: SourceBlockEncoder = serde_json::from_value(value).unwrap();
+        assert_eq!(deserialized, encoder);
+        assert_eq!(
+            deserialized.repair_packets(0, 5),
+            encoder.repair_packets(0, 5)
+        );
+
+        let mut encoder = encoder;
+        encoder.set_repair_esi_seed(Some(9));
+        let value = serde_json::to_value(&encoder).unwrap();
+        let deserialized: SourceBlockEncoder = serde_json::from_value(value).unwrap();
+        assert_eq!(deserialized, encoder);
+    }
+
+    #[test]
+    fn repair_packets_iter() {
+        let data = gen_test_data(16 * 50);
+        let mut encoder = SourceBlockEncoder::new(2, 16, &data);
+        let packets: Vec<EncodingPacket> = encoder.repair_packets_iter(7).take(30).collect();
+        assert_eq!(packets, encoder.repair_packets(7, 30));
+        encoder.set_repair_esi_seed(Some(9));
+        let packets: Vec<EncodingPacket> = encoder.repair_packets_iter(0).take(30).collect();
+        assert_eq!(packets, encoder.repair_packets(0, 30));
+
+        // Ends with the last ESI
+        let last = (1 << 24) - extended_source_block_symbols(50);
+        let mut iter = encoder.repair_packets_iter(last - 2);
+        assert_eq!(iter.size_hint(), (2, Some(2)));
+        assert_eq!(
+            iter.next(),
+            Some(encoder.repair_packets(last - 2, 1).remove(0))
+        );
+        assert!(iter.nth(1).is_none());
+    }
+
+    #[test]
+    fn repair_packet() {
+        let data = gen_test_data(16 * 50);
+        let mut encoder = SourceBlockEncoder::new(1, 16, &data);
+        encoder.set_repair_esi_seed(Some(5));
+        for packet in encoder.repair_packets(100, 10) {
+            let esi = packet.payload_id().encoding_symbol_id();
+            assert_eq!(encoder.repair_packet(esi), Some(packet));
+        }
+    }
+
+    #[test]
+    fn repair_packet_invalid_esi() {
+        let data = gen_test_data(16 * 50);
+        let encoder = SourceBlockEncoder::new(1, 16, &data);
+        // Source, padding, and out of range ESIs
+        assert_eq!(encoder.repair_packet(49), None);
+        assert_eq!(encoder.repair_packet(50), None);
+        assert_eq!(encoder.repair_packet(1 << 24), None);
+    }
+
+    #[test]
+    fn try_write_repair_symbol() {
+        let data = gen_test_data(16 * 50);
+        let encoder = SourceBlockEncoder::new(3, 16, &data);
+        let mut buffer = vec![0; 19];
+        assert_eq!(
+            encoder.try_write_repair_symbol(49, &mut buffer),
+            Err(RepairSymbolError::NotRepairSymbol {
+                encoding_symbol_id: 49
+            })
+        );
+        assert_eq!(
+            encoder.try_write_repair_symbol(1 << 24, &mut buffer),
+            Err(RepairSymbolError::NotRepairSymbol {
+                encoding_symbol_id: 1 << 24
+            })
+        );
+        assert_eq!(
+            encoder.try_write_repair_symbol(60, &mut buffer),
+            Err(RepairSymbolError::BufferTooSmall { required: 20 })
+        );
+        buffer.push(0);
+        assert_eq!(encoder.try_write_repair_symbol(60, &mut buffer), Ok(20));
+        assert_eq!(buffer, encoder.repair_packet(60).unwrap().serialize());
+    }
+
+    #[test]
+    fn write_repair_symbol() {
+        let data = gen_test_data(16 * 50);
+        let encoder = SourceBlockEncoder::new(3, 16, &data);
+        let mut buffer = vec![0xFF; 100];
+        for packet in encoder.repair_packets(0, 20) {
+            let esi = packet.payload_id().encoding_symbol_id();
+            assert_eq!(20, encoder.write_repair_symbol(esi, &mut buffer));
+            assert_eq!(packet.serialize()[..], buffer[..20]);
+            assert!(buffer[20..].iter().all(|&byte| byte == 0xFF));
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "parallel")]
+    fn get_encoded_packets_parallel() {
+        let data = gen_test_data(64 * 1000);
+        let config = ObjectTransmissionInformation::new(data.len() as u64, 64, 4, 1, 8);
+        let encoder = Encoder::new(&data, config);
+        assert_eq!(
+            encoder.get_encoded_packets_parallel(10),
+            encoder.get_encoded_packets(10)
+        );
+    }
 }
@@ -0,0 +1,87 @@
+use crate::base::EncodingPacket;
+use crate::decoder::Decoder;
+use crate::encoder::Encoder;
+
+/// A forward error correction encoder which operates on serialized packets. This trait is object
+/// safe, so that applications can select between FEC schemes at runtime, behind a
+/// `Box<dyn ErasureEncoder>`.
+pub trait ErasureEncoder {
+    /// Returns the serialized source packets, followed by the given number of repair packets, for
+    /// each source block
+    fn encode_packets(&self, repair_packets_per_block: u32) -> Vec<Vec<u8>>;
+}
+
+/// A forward error correction decoder which operates on serialized packets. See `ErasureEncoder`.
+pub trait ErasureDecoder {
+    /// Adds a serialized packet, and returns the object if it has been fully decoded. Malformed
+    /// packets are ignored.
+    fn decode_packet(&mut self, packet: &[u8]) -> Option<Vec<u8>>;
+}
+
+impl ErasureEncoder for Encoder {
+    fn encode_packets(&self, repair_packets_per_block: u32) -> Vec<Vec<u8>> {
+        self.get_encoded_packets(repair_packets_per_block)
+            .iter()
+            .map(|packet| packet.serialize())
+            .collect()
+    }
+}
+
+impl ErasureDecoder for Decoder {
+    fn decode_packet(&mut self, packet: &[u8]) -> Option<Vec<u8>> {
+        // Too short to contain a payload id
+        if packet.len() < 4 {
+            return self.get_result();
+        }
+        self.decode(EncodingPacket::deserialize(packet))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        Decoder, Encoder, EncodingPacket, ErasureDecoder, ErasureEncoder,
+        ObjectTransmissionInformation, PayloadId,
+    };
+    use rand::Rng;
+
+    #[test]
+    fn trait_objects() {
+        let mut data: Vec<u8> = vec![0; 10_000];
+        for byte in data.iter_mut() {
+            *byte = rand::thread_rng().gen();
+        }
+        let encoder = Encoder::with_defaults(&data, 500);
+        let decoder = Decoder::new(encoder.get_config());
+
+        let encoder: Box<dyn ErasureEncoder> = Box::new(encoder);
+        let mut decoder: Box<dyn ErasureDecoder> = Box::new(decoder);
+        assert_eq!(None, decoder.decode_packet(&[1, 2]));
+
+        let mut result = None;
+        // Drop the first packet, so that a repair packet is required
+        for packet in encoder.encode_packets(1).iter().skip(1) {
+            result = decoder.decode_packet(packet);
+        }
+        assert_eq!(result.unwrap(), data);
+    }
+
+    #[test]
+    fn malformed_packets() {
+        // 9 source symbols of 8 bytes, which are extended with a padding symbol to K' = 10
+        let data = vec![7; 72];
+        let encoder = Encoder::new(&data, ObjectTransmissionInformation::new(72, 8, 1, 1, 8));
+        let mut decoder = Decoder::new(encoder.get_config());
+        let padding = EncodingPacket::new(PayloadId::new(0, 9), vec![0; 8]);
+        assert_eq!(None, decoder.decode_packet(&padding.serialize()));
+        let truncated = EncodingPacket::new(PayloadId::new(0, 10), vec![0; 5]);
+        assert_eq!(None, decoder.decode_packet(&truncated.serialize()));
+        assert_eq!(decoder.rejected_packets(), 2);
+
+        let mut result = None;
+        for packet in encoder.encode_packets(0) {
+            result = decoder.decode_packet(&packet);
+        }
+        assert_eq!(result.unwrap(), data);
+    }
+}
@@ -0,0 +1,340 @@
+use crate::base::ConfigError;
+use crate::base::EncodingPacket;
+use crate::base::ObjectTransmissionInformation;
+use crate::decoder::Decoder;
+use crate::encoder::Encoder;
+use crate::systematic_constants::MAX_SOURCE_SYMBOLS_PER_BLOCK;
+use crate::util::div_ceil;
+use serde::{Deserialize, Serialize};
+use std::cmp::min;
+use std::ops::Range;
+
+/// Describes how an extent is split into segments. An extent is an object of any length, which is
+/// split into consecutive segments of `segment_symbols` symbols, by default the 56403 source
+/// symbols allowed in a source block. Each segment is encoded as an object of a single source
+/// block, so that storage systems get one code word per segment, whatever the length of the
+/// extent, without choosing the partitioning of each object themselves.
+///
+/// Like the OTI, the manifest must be stored, or transmitted, reliably. It is serialized as the
+/// extent length as a 64-bit unsigned integer, the symbol size as a 16-bit unsigned integer, and
+/// the number of symbols per segment as a 32-bit unsigned integer, all big endian.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct ExtentManifest {
+    extent_length: u64,
+    symbol_size: u16,
+    segment_symbols: u32,
+}
+
+impl ExtentManifest {
+    /// Splits the extent into segments of the maximum number of source symbols
+    pub fn new(extent_length: u64, symbol_size: u16) -> Result<ExtentManifest, ConfigError> {
+        ExtentManifest::with_segment_symbols(
+            extent_length,
+            symbol_size,
+            MAX_SOURCE_SYMBOLS_PER_BLOCK,
+        )
+    }
+
+    /// Splits the extent into segments of `segment_symbols` source symbols. Smaller segments
+    /// are faster to encode and decode, at the cost of more of them.
+    pub fn with_segment_symbols(
+        extent_length: u64,
+        symbol_size: u16,
+        segment_symbols: u32,
+    ) -> Result<ExtentManifest, ConfigError> {
+        if symbol_size == 0 || segment_symbols == 0 {
+            return Err(ConfigError::ZeroParameter);
+        }
+        if segment_symbols > MAX_SOURCE_SYMBOLS_PER_BLOCK {
+            return Err(ConfigError::SourceBlockTooLarge {
+                source_block_symbols: u64::from(segment_symbols),
+            });
+        }
+        let manifest = ExtentManifest {
+            extent_length,
+            symbol_size,
+            segment_symbols,
+        };
+        let segment_length = manifest.segment_length();
+        if extent_length / segment_length >= u64::from(std::u32::MAX) {
+            return Err(ConfigError::Overflow);
+        }
+        Ok(manifest)
+    }
+
+    pub fn extent_length(&self) -> u64 {
+        self.extent_length
+    }
+
+    pub fn symbol_size(&self) -> u16 {
+        self.symbol_size
+    }
+
+    pub fn segment_symbols(&self) -> u32 {
+        self.segment_symbols
+    }
+
+    pub fn segments(&self) -> u32 {
+        div_ceil(self.extent_length, self.segment_length()) as u32
+    }
+
+    /// Byte range of the extent which the given segment contains
+    pub fn segment_range(&self, segment: u32) -> Range<u64> {
+        assert!(segment < self.segments());
+        let start = u64::from(segment) * self.segment_length();
+        start..min(start + self.segment_length(), self.extent_length)
+    }
+
+    /// Parameters of the object which the given segment is encoded as
+    pub fn segment_config(&self, segment: u32) -> ObjectTransmissionInformation {
+        let range = self.segment_range(segment);
+        ObjectTransmissionInformation::new(range.end - range.start, self.symbol_size, 1, 1, 1)
+    }
+
+    fn segment_length(&self) -> u64 {
+        u64::from(self.segment_symbols) * u64::from(self.symbol_size)
+    }
+
+    pub fn serialize(&self) -> [u8; 14] {
+        let mut result = [0; 14];
+        result[..8].copy_from_slice(&self.extent_length.to_be_bytes());
+        result[8..10].copy_from_slice(&self.symbol_size.to_be_bytes());
+        result[10..].copy_from_slice(&self.segment_symbols.to_be_bytes());
+        result
+    }
+
+    /// Returns an error if the manifest is invalid, for example because it was corrupted
+    pub fn deserialize(data: &[u8; 14]) -> Result<ExtentManifest, ConfigError> {
+        let mut extent_length = [0; 8];
+        extent_length.copy_from_slice(&data[..8]);
+        let mut segment_symbols = [0; 4];
+        segment_symbols.copy_from_slice(&data[10..]);
+        ExtentManifest::with_segment_symbols(
+            u64::from_be_bytes(extent_length),
+            u16::from_be_bytes([data[8], data[9]]),
+            u32::from_be_bytes(segment_symbols),
+        )
+    }
+}
+
+/// A packet of one of the segments of an extent
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct ExtentPacket {
+    segment: u32,
+    packet: EncodingPacket,
+}
+
+impl ExtentPacket {
+    pub fn new(segment: u32, packet: EncodingPacket) -> ExtentPacket {
+        ExtentPacket { segment, packet }
+    }
+
+    pub fn segment(&self) -> u32 {
+        self.segment
+    }
+
+    pub fn packet(&self) -> &EncodingPacket {
+        &self.packet
+    }
+
+    /// Serialized as the segment number as a 32-bit big endian unsigned integer, followed by the
+    /// serialized packet
+    pub fn serialize(&self) -> Vec<u8> {
+        let mut serialized = self.segment.to_be_bytes().to_vec();
+        serialized.extend(self.packet.serialize());
+        serialized
+    }
+
+    pub fn deserialize(data: &[u8]) -> ExtentPacket {
+        ExtentPacket {
+            segment: u32::from_be_bytes([data[0], data[1], data[2], data[3]]),
+            packet: EncodingPacket::deserialize(&data[4..]),
+        }
+    }
+}
+
+/// Encodes an extent, as described by an `ExtentManifest`
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExtentEncoder {
+    manifest: ExtentManifest,
+    segments: Vec<Encoder>,
+}
+
+impl ExtentEncoder {
+    /// Encodes `data` in segments of the maximum number of source symbols
+    pub fn new(data: &[u8], symbol_size: u16) -> Result<ExtentEncoder, ConfigError> {
+        let manifest = ExtentManifest::new(data.len() as u64, symbol_size)?;
+        Ok(ExtentEncoder::with_manifest(data, manifest))
+    }
+
+    /// Encodes `data`, whose length must be the extent length of the manifest
+    pub fn with_manifest(data: &[u8], manifest: ExtentManifest) -> ExtentEncoder {
+        assert_eq!(data.len() as u64, manifest.extent_length());
+        let segments = (0..manifest.segments())
+            .map(|segment| {
+                let range = manifest.segment_range(segment);
+                Encoder::new(
+                    &data[range.start as usize..range.end as usize],
+                    manifest.segment_config(segment),
+                )
+            })
+            .collect();
+        ExtentEncoder { manifest, segments }
+    }
+
+    pub fn manifest(&self) -> &ExtentManifest {
+        &self.manifest
+    }
+
+    /// Returns the encoder of the given segment
+    pub fn segment(&self, segment: u32) -> &Encoder {
+        &self.segments[segment as usize]
+    }
+
+    /// Returns the source packets of every segment, each followed by the given number of its
+    /// repair packets
+    pub fn get_encoded_packets(&self, repair_packets_per_segment: u32) -> Vec<ExtentPacket> {
+        let mut packets = vec![];
+        for (segment, encoder) in self.segments.iter().enumerate() {
+            packets.extend(
+                encoder
+                    .get_encoded_packets(repair_packets_per_segment)
+                    .into_iter()
+                    .map(|packet| ExtentPacket::new(segment as u32, packet)),
+            );
+        }
+        packets
+    }
+}
+
+/// Decodes an extent from the packets of its segments, in any order
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExtentDecoder {
+    manifest: ExtentManifest,
+    // Decoders of the segments which have not been decoded yet
+    decoders: Vec<Option<Decoder>>,
+    segments: Vec<Option<Vec<u8>>>,
+    remaining_segments: u32,
+}
+
+impl ExtentDecoder {
+    pub fn new(manifest: ExtentManifest) -> ExtentDecoder {
+        let decoders = (0..manifest.segments())
+            .map(|segment| Some(Decoder::new(manifest.segment_config(segment))))
+            .collect();
+        ExtentDecoder {
+            segments: vec![None; manifest.segments() as usize],
+            remaining_segments: manifest.segments(),
+            manifest,
+            decoders,
+        }
+    }
+
+    pub fn manifest(&self) -> &ExtentManifest {
+        &self.manifest
+    }
+
+    /// Adds a packet, and returns the extent once every segment has been decoded. Packets of
+    /// segments which do not exist, or have already been decoded, are ignored.
+    pub fn decode(&mut self, packet: ExtentPacket) -> Option<Vec<u8>> {
+        let segment = packet.segment as usize;
+        if segment < self.decoders.len() {
+            if let Some(decoder) = self.decoders[segment].as_mut() {
+                if let Some(result) = decoder.decode(packet.packet) {
+                    // The decoder is dropped, since it holds the received symbols of the segment
+                    self.decoders[segment] = None;
+                    self.segments[segment] = Some(result);
+                    self.remaining_segments -= 1;
+                }
+            }
+        }
+        self.get_result()
+    }
+
+    /// Returns the given segment of the extent, if it has been decoded
+    pub fn segment_result(&self, segment: u32) -> Option<&[u8]> {
+        self.segments[segment as usize].as_deref()
+    }
+
+    /// Returns the segments which have not been decoded yet
+    pub fn missing_segments(&self) -> Vec<u32> {
+        (0..self.manifest.segments())
+            .filter(|segment| self.segments[*segment as usize].is_none())
+            .collect()
+    }
+
+    /// Returns the extent, if every segment has been decoded
+    pub fn get_result(&self) -> Option<Vec<u8>> {
+        if self.remaining_segments > 0 {
+            return None;
+        }
+        let mut result = Vec::with_capacity(self.manifest.extent_length() as usize);
+        for segment in self.segments.iter() {
+            result.extend_from_slice(segment.as_ref().unwrap());
+        }
+        Some(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{ConfigError, ExtentDecoder, ExtentEncoder, ExtentManifest, ExtentPacket};
+    use rand::seq::SliceRandom;
+    use rand::Rng;
+
+    #[test]
+    fn manifest() {
+        let manifest = ExtentManifest::new(5_000_000_000, 1024).unwrap();
+        // 56403 symbols of 1024 bytes per segment
+        assert_eq!(manifest.segments(), 87);
+        assert_eq!(manifest.segment_range(1), 57756672..115513344);
+        assert_eq!(
+            manifest.segment_config(86).transfer_length(),
+            5_000_000_000 - 86 * 57756672
+        );
+        assert_eq!(
+            ExtentManifest::deserialize(&manifest.serialize()),
+            Ok(manifest)
+        );
+
+        assert_eq!(
+            ExtentManifest::with_segment_symbols(1000, 16, 60000),
+            Err(ConfigError::SourceBlockTooLarge {
+                source_block_symbols: 60000
+            })
+        );
+        assert_eq!(
+            ExtentManifest::deserialize(&[0; 14]),
+            Err(ConfigError::ZeroParameter)
+        );
+    }
+
+    #[test]
+    fn extent_round_trip() {
+        let mut data: Vec<u8> = vec![0; 10_000];
+        rand::thread_rng().fill(&mut data[..]);
+        // Segments of 40 symbols, the last of which is partial
+        let manifest = ExtentManifest::with_segment_symbols(data.len() as u64, 48, 40).unwrap();
+        assert_eq!(manifest.segments(), 6);
+        let encoder = ExtentEncoder::with_manifest(&data, manifest.clone());
+        // Lose every fifth packet, which leaves 44 of the 55 packets of each full segment
+        let mut packets: Vec<ExtentPacket> = encoder
+            .get_encoded_packets(15)
+            .into_iter()
+            .enumerate()
+            .filter(|(i, _)| i % 5 != 0)
+            .map(|(_, packet)| packet)
+            .collect();
+        packets.shuffle(&mut rand::thread_rng());
+
+        let mut decoder = ExtentDecoder::new(manifest);
+        let mut result = None;
+        for packet in packets.iter() {
+            let packet = ExtentPacket::deserialize(&packet.serialize());
+            result = decoder.decode(packet);
+        }
+        assert_eq!(decoder.missing_segments(), Vec::<u32>::new());
+        assert_eq!(decoder.segment_result(1), Some(&data[1920..3840]));
+        assert_eq!(result, Some(data));
+    }
+}
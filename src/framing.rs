@@ -0,0 +1,303 @@
+use crate::base::EncodingPacket;
+use crate::base::PayloadId;
+#[cfg(feature = "async")]
+use futures::io::{AsyncRead, AsyncReadExt};
+use std::fmt;
+use std::io;
+use std::io::{Read, Write};
+
+// A frame is the length of the serialized packet, as a 32-bit big-endian unsigned integer,
+// followed by the serialized packet: its payload id and then its symbol data.
+const LENGTH_SIZE: usize = 4;
+// Payload id plus the largest possible symbol
+const MAX_FRAME_LENGTH: u32 = 4 + 65535;
+
+/// Writes a packet as a length-prefixed frame, which can be read back with
+/// `Decoder::decode_from()`
+pub fn write_framed_packet<W: Write>(writer: &mut W, packet: &EncodingPacket) -> io::Result<()> {
+    let serialized = packet.serialize();
+    writer.write_all(&(serialized.len() as u32).to_be_bytes())?;
+    writer.write_all(&serialized)
+}
+
+// Returns None if the reader ends cleanly between frames
+pub(crate) fn read_framed_packet<R: Read>(reader: &mut R) -> io::Result<Option<EncodingPacket>> {
+    let mut length = [0; LENGTH_SIZE];
+    let mut read = 0;
+    while read < LENGTH_SIZE {
+        match reader.read(&mut length[read..]) {
+            Ok(0) => break,
+            Ok(n) => read += n,
+            Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    if read == 0 {
+        return Ok(None);
+    }
+    if read < LENGTH_SIZE {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+    let mut frame = vec![0; frame_length(length)?];
+    reader.read_exact(&mut frame)?;
+    Ok(Some(EncodingPacket::deserialize(&frame)))
+}
+
+/// See `read_framed_packet()`
+#[cfg(feature = "async")]
+pub(crate) async fn read_framed_packet_async<R: AsyncRead + Unpin>(
+    reader: &mut R,
+) -> io::Result<Option<EncodingPacket>> {
+    let mut length = [0; LENGTH_SIZE];
+    let mut read = 0;
+    while read < LENGTH_SIZE {
+        match reader.read(&mut length[read..]).await {
+            Ok(0) => break,
+            Ok(n) => read += n,
+            Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    if read == 0 {
+        return Ok(None);
+    }
+    if read < LENGTH_SIZE {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+    let mut frame = vec![0; frame_length(length)?];
+    reader.read_exact(&mut frame).await?;
+    Ok(Some(EncodingPacket::deserialize(&frame)))
+}
+
+// Length field, flags and payload id of a `FramedPacket`
+const FRAMED_PACKET_HEADER_SIZE: usize = 2 + 1 + 4;
+
+/// Returned when a `FramedPacket` can not be parsed
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum FrameError {
+    /// The data ends before the end of the frame. On a stream transport, more data is needed
+    Incomplete,
+    /// The length field is too small for the flags and payload id, so the data is corrupt
+    InvalidLength,
+}
+
+impl fmt::Display for FrameError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FrameError::Incomplete => write!(f, "incomplete frame"),
+            FrameError::InvalidLength => write!(f, "invalid frame length"),
+        }
+    }
+}
+
+impl std::error::Error for FrameError {}
+
+/// A packet in a self-describing framing, for transports on which a read may not return exactly
+/// one packet, such as streams, or links whose MTU varies. Unlike `EncodingPacket::serialize()`,
+/// each frame states its own length, so frames can be concatenated, and truncation is detected.
+///
+/// A frame is the number of bytes which follow the length field, as a 16-bit big-endian unsigned
+/// integer, then one byte of flags, which are not interpreted by this crate and can be used by
+/// the application, then the payload id, and then the symbol data.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct FramedPacket {
+    flags: u8,
+    packet: EncodingPacket,
+}
+
+impl FramedPacket {
+    /// The symbol of the packet may be at most 65530 bytes, so that the frame length fits in the
+    /// length field
+    pub fn new(packet: EncodingPacket, flags: u8) -> FramedPacket {
+        assert!(
+            packet.data.len() <= 0xFFFF - (FRAMED_PACKET_HEADER_SIZE - 2),
+            "Symbol is too large to be framed"
+        );
+        FramedPacket { flags, packet }
+    }
+
+    pub fn flags(&self) -> u8 {
+        self.flags
+    }
+
+    pub fn packet(&self) -> &EncodingPacket {
+        &self.packet
+    }
+
+    pub fn into_packet(self) -> EncodingPacket {
+        self.packet
+    }
+
+    pub fn serialize(&self) -> Vec<u8> {
+        let length = (FRAMED_PACKET_HEADER_SIZE - 2 + self.packet.data.len()) as u16;
+        let mut serialized = Vec::with_capacity(2 + length as usize);
+        serialized.extend_from_slice(&length.to_be_bytes());
+        serialized.push(self.flags);
+        serialized.extend_from_slice(&self.packet.payload_id.serialize());
+        serialized.extend_from_slice(&self.packet.data);
+        serialized
+    }
+
+    /// Parses the frame at the start of `data`, and returns it with the number of bytes it
+    /// occupied, which is where the next frame, if any, begins
+    pub fn parse(data: &[u8]) -> Result<(FramedPacket, usize), FrameError> {
+        if data.len() < 2 {
+            return Err(FrameError::Incomplete);
+        }
+        let length = u16::from_be_bytes([data[0], data[1]]) as usize;
+        if length < FRAMED_PACKET_HEADER_SIZE - 2 {
+            return Err(FrameError::InvalidLength);
+        }
+        if data.len() < 2 + length {
+            return Err(FrameError::Incomplete);
+        }
+        let payload_id = PayloadId::deserialize(&[data[3], data[4], data[5], data[6]]);
+        let packet = EncodingPacket::new(
+            payload_id,
+            data[FRAMED_PACKET_HEADER_SIZE..(2 + length)].to_vec(),
+        );
+        Ok((
+            FramedPacket {
+                flags: data[2],
+                packet,
+            },
+            2 + length,
+        ))
+    }
+
+    /// Parses all the frames in `data`, e.g. a datagram carrying several packets. Fails if the
+    /// last frame is incomplete.
+    pub fn parse_all(mut data: &[u8]) -> Result<Vec<FramedPacket>, FrameError> {
+        let mut packets = vec![];
+        while !data.is_empty() {
+            let (packet, length) = FramedPacket::parse(data)?;
+            packets.push(packet);
+            data = &data[length..];
+        }
+        Ok(packets)
+    }
+}
+
+fn frame_length(length: [u8; LENGTH_SIZE]) -> io::Result<usize> {
+    let length = u32::from_be_bytes(length);
+    // A packet must contain a payload id, and its length is bounded by the maximum symbol size,
+    // so that a corrupt stream can't cause a huge allocation
+    if !(4..=MAX_FRAME_LENGTH).contains(&length) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "invalid frame length",
+        ));
+    }
+    Ok(length as usize)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{write_framed_packet, Decoder, Encoder, FrameError, FramedPacket};
+    use rand::seq::SliceRandom;
+    use rand::Rng;
+    use std::io;
+
+    fn framed_stream(data: &[u8]) -> Vec<u8> {
+        let encoder = Encoder::with_defaults(data, 64);
+        let mut packets = encoder.get_encoded_packets(15);
+        packets.shuffle(&mut rand::thread_rng());
+        packets.truncate(packets.len() - 10);
+        let mut stream = vec![];
+        for packet in packets.iter() {
+            write_framed_packet(&mut stream, packet).unwrap();
+        }
+        stream
+    }
+
+    #[test]
+    fn decode_from_reader() {
+        let mut data: Vec<u8> = vec![0; 10_000];
+        rand::thread_rng().fill(&mut data[..]);
+        let encoder = Encoder::with_defaults(&data, 64);
+        let stream = framed_stream(&data);
+
+        let mut decoder = Decoder::new(encoder.get_config());
+        let result = decoder.decode_from(&stream[..]).unwrap();
+        assert_eq!(Some(data), result);
+
+        // Not enough packets
+        let mut decoder = Decoder::new(encoder.get_config());
+        let result = decoder.decode_from(&stream[..stream.len() / 2]).unwrap();
+        assert_eq!(None, result);
+    }
+
+    #[test]
+    fn truncated_and_corrupt_frames() {
+        let mut data: Vec<u8> = vec![0; 1000];
+        rand::thread_rng().fill(&mut data[..]);
+        let encoder = Encoder::with_defaults(&data, 64);
+        let stream = framed_stream(&data);
+
+        let mut decoder = Decoder::new(encoder.get_config());
+        let error = decoder.decode_from(&stream[..6]).unwrap_err();
+        assert_eq!(io::ErrorKind::UnexpectedEof, error.kind());
+
+        let mut decoder = Decoder::new(encoder.get_config());
+        let error = decoder.decode_from(&stream[..2]).unwrap_err();
+        assert_eq!(io::ErrorKind::UnexpectedEof, error.kind());
+
+        let mut decoder = Decoder::new(encoder.get_config());
+        let error = decoder
+            .decode_from(&[0xFF, 0xFF, 0xFF, 0xFF, 0, 0, 0, 0][..])
+            .unwrap_err();
+        assert_eq!(io::ErrorKind::InvalidData, error.kind());
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn decode_from_async_reader() {
+        let mut data: Vec<u8> = vec![0; 10_000];
+        rand::thread_rng().fill(&mut data[..]);
+        let encoder = Encoder::with_defaults(&data, 64);
+        let stream = framed_stream(&data);
+
+        let mut decoder = Decoder::new(encoder.get_config());
+        let result = futures::executor::block_on(decoder.decode_from_async(&stream[..])).unwrap();
+        assert_eq!(Some(data), result);
+    }
+
+    #[test]
+    fn framed_packets() {
+        let mut data: Vec<u8> = vec![0; 1000];
+        rand::thread_rng().fill(&mut data[..]);
+        let encoder = Encoder::with_defaults(&data, 64);
+        let packets = encoder.get_encoded_packets(3);
+
+        // Several frames in one buffer, as read from a stream
+        let mut buffer = vec![];
+        for (i, packet) in packets.iter().enumerate() {
+            buffer.extend(FramedPacket::new(packet.clone(), i as u8).serialize());
+        }
+        let framed = FramedPacket::parse_all(&buffer).unwrap();
+        assert_eq!(packets.len(), framed.len());
+        let mut decoder = Decoder::new(encoder.get_config());
+        let mut result = None;
+        for (i, frame) in framed.into_iter().enumerate() {
+            assert_eq!(i as u8, frame.flags());
+            assert_eq!(&packets[i], frame.packet());
+            result = decoder.decode(frame.into_packet());
+        }
+        assert_eq!(Some(data), result);
+
+        let (_, length) = FramedPacket::parse(&buffer).unwrap();
+        assert_eq!(2 + 1 + 4 + 64, length);
+        assert_eq!(
+            Some(FrameError::Incomplete),
+            FramedPacket::parse(&buffer[..length - 1]).err()
+        );
+        assert_eq!(
+            Some(FrameError::Incomplete),
+            FramedPacket::parse_all(&buffer[..buffer.len() - 1]).err()
+        );
+        assert_eq!(
+            Some(FrameError::InvalidLength),
+            FramedPacket::parse(&[0, 4, 0, 0, 0, 0]).err()
+        );
+    }
+}
@@ -0,0 +1,93 @@
+use crate::base::EncodingPacket;
+use crate::config::Config;
+use crate::decoder::SourceBlockDecoder;
+
+/// Decoder for the closed-system mode, where the block structure is implied by a `Config`
+/// negotiated out of band, rather than signaled with an OTI. See `Encoder::with_implied_config()`.
+///
+/// As the transfer length is not known, source blocks are returned individually as they are
+/// decoded, in whatever order that happens, and each is exactly `config.block_length()` bytes.
+/// The last block of an object is zero padded, so the application's own framing must delimit the
+/// data.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ImpliedConfigDecoder {
+    config: Config,
+    // Indexed by source block number, and created when the first packet for a block arrives
+    block_decoders: Vec<Option<SourceBlockDecoder>>,
+    decoded: Vec<bool>,
+}
+
+impl ImpliedConfigDecoder {
+    pub fn new(config: &Config) -> ImpliedConfigDecoder {
+        ImpliedConfigDecoder {
+            config: config.clone(),
+            block_decoders: vec![None; 256],
+            decoded: vec![false; 256],
+        }
+    }
+
+    /// Adds a packet, and returns the source block number and data of its block, if this packet
+    /// completed it. Packets for blocks which have already been decoded are discarded.
+    pub fn decode(&mut self, packet: EncodingPacket) -> Option<(u8, Vec<u8>)> {
+        let block_number = packet.payload_id.source_block_number();
+        let index = block_number as usize;
+        if self.decoded[index] {
+            return None;
+        }
+        let config = &self.config;
+        let result = self.block_decoders[index]
+            .get_or_insert_with(|| {
+                SourceBlockDecoder::new(block_number, config.symbol_size(), config.block_length())
+            })
+            .decode(vec![packet]);
+        if result.is_some() {
+            // Free the symbols of the block, as only whether it was decoded needs to be retained
+            self.block_decoders[index] = None;
+            self.decoded[index] = true;
+        }
+        result.map(|block| (block_number, block))
+    }
+
+    /// Returns whether the given source block has been decoded
+    pub fn is_decoded(&self, source_block_number: u8) -> bool {
+        self.decoded[source_block_number as usize]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Config, Encoder, ImpliedConfigDecoder};
+    use rand::seq::SliceRandom;
+    use rand::Rng;
+
+    #[test]
+    fn round_trip() {
+        let config = Config::new(68, 100);
+        let mut data: Vec<u8> = vec![0; 2 * 6400 + 1000];
+        rand::thread_rng().fill(&mut data[..]);
+        let encoder = Encoder::with_implied_config(&data, &config);
+        let mut packets = encoder.get_encoded_packets(15);
+        packets.shuffle(&mut rand::thread_rng());
+        packets.truncate(packets.len() - 10);
+
+        let mut decoder = ImpliedConfigDecoder::new(&config);
+        let mut blocks = vec![None; 3];
+        for packet in packets {
+            if let Some((block_number, block)) = decoder.decode(packet) {
+                assert!(blocks[block_number as usize].is_none());
+                blocks[block_number as usize] = Some(block);
+            }
+        }
+        let mut result = vec![];
+        for (block_number, block) in blocks.into_iter().enumerate() {
+            assert!(decoder.is_decoded(block_number as u8));
+            let block = block.unwrap();
+            assert_eq!(config.block_length() as usize, block.len());
+            result.extend(block);
+        }
+        assert!(!decoder.is_decoded(3));
+        assert!(result[data.len()..].iter().all(|&byte| byte == 0));
+        result.truncate(data.len());
+        assert_eq!(data, result);
+    }
+}
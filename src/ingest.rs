@@ -0,0 +1,127 @@
+use crate::base::EncodingPacket;
+use crate::decoder::Decoder;
+use std::sync::mpsc;
+use std::sync::mpsc::{Receiver, SyncSender, TrySendError};
+
+/// Returned when a packet could not be added to a `PacketIngest`. Contains the packet.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum IngestError {
+    /// The queue is at capacity, i.e. the decoding thread is not keeping up
+    Full(EncodingPacket),
+    /// The `PacketQueue` has been dropped, e.g. because the object was decoded
+    Closed(EncodingPacket),
+}
+
+/// Handle for adding received packets to a bounded queue, from which a decoding thread consumes
+/// them. This decouples socket reader threads from decoding, while applying backpressure when
+/// decoding falls behind, rather than queuing an unbounded number of packets. It can be cloned to
+/// share the queue between multiple reader threads.
+#[derive(Clone, Debug)]
+pub struct PacketIngest {
+    sender: SyncSender<EncodingPacket>,
+}
+
+/// The consuming end of a `PacketIngest`
+#[derive(Debug)]
+pub struct PacketQueue {
+    receiver: Receiver<EncodingPacket>,
+}
+
+impl PacketIngest {
+    /// Creates a queue which holds at most `capacity` packets
+    pub fn new(capacity: usize) -> (PacketIngest, PacketQueue) {
+        let (sender, receiver) = mpsc::sync_channel(capacity);
+        (PacketIngest { sender }, PacketQueue { receiver })
+    }
+
+    /// Adds a packet without blocking. If the queue is full, the caller can decide whether to
+    /// drop the packet, which is usually acceptable for a fountain code.
+    pub fn try_add(&self, packet: EncodingPacket) -> Result<(), IngestError> {
+        self.sender.try_send(packet).map_err(|error| match error {
+            TrySendError::Full(packet) => IngestError::Full(packet),
+            TrySendError::Disconnected(packet) => IngestError::Closed(packet),
+        })
+    }
+
+    /// Adds a packet, blocking while the queue is full
+    pub fn add_blocking(&self, packet: EncodingPacket) -> Result<(), IngestError> {
+        self.sender
+            .send(packet)
+            .map_err(|error| IngestError::Closed(error.0))
+    }
+}
+
+impl PacketQueue {
+    /// Passes queued packets to the decoder, until the object is decoded, or all `PacketIngest`
+    /// handles have been dropped, in which case None is returned. Packets which are already
+    /// queued are passed in batches, see `Decoder::add_new_packets()`.
+    pub fn decode(&self, decoder: &mut Decoder) -> Option<Vec<u8>> {
+        while let Ok(packet) = self.receiver.recv() {
+            let mut batch = vec![packet];
+            batch.extend(self.receiver.try_iter());
+            decoder.add_new_packets(batch);
+            let result = decoder.get_result();
+            if result.is_some() {
+                return result;
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Decoder, Encoder, IngestError, PacketIngest};
+    use rand::Rng;
+    use std::thread;
+
+    #[test]
+    fn backpressure() {
+        let encoder = Encoder::with_defaults(&[1; 1000], 64);
+        let packets = encoder.get_encoded_packets(0);
+        let (ingest, queue) = PacketIngest::new(2);
+        assert_eq!(Ok(()), ingest.try_add(packets[0].clone()));
+        assert_eq!(Ok(()), ingest.try_add(packets[1].clone()));
+        assert_eq!(
+            Err(IngestError::Full(packets[2].clone())),
+            ingest.try_add(packets[2].clone())
+        );
+        drop(queue);
+        assert_eq!(
+            Err(IngestError::Closed(packets[2].clone())),
+            ingest.add_blocking(packets[2].clone())
+        );
+    }
+
+    #[test]
+    fn threaded_decode() {
+        let mut data: Vec<u8> = vec![0; 100_000];
+        for byte in data.iter_mut() {
+            *byte = rand::thread_rng().gen();
+        }
+        let encoder = Encoder::with_defaults(&data, 1280);
+        let (ingest, queue) = PacketIngest::new(4);
+
+        let mut readers = vec![];
+        for packets in encoder.get_encoded_packets(10).chunks(20) {
+            let ingest = ingest.clone();
+            let packets = packets.to_vec();
+            readers.push(thread::spawn(move || {
+                for packet in packets {
+                    if ingest.add_blocking(packet).is_err() {
+                        // Already decoded
+                        return;
+                    }
+                }
+            }));
+        }
+        drop(ingest);
+
+        let mut decoder = Decoder::new(encoder.get_config());
+        assert_eq!(queue.decode(&mut decoder).unwrap(), data);
+        drop(queue);
+        for reader in readers {
+            reader.join().unwrap();
+        }
+    }
+}
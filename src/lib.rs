@@ -1,29 +1,112 @@
 #![allow(clippy::needless_return, clippy::unreadable_literal)]
 
-mod arraymap;
+mod ack;
+mod allocator;
+mod arq;
+#[cfg(feature = "auth")]
+mod auth;
 mod base;
-mod constraint_matrix;
+mod cache;
+mod calibrate;
+#[cfg(feature = "compress")]
+mod compress;
+mod config;
+mod context;
 mod decoder;
 mod encoder;
-mod iterators;
-mod matrix;
-mod octet;
-mod octets;
-mod pi_solver;
-mod rng;
-mod sparse_matrix;
-mod sparse_vec;
-mod symbol;
-mod systematic_constants;
-mod util;
+mod erasure;
+mod extent;
+mod framing;
+mod implied_config;
+mod ingest;
+mod multipath;
+mod product;
+mod registry;
+mod sim;
+mod sliding_window;
+mod stats;
+mod store;
+mod stream;
+#[cfg(feature = "trace")]
+mod trace;
+#[cfg(all(feature = "uring", target_os = "linux"))]
+mod uring;
 
+// The symbol arithmetic and solver are in raptorq-core, which does not need std
+#[cfg(feature = "failure_injection")]
+use raptorq_core::failure_injection;
+use raptorq_core::{
+    constraint_matrix, matrix, octet, octets, pi_solver, rank_tracker, rng, sparse_matrix, symbol,
+    systematic_constants, tuple, util,
+};
+
+pub use crate::ack::BlockAckFrame;
+pub use crate::allocator::AllocatorHooks;
+pub use crate::arq::{HybridArqPolicy, RecoveryAction};
+#[cfg(feature = "auth")]
+pub use crate::auth::PacketAuthenticator;
+#[cfg(feature = "security")]
+pub use crate::auth::PacketCipher;
+pub use crate::base::ConfigError;
 pub use crate::base::EncodingPacket;
+pub use crate::base::EncodingPacketRef;
+pub use crate::base::MemoryUsage;
 pub use crate::base::ObjectTransmissionInformation;
 pub use crate::base::PayloadId;
+pub use crate::base::SourcePacketRef;
+pub use crate::cache::RepairCacheStats;
+pub use crate::calibrate::{calibrate, Calibration, ThroughputMeasurement};
+#[cfg(feature = "compress")]
+pub use crate::compress::{
+    encode_compressed, CompressedDecoder, CompressedObjectInformation, Compression,
+};
+pub use crate::config::Config;
+pub use crate::context::WorkContext;
+pub use crate::decoder::DecodeWarning;
 pub use crate::decoder::Decoder;
 pub use crate::decoder::SourceBlockDecoder;
+pub use crate::decoder::SubBlockDecoder;
+pub use crate::encoder::encode_repair_symbol;
+pub use crate::encoder::BlockEncoderReader;
+pub use crate::encoder::DeferredEncoder;
 pub use crate::encoder::Encoder;
+pub use crate::encoder::EncoderBuilder;
+#[cfg(feature = "mmap")]
+pub use crate::encoder::FileEncoder;
+pub use crate::encoder::RepairPacketIter;
+pub use crate::encoder::RepairSymbolError;
 pub use crate::encoder::SourceBlockEncoder;
+pub use crate::encoder::SourceBlockEncodingPlan;
+pub use crate::encoder::StreamingEncoder;
+pub use crate::encoder::MAX_REPAIR_PACKETS_PER_ACK;
+pub use crate::erasure::ErasureDecoder;
+pub use crate::erasure::ErasureEncoder;
+pub use crate::extent::{ExtentDecoder, ExtentEncoder, ExtentManifest, ExtentPacket};
+#[cfg(feature = "failure_injection")]
+pub use crate::failure_injection::InjectedFailure;
+pub use crate::framing::write_framed_packet;
+pub use crate::framing::{FrameError, FramedPacket};
+pub use crate::implied_config::ImpliedConfigDecoder;
+pub use crate::ingest::IngestError;
+pub use crate::ingest::PacketIngest;
+pub use crate::ingest::PacketQueue;
+pub use crate::multipath::{MultipathScheduler, PathLossTracker, PathPacket};
+pub use crate::pi_solver::{IntegrityCheck, SolverLimit, SolverPermutations};
+pub use crate::product::{ProductDecoder, ProductEncoder};
+pub use crate::registry::EncoderRegistry;
+pub use crate::sim::{
+    BernoulliChannel, Channel, DecoderReceiver, EncoderSender, GilbertElliottChannel, SimReceiver,
+    SimRng, SimSender, Simulation, SimulationReport,
+};
+pub use crate::sliding_window::{SlidingWindowDecoder, SlidingWindowEncoder, SlidingWindowPacket};
+pub use crate::stats::recommended_repair;
+pub use crate::store::PacketStore;
+pub use crate::store::RingPacketStore;
+pub use crate::stream::{DecoderReader, EncoderWriter};
+#[cfg(feature = "trace")]
+pub use crate::trace::{TraceReader, TracedDecoder};
+#[cfg(all(feature = "uring", target_os = "linux"))]
+pub use crate::uring::RegisteredPacketBuffers;
 
 #[cfg(feature = "benchmarking")]
 pub use crate::constraint_matrix::generate_constraint_matrix;
@@ -34,6 +117,10 @@ pub use crate::matrix::OctetMatrix;
 #[cfg(feature = "benchmarking")]
 pub use crate::octet::Octet;
 #[cfg(feature = "benchmarking")]
+pub use crate::octets::add_assign;
+#[cfg(feature = "benchmarking")]
+pub use crate::octets::fused_addassign_mul_scalar;
+#[cfg(feature = "benchmarking")]
 pub use crate::pi_solver::IntermediateSymbolDecoder;
 #[cfg(feature = "benchmarking")]
 pub use crate::sparse_matrix::SparseOctetMatrix;
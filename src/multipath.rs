@@ -0,0 +1,308 @@
+use crate::base::EncodingPacket;
+use crate::encoder::Encoder;
+use crate::util::div_ceil;
+use serde::{Deserialize, Serialize};
+
+// Path id, followed by the sequence number of the packet on that path
+const PATH_HEADER_SIZE: usize = 1 + 4;
+
+/// A packet tagged with the path it was sent on, and its sequence number on that path, so that
+/// the receiver can attribute losses to paths. See `MultipathScheduler`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PathPacket {
+    path_id: u8,
+    sequence_number: u32,
+    packet: EncodingPacket,
+}
+
+impl PathPacket {
+    pub fn new(path_id: u8, sequence_number: u32, packet: EncodingPacket) -> PathPacket {
+        PathPacket {
+            path_id,
+            sequence_number,
+            packet,
+        }
+    }
+
+    pub fn path_id(&self) -> u8 {
+        self.path_id
+    }
+
+    pub fn sequence_number(&self) -> u32 {
+        self.sequence_number
+    }
+
+    pub fn packet(&self) -> &EncodingPacket {
+        &self.packet
+    }
+
+    pub fn into_packet(self) -> EncodingPacket {
+        self.packet
+    }
+
+    pub fn serialize(&self) -> Vec<u8> {
+        let packet = self.packet.serialize();
+        let mut serialized = Vec::with_capacity(PATH_HEADER_SIZE + packet.len());
+        serialized.push(self.path_id);
+        serialized.extend_from_slice(&self.sequence_number.to_be_bytes());
+        serialized.extend_from_slice(&packet);
+        serialized
+    }
+
+    /// Returns None if the packet is too short to contain the path tag and a payload id
+    pub fn deserialize(data: &[u8]) -> Option<PathPacket> {
+        if data.len() < PATH_HEADER_SIZE + 4 {
+            return None;
+        }
+        Some(PathPacket {
+            path_id: data[0],
+            sequence_number: u32::from_be_bytes([data[1], data[2], data[3], data[4]]),
+            packet: EncodingPacket::deserialize(&data[PATH_HEADER_SIZE..]),
+        })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+struct Path {
+    path_id: u8,
+    repair_percent: u32,
+}
+
+/// Assigns the packets of an object to several network paths, e.g. the LTE and satellite links of
+/// a bonded sender. The source packets of each block are either split round robin between the
+/// paths, or duplicated on every path, see `set_duplicate_source()`. Each path then carries repair
+/// packets amounting to its own percentage of the source packets it carries, so that lossier
+/// paths can be given more. Repair packets are never duplicated: each path is given different
+/// encoding symbol ids.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MultipathScheduler {
+    paths: Vec<Path>,
+    duplicate_source: bool,
+}
+
+impl MultipathScheduler {
+    pub fn new() -> MultipathScheduler {
+        MultipathScheduler {
+            paths: vec![],
+            duplicate_source: false,
+        }
+    }
+
+    /// Adds a path, which carries `repair_percent` percent (rounded up) of its source packets in
+    /// each block, as repair packets. Panics if a path with the same id was already added.
+    pub fn add_path(&mut self, path_id: u8, repair_percent: u32) {
+        assert!(
+            self.paths.iter().all(|path| path.path_id != path_id),
+            "duplicate path id {}",
+            path_id
+        );
+        self.paths.push(Path {
+            path_id,
+            repair_percent,
+        });
+    }
+
+    /// Sends every source packet on every path, for redundant rather than bonded paths. By
+    /// default the source packets are split between the paths.
+    pub fn set_duplicate_source(&mut self, duplicate: bool) {
+        self.duplicate_source = duplicate;
+    }
+
+    /// Returns the packets of the object for each path, in the order in which the paths were
+    /// added, numbered from zero on each path
+    pub fn schedule(&self, encoder: &Encoder) -> Vec<Vec<PathPacket>> {
+        assert!(!self.paths.is_empty());
+        let mut result: Vec<Vec<PathPacket>> = self.paths.iter().map(|_| vec![]).collect();
+        let mut tag = |path: usize, packet: EncodingPacket| {
+            let sequence_number = result[path].len() as u32;
+            result[path].push(PathPacket::new(
+                self.paths[path].path_id,
+                sequence_number,
+                packet,
+            ));
+        };
+        for block in encoder.get_block_encoders().iter() {
+            let source_packets = block.source_packets();
+            let mut source_per_path = vec![0u32; self.paths.len()];
+            for (i, packet) in source_packets.into_iter().enumerate() {
+                if self.duplicate_source {
+                    for (path, sources) in source_per_path.iter_mut().enumerate() {
+                        *sources += 1;
+                        tag(path, packet.clone());
+                    }
+                } else {
+                    let path = i % self.paths.len();
+                    source_per_path[path] += 1;
+                    tag(path, packet);
+                }
+            }
+            let mut start_repair_symbol_id = 0;
+            for (path, sources) in source_per_path.into_iter().enumerate() {
+                let repair = div_ceil(
+                    u64::from(sources) * u64::from(self.paths[path].repair_percent),
+                    100,
+                ) as u32;
+                for packet in block.repair_packets(start_repair_symbol_id, repair) {
+                    tag(path, packet);
+                }
+                start_repair_symbol_id += repair;
+            }
+        }
+        result
+    }
+}
+
+impl Default for MultipathScheduler {
+    fn default() -> MultipathScheduler {
+        MultipathScheduler::new()
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+struct PathReception {
+    path_id: u8,
+    received: u64,
+    // One more than the highest sequence number received
+    expected: u64,
+}
+
+/// Counts the packets received on each path, from their `PathPacket` tags, to estimate the loss
+/// rate of each path. This can be fed back to the sender to adjust the repair percentage of each
+/// path.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PathLossTracker {
+    paths: Vec<PathReception>,
+}
+
+impl PathLossTracker {
+    pub fn new() -> PathLossTracker {
+        PathLossTracker { paths: vec![] }
+    }
+
+    /// Records the packet, and returns it without its tag
+    pub fn record(&mut self, packet: PathPacket) -> EncodingPacket {
+        let index = match self
+            .paths
+            .iter()
+            .position(|path| path.path_id == packet.path_id)
+        {
+            Some(index) => index,
+            None => {
+                self.paths.push(PathReception {
+                    path_id: packet.path_id,
+                    received: 0,
+                    expected: 0,
+                });
+                self.paths.len() - 1
+            }
+        };
+        let path = &mut self.paths[index];
+        path.received += 1;
+        path.expected = path.expected.max(u64::from(packet.sequence_number) + 1);
+        packet.into_packet()
+    }
+
+    /// Returns the number of packets received on the path, and the number which were sent on it
+    /// up to the last one received, or None if nothing was received on it
+    pub fn path_counts(&self, path_id: u8) -> Option<(u64, u64)> {
+        self.paths
+            .iter()
+            .find(|path| path.path_id == path_id)
+            .map(|path| (path.received, path.expected))
+    }
+
+    /// Returns the fraction of packets lost on the path, up to the last one received. Packets
+    /// which are reordered or duplicated can make this inaccurate until the path catches up.
+    pub fn loss_rate(&self, path_id: u8) -> Option<f64> {
+        self.path_counts(path_id)
+            .map(|(received, expected)| 1.0 - (received.min(expected) as f64 / expected as f64))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        Decoder, Encoder, MultipathScheduler, ObjectTransmissionInformation, PathLossTracker,
+        PathPacket,
+    };
+
+    fn test_encoder() -> (Vec<u8>, Encoder) {
+        let data: Vec<u8> = (0..10_000).map(|i| (i % 251) as u8).collect();
+        let config = ObjectTransmissionInformation::new(data.len() as u64, 100, 2, 1, 1);
+        let encoder = Encoder::new(&data, config);
+        (data, encoder)
+    }
+
+    #[test]
+    fn bonded_paths() {
+        let (data, encoder) = test_encoder();
+        let mut scheduler = MultipathScheduler::new();
+        scheduler.add_path(7, 10);
+        scheduler.add_path(9, 50);
+        let paths = scheduler.schedule(&encoder);
+        // Each block has 50 source symbols, split 25 and 25
+        assert_eq!(paths[0].len(), 2 * (25 + 3));
+        assert_eq!(paths[1].len(), 2 * (25 + 13));
+        assert!(paths[0].iter().all(|packet| packet.path_id() == 7));
+        for (i, packet) in paths[1].iter().enumerate() {
+            assert_eq!(packet.sequence_number(), i as u32);
+        }
+        // Repair symbols differ between the paths
+        let mut ids: Vec<_> = paths
+            .iter()
+            .flatten()
+            .map(|packet| packet.packet().payload_id().clone())
+            .collect();
+        ids.sort();
+        ids.dedup();
+        assert_eq!(ids.len(), paths[0].len() + paths[1].len());
+
+        // The first path is lost entirely, and every other packet on the second
+        let mut decoder = Decoder::new(encoder.get_config());
+        let mut tracker = PathLossTracker::new();
+        let mut result = None;
+        for packet in paths[1].iter().step_by(2) {
+            let packet = PathPacket::deserialize(&packet.serialize()).unwrap();
+            result = decoder.decode(tracker.record(packet));
+        }
+        assert_eq!(result, None);
+        assert_eq!(tracker.path_counts(7), None);
+        assert_eq!(tracker.path_counts(9), Some((38, 75)));
+        assert!((tracker.loss_rate(9).unwrap() - 37.0 / 75.0).abs() < 1e-9);
+
+        let mut decoder = Decoder::new(encoder.get_config());
+        let mut result = None;
+        for packet in paths[0].iter().chain(paths[1].iter()) {
+            result = decoder.decode(packet.packet().clone());
+        }
+        assert_eq!(result, Some(data));
+    }
+
+    #[test]
+    fn redundant_paths() {
+        let (data, encoder) = test_encoder();
+        let mut scheduler = MultipathScheduler::new();
+        scheduler.add_path(0, 0);
+        scheduler.add_path(1, 20);
+        scheduler.set_duplicate_source(true);
+        let paths = scheduler.schedule(&encoder);
+        assert_eq!(paths[0].len(), 100);
+        assert_eq!(paths[1].len(), 120);
+
+        // Either path alone is enough
+        for path in paths.iter() {
+            let mut decoder = Decoder::new(encoder.get_config());
+            let mut result = None;
+            for packet in path.iter() {
+                result = decoder.decode(packet.packet().clone());
+            }
+            assert_eq!(result.as_ref(), Some(&data));
+        }
+    }
+
+    #[test]
+    fn malformed_packet() {
+        assert_eq!(PathPacket::deserialize(&[0; 8]), None);
+        assert!(PathPacket::deserialize(&[0; 9]).is_some());
+    }
+}
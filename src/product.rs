@@ -0,0 +1,200 @@
+use crate::base::{EncodingPacket, PayloadId};
+use crate::decoder::SourceBlockDecoder;
+use crate::encoder::SourceBlockEncoder;
+use crate::systematic_constants::extended_source_block_symbols;
+use crate::util::div_ceil;
+
+// Length of the stripes, which every object is zero padded to a whole number of
+fn padded_length(object_lengths: &[usize], symbol_size: u16) -> usize {
+    let longest = object_lengths.iter().cloned().max().unwrap_or(0);
+    div_ceil(longest as u64, u64::from(symbol_size)) as usize * symbol_size as usize
+}
+
+/// The outer encoder of the experimental product code mode, which protects a group of objects
+/// against the loss of whole objects, for example when a storage device fails or a broadcast is
+/// missed entirely.
+///
+/// The objects are zero padded to the same length and cut into stripes of `symbol_size` bytes.
+/// Each stripe position forms a source block with one symbol from every object, whose repair
+/// symbols form the stripes of the parity objects. Each object, and each parity object, is then
+/// transmitted or stored on its own, typically with its own `Encoder` as the inner code. Objects
+/// which are lost can be recovered by a `ProductDecoder` from the others and the parity objects.
+/// As with any RaptorQ source block, receiving exactly as many as there were objects suffices
+/// with high probability, and each further one makes failure about 256 times less likely. Whether
+/// recovery succeeds depends only on which objects were lost, not on their contents.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ProductEncoder {
+    object_lengths: Vec<usize>,
+    symbol_size: u16,
+    parity_objects: Vec<Vec<u8>>,
+}
+
+impl ProductEncoder {
+    pub fn new(objects: &[&[u8]], parity_objects: u32, symbol_size: u16) -> ProductEncoder {
+        assert!(!objects.is_empty());
+        let object_lengths: Vec<usize> = objects.iter().map(|object| object.len()).collect();
+        let length = padded_length(&object_lengths, symbol_size);
+        let stride = symbol_size as usize;
+        let mut parity = vec![vec![0; length]; parity_objects as usize];
+        let mut block = vec![0; objects.len() * stride];
+        for offset in (0..length).step_by(stride) {
+            for (i, object) in objects.iter().enumerate() {
+                let symbol = &mut block[(i * stride)..((i + 1) * stride)];
+                let start = offset.min(object.len());
+                let end = (offset + stride).min(object.len());
+                symbol[..(end - start)].copy_from_slice(&object[start..end]);
+                for byte in symbol[(end - start)..].iter_mut() {
+                    *byte = 0;
+                }
+            }
+            let encoder = SourceBlockEncoder::new(0, symbol_size, &block);
+            for (j, packet) in encoder.repair_packets(0, parity_objects).iter().enumerate() {
+                parity[j][offset..(offset + stride)].copy_from_slice(packet.data());
+            }
+        }
+        ProductEncoder {
+            object_lengths,
+            symbol_size,
+            parity_objects: parity,
+        }
+    }
+
+    /// The lengths of the objects, which a `ProductDecoder` needs
+    pub fn object_lengths(&self) -> &[usize] {
+        &self.object_lengths
+    }
+
+    pub fn symbol_size(&self) -> u16 {
+        self.symbol_size
+    }
+
+    pub fn parity_objects(&self) -> &[Vec<u8>] {
+        &self.parity_objects
+    }
+}
+
+/// Recovers lost objects from the others and the parity objects of a `ProductEncoder`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ProductDecoder {
+    object_lengths: Vec<usize>,
+    symbol_size: u16,
+    // The objects, followed by the parity objects
+    received: Vec<Option<Vec<u8>>>,
+}
+
+impl ProductDecoder {
+    pub fn new(
+        object_lengths: Vec<usize>,
+        parity_objects: u32,
+        symbol_size: u16,
+    ) -> ProductDecoder {
+        assert!(!object_lengths.is_empty());
+        let objects = object_lengths.len() + parity_objects as usize;
+        ProductDecoder {
+            object_lengths,
+            symbol_size,
+            received: vec![None; objects],
+        }
+    }
+
+    /// Adds an object which was received (or decoded by its inner decoder), given its index
+    /// among the objects
+    pub fn add_object(&mut self, index: usize, data: Vec<u8>) {
+        assert_eq!(data.len(), self.object_lengths[index]);
+        self.received[index] = Some(data);
+    }
+
+    /// Adds a parity object, given its index among the parity objects
+    pub fn add_parity_object(&mut self, index: usize, data: Vec<u8>) {
+        let length = padded_length(&self.object_lengths, self.symbol_size);
+        assert_eq!(data.len(), length);
+        self.received[self.object_lengths.len() + index] = Some(data);
+    }
+
+    /// Returns all of the objects, recovering those which were not received, or None if too few
+    /// objects and parity objects have been received
+    pub fn get_result(&self) -> Option<Vec<Vec<u8>>> {
+        let objects = self.object_lengths.len();
+        let available = self.received.iter().filter(|data| data.is_some()).count();
+        if available < objects {
+            return None;
+        }
+        if self.received[..objects].iter().all(|data| data.is_some()) {
+            return Some(self.received[..objects].iter().flatten().cloned().collect());
+        }
+
+        let length = padded_length(&self.object_lengths, self.symbol_size);
+        let stride = self.symbol_size as usize;
+        let first_repair_esi = extended_source_block_symbols(objects as u32);
+        let mut recovered: Vec<Vec<u8>> = self
+            .object_lengths
+            .iter()
+            .map(|length| Vec::with_capacity(*length))
+            .collect();
+        for offset in (0..length).step_by(stride) {
+            let mut packets = vec![];
+            for (i, data) in self.received.iter().enumerate() {
+                if let Some(data) = data {
+                    let (esi, length) = if i < objects {
+                        (i as u32, self.object_lengths[i])
+                    } else {
+                        (first_repair_esi + (i - objects) as u32, length)
+                    };
+                    let mut symbol = vec![0; stride];
+                    let start = offset.min(length);
+                    let end = (offset + stride).min(length);
+                    symbol[..(end - start)].copy_from_slice(&data[start..end]);
+                    packets.push(EncodingPacket::new(PayloadId::new(0, esi), symbol));
+                }
+            }
+            let mut decoder =
+                SourceBlockDecoder::new(0, self.symbol_size, (objects * stride) as u64);
+            let block = decoder.decode(packets)?;
+            for (i, object) in recovered.iter_mut().enumerate() {
+                let remaining = self.object_lengths[i].saturating_sub(offset);
+                let symbol = &block[(i * stride)..((i + 1) * stride)];
+                object.extend_from_slice(&symbol[..remaining.min(stride)]);
+            }
+        }
+        Some(recovered)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Decoder, Encoder, ProductDecoder, ProductEncoder};
+    use rand::Rng;
+
+    #[test]
+    fn recover_lost_objects() {
+        let objects: Vec<Vec<u8>> = [3000, 2500, 10, 0, 2999]
+            .iter()
+            .map(|length| (0..*length).map(|_| rand::thread_rng().gen()).collect())
+            .collect();
+        let references: Vec<&[u8]> = objects.iter().map(|object| &object[..]).collect();
+        let encoder = ProductEncoder::new(&references, 2, 256);
+        assert_eq!(encoder.parity_objects().len(), 2);
+        assert_eq!(encoder.parity_objects()[0].len(), 3072);
+
+        // Each object is sent with RaptorQ as the inner code, and two are lost entirely
+        let mut decoder = ProductDecoder::new(encoder.object_lengths().to_vec(), 2, 256);
+        for &i in [0, 2, 3].iter() {
+            if objects[i].is_empty() {
+                decoder.add_object(i, vec![]);
+                continue;
+            }
+            let inner = Encoder::with_defaults(&objects[i], 500);
+            let mut inner_decoder = Decoder::new(inner.get_config());
+            let mut result = None;
+            for packet in inner.get_encoded_packets(1).into_iter().skip(1) {
+                result = inner_decoder.decode(packet);
+            }
+            decoder.add_object(i, result.unwrap());
+        }
+        assert_eq!(decoder.get_result(), None);
+        decoder.add_parity_object(1, encoder.parity_objects()[1].clone());
+        assert_eq!(decoder.get_result(), None);
+        decoder.add_parity_object(0, encoder.parity_objects()[0].clone());
+        assert_eq!(decoder.get_result().unwrap(), objects);
+    }
+}
@@ -0,0 +1,124 @@
+use crate::encoder::Encoder;
+use serde::{Deserialize, Serialize};
+
+// FNV-1a. This only needs to be stable, so that serialized registries remain valid, because
+// entries are compared against the data on lookup anyway
+fn content_hash(data: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for &byte in data.iter() {
+        hash ^= u64::from(byte);
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    hash
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+struct RegistryEntry {
+    hash: u64,
+    maximum_transmission_unit: u16,
+    encoder: Encoder,
+}
+
+/// Caches encoders by the content of the objects they encode, so that repeatedly broadcasting the
+/// same object (e.g. a firmware image) reuses its transmission parameters and intermediate
+/// symbols, instead of recomputing them. Holds at most `capacity` objects, evicting the least
+/// recently used. The registry can be serialized, to persist it across restarts.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct EncoderRegistry {
+    capacity: usize,
+    // Ordered from least to most recently used
+    entries: Vec<RegistryEntry>,
+}
+
+impl EncoderRegistry {
+    pub fn new(capacity: usize) -> EncoderRegistry {
+        assert!(capacity > 0);
+        EncoderRegistry {
+            capacity,
+            entries: vec![],
+        }
+    }
+
+    /// Returns the encoder for the given object, which is equivalent to
+    /// `Encoder::with_defaults(data, maximum_transmission_unit)`, creating it only if the object
+    /// is not in the registry
+    pub fn get_or_create(&mut self, data: &[u8], maximum_transmission_unit: u16) -> &Encoder {
+        let hash = content_hash(data);
+        let position = self.entries.iter().position(|entry| {
+            entry.hash == hash
+                && entry.maximum_transmission_unit == maximum_transmission_unit
+                && entry.encoder.encodes(data)
+        });
+        let entry = match position {
+            Some(index) => self.entries.remove(index),
+            None => {
+                if self.entries.len() == self.capacity {
+                    self.entries.remove(0);
+                }
+                RegistryEntry {
+                    hash,
+                    maximum_transmission_unit,
+                    encoder: Encoder::with_defaults(data, maximum_transmission_unit),
+                }
+            }
+        };
+        self.entries.push(entry);
+        &self.entries.last().unwrap().encoder
+    }
+
+    /// Returns the encoder for the given object, if it is in the registry, without affecting the
+    /// eviction order
+    pub fn get(&self, data: &[u8], maximum_transmission_unit: u16) -> Option<&Encoder> {
+        let hash = content_hash(data);
+        self.entries
+            .iter()
+            .find(|entry| {
+                entry.hash == hash
+                    && entry.maximum_transmission_unit == maximum_transmission_unit
+                    && entry.encoder.encodes(data)
+            })
+            .map(|entry| &entry.encoder)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Encoder, EncoderRegistry};
+
+    #[test]
+    fn reuse_and_eviction() {
+        let objects: Vec<Vec<u8>> = (0..3).map(|i| vec![i; 1000]).collect();
+        let mut registry = EncoderRegistry::new(2);
+        for object in objects.iter() {
+            assert_eq!(
+                registry.get_or_create(object, 64),
+                &Encoder::with_defaults(object, 64)
+            );
+        }
+        assert_eq!(registry.len(), 2);
+        assert!(registry.get(&objects[0], 64).is_none());
+        assert!(registry.get(&objects[1], 64).is_some());
+        assert!(registry.get(&objects[2], 128).is_none());
+
+        // Using the second object makes the third the least recently used
+        registry.get_or_create(&objects[1], 64);
+        registry.get_or_create(&objects[0], 64);
+        assert!(registry.get(&objects[1], 64).is_some());
+        assert!(registry.get(&objects[2], 64).is_none());
+
+        // A prefix of an object is a different object
+        assert!(registry.get(&objects[1][..999], 64).is_none());
+    }
+}
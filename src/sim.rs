@@ -0,0 +1,552 @@
+use crate::ack::BlockAckFrame;
+use crate::base::EncodingPacket;
+use crate::decoder::Decoder;
+use crate::encoder::Encoder;
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, VecDeque};
+
+/// The pseudo-random number generator (SplitMix64) used by simulations, so that an experiment is
+/// reproduced exactly from its seed, on every platform
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SimRng {
+    state: u64,
+}
+
+impl SimRng {
+    pub fn new(seed: u64) -> SimRng {
+        SimRng { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E3779B97F4A7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+        z ^ (z >> 31)
+    }
+
+    /// Returns a number uniformly distributed in [0, 1)
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Returns true with the given probability
+    pub fn chance(&mut self, probability: f64) -> bool {
+        self.next_f64() < probability
+    }
+}
+
+/// A model of a one-way network path, for `Simulation`
+pub trait Channel {
+    /// Returns the number of ticks until a packet sent at `now` arrives, or None if it is lost
+    fn transmit(&mut self, now: u64, rng: &mut SimRng) -> Option<u64>;
+}
+
+/// Loses each packet independently with a fixed probability, and delays the others by a fixed
+/// number of ticks
+#[derive(Clone, Debug, PartialEq)]
+pub struct BernoulliChannel {
+    loss_probability: f64,
+    delay: u64,
+}
+
+impl BernoulliChannel {
+    pub fn new(loss_probability: f64, delay: u64) -> BernoulliChannel {
+        assert!((0.0..=1.0).contains(&loss_probability));
+        BernoulliChannel {
+            loss_probability,
+            delay,
+        }
+    }
+}
+
+impl Channel for BernoulliChannel {
+    fn transmit(&mut self, _: u64, rng: &mut SimRng) -> Option<u64> {
+        if rng.chance(self.loss_probability) {
+            None
+        } else {
+            Some(self.delay)
+        }
+    }
+}
+
+/// The Gilbert-Elliott model of bursty loss. The channel is either in a good or a bad state, each
+/// with its own loss probability, and moves between them with the given probabilities after each
+/// packet. It starts in the good state.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GilbertElliottChannel {
+    good_to_bad: f64,
+    bad_to_good: f64,
+    good_loss: f64,
+    bad_loss: f64,
+    delay: u64,
+    bad: bool,
+}
+
+impl GilbertElliottChannel {
+    pub fn new(
+        good_to_bad: f64,
+        bad_to_good: f64,
+        good_loss: f64,
+        bad_loss: f64,
+        delay: u64,
+    ) -> GilbertElliottChannel {
+        for probability in [good_to_bad, bad_to_good, good_loss, bad_loss].iter() {
+            assert!((0.0..=1.0).contains(probability));
+        }
+        GilbertElliottChannel {
+            good_to_bad,
+            bad_to_good,
+            good_loss,
+            bad_loss,
+            delay,
+            bad: false,
+        }
+    }
+
+    /// Long run fraction of packets lost
+    pub fn average_loss(&self) -> f64 {
+        let transitions = self.good_to_bad + self.bad_to_good;
+        if transitions == 0.0 {
+            return if self.bad {
+                self.bad_loss
+            } else {
+                self.good_loss
+            };
+        }
+        let bad_fraction = self.good_to_bad / transitions;
+        bad_fraction * self.bad_loss + (1.0 - bad_fraction) * self.good_loss
+    }
+}
+
+impl Channel for GilbertElliottChannel {
+    fn transmit(&mut self, _: u64, rng: &mut SimRng) -> Option<u64> {
+        let lost = rng.chance(if self.bad {
+            self.bad_loss
+        } else {
+            self.good_loss
+        });
+        if rng.chance(if self.bad {
+            self.bad_to_good
+        } else {
+            self.good_to_bad
+        }) {
+            self.bad = !self.bad;
+        }
+        if lost {
+            None
+        } else {
+            Some(self.delay)
+        }
+    }
+}
+
+/// The sending side of a `Simulation`. Repair rate controllers are implemented as senders.
+pub trait SimSender {
+    /// Called once per tick, until the simulation ends. Returns the packets to send in this tick
+    fn tick(&mut self, now: u64) -> Vec<EncodingPacket>;
+
+    /// Called when an acknowledgement from the receiver arrives
+    fn on_ack(&mut self, now: u64, ack: &BlockAckFrame);
+}
+
+/// The receiving side of a `Simulation`
+pub trait SimReceiver {
+    /// Called when a packet arrives. Returns an acknowledgement to send back to the sender, if any
+    fn on_packet(&mut self, now: u64, packet: EncodingPacket) -> Option<BlockAckFrame>;
+
+    /// The simulation ends once this returns true
+    fn is_complete(&self) -> bool;
+}
+
+/// A sender which transmits the source packets of each block followed by a fixed number of
+/// repair packets, at a fixed number of packets per tick. When an acknowledgement arrives, the
+/// packets still queued are replaced by the repair packets it asks for, plus
+/// `extra_packets_per_ack` for each block which has not been decoded.
+pub struct EncoderSender {
+    encoder: Encoder,
+    packets_per_tick: usize,
+    extra_packets_per_ack: u32,
+    next_repair_symbol_id: u32,
+    queue: VecDeque<EncodingPacket>,
+}
+
+impl EncoderSender {
+    pub fn new(
+        encoder: Encoder,
+        packets_per_tick: usize,
+        repair_packets_per_block: u32,
+        extra_packets_per_ack: u32,
+    ) -> EncoderSender {
+        assert!(packets_per_tick > 0);
+        let queue = encoder
+            .get_encoded_packets(repair_packets_per_block)
+            .into_iter()
+            .collect();
+        EncoderSender {
+            encoder,
+            packets_per_tick,
+            extra_packets_per_ack,
+            next_repair_symbol_id: repair_packets_per_block,
+            queue,
+        }
+    }
+}
+
+impl SimSender for EncoderSender {
+    fn tick(&mut self, _: u64) -> Vec<EncodingPacket> {
+        let packets = self.packets_per_tick.min(self.queue.len());
+        self.queue.drain(..packets).collect()
+    }
+
+    fn on_ack(&mut self, _: u64, ack: &BlockAckFrame) {
+        self.queue = self
+            .encoder
+            .repair_packets_for_ack(ack, self.next_repair_symbol_id, self.extra_packets_per_ack)
+            .into_iter()
+            .collect();
+        // Never send the same repair symbol twice
+        let requested = (0..ack.num_blocks())
+            .map(|block| ack.symbols_needed(block as u8))
+            .filter(|needed| *needed > 0)
+            .map(|needed| needed + self.extra_packets_per_ack)
+            .max()
+            .unwrap_or(0);
+        self.next_repair_symbol_id += requested;
+    }
+}
+
+/// A receiver which decodes with a `Decoder`, and acknowledges every `ack_interval` packets, and
+/// once more when the object has been decoded
+pub struct DecoderReceiver {
+    decoder: Decoder,
+    ack_interval: u64,
+    // Packets received since the last acknowledgement
+    unacknowledged: u64,
+    result: Option<Vec<u8>>,
+}
+
+impl DecoderReceiver {
+    /// An `ack_interval` of zero disables the periodic acknowledgements
+    pub fn new(decoder: Decoder, ack_interval: u64) -> DecoderReceiver {
+        DecoderReceiver {
+            decoder,
+            ack_interval,
+            unacknowledged: 0,
+            result: None,
+        }
+    }
+
+    /// Returns the decoded object, once the simulation has completed
+    pub fn result(&self) -> Option<&[u8]> {
+        self.result.as_deref()
+    }
+}
+
+impl SimReceiver for DecoderReceiver {
+    fn on_packet(&mut self, _: u64, packet: EncodingPacket) -> Option<BlockAckFrame> {
+        if self.result.is_some() {
+            return None;
+        }
+        self.unacknowledged += 1;
+        self.result = self.decoder.decode(packet);
+        if self.result.is_some() || self.unacknowledged == self.ack_interval {
+            self.unacknowledged = 0;
+            Some(self.decoder.ack_frame())
+        } else {
+            None
+        }
+    }
+
+    fn is_complete(&self) -> bool {
+        self.result.is_some()
+    }
+}
+
+/// Counters collected by `Simulation::run()`
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct SimulationReport {
+    completed_at: Option<u64>,
+    packets_sent: u64,
+    packets_lost: u64,
+    acks_sent: u64,
+    acks_lost: u64,
+}
+
+impl SimulationReport {
+    /// The tick in which the receiver completed, or None if it did not before the simulation ended
+    pub fn completed_at(&self) -> Option<u64> {
+        self.completed_at
+    }
+
+    pub fn packets_sent(&self) -> u64 {
+        self.packets_sent
+    }
+
+    pub fn packets_lost(&self) -> u64 {
+        self.packets_lost
+    }
+
+    pub fn acks_sent(&self) -> u64 {
+        self.acks_sent
+    }
+
+    pub fn acks_lost(&self) -> u64 {
+        self.acks_lost
+    }
+}
+
+enum EventKind {
+    Packet(EncodingPacket),
+    Ack(BlockAckFrame),
+}
+
+struct Event {
+    time: u64,
+    // Breaks ties in the order the events were scheduled, so that runs are deterministic
+    sequence: u64,
+    kind: EventKind,
+}
+
+impl PartialEq for Event {
+    fn eq(&self, other: &Event) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Event {}
+
+impl PartialOrd for Event {
+    fn partial_cmp(&self, other: &Event) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Event {
+    // Reversed, so that the BinaryHeap returns the earliest event first
+    fn cmp(&self, other: &Event) -> Ordering {
+        (other.time, other.sequence).cmp(&(self.time, self.sequence))
+    }
+}
+
+/// A discrete event simulation of a sender and a receiver, connected by a forward channel for
+/// packets and a reverse channel for acknowledgements. Time advances in ticks, and all randomness
+/// comes from a single seeded `SimRng`, so a run is fully determined by its inputs.
+pub struct Simulation<S: SimSender, R: SimReceiver> {
+    sender: S,
+    receiver: R,
+    forward: Box<dyn Channel>,
+    reverse: Box<dyn Channel>,
+    rng: SimRng,
+    now: u64,
+    next_sequence: u64,
+    events: BinaryHeap<Event>,
+    report: SimulationReport,
+}
+
+impl<S: SimSender, R: SimReceiver> Simulation<S, R> {
+    pub fn new(
+        sender: S,
+        receiver: R,
+        forward: Box<dyn Channel>,
+        reverse: Box<dyn Channel>,
+        seed: u64,
+    ) -> Simulation<S, R> {
+        Simulation {
+            sender,
+            receiver,
+            forward,
+            reverse,
+            rng: SimRng::new(seed),
+            now: 0,
+            next_sequence: 0,
+            events: BinaryHeap::new(),
+            report: SimulationReport::default(),
+        }
+    }
+
+    pub fn sender(&self) -> &S {
+        &self.sender
+    }
+
+    pub fn receiver(&self) -> &R {
+        &self.receiver
+    }
+
+    fn schedule(&mut self, delay: u64, kind: EventKind) {
+        self.events.push(Event {
+            time: self.now + delay,
+            sequence: self.next_sequence,
+            kind,
+        });
+        self.next_sequence += 1;
+    }
+
+    /// Runs until the receiver completes, or until `max_ticks` ticks have elapsed. Within a tick,
+    /// the sender is polled first, and then every event due by the end of the tick is delivered,
+    /// so a channel with zero delay delivers in the tick the packet was sent.
+    pub fn run(&mut self, max_ticks: u64) -> SimulationReport {
+        while self.now < max_ticks && !self.receiver.is_complete() {
+            for packet in self.sender.tick(self.now) {
+                self.report.packets_sent += 1;
+                match self.forward.transmit(self.now, &mut self.rng) {
+                    Some(delay) => self.schedule(delay, EventKind::Packet(packet)),
+                    None => self.report.packets_lost += 1,
+                }
+            }
+            while let Some(event) = self.events.peek() {
+                if event.time > self.now {
+                    break;
+                }
+                match self.events.pop().unwrap().kind {
+                    EventKind::Packet(packet) => {
+                        if let Some(ack) = self.receiver.on_packet(self.now, packet) {
+                            self.report.acks_sent += 1;
+                            match self.reverse.transmit(self.now, &mut self.rng) {
+                                Some(delay) => self.schedule(delay, EventKind::Ack(ack)),
+                                None => self.report.acks_lost += 1,
+                            }
+                        }
+                    }
+                    EventKind::Ack(ack) => self.sender.on_ack(self.now, &ack),
+                }
+                if self.receiver.is_complete() {
+                    self.report.completed_at = Some(self.now);
+                    break;
+                }
+            }
+            self.now += 1;
+        }
+        self.report.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        recommended_repair, BernoulliChannel, Channel, Decoder, DecoderReceiver, Encoder,
+        EncoderSender, GilbertElliottChannel, HybridArqPolicy, RecoveryAction, SimRng, Simulation,
+        SimulationReport, SourceBlockDecoder, SourceBlockEncoder,
+    };
+
+    fn simulate(data: &[u8], seed: u64, ack_interval: u64) -> (SimulationReport, Option<Vec<u8>>) {
+        let encoder = Encoder::with_defaults(data, 500);
+        let decoder = Decoder::new(encoder.get_config());
+        let mut simulation = Simulation::new(
+            EncoderSender::new(encoder, 4, 0, 2),
+            DecoderReceiver::new(decoder, ack_interval),
+            Box::new(GilbertElliottChannel::new(0.05, 0.3, 0.01, 0.5, 3)),
+            Box::new(BernoulliChannel::new(0.1, 3)),
+            seed,
+        );
+        let report = simulation.run(10_000);
+        (
+            report,
+            simulation.receiver().result().map(|result| result.to_vec()),
+        )
+    }
+
+    #[test]
+    fn deterministic_feedback_loop() {
+        let data: Vec<u8> = (0..50_000).map(|i| (i % 251) as u8).collect();
+        let (report, result) = simulate(&data, 7, 10);
+        assert_eq!(result.unwrap(), data);
+        assert!(report.completed_at().is_some());
+        assert!(report.packets_lost() > 0);
+        assert_eq!(simulate(&data, 7, 10).0, report);
+
+        // Without acknowledgements, the lost source packets are never repaired
+        let (report, result) = simulate(&data, 7, 0);
+        assert!(result.is_none());
+        assert_eq!(report.completed_at(), None);
+        assert_eq!(report.acks_sent(), 0);
+    }
+
+    #[test]
+    fn channel_loss_rates() {
+        let mut rng = SimRng::new(1);
+        let mut bernoulli = BernoulliChannel::new(0.2, 0);
+        let mut bursty = GilbertElliottChannel::new(0.02, 0.2, 0.0, 0.8, 0);
+        let expected = bursty.average_loss();
+        let trials = 100_000;
+        let mut lost = [0; 2];
+        for now in 0..trials {
+            lost[0] += u64::from(bernoulli.transmit(now, &mut rng).is_none());
+            lost[1] += u64::from(bursty.transmit(now, &mut rng).is_none());
+        }
+        assert!((lost[0] as f64 / trials as f64 - 0.2).abs() < 0.01);
+        assert!((lost[1] as f64 / trials as f64 - expected).abs() < 0.02);
+    }
+
+    #[test]
+    fn hybrid_arq_policy() {
+        let policy = HybridArqPolicy::new(0.1, 0.99, 4);
+        // Enough repair packets are already in flight
+        assert_eq!(policy.decide(&[3, 7], 2, 10), RecoveryAction::Wait);
+        // A few missing source symbols are retransmitted
+        assert_eq!(
+            policy.decide(&[3, 7], 2, 1),
+            RecoveryAction::RetransmitSource(vec![3, 7])
+        );
+
+        // Otherwise, enough repair packets are requested to meet the budget over a lossy channel
+        let symbol_size = 16;
+        let data: Vec<u8> = (0..(20 * symbol_size)).map(|i| (i % 251) as u8).collect();
+        let encoder = SourceBlockEncoder::new(0, symbol_size as u16, &data);
+        let missing: Vec<u32> = (0..20).collect();
+        let requested = match policy.decide(&missing, 20, 0) {
+            RecoveryAction::RequestRepair(requested) => requested,
+            action => panic!("Unexpected {:?}", action),
+        };
+        assert!(requested > 22);
+        assert!(policy.success_probability(20, requested) >= 0.99);
+        assert!(policy.success_probability(20, requested - 1) < 0.99);
+
+        let mut rng = SimRng::new(5);
+        let mut channel = BernoulliChannel::new(0.1, 0);
+        let trials = 500;
+        let mut decoded = 0;
+        for trial in 0..trials {
+            let mut decoder = SourceBlockDecoder::new(0, symbol_size as u16, data.len() as u64);
+            let packets = encoder
+                .repair_packets(trial * requested, requested)
+                .into_iter()
+                .filter(|_| channel.transmit(0, &mut rng).is_some());
+            if decoder.decode(packets) == Some(data.clone()) {
+                decoded += 1;
+            }
+        }
+        assert!(decoded as f64 / trials as f64 > 0.97);
+    }
+
+    #[test]
+    fn recommended_repair_over_lossy_channel() {
+        // Without loss, only the decoding failure probability needs to be covered
+        assert_eq!(recommended_repair(100, 0.0, 0.99), 0);
+        assert_eq!(recommended_repair(100, 0.0, 0.999), 1);
+
+        let symbol_size = 16;
+        let data: Vec<u8> = (0..(20 * symbol_size)).map(|i| (i % 251) as u8).collect();
+        let encoder = SourceBlockEncoder::new(0, symbol_size as u16, &data);
+        let repair = recommended_repair(20, 0.2, 0.99);
+        assert!(repair > 5);
+        assert!(recommended_repair(20, 0.3, 0.99) > repair);
+
+        let mut rng = SimRng::new(9);
+        let mut channel = BernoulliChannel::new(0.2, 0);
+        let trials = 500;
+        let mut decoded = 0;
+        for trial in 0..trials {
+            let mut decoder = SourceBlockDecoder::new(0, symbol_size as u16, data.len() as u64);
+            let packets = encoder
+                .source_packets()
+                .into_iter()
+                .chain(encoder.repair_packets(trial * repair, repair))
+                .filter(|_| channel.transmit(0, &mut rng).is_some());
+            if decoder.decode(packets) == Some(data.clone()) {
+                decoded += 1;
+            }
+        }
+        assert!(decoded as f64 / trials as f64 > 0.97);
+    }
+}
@@ -0,0 +1,514 @@
+use crate::matrix::{DenseOctetMatrix, OctetMatrix};
+use crate::octet::Octet;
+use crate::rng::rand;
+use crate::symbol::Symbol;
+use crate::util::get_both_indices;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+
+// Repair ids are 24 bits, like encoding symbol ids, so that they can seed rand() without
+// overflowing
+const MAX_REPAIR_ID: u32 = 16777216;
+
+// Coefficient of the symbol at the given position in the window, in the linear combination which
+// forms a repair symbol. Never zero, so that every symbol in the window is protected
+fn coefficient(repair_id: u32, position: u32) -> Octet {
+    Octet::new(rand(repair_id, position, 255) as u8 + 1)
+}
+
+fn add_scaled(dest: &mut Symbol, other: &Symbol, scalar: &Octet) {
+    if *scalar == Octet::one() {
+        *dest += other;
+    } else {
+        dest.fused_addassign_mul_scalar(other, scalar);
+    }
+}
+
+/// A packet of the experimental sliding window mode. See `SlidingWindowEncoder`.
+///
+/// It is serialized as a type byte (0 = source, 1 = repair), followed by the 32-bit sequence
+/// number for a source packet, or the 32-bit first sequence number, 16-bit window length and
+/// 32-bit repair id for a repair packet, and then the symbol.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum SlidingWindowPacket {
+    Source {
+        sequence_number: u32,
+        data: Vec<u8>,
+    },
+    /// A linear combination of the source symbols `first_sequence_number` to
+    /// `first_sequence_number + window_length - 1`
+    Repair {
+        first_sequence_number: u32,
+        window_length: u16,
+        repair_id: u32,
+        data: Vec<u8>,
+    },
+}
+
+impl SlidingWindowPacket {
+    pub fn serialize(&self) -> Vec<u8> {
+        let mut serialized = vec![];
+        match self {
+            SlidingWindowPacket::Source {
+                sequence_number,
+                data,
+            } => {
+                serialized.push(0);
+                serialized.extend_from_slice(&sequence_number.to_be_bytes());
+                serialized.extend_from_slice(data);
+            }
+            SlidingWindowPacket::Repair {
+                first_sequence_number,
+                window_length,
+                repair_id,
+                data,
+            } => {
+                serialized.push(1);
+                serialized.extend_from_slice(&first_sequence_number.to_be_bytes());
+                serialized.extend_from_slice(&window_length.to_be_bytes());
+                serialized.extend_from_slice(&repair_id.to_be_bytes());
+                serialized.extend_from_slice(data);
+            }
+        }
+        serialized
+    }
+
+    /// Returns None if the packet is malformed
+    pub fn deserialize(data: &[u8]) -> Option<SlidingWindowPacket> {
+        let read_u32 = |offset: usize| {
+            u32::from_be_bytes([
+                data[offset],
+                data[offset + 1],
+                data[offset + 2],
+                data[offset + 3],
+            ])
+        };
+        match data.first() {
+            Some(0) if data.len() >= 5 => Some(SlidingWindowPacket::Source {
+                sequence_number: read_u32(1),
+                data: data[5..].to_vec(),
+            }),
+            Some(1) if data.len() >= 11 => Some(SlidingWindowPacket::Repair {
+                first_sequence_number: read_u32(1),
+                window_length: u16::from_be_bytes([data[5], data[6]]),
+                repair_id: read_u32(7),
+                data: data[11..].to_vec(),
+            }),
+            _ => None,
+        }
+    }
+}
+
+/// Experimental sliding window FEC, for streams where the latency of collecting a source block
+/// is not acceptable, e.g. live video.
+///
+/// Each repair symbol is a random linear combination, over GF(256), of the most recent source
+/// symbols, so a lost source symbol can be recovered as soon as enough repair symbols covering it
+/// have arrived. This is not part of RFC 6330, and its packets are not compatible with any other
+/// RaptorQ implementation.
+///
+/// All source symbols must be `symbol_size` bytes. Sequence numbers are assumed not to wrap.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SlidingWindowEncoder {
+    symbol_size: u16,
+    window_size: u16,
+    window: VecDeque<Symbol>,
+    next_sequence_number: u32,
+    next_repair_id: u32,
+}
+
+impl SlidingWindowEncoder {
+    pub fn new(symbol_size: u16, window_size: u16) -> SlidingWindowEncoder {
+        assert!(window_size > 0);
+        SlidingWindowEncoder {
+            symbol_size,
+            window_size,
+            window: VecDeque::with_capacity(window_size as usize),
+            next_sequence_number: 0,
+            next_repair_id: 0,
+        }
+    }
+
+    /// Adds a source symbol to the window, evicting the oldest one if it is full, and returns the
+    /// packet to send for it
+    pub fn add_source_symbol(&mut self, data: &[u8]) -> SlidingWindowPacket {
+        assert_eq!(data.len(), self.symbol_size as usize);
+        if self.window.len() == self.window_size as usize {
+            self.window.pop_front();
+        }
+        self.window.push_back(Symbol::new(data.to_vec()));
+        let sequence_number = self.next_sequence_number;
+        self.next_sequence_number += 1;
+        SlidingWindowPacket::Source {
+            sequence_number,
+            data: data.to_vec(),
+        }
+    }
+
+    /// Generates a repair packet over the current window. At least one source symbol must have
+    /// been added.
+    pub fn repair_packet(&mut self) -> SlidingWindowPacket {
+        assert!(!self.window.is_empty());
+        let repair_id = self.next_repair_id;
+        self.next_repair_id = (self.next_repair_id + 1) % MAX_REPAIR_ID;
+
+        let mut result = Symbol::zero(self.symbol_size);
+        for (position, symbol) in self.window.iter().enumerate() {
+            add_scaled(
+                &mut result,
+                symbol,
+                &coefficient(repair_id, position as u32),
+            );
+        }
+        SlidingWindowPacket::Repair {
+            first_sequence_number: self.next_sequence_number - self.window.len() as u32,
+            window_length: self.window.len() as u16,
+            repair_id,
+            data: result.into_bytes(),
+        }
+    }
+}
+
+// A received repair symbol, as an equation in the source symbols of its window
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+struct RepairEquation {
+    first_sequence_number: u32,
+    repair_id: u32,
+    coefficients: Vec<Octet>,
+    value: Symbol,
+}
+
+/// Receiver of the experimental sliding window mode. See `SlidingWindowEncoder`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SlidingWindowDecoder {
+    symbol_size: u16,
+    window_size: u16,
+    // Received or recovered source symbols, which may still be needed to recover others
+    known: HashMap<u32, Symbol>,
+    equations: Vec<RepairEquation>,
+    highest_sequence_number: u32,
+}
+
+impl SlidingWindowDecoder {
+    pub fn new(symbol_size: u16, window_size: u16) -> SlidingWindowDecoder {
+        SlidingWindowDecoder {
+            symbol_size,
+            window_size,
+            known: HashMap::new(),
+            equations: vec![],
+            highest_sequence_number: 0,
+        }
+    }
+
+    /// Returns the lost source symbols, and their sequence numbers, which the packet allowed to
+    /// be recovered. Symbols which have left the window of every repair packet that could still
+    /// arrive are forgotten, and can no longer be recovered. Duplicate repair packets are
+    /// ignored, and at most the newest `2 * window_size` repair packets are held.
+    pub fn add_packet(&mut self, packet: SlidingWindowPacket) -> Vec<(u32, Vec<u8>)> {
+        match packet {
+            SlidingWindowPacket::Source {
+                sequence_number,
+                data,
+            } => {
+                if data.len() != self.symbol_size as usize {
+                    return vec![];
+                }
+                self.observe(sequence_number);
+                self.known.insert(sequence_number, Symbol::new(data));
+            }
+            SlidingWindowPacket::Repair {
+                first_sequence_number,
+                window_length,
+                repair_id,
+                data,
+            } => {
+                if data.len() != self.symbol_size as usize
+                    || window_length == 0
+                    || window_length > self.window_size
+                    || repair_id >= MAX_REPAIR_ID
+                {
+                    return vec![];
+                }
+                // Sequence numbers don't wrap, so a window which extends past the last one is
+                // malformed
+                let last_sequence_number =
+                    match first_sequence_number.checked_add(u32::from(window_length) - 1) {
+                        Some(last_sequence_number) => last_sequence_number,
+                        None => return vec![],
+                    };
+                // A duplicate adds no information
+                if self.equations.iter().any(|equation| {
+                    equation.first_sequence_number == first_sequence_number
+                        && equation.repair_id == repair_id
+                }) {
+                    return vec![];
+                }
+                self.observe(last_sequence_number);
+                // Only the symbols of the last two windows can still be recovered, so more
+                // equations than that are redundant. The oldest ones cover the fewest of them
+                if self.equations.len() >= self.max_equations() {
+                    self.equations.remove(0);
+                }
+                self.equations.push(RepairEquation {
+                    first_sequence_number,
+                    repair_id,
+                    coefficients: (0..u32::from(window_length))
+                        .map(|position| coefficient(repair_id, position))
+                        .collect(),
+                    value: Symbol::new(data),
+                });
+            }
+        }
+        self.prune();
+        self.solve()
+    }
+
+    // Most equations held, which is the number of symbols kept by prune()
+    fn max_equations(&self) -> usize {
+        2 * self.window_size as usize
+    }
+
+    fn observe(&mut self, sequence_number: u32) {
+        if sequence_number > self.highest_sequence_number {
+            self.highest_sequence_number = sequence_number;
+        }
+    }
+
+    // Forgets symbols and equations which are too old to be useful
+    fn prune(&mut self) {
+        let horizon = self
+            .highest_sequence_number
+            .saturating_sub(2 * u32::from(self.window_size));
+        self.known
+            .retain(|&sequence_number, _| sequence_number >= horizon);
+        self.equations
+            .retain(|equation| equation.first_sequence_number >= horizon);
+    }
+
+    // Forgets equations which have no unknowns left
+    fn drop_solved_equations(&mut self) {
+        let known = &self.known;
+        self.equations.retain(|equation| {
+            (0..equation.coefficients.len() as u32)
+                .any(|position| !known.contains_key(&(equation.first_sequence_number + position)))
+        });
+    }
+
+    // Eliminates the known symbols from each equation, and then solves for as many of the
+    // remaining unknowns as possible
+    fn solve(&mut self) -> Vec<(u32, Vec<u8>)> {
+        let mut unknowns = vec![];
+        // Column of each unknown
+        let mut columns = HashMap::new();
+        let mut equations = vec![];
+        for equation in self.equations.iter() {
+            let mut value = equation.value.clone();
+            let mut terms = vec![];
+            for (position, coefficient) in equation.coefficients.iter().enumerate() {
+                let sequence_number = equation.first_sequence_number + position as u32;
+                match self.known.get(&sequence_number) {
+                    Some(symbol) => add_scaled(&mut value, symbol, coefficient),
+                    None => {
+                        let col = *columns.entry(sequence_number).or_insert_with(|| {
+                            unknowns.push(sequence_number);
+                            unknowns.len() - 1
+                        });
+                        terms.push((col, coefficient.clone()));
+                    }
+                }
+            }
+            if !terms.is_empty() {
+                equations.push((terms, value));
+            }
+        }
+        if equations.is_empty() {
+            self.drop_solved_equations();
+            return vec![];
+        }
+
+        let mut matrix = DenseOctetMatrix::new(equations.len(), unknowns.len(), 0, 0, 0);
+        let mut values = vec![];
+        for (row, (terms, value)) in equations.into_iter().enumerate() {
+            for (col, coefficient) in terms {
+                matrix.set(row, col, coefficient);
+            }
+            values.push(value);
+        }
+
+        // Reduce to reduced row echelon form. Any row with a single non-zero then determines
+        // that unknown
+        let mut pivot_row = 0;
+        for col in 0..unknowns.len() {
+            let pivot =
+                (pivot_row..matrix.height()).find(|&row| matrix.get(row, col) != Octet::zero());
+            let pivot = match pivot {
+                Some(pivot) => pivot,
+                None => continue,
+            };
+            matrix.swap_rows(pivot_row, pivot);
+            values.swap(pivot_row, pivot);
+            let inverse = &Octet::one() / &matrix.get(pivot_row, col);
+            matrix.mul_assign_row(pivot_row, &inverse);
+            values[pivot_row].mulassign_scalar(&inverse);
+            for row in 0..matrix.height() {
+                let factor = matrix.get(row, col);
+                if row != pivot_row && factor != Octet::zero() {
+                    matrix.fma_rows(row, pivot_row, &factor);
+                    let (dest, pivot_value) = get_both_indices(&mut values, row, pivot_row);
+                    add_scaled(dest, pivot_value, &factor);
+                }
+            }
+            pivot_row += 1;
+        }
+
+        let mut recovered = vec![];
+        for (row, value) in values.into_iter().enumerate().take(pivot_row) {
+            let (_, nonzeros) = matrix.count_ones_and_nonzeros(row, 0, unknowns.len());
+            if nonzeros == 1 {
+                let col = (0..unknowns.len())
+                    .find(|&col| matrix.get(row, col) != Octet::zero())
+                    .unwrap();
+                recovered.push((unknowns[col], value));
+            }
+        }
+        recovered.sort_by_key(|(sequence_number, _)| *sequence_number);
+        for (sequence_number, symbol) in recovered.iter() {
+            self.known.insert(*sequence_number, symbol.clone());
+        }
+        self.drop_solved_equations();
+        recovered
+            .into_iter()
+            .map(|(sequence_number, symbol)| (sequence_number, symbol.into_bytes()))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::sliding_window::{coefficient, MAX_REPAIR_ID};
+    use crate::{SlidingWindowDecoder, SlidingWindowEncoder, SlidingWindowPacket};
+    use rand::Rng;
+
+    #[test]
+    fn packet_serialization() {
+        let source = SlidingWindowPacket::Source {
+            sequence_number: 7,
+            data: vec![1, 2, 3],
+        };
+        let repair = SlidingWindowPacket::Repair {
+            first_sequence_number: 3,
+            window_length: 5,
+            repair_id: 2,
+            data: vec![4, 5, 6],
+        };
+        assert_eq!(
+            SlidingWindowPacket::deserialize(&source.serialize()),
+            Some(source)
+        );
+        assert_eq!(
+            SlidingWindowPacket::deserialize(&repair.serialize()),
+            Some(repair)
+        );
+        assert_eq!(SlidingWindowPacket::deserialize(&[1, 0, 0, 0, 3]), None);
+        assert_eq!(SlidingWindowPacket::deserialize(&[2, 0, 0, 0, 0]), None);
+    }
+
+    #[test]
+    fn recover_losses() {
+        let symbol_size = 16;
+        let mut encoder = SlidingWindowEncoder::new(symbol_size, 16);
+        let mut decoder = SlidingWindowDecoder::new(symbol_size, 16);
+
+        let mut lost = vec![];
+        let mut recovered = vec![];
+        for sequence_number in 0..200u32 {
+            let mut data = vec![0; symbol_size as usize];
+            rand::thread_rng().fill(&mut data[..]);
+            let packet = encoder.add_source_symbol(&data);
+            // Lose 20% of source packets, in bursts of two
+            if sequence_number % 10 == 3 || sequence_number % 10 == 4 {
+                lost.push((sequence_number, data));
+            } else {
+                recovered.extend(decoder.add_packet(packet));
+            }
+            // 25% overhead
+            if sequence_number % 4 == 3 {
+                recovered.extend(decoder.add_packet(encoder.repair_packet()));
+            }
+        }
+        // The last losses are only covered by repair packets which haven't been sent yet
+        recovered.extend(decoder.add_packet(encoder.repair_packet()));
+        recovered.extend(decoder.add_packet(encoder.repair_packet()));
+
+        recovered.sort();
+        assert_eq!(recovered, lost);
+    }
+
+    #[test]
+    fn window_past_last_sequence_number() {
+        let mut decoder = SlidingWindowDecoder::new(4, 16);
+        let packet = SlidingWindowPacket::Repair {
+            first_sequence_number: u32::max_value() - 2,
+            window_length: 4,
+            repair_id: 0,
+            data: vec![0; 4],
+        };
+        assert_eq!(decoder.add_packet(packet), vec![]);
+        assert!(decoder.equations.is_empty());
+
+        let packet = SlidingWindowPacket::Repair {
+            first_sequence_number: u32::max_value() - 3,
+            window_length: 4,
+            repair_id: 0,
+            data: vec![0; 4],
+        };
+        assert_eq!(decoder.add_packet(packet), vec![]);
+        assert_eq!(decoder.highest_sequence_number, u32::max_value());
+    }
+
+    #[test]
+    fn duplicate_and_excess_repair_packets() {
+        let mut encoder = SlidingWindowEncoder::new(4, 8);
+        let mut decoder = SlidingWindowDecoder::new(4, 8);
+        for _ in 0..8 {
+            encoder.add_source_symbol(&[1, 2, 3, 4]);
+        }
+        let packet = encoder.repair_packet();
+        decoder.add_packet(packet.clone());
+        decoder.add_packet(packet);
+        assert_eq!(decoder.equations.len(), 1);
+
+        // Distinct repair packets which are all multiples of each other, so that no symbol is
+        // ever recovered, and none is dropped as solved
+        let ratio = |repair_id| &coefficient(repair_id, 0) / &coefficient(repair_id, 1);
+        let dependent: Vec<u32> = (0..MAX_REPAIR_ID)
+            .filter(|&repair_id| ratio(repair_id) == ratio(0))
+            .take(100)
+            .collect();
+        assert_eq!(dependent.len(), 100);
+        let mut decoder = SlidingWindowDecoder::new(4, 8);
+        for &repair_id in dependent.iter() {
+            let packet = SlidingWindowPacket::Repair {
+                first_sequence_number: 0,
+                window_length: 2,
+                repair_id,
+                data: vec![0; 4],
+            };
+            assert_eq!(decoder.add_packet(packet), vec![]);
+            assert!(decoder.equations.len() <= 16);
+        }
+        assert_eq!(decoder.equations.len(), 16);
+        // The oldest were dropped
+        assert_eq!(decoder.equations[0].repair_id, dependent[84]);
+
+        // Repair ids are 24 bits
+        let packet = SlidingWindowPacket::Repair {
+            first_sequence_number: 0,
+            window_length: 8,
+            repair_id: u32::max_value(),
+            data: vec![0; 4],
+        };
+        assert_eq!(decoder.add_packet(packet), vec![]);
+        assert_eq!(decoder.equations.len(), 16);
+    }
+}
@@ -0,0 +1,79 @@
+/// Returns the number of repair packets to send along with the source packets of a source block
+/// of `source_symbols` symbols, so that it decodes with probability at least `target_success`
+/// over a channel which loses `observed_loss` of the packets.
+///
+/// Losses are assumed to be independent, so the number of packets received is binomially
+/// distributed, and decoding with a given number of symbols fails with the probabilities of RFC
+/// 6330: about 1% with exactly as many symbols as source symbols, and a hundred times less with
+/// each further one. The loss rate is usually measured by the receiver, and reported back to the
+/// sender.
+pub fn recommended_repair(source_symbols: u32, observed_loss: f64, target_success: f64) -> u32 {
+    assert!(source_symbols > 0);
+    assert!((0.0..1.0).contains(&observed_loss));
+    assert!(target_success > 0.0 && target_success < 1.0);
+    packets_needed(
+        source_symbols,
+        observed_loss,
+        target_success,
+        source_symbols,
+    ) - source_symbols
+}
+
+// Returns the fewest packets, and at least `minimum`, which must be sent so that a block which
+// needs `symbols_needed` more symbols decodes with probability at least `target_success`
+pub(crate) fn packets_needed(
+    symbols_needed: u32,
+    loss_rate: f64,
+    target_success: f64,
+    minimum: u32,
+) -> u32 {
+    // Start from the number which would suffice on average, and add one at a time
+    let expected = (f64::from(symbols_needed) / (1.0 - loss_rate)).ceil() as u32;
+    let mut sent = expected.max(minimum);
+    while success_probability(symbols_needed, sent, loss_rate) < target_success {
+        sent += 1;
+    }
+    sent
+}
+
+// Probability that a block which needs `symbols_needed` more symbols decodes once `sent` more
+// packets have been sent
+pub(crate) fn success_probability(symbols_needed: u32, sent: u32, loss_rate: f64) -> f64 {
+    if symbols_needed == 0 {
+        return 1.0;
+    }
+    if sent < symbols_needed {
+        return 0.0;
+    }
+    let arrived = binomial_distribution(sent, 1.0 - loss_rate);
+    (symbols_needed..=sent)
+        .map(|received| {
+            arrived[received as usize] * (1.0 - failure_probability(received - symbols_needed))
+        })
+        .sum()
+}
+
+// Probability that decoding fails with `extra` more symbols than needed. See section 1 of RFC 6330
+fn failure_probability(extra: u32) -> f64 {
+    0.01f64.powi(extra as i32 + 1)
+}
+
+// Probability of each number of successes in `trials` independent trials. It is computed in log
+// space, since the probabilities of the extremes underflow for many trials
+fn binomial_distribution(trials: u32, success: f64) -> Vec<f64> {
+    if success >= 1.0 {
+        let mut result = vec![0.0; trials as usize + 1];
+        result[trials as usize] = 1.0;
+        return result;
+    }
+    let n = f64::from(trials);
+    let odds = (success / (1.0 - success)).ln();
+    let mut log_probability = n * (1.0 - success).ln();
+    let mut result = Vec::with_capacity(trials as usize + 1);
+    for k in 0..=trials {
+        result.push(log_probability.exp());
+        let k = f64::from(k);
+        log_probability += ((n - k) / (k + 1.0)).ln() + odds;
+    }
+    result
+}
@@ -0,0 +1,106 @@
+use crate::base::{EncodingPacket, PayloadId};
+use std::collections::HashMap;
+
+/// Random access storage of sent packets, so that a sender can serve requests from receivers to
+/// resend a particular packet. See `Encoder::get_encoded_packets_stored()` and
+/// `Encoder::resend()`.
+pub trait PacketStore {
+    /// Stores the packet, possibly evicting others
+    fn insert(&mut self, packet: EncodingPacket);
+
+    /// Returns the packet with the given payload id, if it is stored
+    fn get(&self, payload_id: &PayloadId) -> Option<EncodingPacket>;
+}
+
+/// An in-memory `PacketStore` which holds the most recently inserted `capacity` packets
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RingPacketStore {
+    slots: Vec<Option<EncodingPacket>>,
+    next_slot: usize,
+    index: HashMap<PayloadId, usize>,
+}
+
+impl RingPacketStore {
+    pub fn new(capacity: usize) -> RingPacketStore {
+        assert!(capacity > 0);
+        RingPacketStore {
+            slots: vec![None; capacity],
+            next_slot: 0,
+            index: HashMap::new(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.index.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.index.is_empty()
+    }
+}
+
+impl PacketStore for RingPacketStore {
+    fn insert(&mut self, packet: EncodingPacket) {
+        if let Some(&slot) = self.index.get(&packet.payload_id) {
+            self.slots[slot] = Some(packet);
+            return;
+        }
+        if let Some(evicted) = self.slots[self.next_slot].take() {
+            self.index.remove(&evicted.payload_id);
+        }
+        self.index.insert(packet.payload_id.clone(), self.next_slot);
+        self.slots[self.next_slot] = Some(packet);
+        self.next_slot = (self.next_slot + 1) % self.slots.len();
+    }
+
+    fn get(&self, payload_id: &PayloadId) -> Option<EncodingPacket> {
+        self.index
+            .get(payload_id)
+            .and_then(|&slot| self.slots[slot].clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Encoder, PacketStore, PayloadId, RingPacketStore};
+
+    #[test]
+    fn ring_eviction() {
+        let encoder = Encoder::with_defaults(&[7; 1000], 64);
+        let packets = encoder.get_encoded_packets(0);
+        let mut store = RingPacketStore::new(4);
+        for packet in packets.iter().take(6) {
+            store.insert(packet.clone());
+        }
+        assert_eq!(store.len(), 4);
+        assert_eq!(store.get(packets[0].payload_id()), None);
+        assert_eq!(store.get(packets[5].payload_id()), Some(packets[5].clone()));
+
+        // Reinserting doesn't evict anything
+        store.insert(packets[2].clone());
+        assert_eq!(store.get(packets[2].payload_id()), Some(packets[2].clone()));
+        assert_eq!(store.len(), 4);
+    }
+
+    #[test]
+    fn resend() {
+        let mut data: Vec<u8> = vec![0; 1000];
+        for (i, byte) in data.iter_mut().enumerate() {
+            *byte = i as u8;
+        }
+        let encoder = Encoder::with_defaults(&data, 64);
+        let mut store = RingPacketStore::new(8);
+        let packets = encoder.get_encoded_packets_stored(3, &mut store);
+        assert_eq!(packets, encoder.get_encoded_packets(3));
+        assert_eq!(store.len(), 8);
+
+        // Evicted packets are regenerated
+        for packet in packets.iter() {
+            assert_eq!(
+                encoder.resend(&store, packet.payload_id()).as_ref(),
+                Some(packet)
+            );
+        }
+        assert_eq!(encoder.resend(&store, &PayloadId::new(1, 0)), None);
+    }
+}
@@ -0,0 +1,120 @@
+use crate::config::Config;
+use crate::decoder::Decoder;
+use crate::encoder::Encoder;
+use std::io;
+use std::io::{BufRead, Cursor, Read, Write};
+
+/// Collects an object through `std::io::Write`, for example as the output of a compressor or an
+/// archiver, and encodes it with the given `Config` once it is complete. The whole object is
+/// buffered, since its length determines how it is split into source blocks.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EncoderWriter {
+    config: Config,
+    data: Vec<u8>,
+}
+
+impl EncoderWriter {
+    pub fn new(config: Config) -> EncoderWriter {
+        EncoderWriter {
+            config,
+            data: vec![],
+        }
+    }
+
+    /// Number of bytes written so far
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    /// Returns an encoder for everything that was written
+    pub fn finish(self) -> Encoder {
+        Encoder::with_config(&self.data, &self.config)
+    }
+}
+
+impl Write for EncoderWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.data.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Reads a decoded object through `std::io::Read` and `std::io::BufRead`, for example as the
+/// input of a decompressor or an archive reader
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DecoderReader {
+    data: Cursor<Vec<u8>>,
+}
+
+impl DecoderReader {
+    /// Returns None if the decoder has not decoded the object yet
+    pub fn new(decoder: &Decoder) -> Option<DecoderReader> {
+        Some(DecoderReader {
+            data: Cursor::new(decoder.get_result()?),
+        })
+    }
+
+    /// Returns the whole object, regardless of how much of it has been read
+    pub fn into_inner(self) -> Vec<u8> {
+        self.data.into_inner()
+    }
+}
+
+impl Read for DecoderReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.data.read(buf)
+    }
+}
+
+impl BufRead for DecoderReader {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        self.data.fill_buf()
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.data.consume(amt)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Config, Decoder, DecoderReader, EncoderWriter};
+    use std::io;
+    use std::io::{BufRead, Read};
+
+    #[test]
+    fn stream_round_trip() {
+        let lines: Vec<String> = (0..2000).map(|i| format!("line {}\n", i)).collect();
+        let data = lines.concat().into_bytes();
+
+        let mut writer = EncoderWriter::new(Config::new(512, 64));
+        // Copied in several writes, as a pipeline would
+        let copied = io::copy(&mut &data[..], &mut writer).unwrap();
+        assert_eq!(copied as usize, data.len());
+        assert_eq!(writer.len(), data.len());
+        let encoder = writer.finish();
+
+        let mut decoder = Decoder::new(encoder.get_config());
+        assert!(DecoderReader::new(&decoder).is_none());
+        for packet in encoder.get_encoded_packets(1).into_iter().skip(1) {
+            decoder.decode(packet);
+        }
+
+        let mut reader = DecoderReader::new(&decoder).unwrap();
+        let mut first_line = String::new();
+        reader.read_line(&mut first_line).unwrap();
+        assert_eq!(first_line, lines[0]);
+        let mut rest = vec![];
+        reader.read_to_end(&mut rest).unwrap();
+        assert_eq!(rest, &data[first_line.len()..]);
+        assert_eq!(reader.into_inner(), data);
+    }
+}
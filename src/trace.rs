@@ -0,0 +1,200 @@
+use crate::base::{EncodingPacket, ObjectTransmissionInformation, PayloadId};
+use crate::decoder::Decoder;
+use std::io;
+use std::io::{Read, Write};
+use std::time::{Duration, Instant};
+
+const MAGIC: &[u8; 4] = b"RQTR";
+const VERSION: u8 = 1;
+
+/// Decoder which records every packet it accepts, with the time it was received, so that a
+/// failed decode can be reproduced exactly with `TraceReader::replay()`.
+///
+/// The trace starts with the magic bytes "RQTR", a version byte, and the 12 byte OTI. Each packet
+/// is then recorded as the microseconds elapsed since the previous packet, the 4 byte payload id,
+/// the length of the data, and the data. The elapsed time and the length are LEB128 encoded.
+#[derive(Debug)]
+pub struct TracedDecoder<W: Write> {
+    decoder: Decoder,
+    writer: W,
+    last_packet: Instant,
+}
+
+impl<W: Write> TracedDecoder<W> {
+    pub fn new(config: ObjectTransmissionInformation, mut writer: W) -> io::Result<Self> {
+        writer.write_all(MAGIC)?;
+        writer.write_all(&[VERSION])?;
+        writer.write_all(&config.serialize())?;
+        Ok(TracedDecoder {
+            decoder: Decoder::new(config),
+            writer,
+            last_packet: Instant::now(),
+        })
+    }
+
+    /// Records the packet, then decodes it
+    pub fn decode(&mut self, packet: EncodingPacket) -> io::Result<Option<Vec<u8>>> {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last_packet);
+        self.last_packet = now;
+
+        let mut record = vec![];
+        write_varint(&mut record, elapsed.as_micros() as u64);
+        record.extend_from_slice(&packet.payload_id().serialize());
+        write_varint(&mut record, packet.data().len() as u64);
+        record.extend_from_slice(packet.data());
+        self.writer.write_all(&record)?;
+
+        Ok(self.decoder.decode(packet))
+    }
+
+    /// Access to the underlying decoder, e.g. for configuration
+    pub fn decoder_mut(&mut self) -> &mut Decoder {
+        &mut self.decoder
+    }
+
+    /// Flushes and returns the trace writer
+    pub fn into_writer(mut self) -> io::Result<W> {
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+/// Reads a trace recorded by a `TracedDecoder`
+#[derive(Debug)]
+pub struct TraceReader<R: Read> {
+    config: ObjectTransmissionInformation,
+    reader: R,
+}
+
+impl<R: Read> TraceReader<R> {
+    pub fn new(mut reader: R) -> io::Result<Self> {
+        let mut header = [0; 5];
+        reader.read_exact(&mut header)?;
+        if &header[0..4] != MAGIC || header[4] != VERSION {
+            return Err(invalid_data("not a packet trace"));
+        }
+        let mut config = [0; 12];
+        reader.read_exact(&mut config)?;
+        Ok(TraceReader {
+            config: ObjectTransmissionInformation::deserialize(&config),
+            reader,
+        })
+    }
+
+    /// The configuration of the traced decoder
+    pub fn config(&self) -> ObjectTransmissionInformation {
+        self.config.clone()
+    }
+
+    /// Returns the next packet, and the time elapsed since the previous one was received, or None
+    /// at the end of the trace
+    pub fn next_packet(&mut self) -> io::Result<Option<(Duration, EncodingPacket)>> {
+        let elapsed = match read_varint(&mut self.reader, true)? {
+            Some(elapsed) => Duration::from_micros(elapsed),
+            None => return Ok(None),
+        };
+        let mut payload_id = [0; 4];
+        self.reader.read_exact(&mut payload_id)?;
+        let length = read_varint(&mut self.reader, false)?.unwrap();
+        // Bound the allocation by the data actually present, as the trace may be truncated
+        let mut data = vec![];
+        self.reader.by_ref().take(length).read_to_end(&mut data)?;
+        if data.len() as u64 != length {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        Ok(Some((
+            elapsed,
+            EncodingPacket::new(PayloadId::deserialize(&payload_id), data),
+        )))
+    }
+
+    /// Feeds every packet in the trace to the decoder, which should be created from `config()`
+    /// and configured like the traced decoder. Returns the result of the first packet which
+    /// completes the object, exactly as the traced decoder did.
+    pub fn replay(mut self, decoder: &mut Decoder) -> io::Result<Option<Vec<u8>>> {
+        while let Some((_, packet)) = self.next_packet()? {
+            if let Some(result) = decoder.decode(packet) {
+                return Ok(Some(result));
+            }
+        }
+        Ok(None)
+    }
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+fn write_varint(output: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        output.push((value & 0x7F) as u8 | 0x80);
+        value >>= 7;
+    }
+    output.push(value as u8);
+}
+
+// Returns None if the reader is at its end before the first byte, and that is allowed
+fn read_varint<R: Read>(reader: &mut R, allow_end: bool) -> io::Result<Option<u64>> {
+    let mut value = 0;
+    for i in 0..10 {
+        let mut byte = [0];
+        if reader.read(&mut byte)? == 0 {
+            if i == 0 && allow_end {
+                return Ok(None);
+            }
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        value |= u64::from(byte[0] & 0x7F) << (7 * i);
+        if byte[0] & 0x80 == 0 {
+            return Ok(Some(value));
+        }
+    }
+    Err(invalid_data("invalid varint"))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Decoder, Encoder, TraceReader, TracedDecoder};
+    use rand::seq::SliceRandom;
+    use rand::Rng;
+
+    #[test]
+    fn record_and_replay() {
+        let mut data: Vec<u8> = vec![0; 10_000];
+        rand::thread_rng().fill(&mut data[..]);
+        let encoder = Encoder::with_defaults(&data, 64);
+        let mut packets = encoder.get_encoded_packets(15);
+        packets.shuffle(&mut rand::thread_rng());
+        packets.truncate(packets.len() - 10);
+
+        let mut traced = TracedDecoder::new(encoder.get_config(), vec![]).unwrap();
+        let mut result = None;
+        for packet in packets.iter().take(20) {
+            assert_eq!(traced.decode(packet.clone()).unwrap(), None);
+        }
+        for packet in packets.iter().skip(20) {
+            result = traced.decode(packet.clone()).unwrap();
+            if result.is_some() {
+                break;
+            }
+        }
+        assert_eq!(result, Some(data.clone()));
+        let trace = traced.into_writer().unwrap();
+
+        let mut reader = TraceReader::new(&trace[..]).unwrap();
+        assert_eq!(reader.config(), encoder.get_config());
+        let (_, first) = reader.next_packet().unwrap().unwrap();
+        assert_eq!(first, packets[0]);
+
+        let reader = TraceReader::new(&trace[..]).unwrap();
+        let mut decoder = Decoder::new(reader.config());
+        assert_eq!(reader.replay(&mut decoder).unwrap(), Some(data));
+
+        // A truncated trace is an error, rather than a failed decode
+        let reader = TraceReader::new(&trace[..trace.len() - 1]).unwrap();
+        let mut decoder = Decoder::new(reader.config());
+        assert!(reader.replay(&mut decoder).is_err());
+        assert!(TraceReader::new(&trace[1..]).is_err());
+    }
+}
@@ -0,0 +1,224 @@
+use crate::encoder::{RepairSymbolError, SourceBlockEncoder};
+use io_uring::{opcode, squeue, types, IoUring};
+use std::io;
+use std::mem;
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::ptr;
+
+// See io_uring_register(2)
+const IORING_UNREGISTER_BUFFERS: u32 = 1;
+
+/// Packet buffers which are registered with an io_uring instance as fixed buffers. Repair packets
+/// are written into them in place, with `SourceBlockEncoder::try_write_repair_symbol()`, and sent
+/// from them with `IORING_OP_WRITE_FIXED`, so that sending a packet neither allocates nor copies
+/// it, and the kernel does not need to map the buffer for each send.
+///
+/// The buffers are allocated once, and do not move while they are registered. They are
+/// unregistered from the ring before they are freed, when dropped or by `unregister()`, so the
+/// ring never holds freed memory. A buffer must not be written to while a write of it is in
+/// flight, which callers guarantee when pushing the entry returned by `write_entry()`.
+#[derive(Debug)]
+pub struct RegisteredPacketBuffers {
+    buffers: Vec<Box<[u8]>>,
+    // Length of the packet last written to each buffer
+    lengths: Vec<usize>,
+    // A duplicate of the file descriptor of the ring, which keeps the ring open, so that the
+    // buffers can be unregistered from it even if the IoUring has been dropped first
+    ring_fd: RawFd,
+    registered: bool,
+}
+
+impl RegisteredPacketBuffers {
+    /// Allocates `count` buffers of `packet_size` bytes, which is the symbol size plus 4 for the
+    /// payload id, and registers them with the ring, which must not have buffers registered
+    /// already. The buffers stay registered until they are dropped or `unregister()` is called,
+    /// so no other buffers may be registered with the ring, or unregistered from it, meanwhile.
+    pub fn register(
+        ring: &IoUring,
+        count: u16,
+        packet_size: usize,
+    ) -> io::Result<RegisteredPacketBuffers> {
+        let mut buffers: Vec<Box<[u8]>> = (0..count)
+            .map(|_| vec![0; packet_size].into_boxed_slice())
+            .collect();
+        let iovecs: Vec<libc::iovec> = buffers
+            .iter_mut()
+            .map(|buffer| libc::iovec {
+                iov_base: buffer.as_mut_ptr() as *mut libc::c_void,
+                iov_len: buffer.len(),
+            })
+            .collect();
+        let ring_fd = unsafe { libc::dup(ring.as_raw_fd()) };
+        if ring_fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        // The buffers are heap allocated, so they stay where they are when the Vec moves, and
+        // they are only freed after being unregistered, see drop()
+        if let Err(error) = unsafe { ring.submitter().register_buffers(&iovecs) } {
+            unsafe { libc::close(ring_fd) };
+            return Err(error);
+        }
+        Ok(RegisteredPacketBuffers {
+            lengths: vec![0; buffers.len()],
+            buffers,
+            ring_fd,
+            registered: true,
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.buffers.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.buffers.is_empty()
+    }
+
+    /// Writes the packet of the repair symbol with the given ESI to the buffer with the given
+    /// index. Returns the length of the packet. The buffer must not have a write in flight, which
+    /// is part of the contract of pushing the entry returned by `write_entry()`.
+    pub fn write_repair_packet(
+        &mut self,
+        index: u16,
+        encoder: &SourceBlockEncoder,
+        encoding_symbol_id: u32,
+    ) -> Result<usize, RepairSymbolError> {
+        let length = encoder
+            .try_write_repair_symbol(encoding_symbol_id, &mut self.buffers[index as usize])?;
+        self.lengths[index as usize] = length;
+        Ok(length)
+    }
+
+    /// Returns the packet last written to the buffer with the given index
+    pub fn packet(&self, index: u16) -> &[u8] {
+        &self.buffers[index as usize][..self.lengths[index as usize]]
+    }
+
+    /// Returns a submission queue entry which writes the packet last written to the buffer with
+    /// the given index to `fd`, which is usually a connected UDP socket.
+    ///
+    /// Pushing the entry is unsafe. The caller must ensure that these buffers, and the ring they
+    /// were registered with, outlive the write, and that the buffer is not written to, by
+    /// `write_repair_packet()`, until the completion of the write has been reaped. Otherwise the
+    /// packet is modified while the kernel reads it.
+    pub fn write_entry(&self, index: u16, fd: RawFd) -> squeue::Entry {
+        opcode::WriteFixed::new(
+            types::Fd(fd),
+            self.buffers[index as usize].as_ptr(),
+            self.lengths[index as usize] as u32,
+            index,
+        )
+        .build()
+    }
+
+    /// Unregisters the buffers from the ring, after which they are freed. Dropping the buffers
+    /// does the same, but ignores errors.
+    pub fn unregister(mut self) -> io::Result<()> {
+        self.unregister_buffers()
+    }
+
+    fn unregister_buffers(&mut self) -> io::Result<()> {
+        if !self.registered {
+            return Ok(());
+        }
+        let result = unsafe {
+            libc::syscall(
+                libc::SYS_io_uring_register,
+                self.ring_fd,
+                IORING_UNREGISTER_BUFFERS,
+                ptr::null::<libc::c_void>(),
+                0,
+            )
+        };
+        if result < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        self.registered = false;
+        Ok(())
+    }
+}
+
+impl Drop for RegisteredPacketBuffers {
+    fn drop(&mut self) {
+        // If the buffers can't be unregistered, the ring may still use them, so they are leaked
+        // rather than freed
+        if self.unregister_buffers().is_err() {
+            mem::forget(mem::take(&mut self.buffers));
+        }
+        unsafe { libc::close(self.ring_fd) };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::uring::RegisteredPacketBuffers;
+    use crate::{EncodingPacket, SourceBlockDecoder, SourceBlockEncoder};
+    use io_uring::IoUring;
+    use std::net::UdpSocket;
+    use std::os::unix::io::AsRawFd;
+
+    #[test]
+    fn send_registered_repair_packets() {
+        let mut ring = match IoUring::new(8) {
+            Ok(ring) => ring,
+            // io_uring is unavailable, for example in containers which disallow it
+            Err(_) => return,
+        };
+        let data: Vec<u8> = (0..(64 * 20)).map(|i| i as u8).collect();
+        let encoder = SourceBlockEncoder::new(0, 64, &data);
+        let mut buffers = RegisteredPacketBuffers::register(&ring, 4, 68).unwrap();
+        assert_eq!(buffers.len(), 4);
+
+        let receiver = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let sender = UdpSocket::bind("127.0.0.1:0").unwrap();
+        sender.connect(receiver.local_addr().unwrap()).unwrap();
+
+        let mut decoder = SourceBlockDecoder::new(0, 64, data.len() as u64);
+        let mut result = None;
+        let mut esi = 20;
+        while result.is_none() {
+            for index in 0..4 {
+                assert_eq!(
+                    buffers.write_repair_packet(index, &encoder, esi + index as u32),
+                    Ok(68)
+                );
+                unsafe {
+                    ring.submission()
+                        .push(&buffers.write_entry(index, sender.as_raw_fd()))
+                        .unwrap();
+                }
+            }
+            ring.submit_and_wait(4).unwrap();
+            for completion in ring.completion() {
+                assert_eq!(completion.result(), 68);
+            }
+            let mut datagram = [0; 100];
+            for index in 0..4 {
+                let length = receiver.recv(&mut datagram).unwrap();
+                assert_eq!(&datagram[..length], buffers.packet(index));
+                result = result
+                    .or(decoder.decode(vec![EncodingPacket::deserialize(&datagram[..length])]));
+            }
+            esi += 4;
+        }
+        assert_eq!(result, Some(data));
+        buffers.unregister().unwrap();
+    }
+
+    #[test]
+    fn unregister_on_drop() {
+        let ring = match IoUring::new(8) {
+            Ok(ring) => ring,
+            Err(_) => return,
+        };
+        let buffers = RegisteredPacketBuffers::register(&ring, 4, 68).unwrap();
+        // Buffers are already registered
+        assert!(RegisteredPacketBuffers::register(&ring, 4, 68).is_err());
+        drop(buffers);
+        // Dropping unregistered them, so others can be registered
+        let buffers = RegisteredPacketBuffers::register(&ring, 4, 68).unwrap();
+        // The buffers are unregistered even if the ring is dropped first
+        drop(ring);
+        buffers.unregister().unwrap();
+    }
+}
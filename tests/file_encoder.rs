@@ -0,0 +1,82 @@
+// Checks that FileEncoder bounds the memory it holds by its capacity, rather than by the size of
+// the file. Enabled by the mmap feature.
+use raptorq::{FileEncoder, ObjectTransmissionInformation};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::fs;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+// Tracks the bytes allocated on the heap, and their peak since it was last reset
+struct CountingAllocator;
+
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+fn record_allocation(size: usize) {
+    let allocated = ALLOCATED.fetch_add(size, Ordering::SeqCst) + size;
+    PEAK.fetch_max(allocated, Ordering::SeqCst);
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        record_allocation(layout.size());
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        ALLOCATED.fetch_sub(layout.size(), Ordering::SeqCst);
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATED.fetch_sub(layout.size(), Ordering::SeqCst);
+        record_allocation(new_size);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+#[test]
+fn memory_bounded_by_capacity() {
+    let symbol_size = 1024;
+    let block_symbols = 200;
+    let blocks = 24;
+    let block_bytes = symbol_size * block_symbols;
+    let data: Vec<u8> = (0..(block_bytes * blocks)).map(|i| (i * 7) as u8).collect();
+    let path = std::env::temp_dir().join(format!("raptorq-file-encoder-{}", std::process::id()));
+    fs::write(&path, &data).unwrap();
+    drop(data);
+    let file = fs::File::open(&path).unwrap();
+    let config = ObjectTransmissionInformation::new(
+        (block_bytes * blocks) as u64,
+        symbol_size as u16,
+        blocks as u8,
+        1,
+        8,
+    );
+
+    let capacity = 2;
+    let before = ALLOCATED.load(Ordering::SeqCst);
+    PEAK.store(before, Ordering::SeqCst);
+    let mut encoder = unsafe { FileEncoder::new(&file, config, capacity) }.unwrap();
+    assert_eq!(encoder.source_blocks(), blocks);
+    for sbn in 0..blocks {
+        let packets = encoder.block_encoder(sbn as u8).repair_packets(0, 1);
+        assert_eq!(packets.len(), 1);
+    }
+    let peak = PEAK.load(Ordering::SeqCst) - before;
+    drop(encoder);
+    fs::remove_file(&path).unwrap();
+
+    // The intermediate symbols of each held block are slightly larger than the block, and one more
+    // block is solved for while they are held. Holding every block would need the whole file
+    let bound = (capacity + 2) * block_bytes * 3 / 2;
+    assert!(
+        peak < bound,
+        "{} bytes were allocated at peak, more than {}",
+        peak,
+        bound
+    );
+    assert!(bound < block_bytes * blocks / 2);
+}
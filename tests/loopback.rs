@@ -0,0 +1,128 @@
+use rand::seq::SliceRandom;
+use rand::Rng;
+use raptorq::{Decoder, Encoder, EncodingPacket};
+use std::net::UdpSocket;
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+const MTUS: [u16; 4] = [576, 1280, 1400, 8960];
+
+fn gen_data(size: usize) -> Vec<u8> {
+    let mut data: Vec<u8> = vec![0; size];
+    for byte in data.iter_mut() {
+        *byte = rand::thread_rng().gen();
+    }
+    data
+}
+
+// Sits in front of a transport, and deterministically drops every n-th packet passed through it
+struct LossyShim<F: FnMut(&[u8])> {
+    send: F,
+    drop_every: usize,
+    sent: usize,
+}
+
+impl<F: FnMut(&[u8])> LossyShim<F> {
+    fn new(send: F, drop_every: usize) -> LossyShim<F> {
+        LossyShim {
+            send,
+            drop_every,
+            sent: 0,
+        }
+    }
+
+    fn send(&mut self, packet: &[u8]) {
+        self.sent += 1;
+        if self.drop_every != 0 && self.sent % self.drop_every == 0 {
+            return;
+        }
+        (self.send)(packet);
+    }
+}
+
+fn encode_shuffled(
+    data: &[u8],
+    mtu: u16,
+    repair_packets_per_block: u32,
+) -> (Encoder, Vec<Vec<u8>>) {
+    let encoder = Encoder::with_defaults(data, mtu);
+    let mut packets: Vec<Vec<u8>> = encoder
+        .get_encoded_packets(repair_packets_per_block)
+        .iter()
+        .map(|packet| packet.serialize())
+        .collect();
+    packets.shuffle(&mut rand::thread_rng());
+    (encoder, packets)
+}
+
+#[test]
+fn channel_transfer_with_loss() {
+    for &mtu in MTUS.iter() {
+        let data = gen_data(64 * 1024);
+        let (encoder, packets) = encode_shuffled(&data, mtu, 20);
+        let (tx, rx) = mpsc::channel();
+        let mut shim = LossyShim::new(move |packet: &[u8]| tx.send(packet.to_vec()).unwrap(), 10);
+        for packet in packets.iter() {
+            shim.send(packet);
+        }
+        drop(shim);
+
+        let mut decoder = Decoder::new(encoder.get_config());
+        let mut result = None;
+        for packet in rx.iter() {
+            result = decoder.decode(EncodingPacket::deserialize(&packet));
+            if result.is_some() {
+                break;
+            }
+        }
+        assert_eq!(result.unwrap(), data, "mtu = {}", mtu);
+    }
+}
+
+#[test]
+fn udp_loopback_transfer_with_loss() {
+    for &mtu in MTUS.iter() {
+        let data = gen_data(64 * 1024);
+        let (encoder, packets) = encode_shuffled(&data, mtu, 20);
+
+        let receiver = UdpSocket::bind("127.0.0.1:0").unwrap();
+        receiver
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        let receiver_address = receiver.local_addr().unwrap();
+        let config = encoder.get_config();
+
+        let receiver_thread = thread::spawn(move || {
+            let mut decoder = Decoder::new(config);
+            let mut buffer = vec![0; 65536];
+            loop {
+                let (length, _) = receiver
+                    .recv_from(&mut buffer)
+                    .expect("timed out before the transfer was decoded");
+                if let Some(result) = decoder.decode(EncodingPacket::deserialize(&buffer[..length]))
+                {
+                    return result;
+                }
+            }
+        });
+
+        let sender = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let mut shim = LossyShim::new(
+            |packet: &[u8]| {
+                assert_eq!(
+                    packet.len(),
+                    sender.send_to(packet, receiver_address).unwrap()
+                );
+                // Pace the sender so the loopback socket buffer doesn't overflow
+                thread::sleep(Duration::from_micros(50));
+            },
+            10,
+        );
+        for packet in packets.iter() {
+            shim.send(packet);
+        }
+
+        assert_eq!(receiver_thread.join().unwrap(), data, "mtu = {}", mtu);
+    }
+}
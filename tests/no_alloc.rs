@@ -0,0 +1,85 @@
+use raptorq::{RepairSymbolError, SourceBlockEncoder};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use std::ptr;
+
+// Fails every allocation made by a thread while it is forbidden to allocate, so that any
+// allocation in the code under test aborts the test
+struct FailingAllocator;
+
+thread_local! {
+    static FORBIDDEN: Cell<bool> = Cell::new(false);
+}
+
+fn forbidden() -> bool {
+    FORBIDDEN
+        .try_with(|forbidden| forbidden.get())
+        .unwrap_or(false)
+}
+
+unsafe impl GlobalAlloc for FailingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        if forbidden() {
+            return ptr::null_mut();
+        }
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        if forbidden() {
+            return ptr::null_mut();
+        }
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: FailingAllocator = FailingAllocator;
+
+fn without_allocation<T, F: FnOnce() -> T>(f: F) -> T {
+    FORBIDDEN.with(|forbidden| forbidden.set(true));
+    let result = f();
+    FORBIDDEN.with(|forbidden| forbidden.set(false));
+    result
+}
+
+#[test]
+fn write_repair_symbols_without_allocation() {
+    let symbol_size = 64;
+    let data: Vec<u8> = (0..(symbol_size * 500)).map(|i| i as u8).collect();
+    let encoder = SourceBlockEncoder::new(1, symbol_size as u16, &data);
+    let expected = encoder.repair_packets(0, 50);
+    let mut buffers = vec![vec![0; symbol_size + 4]; expected.len()];
+
+    let mut results = Vec::with_capacity(expected.len() + 2);
+    without_allocation(|| {
+        for (packet, buffer) in expected.iter().zip(buffers.iter_mut()) {
+            let esi = packet.payload_id().encoding_symbol_id();
+            results.push(encoder.try_write_repair_symbol(esi, buffer));
+        }
+        // Errors are returned without allocating too
+        results.push(encoder.try_write_repair_symbol(0, &mut buffers[0]));
+        results.push(encoder.try_write_repair_symbol(600, &mut buffers[0][..10]));
+    });
+
+    assert_eq!(
+        results.pop(),
+        Some(Err(RepairSymbolError::BufferTooSmall {
+            required: symbol_size + 4
+        }))
+    );
+    assert_eq!(
+        results.pop(),
+        Some(Err(RepairSymbolError::NotRepairSymbol {
+            encoding_symbol_id: 0
+        }))
+    );
+    for ((result, buffer), packet) in results.iter().zip(buffers.iter()).zip(expected.iter()) {
+        assert_eq!(result, &Ok(symbol_size + 4));
+        assert_eq!(buffer, &packet.serialize());
+    }
+}
@@ -0,0 +1,168 @@
+// Performance regression test, enabled by the perf-test feature, and run by `make perf`:
+//
+//   cargo test --release --features perf-test --test perf
+//
+// It encodes and decodes a fixed scenario, and fails if the throughput is lower than the baseline
+// in perf_baseline.txt by more than the threshold. Throughput depends on the machine, so it is
+// measured relative to a reference workload run in the same process, which xors buffers as large
+// as the object, and the baseline holds these ratios. They vary much less between machines than
+// the throughput does, so the baseline is committed. Changes which are expected to change
+// performance record a new one with:
+//
+//   RAPTORQ_PERF_RECORD=1 cargo test --release --features perf-test --test perf
+//
+// The threshold is a percentage, which defaults to 30 to allow for the remaining variation
+// between machines, and can be set with RAPTORQ_PERF_THRESHOLD, e.g. lower to compare two
+// commits on one machine. The test fails in debug builds, which are too slow to be meaningful,
+// and if the baseline is missing.
+use raptorq::{Decoder, Encoder, EncodingPacket};
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::time::{Duration, Instant};
+
+const BASELINE_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/perf_baseline.txt");
+const DEFAULT_THRESHOLD_PERCENT: f64 = 30.0;
+
+// The scenario: 10 MB with a symbol size of 1280 bytes, and 5% of the packets lost
+const LENGTH: usize = 10 * 1024 * 1024;
+const SYMBOL_SIZE: u16 = 1280;
+const DROP_EVERY: usize = 20;
+// The best of several runs is used, which is less noisy than the mean
+const RUNS: usize = 3;
+
+fn megabytes_per_second(time: Duration) -> f64 {
+    LENGTH as f64 / (1024.0 * 1024.0) / time.as_secs_f64()
+}
+
+// Returns the throughput, in MB/s, of xoring buffers as large as the object, which the throughput
+// of the scenario is measured relative to
+fn run_reference(data: &[u8]) -> f64 {
+    let mut accumulator = vec![0u8; LENGTH];
+    let start = Instant::now();
+    // About as many passes over the object as encoding makes over its symbols
+    for shift in 0..16 {
+        for (dest, (i, byte)) in accumulator.iter_mut().zip(data.iter().enumerate()) {
+            *dest ^= byte.rotate_left(shift) ^ (i >> shift) as u8;
+        }
+    }
+    let time = start.elapsed();
+    assert!(accumulator.iter().any(|byte| *byte != 0));
+    megabytes_per_second(time)
+}
+
+// Returns the throughput of encoding, and of decoding, in MB/s
+fn run_scenario(data: &[u8]) -> (f64, f64) {
+    let start = Instant::now();
+    let encoder = Encoder::builder(SYMBOL_SIZE).build(data).unwrap();
+    let source_symbols = (LENGTH / SYMBOL_SIZE as usize) as u32;
+    // Enough repair packets to make up for the loss, and two more
+    let repair = source_symbols / (DROP_EVERY as u32 - 1) + 2;
+    let packets = encoder.get_encoded_packets(repair);
+    let encode_time = start.elapsed();
+
+    let packets: Vec<EncodingPacket> = packets
+        .into_iter()
+        .enumerate()
+        .filter(|(i, _)| i % DROP_EVERY != 0)
+        .map(|(_, packet)| packet)
+        .collect();
+    let start = Instant::now();
+    let mut decoder = Decoder::new(encoder.get_config());
+    let mut result = None;
+    for packet in packets {
+        result = decoder.decode(packet);
+        if result.is_some() {
+            break;
+        }
+    }
+    let decode_time = start.elapsed();
+    assert_eq!(result.as_deref(), Some(data));
+
+    (
+        megabytes_per_second(encode_time),
+        megabytes_per_second(decode_time),
+    )
+}
+
+// Reads lines of the form "name value", ignoring blank lines and comments
+fn read_baseline(contents: &str) -> BTreeMap<String, f64> {
+    contents
+        .lines()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| {
+            let mut parts = line.split_whitespace();
+            let name = parts.next().unwrap().to_string();
+            let value = parts
+                .next()
+                .and_then(|value| value.parse().ok())
+                .unwrap_or_else(|| panic!("Invalid baseline entry: {}", line));
+            (name, value)
+        })
+        .collect()
+}
+
+fn write_baseline(encode: f64, decode: f64) {
+    let contents = format!(
+        "# Throughput of the scenario in tests/perf.rs, relative to that of the reference\n\
+         # workload. See that file for how to record it\n\
+         encode {:.3}\n\
+         decode {:.3}\n",
+        encode, decode
+    );
+    fs::write(BASELINE_PATH, contents).expect("Failed to write the baseline");
+}
+
+#[test]
+fn throughput_does_not_regress() {
+    assert!(
+        !cfg!(debug_assertions),
+        "The performance test must be run with --release"
+    );
+    let record = env::var("RAPTORQ_PERF_RECORD").is_ok();
+    let baseline = fs::read_to_string(BASELINE_PATH).ok();
+    assert!(
+        record || baseline.is_some(),
+        "No baseline in {}. Record one with RAPTORQ_PERF_RECORD=1",
+        BASELINE_PATH
+    );
+
+    let data: Vec<u8> = (0..LENGTH).map(|i| (i * 31 + 7) as u8).collect();
+    let mut reference: f64 = 0.0;
+    let mut encode: f64 = 0.0;
+    let mut decode: f64 = 0.0;
+    for _ in 0..RUNS {
+        reference = reference.max(run_reference(&data));
+        let (run_encode, run_decode) = run_scenario(&data);
+        encode = encode.max(run_encode);
+        decode = decode.max(run_decode);
+    }
+    println!(
+        "reference: {:.1} MB/s, encode: {:.1} MB/s, decode: {:.1} MB/s",
+        reference, encode, decode
+    );
+    let encode = encode / reference;
+    let decode = decode / reference;
+
+    if record {
+        write_baseline(encode, decode);
+        return;
+    }
+    let threshold = env::var("RAPTORQ_PERF_THRESHOLD")
+        .map(|threshold| threshold.parse().expect("Invalid threshold"))
+        .unwrap_or(DEFAULT_THRESHOLD_PERCENT);
+    let baseline = read_baseline(&baseline.unwrap());
+    for (name, measured) in [("encode", encode), ("decode", decode)].iter() {
+        let expected = baseline[*name];
+        let minimum = expected * (1.0 - threshold / 100.0);
+        assert!(
+            *measured >= minimum,
+            "{} throughput of {:.3} of the reference is more than {}% below the baseline of {:.3}",
+            name,
+            measured,
+            threshold,
+            expected
+        );
+    }
+}